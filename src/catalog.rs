@@ -0,0 +1,709 @@
+//! The scripts and shortcuts visible through the lookup order, where the
+//! first source defining a name hides the others.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use hashbrown::HashMap;
+
+use crate::error::ZirvError;
+use crate::utils::{
+    RESERVED_FILES, SUPPORTED_EXTENSIONS, ScriptLocation, ScriptSource, Shortcut, ShortcutParam,
+    Shortcuts, file_to_scripts, parse_script_content, scripts_file,
+};
+
+/// A script that lookup would run for its name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogScript {
+    /// The name the script is run with, e.g. `db/migrate`.
+    pub name: String,
+    pub source: ScriptSource,
+    pub path: ScriptLocation,
+    /// Same-named scripts from later sources, hidden by this one.
+    pub overrides: Vec<(ScriptSource, ScriptLocation)>,
+}
+
+impl CatalogScript {
+    /// The script's file name relative to its source, e.g. `db/migrate.yaml`,
+    /// or `.zirv.yaml#build` for a script in a file of scripts.
+    pub fn file_name(&self) -> String {
+        match &self.path {
+            ScriptLocation::Entry { file, name } => {
+                let file = file.file_name().unwrap_or_default().to_string_lossy();
+                format!("{file}#{name}")
+            }
+            ScriptLocation::File(path) => match path.extension() {
+                Some(ext) => format!("{}.{}", self.name, ext.to_string_lossy()),
+                None => self.name.clone(),
+            },
+        }
+    }
+}
+
+/// A shortcut as lookup would resolve it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogShortcut {
+    pub key: String,
+    pub target: Shortcut,
+    pub source: ScriptSource,
+    /// Mappings of the same key in later sources that point elsewhere.
+    pub conflicts: Vec<(ScriptSource, Shortcut)>,
+}
+
+/// Returns every effective script in `sources`, sorted by name. Within a
+/// source, scripts in the directory hide those in its file of scripts.
+pub fn effective_scripts(
+    sources: &[(ScriptSource, PathBuf)],
+) -> Result<Vec<CatalogScript>, Box<dyn std::error::Error>> {
+    let mut scripts: Vec<CatalogScript> = Vec::new();
+    for (source, dir) in sources {
+        let mut found: Vec<(String, ScriptLocation)> = Vec::new();
+        if dir.is_dir() {
+            found.extend(
+                script_names(dir)?
+                    .into_iter()
+                    .map(|(name, path)| (name, ScriptLocation::File(path))),
+            );
+        }
+        found.extend(file_script_names(dir)?);
+        for (name, path) in found {
+            match scripts.iter_mut().find(|s| s.name == name) {
+                Some(existing) => existing.overrides.push((*source, path)),
+                None => scripts.push(CatalogScript {
+                    name,
+                    source: *source,
+                    path,
+                    overrides: Vec::new(),
+                }),
+            }
+        }
+    }
+    scripts.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(scripts)
+}
+
+/// Returns every effective shortcut in `sources`, sorted by key. Earlier
+/// sources take precedence, like they do for scripts.
+pub fn effective_shortcuts(
+    sources: &[(ScriptSource, PathBuf)],
+) -> Result<Vec<CatalogShortcut>, Box<dyn std::error::Error>> {
+    let mut shortcuts: Vec<CatalogShortcut> = Vec::new();
+    for (source, dir) in sources {
+        for (key, target) in load_shortcuts(dir)?.shortcuts {
+            match shortcuts.iter_mut().find(|s| s.key == key) {
+                Some(existing) if existing.target != target => {
+                    existing.conflicts.push((*source, target))
+                }
+                Some(_) => {}
+                None => shortcuts.push(CatalogShortcut {
+                    key,
+                    target,
+                    source: *source,
+                    conflicts: Vec::new(),
+                }),
+            }
+        }
+    }
+    shortcuts.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(shortcuts)
+}
+
+/// A script file in one particular source, as picked by a management
+/// command such as `remove`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocatedScript {
+    pub name: String,
+    pub source: ScriptSource,
+    /// The source directory the script was found in.
+    pub dir: PathBuf,
+    pub path: PathBuf,
+}
+
+/// Finds the script `name` refers to in the sources, optionally only in
+/// `scope`. `name` may be a script name, a file name or a shortcut key of
+/// the source it is found in.
+///
+/// Fails when no source has the script, or when several do and `scope`
+/// does not say which one is meant.
+pub fn locate_script(
+    sources: &[(ScriptSource, PathBuf)],
+    name: &str,
+    scope: Option<ScriptSource>,
+) -> Result<LocatedScript, Box<dyn std::error::Error>> {
+    let mut found = Vec::new();
+    for (source, dir) in sources {
+        if scope.is_some_and(|scope| scope != *source) || !dir.is_dir() {
+            continue;
+        }
+        let wanted = match load_shortcuts(dir)?.shortcuts.get(name) {
+            Some(target) => script_name(target.script()).to_string(),
+            None => script_name(name).to_string(),
+        };
+        if let Some((name, path)) = script_names(dir)?.into_iter().find(|(n, _)| *n == wanted) {
+            found.push(LocatedScript {
+                name,
+                source: *source,
+                dir: dir.clone(),
+                path,
+            });
+        }
+    }
+
+    match found.len() {
+        0 => match scope {
+            Some(scope) => Err(ZirvError::not_found(
+                name,
+                format!("No {} script found for '{name}'", scope.as_str()),
+            )
+            .into()),
+            None => Err(ZirvError::not_found(
+                name,
+                format!("No script or shortcut found for '{name}'"),
+            )
+            .into()),
+        },
+        1 => Ok(found.remove(0)),
+        _ => {
+            let places: Vec<String> = found
+                .iter()
+                .map(|s| format!("{} ({})", s.source.as_str(), s.path.display()))
+                .collect();
+            Err(format!(
+                "'{name}' exists in {}; pass --local or --global to choose",
+                places.join(" and ")
+            )
+            .into())
+        }
+    }
+}
+
+/// The script name a shortcut target or file name refers to, i.e. without a
+/// supported extension: `db/migrate.yaml` refers to `db/migrate`.
+pub fn script_name(target: &str) -> &str {
+    match target.rsplit_once('.') {
+        Some((name, ext)) if SUPPORTED_EXTENSIONS.contains(&ext) => name,
+        _ => target,
+    }
+}
+
+/// The shortcuts pointing at the script `name`, as `(key, target)` pairs
+/// sorted by key.
+pub fn shortcuts_to(shortcuts: &Shortcuts, name: &str) -> Vec<(String, Shortcut)> {
+    let mut found: Vec<(String, Shortcut)> = shortcuts
+        .shortcuts
+        .iter()
+        .filter(|(_, target)| script_name(target.script()) == name)
+        .map(|(key, target)| (key.clone(), target.clone()))
+        .collect();
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+    found
+}
+
+/// A script found by name, with the params bundled into the shortcut that
+/// led to it, if any.
+pub type ResolvedScript = (ScriptLocation, Vec<ShortcutParam>);
+
+/// How many shortcuts may point at one another before lookup gives up.
+const MAX_SHORTCUT_DEPTH: usize = 8;
+
+/// Resolves the shortcut `key` of the `.shortcuts.yaml` in `dir`, or
+/// returns `None` if there is no such shortcut.
+///
+/// A shortcut may point at another shortcut of the same file; the chain is
+/// followed, with the params bundled by inner shortcuts coming first. Fails
+/// on cycles and on targets that are neither a script nor a shortcut.
+pub fn resolve_shortcut(
+    dir: &Path,
+    shortcuts: &Shortcuts,
+    key: &str,
+) -> Result<Option<ResolvedScript>, String> {
+    let Some(mut shortcut) = shortcuts.shortcuts.get(key) else {
+        return Ok(None);
+    };
+    let mut chain = vec![key];
+    let mut bundled = Vec::new();
+    loop {
+        bundled.splice(0..0, shortcut.params().iter().cloned());
+        let target = shortcut.script();
+        if let Some(location) = script_file(dir, target) {
+            let location = location.canonicalize().map_err(|e| e.to_string())?;
+            return Ok(Some((location, bundled)));
+        }
+
+        let Some(next) = shortcuts.shortcuts.get(target) else {
+            let last = chain.last().unwrap_or(&key);
+            return Err(format!(
+                "Shortcut '{last}' points to '{target}' which does not exist"
+            ));
+        };
+        if chain.contains(&target) {
+            return Err(format!(
+                "Shortcut cycle: {} -> {target}",
+                chain.join(" -> ")
+            ));
+        }
+        if chain.len() == MAX_SHORTCUT_DEPTH {
+            return Err(format!(
+                "Shortcut '{key}' goes through more than {MAX_SHORTCUT_DEPTH} shortcuts"
+            ));
+        }
+        chain.push(target);
+        shortcut = next;
+    }
+}
+
+/// The script file `target` names in `dir`, with or without its extension.
+///
+/// `<file>#<name>` names the script `name` in a file of scripts, looked up
+/// in `dir` and then in its parent, where `.zirv.yaml` lives.
+pub fn script_file(dir: &Path, target: &str) -> Option<ScriptLocation> {
+    if let Some((file, name)) = target.rsplit_once('#') {
+        return [Some(dir), dir.parent()]
+            .into_iter()
+            .flatten()
+            .map(|dir| dir.join(file))
+            .find(|path| path.is_file())
+            .filter(|path| file_to_scripts(path).is_ok_and(|scripts| scripts.contains_key(name)))
+            .map(|file| ScriptLocation::Entry {
+                file,
+                name: name.to_string(),
+            });
+    }
+    let path = dir.join(target);
+    if path.is_file() {
+        return Some(ScriptLocation::File(path));
+    }
+    SUPPORTED_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{target}.{ext}")))
+        .find(|path| path.is_file())
+        .map(ScriptLocation::File)
+}
+
+/// Reads the `.shortcuts.yaml` of `dir`, or no shortcuts if there is none.
+pub fn load_shortcuts(dir: &Path) -> Result<Shortcuts, Box<dyn std::error::Error>> {
+    let path = dir.join(".shortcuts.yaml");
+    if !path.is_file() {
+        return Ok(Shortcuts::default());
+    }
+    Ok(serde_yaml::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Writes the `.shortcuts.yaml` of `dir`, with keys in alphabetical order.
+pub fn save_shortcuts(dir: &Path, shortcuts: &Shortcuts) -> Result<(), Box<dyn std::error::Error>> {
+    let sorted: BTreeMap<&String, &Shortcut> = shortcuts.shortcuts.iter().collect();
+    let content = serde_yaml::to_string(&BTreeMap::from([("shortcuts", sorted)]))?;
+    fs::write(dir.join(".shortcuts.yaml"), content)?;
+    Ok(())
+}
+
+/// The `aliases` declared inside script files, across a set of roots.
+#[derive(Debug, Default)]
+pub struct AliasIndex {
+    /// Alias to the script declaring it; the first root wins.
+    pub aliases: HashMap<String, PathBuf>,
+    /// Aliases declared by more than one script: the alias, the script that
+    /// keeps it and the one that is ignored.
+    pub conflicts: Vec<(String, PathBuf, PathBuf)>,
+}
+
+/// Alias indexes built so far in this run, by the roots they cover, so
+/// scripts are parsed at most once however often lookup falls back to them.
+static ALIAS_INDEXES: Mutex<Vec<(Vec<PathBuf>, Arc<AliasIndex>)>> = Mutex::new(Vec::new());
+
+impl AliasIndex {
+    /// Returns the index for `roots`, building it on first use. Scripts that
+    /// fail to parse are skipped; `zirv validate` reports them. Conflicting
+    /// aliases are left to [`AliasIndex::warnings`].
+    pub fn for_roots(roots: &[PathBuf]) -> Result<Arc<AliasIndex>, Box<dyn std::error::Error>> {
+        let mut indexes = ALIAS_INDEXES.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, index)) = indexes.iter().find(|(r, _)| r == roots) {
+            return Ok(index.clone());
+        }
+
+        let index = Arc::new(Self::build(roots)?);
+        indexes.push((roots.to_vec(), index.clone()));
+        Ok(index)
+    }
+
+    /// The index for `roots` if lookup has built it in this run.
+    pub fn built(roots: &[PathBuf]) -> Option<Arc<AliasIndex>> {
+        let indexes = ALIAS_INDEXES.lock().unwrap_or_else(|e| e.into_inner());
+        indexes
+            .iter()
+            .find(|(r, _)| r == roots)
+            .map(|(_, index)| index.clone())
+    }
+
+    fn build(roots: &[PathBuf]) -> Result<AliasIndex, Box<dyn std::error::Error>> {
+        let mut index = AliasIndex::default();
+        for root in roots.iter().filter(|root| root.is_dir()) {
+            for (_, path) in script_names(root)? {
+                let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+                let Ok(script) = fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| {
+                        parse_script_content(&content, ext).map_err(|e| e.to_string())
+                    })
+                else {
+                    continue;
+                };
+                for alias in script.aliases {
+                    match index.aliases.get(&alias) {
+                        Some(existing) => {
+                            index
+                                .conflicts
+                                .push((alias, existing.clone(), path.clone()))
+                        }
+                        None => {
+                            index.aliases.insert(alias, path.clone());
+                        }
+                    }
+                }
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn get(&self, alias: &str) -> Option<&PathBuf> {
+        self.aliases.get(alias)
+    }
+
+    /// One message per conflicting alias.
+    pub fn warnings(&self) -> Vec<String> {
+        self.conflicts
+            .iter()
+            .map(|(alias, kept, ignored)| {
+                format!(
+                    "alias '{alias}' is declared by both {} and {}; using the first",
+                    kept.display(),
+                    ignored.display()
+                )
+            })
+            .collect()
+    }
+}
+
+/// Returns the run name (e.g. `db/migrate`) and path of every script under
+/// `root`, descending into namespace subdirectories. When a name exists with
+/// several extensions, the one lookup would pick is returned.
+pub fn script_names(root: &Path) -> Result<Vec<(String, PathBuf)>, Box<dyn std::error::Error>> {
+    scripts_in_namespace(root, "")
+}
+
+/// Returns the name and location of every script in the file of scripts
+/// next to `dir`, if there is one; see [`scripts_file`].
+pub fn file_script_names(
+    dir: &Path,
+) -> Result<Vec<(String, ScriptLocation)>, Box<dyn std::error::Error>> {
+    let Some(file) = scripts_file(dir) else {
+        return Ok(Vec::new());
+    };
+    Ok(file_to_scripts(&file)?
+        .into_keys()
+        .map(|name| {
+            let location = ScriptLocation::Entry {
+                file: file.clone(),
+                name: name.clone(),
+            };
+            (name, location)
+        })
+        .collect())
+}
+
+fn scripts_in_namespace(
+    root: &Path,
+    namespace: &str,
+) -> Result<Vec<(String, PathBuf)>, Box<dyn std::error::Error>> {
+    let mut found: Vec<(String, usize, PathBuf)> = Vec::new();
+    let mut nested = Vec::new();
+
+    for entry in fs::read_dir(root.join(namespace))? {
+        let path = entry?.path();
+        let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+        if file_name.starts_with('.')
+            || (namespace.is_empty() && RESERVED_FILES.contains(&file_name.as_str()))
+        {
+            continue;
+        }
+        let prefixed = |name: &str| {
+            if namespace.is_empty() {
+                name.to_string()
+            } else {
+                format!("{namespace}/{name}")
+            }
+        };
+
+        if path.is_dir() {
+            nested.push(prefixed(&file_name));
+        } else if let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+            && let Some(rank) = path
+                .extension()
+                .and_then(|s| s.to_str())
+                .and_then(|ext| SUPPORTED_EXTENSIONS.iter().position(|e| *e == ext))
+        {
+            found.push((prefixed(stem), rank, path.clone()));
+        }
+    }
+
+    found.sort();
+    found.dedup_by(|a, b| a.0 == b.0);
+    // `deploy.prod.yaml` next to `deploy.yaml` is an overlay, not a script.
+    let names: Vec<String> = found.iter().map(|(name, _, _)| name.clone()).collect();
+    found.retain(|(name, _, _)| {
+        let (parent, stem) = name.rsplit_once('/').unwrap_or(("", name));
+        !stem.rsplit_once('.').is_some_and(|(base, _)| {
+            let base = match parent {
+                "" => base.to_string(),
+                parent => format!("{parent}/{base}"),
+            };
+            names.contains(&base)
+        })
+    });
+    let mut scripts: Vec<(String, PathBuf)> = found
+        .into_iter()
+        .map(|(name, _, path)| (name, path))
+        .collect();
+    for namespace in nested {
+        scripts.extend(scripts_in_namespace(root, &namespace)?);
+    }
+    Ok(scripts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+
+    #[test]
+    fn test_local_scripts_and_shortcuts_take_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("local");
+        let global = dir.path().join("global");
+        create_dir_all(local.join("db")).unwrap();
+        create_dir_all(&global).unwrap();
+        for path in [
+            local.join("build.yaml"),
+            local.join("db").join("migrate.yaml"),
+            global.join("build.json"),
+            global.join("deploy.yaml"),
+        ] {
+            write(path, "").unwrap();
+        }
+        write(
+            local.join(".shortcuts.yaml"),
+            "shortcuts:\n  b: build\n  d: deploy\n",
+        )
+        .unwrap();
+        write(
+            global.join(".shortcuts.yaml"),
+            "shortcuts:\n  b: bump\n  d: deploy\n  m: db/migrate\n",
+        )
+        .unwrap();
+        let sources = [
+            (ScriptSource::Local, local.clone()),
+            (ScriptSource::Global, global.clone()),
+        ];
+
+        let scripts = effective_scripts(&sources).unwrap();
+        let names: Vec<(&str, ScriptSource)> = scripts
+            .iter()
+            .map(|s| (s.name.as_str(), s.source))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("build", ScriptSource::Local),
+                ("db/migrate", ScriptSource::Local),
+                ("deploy", ScriptSource::Global),
+            ]
+        );
+        assert_eq!(
+            scripts[0].overrides,
+            vec![(
+                ScriptSource::Global,
+                ScriptLocation::File(global.join("build.json"))
+            )]
+        );
+        assert_eq!(scripts[1].file_name(), "db/migrate.yaml");
+
+        let shortcuts = effective_shortcuts(&sources).unwrap();
+        let keys: Vec<(&str, &str, ScriptSource)> = shortcuts
+            .iter()
+            .map(|s| (s.key.as_str(), s.target.script(), s.source))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("b", "build", ScriptSource::Local),
+                ("d", "deploy", ScriptSource::Local),
+                ("m", "db/migrate", ScriptSource::Global),
+            ]
+        );
+        assert_eq!(
+            shortcuts[0].conflicts,
+            vec![(ScriptSource::Global, Shortcut::from("bump"))]
+        );
+        assert!(shortcuts[1].conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_shortcut_chain_carries_params() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path().join("deploy.yaml"), "").unwrap();
+        let shortcuts: Shortcuts = serde_yaml::from_str(
+            "shortcuts:\n  dp:\n    script: deploy\n    params: [prod]\n  dpe:\n    script: dp\n    params: [{ region: eu }]\n  loop: loop\n",
+        )
+        .unwrap();
+
+        let (path, bundled) = resolve_shortcut(dir.path(), &shortcuts, "dpe")
+            .unwrap()
+            .unwrap();
+        assert!(path.file().ends_with("deploy.yaml"));
+        assert_eq!(
+            Shortcut::WithParams {
+                script: "deploy".to_string(),
+                params: bundled
+            }
+            .to_string(),
+            "deploy prod region=eu"
+        );
+        assert_eq!(
+            resolve_shortcut(dir.path(), &shortcuts, "loop"),
+            Err("Shortcut cycle: loop -> loop".to_string())
+        );
+        assert_eq!(resolve_shortcut(dir.path(), &shortcuts, "deploy"), Ok(None));
+    }
+
+    #[test]
+    fn test_alias_conflicts_name_both_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("local");
+        let global = dir.path().join("global");
+        create_dir_all(&local).unwrap();
+        create_dir_all(&global).unwrap();
+        write(
+            local.join("build.yaml"),
+            "name: Build\naliases: [b]\ncommands: []\n",
+        )
+        .unwrap();
+        write(
+            local.join("bump.yaml"),
+            "name: Bump\naliases: [b, up]\ncommands: []\n",
+        )
+        .unwrap();
+        write(
+            global.join("upload.yaml"),
+            "name: Upload\naliases: [up]\ncommands: []\n",
+        )
+        .unwrap();
+        write(global.join("broken.yaml"), "name: [\n").unwrap();
+
+        let roots = [local.clone(), global.clone()];
+        let index = AliasIndex::for_roots(&roots).unwrap();
+        assert_eq!(index.get("b"), Some(&local.join("build.yaml")));
+        assert_eq!(index.get("up"), Some(&local.join("bump.yaml")));
+        assert_eq!(
+            index.warnings(),
+            vec![
+                format!(
+                    "alias 'b' is declared by both {} and {}; using the first",
+                    local.join("build.yaml").display(),
+                    local.join("bump.yaml").display()
+                ),
+                format!(
+                    "alias 'up' is declared by both {} and {}; using the first",
+                    local.join("bump.yaml").display(),
+                    global.join("upload.yaml").display()
+                ),
+            ]
+        );
+        assert!(Arc::ptr_eq(&index, &AliasIndex::for_roots(&roots).unwrap()));
+        assert!(Arc::ptr_eq(&index, &AliasIndex::built(&roots).unwrap()));
+        assert!(AliasIndex::built(&[global]).is_none());
+    }
+
+    #[test]
+    fn test_overlays_are_not_scripts() {
+        let dir = tempfile::tempdir().unwrap();
+        create_dir_all(dir.path().join("db")).unwrap();
+        for name in [
+            "deploy.yaml",
+            "deploy.prod.yaml",
+            "deploy.staging.json",
+            "v1.2.yaml",
+            "db/seed.yaml",
+            "db/seed.dev.toml",
+        ] {
+            write(dir.path().join(name), "").unwrap();
+        }
+
+        let names: Vec<String> = script_names(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["deploy", "v1.2", "db/seed"]);
+    }
+
+    #[test]
+    fn test_directory_scripts_hide_scripts_file_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join(".zirv");
+        create_dir_all(&local).unwrap();
+        write(local.join("build.yaml"), "name: Build\ncommands: []\n").unwrap();
+        write(
+            dir.path().join(".zirv.yaml"),
+            "scripts:\n  build:\n    name: Inline Build\n    commands: []\n  lint:\n    name: Lint\n    commands: []\n",
+        )
+        .unwrap();
+
+        let scripts = effective_scripts(&[(ScriptSource::Local, local.clone())]).unwrap();
+        let entries: Vec<(&str, String)> = scripts
+            .iter()
+            .map(|s| (s.name.as_str(), s.file_name()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("build", "build.yaml".to_string()),
+                ("lint", ".zirv.yaml#lint".to_string())
+            ]
+        );
+        assert_eq!(
+            scripts[0].overrides,
+            [(
+                ScriptSource::Local,
+                ScriptLocation::Entry {
+                    file: dir.path().join(".zirv.yaml"),
+                    name: "build".to_string(),
+                }
+            )]
+        );
+
+        std::fs::remove_dir_all(&local).unwrap();
+        let scripts = effective_scripts(&[(ScriptSource::Local, local)]).unwrap();
+        assert_eq!(scripts.len(), 2);
+        assert_eq!(scripts[0].file_name(), ".zirv.yaml#build");
+    }
+
+    #[test]
+    fn test_vars_file_is_not_a_script() {
+        let dir = tempfile::tempdir().unwrap();
+        create_dir_all(dir.path().join("db")).unwrap();
+        for name in ["vars.yaml", "build.yaml", "db/vars.yaml"] {
+            write(dir.path().join(name), "").unwrap();
+        }
+
+        let names: Vec<String> = script_names(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["build", "db/vars"]);
+    }
+}
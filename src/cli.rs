@@ -0,0 +1,620 @@
+//! The `zirv` command line: parses the arguments and dispatches to the
+//! built-in commands or runs a script.
+
+use std::{io::IsTerminal, path::PathBuf, process::ExitCode};
+
+use crate::commands::{
+    clean::{Generated, clean},
+    config::show_config,
+    create::{create_script, create_script_interactive},
+    dangerous::confirm_dangerous,
+    edit::{edit_script, editor_command},
+    explain::explain_script,
+    export::export_script,
+    help::{show_help, show_script},
+    history::read_recent_history,
+    hook::{install_hook, uninstall_hook},
+    import::import_scripts,
+    init::{InitOptions, init_zirv},
+    last::{RunRecord, choose_record, history_path, read_records, record_run, show_history},
+    list::list_scripts,
+    promote::copy_script,
+    remove::remove_script,
+    rename::rename_script,
+    run_url::fetch_script,
+    schema::print_schema,
+    shortcut::{add_shortcut, list_shortcuts, remove_shortcut},
+    stats::{script_stats, show_stats},
+    template::init_from_template,
+    validate::validate_scripts,
+    version::{CratesIo, VERSION_CHECK_FILE, check_version, get_version},
+};
+use clap::Parser;
+
+use crate::{
+    error::ZirvError,
+    input::Input,
+    pager,
+    script_runner::{
+        Script, WatchOptions, execute, execute_repeated, execute_watching,
+        plan::plan,
+        process::{RunGuard, interrupt, interrupted, kill_all},
+        run_options::Verbosity,
+        run_with_report,
+        style::Style,
+    },
+    utils::{SCRIPT_DIR_NAME, ScriptLocation, ScriptSource, merge_params, zirv_home},
+};
+
+/// Runs the `zirv` command line with the process's arguments and returns
+/// the code to exit with. This is all the `zirv` binary does.
+pub async fn run_cli() -> ExitCode {
+    // Parse CLI arguments.
+    let mut input = Input::parse();
+
+    // Ctrl-C only reaches the steps in the terminal's foreground, so stop
+    // the others, and let a running script record how it ended, before
+    // exiting; a second Ctrl-C kills them at once.
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            tokio::select! {
+                _ = interrupt() => {}
+                _ = tokio::signal::ctrl_c() => kill_all(),
+            }
+            std::process::exit(130);
+        }
+    });
+
+    match run(&mut input).await {
+        _ if interrupted() => ExitCode::from(130),
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let color = input.color_mode().enabled(std::io::stderr().is_terminal());
+            eprintln!("{}", Style::Red.paint_if(color, &e.to_string()));
+            if input.verbosity() >= Verbosity::Verbose {
+                let mut source = std::error::Error::source(&e);
+                while let Some(cause) = source {
+                    eprintln!("  caused by: {cause}");
+                    source = cause.source();
+                }
+            }
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+async fn run(input: &mut Input) -> Result<(), ZirvError> {
+    // The configuration is only read where it is used, so that a broken
+    // config.yaml does not keep `validate`, `config` or `edit` from
+    // pointing it out.
+
+    // Check for built-in commands before attempting to find a script file.
+    match input.builtin() {
+        Some("help" | "h") => {
+            let mut buffer = Vec::new();
+            match input.params.split_first() {
+                Some((name, rest)) => {
+                    let path = input.find_named_script(name, &mut rest.to_vec())?;
+                    show_script(&mut buffer, &path)?;
+                }
+                None => show_help(&mut buffer, &input.script_sources()?)?,
+            }
+            pager::show(&String::from_utf8_lossy(&buffer), !input.no_pager)?;
+            return Ok(());
+        }
+        Some("version" | "v") => {
+            get_version(&mut std::io::stdout())?;
+            if input.check {
+                let cache = zirv_home().map(|dir| dir.join(VERSION_CHECK_FILE));
+                check_version(
+                    &mut std::io::stdout(),
+                    env!("CARGO_PKG_VERSION"),
+                    &CratesIo,
+                    cache.as_deref(),
+                    std::time::SystemTime::now(),
+                )?;
+            }
+            return Ok(());
+        }
+        Some("explain") => {
+            input.load_config()?;
+            let Some((name, rest)) = input.params.split_first() else {
+                return Err("Usage: zirv explain <script> [params...] [--json]".into());
+            };
+            let mut rest = rest.to_vec();
+            let (path, bundled) = input.resolve_named_script(name, &mut rest)?;
+            let (script, overlay, warnings) = input.load_script(&path)?;
+            warn(input, &warnings);
+            let selection = input.step_selection();
+            let overlay = match overlay {
+                Some(overlay) => Some(overlay.for_steps(&selection.resolve(&script)?)),
+                None => None,
+            };
+            let script = selection.apply(&script)?;
+            let params = merge_params(
+                script.params.as_deref().unwrap_or_default(),
+                &bundled,
+                &rest,
+            )?;
+            let options = input.run_options(input.project_root(&path)?)?;
+            explain_script(
+                &mut std::io::stdout(),
+                &script,
+                overlay.as_ref(),
+                &params,
+                &options,
+                input.json,
+            )?;
+            return Ok(());
+        }
+        Some("export") => {
+            let Some((name, rest)) = input.params.split_first() else {
+                return Err("Usage: zirv export <script> [--format sh|ps1]".into());
+            };
+            let path = input.find_named_script(name, &mut rest.to_vec())?;
+            let (script, _, warnings) = input.load_script(&path)?;
+            warn(input, &warnings);
+            export_script(&mut std::io::stdout(), &script, input.export_format()?)?;
+            return Ok(());
+        }
+        Some("import") => {
+            let Some(from) = input
+                .from
+                .as_deref()
+                .or(input.params.first().map(String::as_str))
+            else {
+                return Err(
+                    "Usage: zirv import --from <package.json|Makefile|justfile> [--global] [--force]"
+                        .into(),
+                );
+            };
+            import_scripts(
+                &mut std::io::stdout(),
+                std::path::Path::new(from),
+                &input.target_dir()?,
+                input.force,
+                |prompt| {
+                    Ok(dialoguer::Confirm::new()
+                        .with_prompt(prompt)
+                        .default(false)
+                        .interact()?)
+                },
+            )?;
+            return Ok(());
+        }
+        Some("run-url") => {
+            input.load_config()?;
+            let Some((url, rest)) = input.params.split_first() else {
+                return Err("Usage: zirv run-url <url> --sha256 <hash> [params...] [--yes]".into());
+            };
+            let Some(sha256) = &input.sha256 else {
+                return Err("zirv run-url needs --sha256 <hash> to verify the download".into());
+            };
+            let script = fetch_script(url, sha256)?;
+            let params = merge_params(script.params.as_deref().unwrap_or_default(), &[], rest)?;
+            let options = input.run_options(None)?;
+
+            plan(&script, &params, &options)?.write_text(&mut std::io::stdout())?;
+            let confirmed = input.yes
+                || dialoguer::Confirm::new()
+                    .with_prompt("Run this script?")
+                    .default(false)
+                    .interact()
+                    .map_err(|e| ZirvError::Other(e.into()))?;
+            if !confirmed {
+                println!("Nothing was run.");
+                return Ok(());
+            }
+            // A fetched script has no file; it goes by its own name.
+            confirm_script(input, &script, &script.name)?;
+            execute(&script, &params, &options).await?;
+            return Ok(());
+        }
+        Some("install-hook") => {
+            let [hook, script] = input.params.as_slice() else {
+                return Err("Usage: zirv install-hook <hook> <script> [--yes]".into());
+            };
+            input.find_named_script(script, &mut Vec::new())?;
+            let yes = input.yes;
+            install_hook(
+                &mut std::io::stdout(),
+                &std::env::current_dir()?,
+                hook,
+                script,
+                |prompt| {
+                    if yes {
+                        return Ok(true);
+                    }
+                    if !(std::io::stdin().is_terminal() && std::io::stderr().is_terminal()) {
+                        return Err(
+                            "not a terminal; pass --yes to install the hook non-interactively"
+                                .into(),
+                        );
+                    }
+                    Ok(dialoguer::Confirm::new()
+                        .with_prompt(prompt)
+                        .default(false)
+                        .interact()?)
+                },
+            )?;
+            return Ok(());
+        }
+        Some("uninstall-hook") => {
+            let [hook] = input.params.as_slice() else {
+                return Err("Usage: zirv uninstall-hook <hook>".into());
+            };
+            uninstall_hook(&mut std::io::stdout(), &std::env::current_dir()?, hook)?;
+            return Ok(());
+        }
+        Some("list") => {
+            list_scripts(&mut std::io::stdout(), &input.script_sources()?, input.json)?;
+            return Ok(());
+        }
+        Some("history") => {
+            let limit = match input.params.as_slice() {
+                [] => 20,
+                [n] => n
+                    .parse()
+                    .map_err(|_| format!("'{n}' is not a number of runs"))?,
+                _ => return Err("Usage: zirv history [N]".into()),
+            };
+            let path = history_path().ok_or("Could not determine home directory")?;
+            show_history(&mut std::io::stdout(), &read_records(&path)?, limit)?;
+            return Ok(());
+        }
+        Some("stats") => {
+            let path = history_path().ok_or("Could not determine home directory")?;
+            let mut records = read_records(&path)?;
+            let script = match input.params.as_slice() {
+                [] => None,
+                [name] => Some(input.find_named_script(name, &mut Vec::new())?),
+                _ => return Err("Usage: zirv stats [script] [--json]".into()),
+            };
+            if let Some(script) = &script {
+                let script = script.canonicalize()?;
+                records.retain(|record| record.script == script);
+            }
+            show_stats(
+                &mut std::io::stdout(),
+                &script_stats(&records),
+                script.is_some(),
+                input.json,
+            )?;
+            return Ok(());
+        }
+        Some("last") => {
+            if !input.params.is_empty() {
+                return Err("Usage: zirv last [--pick[=N]]".into());
+            }
+            input.load_config()?;
+            let path = history_path().ok_or("Could not determine home directory")?;
+            let records = read_records(&path)?;
+            let record = choose_record(&records, input.pick, |items| {
+                Ok(dialoguer::Select::new()
+                    .with_prompt("Run again")
+                    .items(items)
+                    .default(0)
+                    .interact()?)
+            })?;
+            let script = input.step_selection().apply(&record.load_script()?)?;
+            return run_script(
+                input,
+                &record.script,
+                record.env.as_deref(),
+                &script,
+                &record.params,
+                Vec::new(),
+            )
+            .await;
+        }
+        Some("config") => {
+            let problems = input.load_readable_config()?;
+            show_config(&mut std::io::stdout(), &input.settings()?)?;
+            if !problems.is_empty() {
+                return Err(
+                    format!("Ignored invalid configuration: {}", problems.join("; ")).into(),
+                );
+            }
+            return Ok(());
+        }
+        Some("schema") => {
+            print_schema(&mut std::io::stdout())?;
+            return Ok(());
+        }
+        Some("validate") => {
+            let paths = if input.params.is_empty() {
+                vec![
+                    input
+                        .project_dir()?
+                        .unwrap_or_else(|| PathBuf::from(SCRIPT_DIR_NAME)),
+                ]
+            } else {
+                input.params.iter().map(PathBuf::from).collect()
+            };
+            validate_scripts(&mut std::io::stdout(), &paths, input.strict, &input.vars()?)?;
+            return Ok(());
+        }
+        Some("edit") => {
+            let Some((name, rest)) = input.params.split_first() else {
+                return Err("Usage: zirv edit <script>".into());
+            };
+            let resolved = input.find_named_script(name, &mut rest.to_vec());
+            let new_dir = input
+                .project_dir()?
+                .unwrap_or_else(|| PathBuf::from(SCRIPT_DIR_NAME));
+            edit_script(
+                &mut std::io::stdout(),
+                name,
+                resolved,
+                &new_dir,
+                &editor_command(),
+                &input.vars()?,
+                |prompt| {
+                    Ok(dialoguer::Confirm::new()
+                        .with_prompt(prompt)
+                        .default(false)
+                        .interact()?)
+                },
+            )?;
+            return Ok(());
+        }
+        Some("remove") => {
+            let [name] = input.params.as_slice() else {
+                return Err("Usage: zirv remove <script> [--local|--global] [--yes]".into());
+            };
+            let yes = input.yes;
+            remove_script(
+                &mut std::io::stdout(),
+                &input.script_sources()?,
+                name,
+                input.scope()?,
+                |prompt| {
+                    Ok(yes
+                        || dialoguer::Confirm::new()
+                            .with_prompt(prompt)
+                            .default(false)
+                            .interact()?)
+                },
+            )?;
+            return Ok(());
+        }
+        Some("clean") => {
+            let kinds: Vec<Generated> = if input.all {
+                Generated::ALL.to_vec()
+            } else {
+                [
+                    (input.cache, Generated::Cache),
+                    (input.history, Generated::History),
+                ]
+                .into_iter()
+                .filter_map(|(given, kind)| given.then_some(kind))
+                .collect()
+            };
+            if kinds.is_empty() {
+                return Err(
+                    "Usage: zirv clean --cache|--history|--all [--dry-run] [--local|--global]"
+                        .into(),
+                );
+            }
+            let scope = input.scope()?;
+            let dirs: Vec<PathBuf> = input
+                .script_sources()?
+                .into_iter()
+                .filter(|(source, dir)| {
+                    *source != ScriptSource::Extra
+                        && scope.is_none_or(|scope| scope == *source)
+                        && dir.is_dir()
+                })
+                .map(|(_, dir)| dir)
+                .collect();
+            clean(&mut std::io::stdout(), &dirs, &kinds, input.dry_run)?;
+            return Ok(());
+        }
+        Some("rename") => {
+            let [old, new] = input.params.as_slice() else {
+                return Err("Usage: zirv rename <old> <new> [--local|--global]".into());
+            };
+            rename_script(
+                &mut std::io::stdout(),
+                &input.script_sources()?,
+                old,
+                new,
+                input.scope()?,
+            )?;
+            return Ok(());
+        }
+        Some(command @ ("promote" | "demote")) => {
+            let [name] = input.params.as_slice() else {
+                return Err(format!("Usage: zirv {command} <script> [--move]").into());
+            };
+            let (from, to_dir) = if command == "promote" {
+                let global = zirv_home().ok_or("Could not determine home directory")?;
+                (ScriptSource::Local, global)
+            } else {
+                let local = input
+                    .project_dir()?
+                    .unwrap_or_else(|| PathBuf::from(SCRIPT_DIR_NAME));
+                (ScriptSource::Global, local)
+            };
+            copy_script(
+                &mut std::io::stdout(),
+                &input.script_sources()?,
+                name,
+                from,
+                &to_dir,
+                input.move_source,
+                |prompt| {
+                    Ok(dialoguer::Confirm::new()
+                        .with_prompt(prompt)
+                        .default(false)
+                        .interact()?)
+                },
+            )?;
+            return Ok(());
+        }
+        Some("shortcut") => {
+            let dir = input.target_dir()?;
+            let mut stdout = std::io::stdout();
+            match input.params.as_slice() {
+                [action, key, target] if action == "add" => {
+                    add_shortcut(&mut stdout, &dir, key, target)?
+                }
+                [action, key] if action == "remove" => remove_shortcut(&mut stdout, &dir, key)?,
+                [action] if action == "list" => {
+                    list_shortcuts(&mut stdout, &input.script_sources()?)?
+                }
+                _ => {
+                    return Err(
+                        "Usage: zirv shortcut add <key> <script> | remove <key> | list [--global]"
+                            .into(),
+                    );
+                }
+            }
+            return Ok(());
+        }
+        Some("init" | "i") => {
+            if let Some(template) = &input.template {
+                let target = if input.global {
+                    zirv_home().ok_or("Could not determine home directory")?
+                } else {
+                    std::env::current_dir()?.join(SCRIPT_DIR_NAME)
+                };
+                init_from_template(&mut std::io::stdout(), template, &target, input.force)?;
+                return Ok(());
+            }
+            init_zirv(&InitOptions {
+                local: input.local,
+                global: input.global,
+                yes: input.yes,
+                gitignore: input.gitignore,
+            })?;
+            return Ok(());
+        }
+        Some("create" | "c") => {
+            match input.params.as_slice() {
+                [] if !input.create_flags_given() => {
+                    let history = match input.from_history {
+                        Some(limit) => Some(read_recent_history(limit)?),
+                        None => None,
+                    };
+                    create_script_interactive(
+                        &input.target_dir()?,
+                        zirv_home().as_deref(),
+                        history.as_deref(),
+                    )?
+                }
+                [name] if input.from_history.is_none() => {
+                    create_script(
+                        &mut std::io::stdout(),
+                        &input.target_dir()?,
+                        name,
+                        input.shortcut.as_deref(),
+                        input.script_format()?,
+                        input.force,
+                    )?;
+                }
+                _ => {
+                    return Err("Usage: zirv create [--from-history[=N]] | zirv create <name> [--shortcut <key>] [--global] [--format yaml|json|toml] [--force]".into());
+                }
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    // For all other commands, attempt to find a script file.
+    input.load_config()?;
+    let (file_path, bundled) = input.get_file_path()?;
+
+    let (script, _, warnings) = input.load_script(&file_path)?;
+    let script = input.step_selection().apply(&script)?;
+    let params = merge_params(
+        script.params.as_deref().unwrap_or_default(),
+        &bundled,
+        &input.params,
+    )?;
+
+    run_script(
+        input,
+        &file_path,
+        input.env.as_deref(),
+        &script,
+        &params,
+        warnings,
+    )
+    .await
+}
+
+/// Prints `warnings` about a script that is not run, unless quiet.
+fn warn(input: &Input, warnings: &[String]) {
+    if input.verbosity() > Verbosity::Quiet {
+        for warning in warnings {
+            eprintln!("Warning: {warning}");
+        }
+    }
+}
+
+/// Runs `script`, found at `path`, as the flags ask, and records the run in
+/// the history `zirv last` repeats runs from. `warnings` about the script
+/// are printed as part of the run.
+async fn run_script(
+    input: &Input,
+    path: &ScriptLocation,
+    env: Option<&str>,
+    script: &Script,
+    params: &[String],
+    warnings: Vec<String>,
+) -> Result<(), ZirvError> {
+    confirm_script(input, script, &input.run_name(path)?)?;
+    let mut options = input.run_options(input.project_root(path)?)?;
+    options.warnings = warnings;
+    // On Ctrl-C, zirv waits for the run to record how it ended.
+    let _guard = RunGuard::hold();
+
+    let result = if !input.watch.is_empty() {
+        let watch = WatchOptions {
+            paths: input.watch.clone(),
+            ignore: input.watch_ignore.clone(),
+        };
+        execute_watching(script, params, &options, &watch)
+            .await
+            .map_err(ZirvError::from)
+    } else if let Some(n) = input.repeat {
+        execute_repeated(script, params, &options, n as usize)
+            .await
+            .map_err(ZirvError::from)
+    } else {
+        let (report, result) = run_with_report(script, params, &options).await;
+        let result = result.map_err(ZirvError::from);
+        let exit_code = result.as_ref().map_or_else(ZirvError::exit_code, |_| 0);
+        record_run(&RunRecord::new(path, env, params, exit_code).with_report(&report));
+        return result;
+    };
+    // Watch and repeat runs report Ctrl-C as failed runs, or not at all.
+    let exit_code = if interrupted() {
+        130
+    } else {
+        result.as_ref().map_or_else(ZirvError::exit_code, |_| 0)
+    };
+    record_run(&RunRecord::new(path, env, params, exit_code));
+    result
+}
+
+/// Asks the user to confirm a `dangerous` script, run by `name`, on the
+/// terminal, before anything of the run is shown.
+fn confirm_script(input: &Input, script: &Script, name: &str) -> Result<(), ZirvError> {
+    let interactive = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    confirm_dangerous(
+        &mut std::io::stderr(),
+        script,
+        name,
+        input.yes,
+        interactive,
+        |prompt| {
+            Ok(dialoguer::Input::<String>::new()
+                .with_prompt(prompt)
+                .allow_empty(true)
+                .interact_text()?)
+        },
+    )
+}
@@ -0,0 +1,285 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    commands::{last::HISTORY_FILE, version::VERSION_CHECK_FILE},
+    script_runner::cache::CACHE_DIR_NAME,
+};
+
+/// The file a project's shortcuts live in, which is never removed.
+const SHORTCUTS_FILE: &str = ".shortcuts.yaml";
+
+/// A kind of data zirv writes into a `.zirv` directory by itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generated {
+    Cache,
+    History,
+}
+
+impl Generated {
+    pub const ALL: [Generated; 2] = [Generated::Cache, Generated::History];
+
+    /// The files and directories holding this kind of data in the `.zirv`
+    /// directory `dir`.
+    pub fn paths(self, dir: &Path) -> Vec<PathBuf> {
+        match self {
+            Generated::Cache => vec![dir.join(CACHE_DIR_NAME), dir.join(VERSION_CHECK_FILE)],
+            Generated::History => vec![dir.join(HISTORY_FILE)],
+        }
+    }
+}
+
+/// Removes the `kinds` of generated data from each of the `.zirv`
+/// directories `dirs`, printing what was removed and how much space that
+/// freed. With `dry_run`, only prints what would be removed.
+pub fn clean<W: Write>(
+    writer: &mut W,
+    dirs: &[PathBuf],
+    kinds: &[Generated],
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut freed = 0;
+    for dir in dirs {
+        let generated: Vec<PathBuf> = kinds.iter().flat_map(|kind| kind.paths(dir)).collect();
+        for path in &generated {
+            let mut files = Vec::new();
+            let mut subdirs = Vec::new();
+            match walk(path, &mut files, &mut subdirs) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            }
+            for file in files.iter().map(|(file, _)| file).chain(&subdirs) {
+                check_removable(file, &generated)?;
+            }
+
+            let size: u64 = files.iter().map(|(_, size)| size).sum();
+            if !dry_run {
+                for (file, _) in &files {
+                    fs::remove_file(file)?;
+                }
+                for subdir in &subdirs {
+                    fs::remove_dir(subdir)?;
+                }
+            }
+            writeln!(
+                writer,
+                "{} {} ({})",
+                if dry_run { "Would remove" } else { "Removed" },
+                path.display(),
+                format_size(size)
+            )?;
+            freed += size;
+        }
+    }
+
+    if dry_run {
+        writeln!(writer, "Would free {}.", format_size(freed))?;
+    } else {
+        writeln!(writer, "Freed {}.", format_size(freed))?;
+    }
+    Ok(())
+}
+
+/// Collects the files under `path` with their sizes, and the directories
+/// with every directory before the one containing it. Symbolic links are
+/// collected as files and never followed.
+fn walk(path: &Path, files: &mut Vec<(PathBuf, u64)>, dirs: &mut Vec<PathBuf>) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path)? {
+            walk(&entry?.path(), files, dirs)?;
+        }
+        dirs.push(path.to_path_buf());
+    } else {
+        files.push((path.to_path_buf(), metadata.len()));
+    }
+    Ok(())
+}
+
+/// Fails unless `path` is, or is inside, one of the `generated` paths, so
+/// that scripts and shortcuts can never be removed.
+fn check_removable(path: &Path, generated: &[PathBuf]) -> Result<(), String> {
+    let is_shortcuts = path.file_name().is_some_and(|name| name == SHORTCUTS_FILE);
+    if is_shortcuts || !generated.iter().any(|prefix| path.starts_with(prefix)) {
+        return Err(format!(
+            "Refusing to remove {}: it is not data zirv generated",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// `bytes` in the largest unit that keeps the number at least 1, e.g.
+/// `3.4 KiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+    use tempfile::tempdir;
+
+    /// A `.zirv` directory with scripts, shortcuts, settings and some of
+    /// every kind of generated data.
+    fn populate(dir: &Path) {
+        create_dir_all(dir.join("db")).unwrap();
+        write(dir.join("build.yaml"), "name: Build\ncommands: []\n").unwrap();
+        write(dir.join("db").join("migrate.json"), "{}").unwrap();
+        write(dir.join(SHORTCUTS_FILE), "shortcuts:\n  b: build\n").unwrap();
+        write(dir.join("config.yaml"), "color: false\n").unwrap();
+        create_dir_all(dir.join(CACHE_DIR_NAME)).unwrap();
+        write(dir.join(CACHE_DIR_NAME).join("ab12.json"), "x".repeat(2048)).unwrap();
+        write(dir.join(VERSION_CHECK_FILE), "2.1.0").unwrap();
+        write(dir.join(HISTORY_FILE), "{}\n").unwrap();
+    }
+
+    fn user_files_remain(dir: &Path) {
+        for file in [
+            "build.yaml",
+            "db/migrate.json",
+            SHORTCUTS_FILE,
+            "config.yaml",
+        ] {
+            assert!(dir.join(file).is_file(), "{file} was removed");
+        }
+    }
+
+    #[test]
+    fn test_clean_selected_kinds() {
+        let root = tempdir().unwrap();
+        let dir = root.path().join(".zirv");
+        populate(&dir);
+
+        let mut output = Vec::new();
+        clean(
+            &mut output,
+            std::slice::from_ref(&dir),
+            &[Generated::Cache],
+            false,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains(&format!(
+            "Removed {} (2.0 KiB)",
+            dir.join(CACHE_DIR_NAME).display()
+        )));
+        assert!(output.contains(&format!(
+            "Removed {} (5 B)",
+            dir.join(VERSION_CHECK_FILE).display()
+        )));
+        assert!(output.ends_with("Freed 2.0 KiB.\n"), "{output}");
+
+        assert!(!dir.join(CACHE_DIR_NAME).exists());
+        assert!(!dir.join(VERSION_CHECK_FILE).exists());
+        assert!(dir.join(HISTORY_FILE).is_file());
+        user_files_remain(&dir);
+    }
+
+    #[test]
+    fn test_clean_all_and_dry_run() {
+        let root = tempdir().unwrap();
+        let dir = root.path().join(".zirv");
+        populate(&dir);
+
+        let mut output = Vec::new();
+        clean(
+            &mut output,
+            std::slice::from_ref(&dir),
+            &Generated::ALL,
+            true,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output
+                .lines()
+                .filter(|line| line.starts_with("Would remove"))
+                .count(),
+            3
+        );
+        assert!(output.ends_with("Would free 2.0 KiB.\n"), "{output}");
+        assert!(dir.join(CACHE_DIR_NAME).join("ab12.json").is_file());
+        assert!(dir.join(HISTORY_FILE).is_file());
+
+        let mut output = Vec::new();
+        clean(
+            &mut output,
+            std::slice::from_ref(&dir),
+            &Generated::ALL,
+            false,
+        )
+        .unwrap();
+        for kind in Generated::ALL {
+            for path in kind.paths(&dir) {
+                assert!(!path.exists(), "{} remains", path.display());
+            }
+        }
+        user_files_remain(&dir);
+
+        let mut output = Vec::new();
+        clean(
+            &mut output,
+            std::slice::from_ref(&dir),
+            &Generated::ALL,
+            false,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "Freed 0 B.\n");
+    }
+
+    #[test]
+    fn test_scripts_are_never_removable() {
+        let dir = Path::new("/project/.zirv");
+        let generated = Generated::Cache.paths(dir);
+        assert!(check_removable(&dir.join(CACHE_DIR_NAME).join("ab12.json"), &generated).is_ok());
+        assert!(check_removable(&dir.join("build.yaml"), &generated).is_err());
+        assert!(check_removable(&dir.join(SHORTCUTS_FILE), &generated).is_err());
+        assert!(
+            check_removable(&dir.join(CACHE_DIR_NAME).join(SHORTCUTS_FILE), &generated).is_err()
+        );
+        assert!(check_removable(Path::new("/project/.zirv.yaml"), &generated).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_not_followed() {
+        let root = tempdir().unwrap();
+        let dir = root.path().join(".zirv");
+        populate(&dir);
+        std::os::unix::fs::symlink(&dir, dir.join(CACHE_DIR_NAME).join("scripts")).unwrap();
+
+        clean(
+            &mut Vec::new(),
+            std::slice::from_ref(&dir),
+            &[Generated::Cache],
+            false,
+        )
+        .unwrap();
+        assert!(!dir.join(CACHE_DIR_NAME).exists());
+        user_files_remain(&dir);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+}
@@ -0,0 +1,88 @@
+use std::io::Write;
+
+use serde_yaml::Value;
+
+use crate::config::Origin;
+
+/// A setting as `zirv config` shows it.
+#[derive(Debug, PartialEq)]
+pub struct Setting {
+    pub name: &'static str,
+    /// The effective value; `Null` when the setting is off.
+    pub value: Value,
+    /// Where the value comes from, or `None` for the default.
+    pub origin: Option<Origin>,
+}
+
+/// Prints the effective settings as YAML, each with a comment naming where
+/// its value comes from. Lists are printed on one line, like `[a, b]`.
+pub fn show_config<W: Write>(
+    writer: &mut W,
+    settings: &[Setting],
+) -> Result<(), Box<dyn std::error::Error>> {
+    for setting in settings {
+        let value = match &setting.value {
+            Value::Sequence(items) => {
+                let items = items
+                    .iter()
+                    .map(serde_yaml::to_string)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let items: Vec<&str> = items.iter().map(|item| item.trim_end()).collect();
+                format!("[{}]", items.join(", "))
+            }
+            value => serde_yaml::to_string(value)?,
+        };
+        let origin = match (&setting.origin, &setting.value) {
+            (Some(origin), _) => origin.to_string(),
+            (None, Value::Null) => "not set".to_string(),
+            (None, _) => "default".to_string(),
+        };
+        writeln!(writer, "{}: {}  # {origin}", setting.name, value.trim_end())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_show_config() {
+        let settings = [
+            Setting {
+                name: "shell",
+                value: Value::from("bash"),
+                origin: Some(Origin::Project(PathBuf::from("/p/.zirv/config.yaml"))),
+            },
+            Setting {
+                name: "ui",
+                value: Value::from("plain"),
+                origin: Some(Origin::Env("ZIRV_UI")),
+            },
+            Setting {
+                name: "report",
+                value: Value::Null,
+                origin: None,
+            },
+            Setting {
+                name: "color",
+                value: Value::from(true),
+                origin: None,
+            },
+            Setting {
+                name: "path",
+                value: Value::from(vec!["/opt/scripts", "/p/tools"]),
+                origin: Some(Origin::Env("ZIRV_PATH")),
+            },
+        ];
+
+        let mut buffer = Vec::new();
+        show_config(&mut buffer, &settings).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            text,
+            "shell: bash  # /p/.zirv/config.yaml\nui: plain  # ZIRV_UI\nreport: null  # not set\ncolor: true  # default\npath: [/opt/scripts, /p/tools]  # ZIRV_PATH\n"
+        );
+    }
+}
@@ -1,88 +1,790 @@
-use dialoguer::{Confirm, Input};
+use dialoguer::{Confirm, Input, MultiSelect, Select};
 use std::fs;
-use std::path::PathBuf;
-
-use crate::utils::{SCRIPT_DIR_NAME, Shortcuts, home_dir};
-
-const DEFAULT_TEMPLATE: &str = r#"name: "Name"
-description: "Description"
-#params:
-#  - "commit_message"
-commands:
-#  - command: example
-#    description: Step description
-#    options:
-#      interactive: bool
-#      operating_system: linux|windows|macos
-#      proceed_on_failure: bool
-#      delay_ms: int
-#  - command: example2
-#    description: Step 2 description
-#    options:
-#      interactive: bool
-#      operating_system: linux|windows|macos
-#      proceed_on_failure: bool
-#      delay_ms: int
-"#;
-
-/// Interactively creates a new script file using dialogue.
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::BUILTIN_COMMANDS;
+use crate::catalog::{load_shortcuts, save_shortcuts, script_names};
+use crate::script_runner::{
+    command::Command, command_types::CommandTypes, operating_system::OperatingSystem,
+    options::Options, script::Script, secret::Secret,
+};
+use crate::utils::Shortcut;
+
+/// The example script new scripts start from, named `name`.
+pub fn template_script(name: &str) -> Script {
+    Script {
+        name: name.to_string(),
+        description: Some("Description".to_string()),
+        commands: vec![CommandTypes::Command(Command {
+            command: "echo \"Hello from zirv\"".to_string(),
+            description: Some("Step description".to_string()),
+            ..Default::default()
+        })],
+        ..Default::default()
+    }
+}
+
+/// The file format of a new script.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ScriptFormat {
+    #[default]
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl ScriptFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScriptFormat::Yaml => "yaml",
+            ScriptFormat::Json => "json",
+            ScriptFormat::Toml => "toml",
+        }
+    }
+
+    /// Renders `script` in this format.
+    pub fn serialize(&self, script: &Script) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(match self {
+            ScriptFormat::Yaml => serde_yaml::to_string(script)?,
+            ScriptFormat::Json => serde_json::to_string_pretty(script)? + "\n",
+            ScriptFormat::Toml => toml::to_string(script)?,
+        })
+    }
+
+    /// The starting content of the new script `name`, see [`template_script`].
+    pub fn template(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        self.serialize(&template_script(name))
+    }
+}
+
+/// Checks that `name` can be used for a new script: a plain file name that
+/// is not shadowed by a built-in command.
+pub(crate) fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Script name must not be empty".to_string());
+    }
+    if name.contains(['/', '\\']) {
+        return Err(format!(
+            "Script name '{name}' must not contain path separators"
+        ));
+    }
+    if BUILTIN_COMMANDS.contains(&name) {
+        return Err(format!(
+            "'{name}' is a built-in command and cannot be a script name"
+        ));
+    }
+    Ok(())
+}
+
+/// Creates the script `name` from the template in `dir` without prompting,
+/// optionally adding the shortcut `shortcut` for it.
 ///
-/// This command will ask the user for:
-///  - The script name (the file will be named `<name>.yaml`)
-///  - An optional shortcut key (if provided, the shortcut is appended to the .shortcuts.yaml file)
-///  - Whether the file should be created in the global folder (home directory) or in the current directory
-pub fn create_script_interactive() -> Result<(), Box<dyn std::error::Error>> {
-    let name: String = Input::new()
-        .with_prompt("Enter the name for the new script")
-        .interact_text()?;
-
-    let shortcut: String = Input::new()
-        .with_prompt("Enter a shortcut key (optional, leave empty if none)")
-        .allow_empty(true)
-        .interact_text()?;
-
-    let global: bool = Confirm::new()
-        .with_prompt("Create the script in the global .zirv folder (in your home directory)?")
-        .default(false)
-        .interact()?;
-
-    let target_dir: PathBuf = if global {
-        home_dir()?.join(SCRIPT_DIR_NAME)
-    } else {
-        std::env::current_dir()?.join(SCRIPT_DIR_NAME)
-    };
+/// An existing script of the same name, in any format, is only replaced
+/// with `force`. The same goes for a shortcut key already pointing
+/// elsewhere. Returns the path of the new script.
+pub fn create_script<W: Write>(
+    writer: &mut W,
+    dir: &Path,
+    name: &str,
+    shortcut: Option<&str>,
+    format: ScriptFormat,
+    force: bool,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let content = format.template(name)?;
+    save_script(writer, dir, name, shortcut, format, force, &content)
+}
 
-    if !target_dir.exists() {
-        fs::create_dir_all(&target_dir)?;
-        println!("Created directory: {target_dir:?}");
-    } else {
-        println!("Directory already exists: {target_dir:?}");
-    }
-
-    let file_name = format!("{name}.yaml");
-    let script_path = target_dir.join(&file_name);
-    if script_path.exists() {
-        println!("Script file already exists: {script_path:?}");
-    } else {
-        fs::write(&script_path, DEFAULT_TEMPLATE)?;
-        println!("Created script file: {script_path:?}");
-    }
-
-    if !shortcut.trim().is_empty() {
-        let shortcuts_path = target_dir.join(".shortcuts.yaml");
-        let mut shortcuts: Shortcuts = if shortcuts_path.exists() {
-            let content = fs::read_to_string(&shortcuts_path)?;
-            serde_yaml::from_str(&content).unwrap_or_default()
-        } else {
-            Shortcuts::default()
-        };
+/// Same-named scripts in `dir`, in any format.
+fn existing_scripts(dir: &Path, name: &str) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    Ok(script_names(dir)?
+        .into_iter()
+        .filter(|(n, _)| n == name)
+        .map(|(_, path)| path)
+        .collect())
+}
+
+/// Writes `content` to `dir` as the script `name`; see [`create_script`].
+fn save_script<W: Write>(
+    writer: &mut W,
+    dir: &Path,
+    name: &str,
+    shortcut: Option<&str>,
+    format: ScriptFormat,
+    force: bool,
+    content: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    validate_name(name)?;
+    if let Some(key) = shortcut
+        && BUILTIN_COMMANDS.contains(&key)
+    {
+        return Err(format!("'{key}' is a built-in command and cannot be a shortcut").into());
+    }
+
+    let existing = existing_scripts(dir, name)?;
+    if let Some(path) = existing.first()
+        && !force
+    {
+        return Err(format!(
+            "{} already exists; use --force to overwrite it",
+            path.display()
+        )
+        .into());
+    }
+
+    let file_name = format!("{name}.{}", format.extension());
+    let mut shortcuts = load_shortcuts(dir)?;
+    if let Some(key) = shortcut
+        && let Some(other) = shortcuts.shortcuts.get(key)
+        && other.script() != name
+        && other.script() != file_name
+        && !force
+    {
+        return Err(format!(
+            "Shortcut '{key}' already points at '{other}'; use --force to replace it"
+        )
+        .into());
+    }
+
+    fs::create_dir_all(dir)?;
+    // A same-named script in another format would keep shadowing the new one.
+    for path in &existing {
+        fs::remove_file(path)?;
+    }
+    let script_path = dir.join(&file_name);
+    fs::write(&script_path, content)?;
+    writeln!(writer, "Created script file: {}", script_path.display())?;
+
+    if let Some(key) = shortcut {
         shortcuts
             .shortcuts
-            .insert(shortcut.clone(), file_name.clone());
-        let yaml_string = serde_yaml::to_string(&shortcuts)?;
-        fs::write(&shortcuts_path, yaml_string)?;
-        println!("Updated shortcuts file: {shortcuts_path:?}");
+            .insert(key.to_string(), Shortcut::from(file_name.as_str()));
+        save_shortcuts(dir, &shortcuts)?;
+        writeln!(writer, "Added shortcut '{key}' -> {file_name}")?;
+    }
+    Ok(script_path)
+}
+
+/// The questions the `create` wizard asks. [`DialoguerPrompter`] asks them
+/// in the terminal; tests answer them from a list.
+pub trait Prompter {
+    fn text(
+        &mut self,
+        prompt: &str,
+        allow_empty: bool,
+    ) -> Result<String, Box<dyn std::error::Error>>;
+    fn confirm(&mut self, prompt: &str, default: bool) -> Result<bool, Box<dyn std::error::Error>>;
+    /// Returns the index of the chosen item; the first one is the default.
+    fn select(&mut self, prompt: &str, items: &[&str])
+    -> Result<usize, Box<dyn std::error::Error>>;
+    /// Returns the indices of the chosen items, in order; none is chosen by
+    /// default.
+    fn multi_select(
+        &mut self,
+        prompt: &str,
+        items: &[String],
+    ) -> Result<Vec<usize>, Box<dyn std::error::Error>>;
+}
+
+pub struct DialoguerPrompter;
+
+impl Prompter for DialoguerPrompter {
+    fn text(
+        &mut self,
+        prompt: &str,
+        allow_empty: bool,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(Input::new()
+            .with_prompt(prompt)
+            .allow_empty(allow_empty)
+            .interact_text()?)
+    }
+
+    fn confirm(&mut self, prompt: &str, default: bool) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(Confirm::new()
+            .with_prompt(prompt)
+            .default(default)
+            .interact()?)
+    }
+
+    fn select(
+        &mut self,
+        prompt: &str,
+        items: &[&str],
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        Ok(Select::new()
+            .with_prompt(prompt)
+            .items(items)
+            .default(0)
+            .interact()?)
+    }
+
+    fn multi_select(
+        &mut self,
+        prompt: &str,
+        items: &[String],
+    ) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+        Ok(MultiSelect::new()
+            .with_prompt(prompt)
+            .items(items)
+            .interact()?)
+    }
+}
+
+/// Splits a comma-separated answer into its non-empty parts.
+fn split_list(answer: &str) -> Vec<String> {
+    answer
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Asks for the steps of a new script until an empty command is entered.
+fn prompt_steps<W: Write>(
+    writer: &mut W,
+    prompter: &mut impl Prompter,
+) -> Result<Vec<CommandTypes>, Box<dyn std::error::Error>> {
+    let mut steps = Vec::new();
+    loop {
+        let command = prompter.text(
+            &format!(
+                "Command for step {} (leave empty to finish)",
+                steps.len() + 1
+            ),
+            true,
+        )?;
+        if command.trim().is_empty() {
+            break;
+        }
+        let description = prompter.text("Step description (optional)", true)?;
+        let proceed_on_failure = prompter.confirm("Continue if this step fails?", false)?;
+        let interactive =
+            prompter.confirm("Does this step need the terminal (interactive)?", false)?;
+        let operating_system = match prompter.select(
+            "Run this step on",
+            &["any operating system", "linux", "windows", "macos"],
+        )? {
+            1 => Some(OperatingSystem::Linux),
+            2 => Some(OperatingSystem::Windows),
+            3 => Some(OperatingSystem::MacOS),
+            _ => None,
+        };
+
+        let options = Options {
+            proceed_on_failure: proceed_on_failure.then_some(true),
+            interactive: interactive.then_some(true),
+            operating_system,
+            ..Default::default()
+        };
+        let has_options = proceed_on_failure || interactive || options.operating_system.is_some();
+        steps.push(CommandTypes::Command(Command {
+            command: command.trim().to_string(),
+            description: (!description.trim().is_empty()).then(|| description.trim().to_string()),
+            options: has_options.then_some(options),
+            ..Default::default()
+        }));
+        writeln!(writer, "Added step {}.", steps.len())?;
     }
+    Ok(steps)
+}
+
+/// Lets the user pick steps from `history`, keeping their order.
+fn pick_history_steps(
+    prompter: &mut impl Prompter,
+    history: &[String],
+) -> Result<Vec<CommandTypes>, Box<dyn std::error::Error>> {
+    let chosen = prompter.multi_select(
+        "Select the commands to include (space to select, enter to confirm)",
+        history,
+    )?;
+    if chosen.is_empty() {
+        return Err("No commands selected".into());
+    }
+    Ok(chosen
+        .into_iter()
+        .map(|i| {
+            CommandTypes::Command(Command {
+                command: history[i].clone(),
+                description: Some(String::new()),
+                ..Default::default()
+            })
+        })
+        .collect())
+}
+
+/// Walks the user through a new script: its name, params, secrets, steps,
+/// file format and shortcut, then writes it to `local_dir` or, if chosen, `global_dir`.
+///
+/// Params and secrets are asked for first so the step prompts can list the
+/// `${...}` placeholders they provide. With `history`, the steps are picked
+/// from those commands instead. Returns the path of the new script, or
+/// `None` if the user declined to replace an existing one.
+pub fn create_script_with<W: Write>(
+    writer: &mut W,
+    prompter: &mut impl Prompter,
+    local_dir: &Path,
+    global_dir: Option<&Path>,
+    history: Option<&[String]>,
+) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+    let name = prompter.text("Enter the name for the new script", false)?;
+    let name = name.trim();
+    validate_name(name)?;
+
+    let dir = match global_dir {
+        Some(global_dir)
+            if prompter.confirm(
+                "Create the script in the global .zirv folder (in your home directory)?",
+                false,
+            )? =>
+        {
+            global_dir
+        }
+        _ => local_dir,
+    };
+    let force = match existing_scripts(dir, name)?.first() {
+        Some(path) => {
+            if !prompter.confirm(
+                &format!("{} already exists. Overwrite it?", path.display()),
+                false,
+            )? {
+                writeln!(writer, "Nothing created.")?;
+                return Ok(None);
+            }
+            true
+        }
+        None => false,
+    };
+
+    let description = prompter.text("Description (optional)", true)?;
+    let params = split_list(&prompter.text("Parameters, comma-separated (optional)", true)?);
+    let mut secrets = Vec::new();
+    for secret in split_list(&prompter.text("Secrets, comma-separated (optional)", true)?) {
+        let default_var = secret.to_uppercase();
+        let env_var = prompter.text(
+            &format!("Environment variable holding '{secret}' (default {default_var})"),
+            true,
+        )?;
+        secrets.push(Secret {
+            name: secret,
+            env_var: match env_var.trim() {
+                "" => default_var,
+                var => var.to_string(),
+            },
+        });
+    }
+
+    let placeholders: Vec<String> = params
+        .iter()
+        .chain(secrets.iter().map(|s| &s.name))
+        .map(|name| format!("${{{name}}}"))
+        .collect();
+    if !placeholders.is_empty() {
+        writeln!(writer, "Steps can use {}.", placeholders.join(", "))?;
+    }
+    let commands = match history {
+        Some(history) => pick_history_steps(prompter, history)?,
+        None => prompt_steps(writer, prompter)?,
+    };
 
+    let formats = [ScriptFormat::Yaml, ScriptFormat::Json, ScriptFormat::Toml];
+    let format = formats[prompter.select("File format", &formats.map(|f| f.extension()))?];
+    let shortcut = prompter.text("Enter a shortcut key (optional, leave empty if none)", true)?;
+    let shortcut = Some(shortcut.trim()).filter(|key| !key.is_empty());
+
+    let script = Script {
+        name: name.to_string(),
+        description: (!description.trim().is_empty()).then(|| description.trim().to_string()),
+        params: (!params.is_empty()).then_some(params),
+        secrets: (!secrets.is_empty()).then_some(secrets),
+        commands,
+        ..Default::default()
+    };
+    let content = format.serialize(&script)?;
+    save_script(writer, dir, name, shortcut, format, force, &content).map(Some)
+}
+
+/// Interactively creates a new script in the terminal; see
+/// [`create_script_with`].
+pub fn create_script_interactive(
+    local_dir: &Path,
+    global_dir: Option<&Path>,
+    history: Option<&[String]>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    create_script_with(
+        &mut std::io::stdout(),
+        &mut DialoguerPrompter,
+        local_dir,
+        global_dir,
+        history,
+    )?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::file_to_script;
+    use std::collections::VecDeque;
+    use std::fs::{read_to_string, write};
+    use tempfile::tempdir;
+
+    enum Answer {
+        Text(&'static str),
+        Confirm(bool),
+        Select(usize),
+        MultiSelect(&'static [usize]),
+    }
+
+    /// Answers the wizard's questions in order, failing on any mismatch.
+    struct Scripted(VecDeque<Answer>);
+
+    impl Prompter for Scripted {
+        fn text(&mut self, prompt: &str, _: bool) -> Result<String, Box<dyn std::error::Error>> {
+            match self.0.pop_front() {
+                Some(Answer::Text(text)) => Ok(text.to_string()),
+                _ => panic!("unexpected text prompt: {prompt}"),
+            }
+        }
+
+        fn confirm(&mut self, prompt: &str, _: bool) -> Result<bool, Box<dyn std::error::Error>> {
+            match self.0.pop_front() {
+                Some(Answer::Confirm(yes)) => Ok(yes),
+                _ => panic!("unexpected confirm prompt: {prompt}"),
+            }
+        }
+
+        fn select(
+            &mut self,
+            prompt: &str,
+            _: &[&str],
+        ) -> Result<usize, Box<dyn std::error::Error>> {
+            match self.0.pop_front() {
+                Some(Answer::Select(index)) => Ok(index),
+                _ => panic!("unexpected select prompt: {prompt}"),
+            }
+        }
+
+        fn multi_select(
+            &mut self,
+            prompt: &str,
+            _: &[String],
+        ) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+            match self.0.pop_front() {
+                Some(Answer::MultiSelect(indices)) => Ok(indices.to_vec()),
+                _ => panic!("unexpected multi-select prompt: {prompt}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_wizard_builds_steps() {
+        use Answer::*;
+        let dir = tempdir().unwrap();
+        let local = dir.path().join("project");
+        let global = dir.path().join("home");
+        let mut prompter = Scripted(VecDeque::from([
+            Text("deploy"),
+            Confirm(false),
+            Text("Deploy the app"),
+            Text("env, version"),
+            Text("token"),
+            Text(""),
+            Text("cargo build --release"),
+            Text("Build"),
+            Confirm(false),
+            Confirm(false),
+            Select(0),
+            Text("./deploy.sh ${env} ${version} ${token}"),
+            Text(""),
+            Confirm(true),
+            Confirm(true),
+            Select(1),
+            Text(""),
+            Select(0),
+            Text("d"),
+        ]));
+
+        let mut buffer = Vec::new();
+        let path = create_script_with(&mut buffer, &mut prompter, &local, Some(&global), None)
+            .unwrap()
+            .unwrap();
+        assert!(prompter.0.is_empty());
+        assert_eq!(path, local.join("deploy.yaml"));
+        assert!(
+            String::from_utf8(buffer)
+                .unwrap()
+                .starts_with("Steps can use ${env}, ${version}, ${token}.\nAdded step 1.\n")
+        );
+
+        let script = file_to_script(&path).unwrap();
+        assert_eq!(script.name, "deploy");
+        assert_eq!(script.description.as_deref(), Some("Deploy the app"));
+        assert_eq!(script.params, Some(vec!["env".into(), "version".into()]));
+        let secrets = script.secrets.unwrap();
+        assert_eq!(
+            (secrets[0].name.as_str(), secrets[0].env_var.as_str()),
+            ("token", "TOKEN")
+        );
+        let steps: Vec<&Command> = script
+            .commands
+            .iter()
+            .map(|step| match step {
+                CommandTypes::Command(command) => command,
+                CommandTypes::Commands(_) => panic!("unexpected group"),
+            })
+            .collect();
+        assert_eq!(steps[0].command, "cargo build --release");
+        assert_eq!(steps[0].description.as_deref(), Some("Build"));
+        assert!(steps[0].options.is_none());
+        let options = steps[1].options.as_ref().unwrap();
+        assert!(options.proceed_on_failure() && options.interactive());
+        assert_eq!(options.operating_system, Some(OperatingSystem::Linux));
+        assert_eq!(
+            read_to_string(local.join(".shortcuts.yaml")).unwrap(),
+            "shortcuts:\n  d: deploy.yaml\n"
+        );
+    }
+
+    #[test]
+    fn test_wizard_formats() {
+        let dir = tempdir().unwrap();
+        for (index, ext) in ["yaml", "json", "toml"].into_iter().enumerate() {
+            let mut prompter = Scripted(VecDeque::from([
+                Answer::Text(ext),
+                Answer::Text("Checks"),
+                Answer::Text("target"),
+                Answer::Text(""),
+                Answer::Text("cargo check --target ${target}"),
+                Answer::Text(""),
+                Answer::Confirm(true),
+                Answer::Confirm(false),
+                Answer::Select(0),
+                Answer::Text(""),
+                Answer::Select(index),
+                Answer::Text(""),
+            ]));
+
+            let path = create_script_with(&mut Vec::new(), &mut prompter, dir.path(), None, None)
+                .unwrap()
+                .unwrap();
+            assert_eq!(path, dir.path().join(format!("{ext}.{ext}")));
+            let script = file_to_script(&path).unwrap();
+            assert_eq!(script.params, Some(vec!["target".to_string()]));
+            assert_eq!(script.commands[0].label(), "cargo check --target ${target}");
+        }
+    }
+
+    #[test]
+    fn test_wizard_from_history() {
+        let dir = tempdir().unwrap();
+        let history = ["cargo fmt", "cargo build", "cargo test"].map(String::from);
+        let mut prompter = Scripted(VecDeque::from([
+            Answer::Text("check"),
+            Answer::Text(""),
+            Answer::Text(""),
+            Answer::Text(""),
+            Answer::MultiSelect(&[0, 2]),
+            Answer::Select(0),
+            Answer::Text(""),
+        ]));
+
+        let path = create_script_with(
+            &mut Vec::new(),
+            &mut prompter,
+            dir.path(),
+            None,
+            Some(&history),
+        )
+        .unwrap()
+        .unwrap();
+        let script = file_to_script(&path).unwrap();
+        let labels: Vec<String> = script.commands.iter().map(|step| step.label()).collect();
+        assert_eq!(labels, ["cargo fmt", "cargo test"]);
+        assert!(
+            read_to_string(&path)
+                .unwrap()
+                .contains("- command: cargo fmt\n  description: ''\n")
+        );
+    }
+
+    #[test]
+    fn test_wizard_keeps_existing_script() {
+        let dir = tempdir().unwrap();
+        write(dir.path().join("deploy.json"), "{}").unwrap();
+        let mut prompter = Scripted(VecDeque::from([
+            Answer::Text("deploy"),
+            Answer::Confirm(false),
+        ]));
+
+        let mut buffer = Vec::new();
+        let created =
+            create_script_with(&mut buffer, &mut prompter, dir.path(), None, None).unwrap();
+        assert_eq!(created, None);
+        assert_eq!(String::from_utf8(buffer).unwrap(), "Nothing created.\n");
+        assert_eq!(
+            read_to_string(dir.path().join("deploy.json")).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn test_create_each_format() {
+        let dir = tempdir().unwrap();
+        let scripts = dir.path().join(".zirv");
+
+        for format in [ScriptFormat::Yaml, ScriptFormat::Json, ScriptFormat::Toml] {
+            let name = format!("deploy-{}", format.extension());
+            let path =
+                create_script(&mut Vec::new(), &scripts, &name, None, format, false).unwrap();
+            assert_eq!(path, scripts.join(format!("{name}.{}", format.extension())));
+            let script = file_to_script(&path).unwrap();
+            assert_eq!(script.name, name);
+            assert_eq!(script.commands[0].label(), "echo \"Hello from zirv\"");
+        }
+        assert!(!scripts.join(".shortcuts.yaml").exists());
+    }
+
+    #[test]
+    fn test_create_with_shortcut_and_force() {
+        let dir = tempdir().unwrap();
+        let scripts = dir.path().join(".zirv");
+
+        let mut buffer = Vec::new();
+        create_script(
+            &mut buffer,
+            &scripts,
+            "deploy",
+            Some("d"),
+            ScriptFormat::Yaml,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            format!(
+                "Created script file: {}\nAdded shortcut 'd' -> deploy.yaml\n",
+                scripts.join("deploy.yaml").display()
+            )
+        );
+        assert_eq!(
+            read_to_string(scripts.join("deploy.yaml")).unwrap(),
+            ScriptFormat::Yaml.template("deploy").unwrap()
+        );
+        assert_eq!(
+            read_to_string(scripts.join(".shortcuts.yaml")).unwrap(),
+            "shortcuts:\n  d: deploy.yaml\n"
+        );
+
+        write(scripts.join("deploy.yaml"), "name: Mine\ncommands: []\n").unwrap();
+        let error = create_script(
+            &mut Vec::new(),
+            &scripts,
+            "deploy",
+            None,
+            ScriptFormat::Json,
+            false,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.ends_with("deploy.yaml already exists; use --force to overwrite it"));
+        assert_eq!(
+            read_to_string(scripts.join("deploy.yaml")).unwrap(),
+            "name: Mine\ncommands: []\n"
+        );
+
+        create_script(
+            &mut Vec::new(),
+            &scripts,
+            "deploy",
+            Some("d"),
+            ScriptFormat::Json,
+            true,
+        )
+        .unwrap();
+        assert!(!scripts.join("deploy.yaml").exists());
+        assert!(scripts.join("deploy.json").is_file());
+        assert_eq!(
+            read_to_string(scripts.join(".shortcuts.yaml")).unwrap(),
+            "shortcuts:\n  d: deploy.json\n"
+        );
+    }
+
+    #[test]
+    fn test_shortcut_taken_needs_force() {
+        let dir = tempdir().unwrap();
+        write(dir.path().join("build.yaml"), "name: Build\ncommands: []\n").unwrap();
+        write(
+            dir.path().join(".shortcuts.yaml"),
+            "shortcuts:\n  b: build\n",
+        )
+        .unwrap();
+
+        let error = create_script(
+            &mut Vec::new(),
+            dir.path(),
+            "deploy",
+            Some("b"),
+            ScriptFormat::Yaml,
+            false,
+        )
+        .unwrap_err()
+        .to_string();
+        assert_eq!(
+            error,
+            "Shortcut 'b' already points at 'build'; use --force to replace it"
+        );
+        assert!(!dir.path().join("deploy.yaml").exists());
+    }
+
+    #[test]
+    fn test_rejects_bad_names() {
+        let dir = tempdir().unwrap();
+        for (name, expected) in [
+            (
+                "db/migrate",
+                "Script name 'db/migrate' must not contain path separators",
+            ),
+            (
+                "db\\migrate",
+                "Script name 'db\\migrate' must not contain path separators",
+            ),
+            (
+                "list",
+                "'list' is a built-in command and cannot be a script name",
+            ),
+            ("", "Script name must not be empty"),
+        ] {
+            let error = create_script(
+                &mut Vec::new(),
+                dir.path(),
+                name,
+                None,
+                ScriptFormat::Yaml,
+                false,
+            )
+            .unwrap_err()
+            .to_string();
+            assert_eq!(error, expected);
+        }
+        let error = create_script(
+            &mut Vec::new(),
+            dir.path(),
+            "deploy",
+            Some("help"),
+            ScriptFormat::Yaml,
+            false,
+        )
+        .unwrap_err()
+        .to_string();
+        assert_eq!(
+            error,
+            "'help' is a built-in command and cannot be a shortcut"
+        );
+        assert!(std::fs::read_dir(dir.path()).unwrap().next().is_none());
+    }
+}
@@ -0,0 +1,133 @@
+use std::io::Write;
+
+use crate::{error::ZirvError, script_runner::Script};
+
+/// Makes sure a `dangerous` script is meant to run: unless `yes` is set,
+/// prints its `confirm_message` and asks the user, through `prompt`, to type
+/// `name`, the name the script is run by. Without a terminal to ask on
+/// (`interactive` false) the run is refused.
+pub fn confirm_dangerous<W, F>(
+    writer: &mut W,
+    script: &Script,
+    name: &str,
+    yes: bool,
+    interactive: bool,
+    prompt: F,
+) -> Result<(), ZirvError>
+where
+    W: Write,
+    F: FnOnce(&str) -> Result<String, Box<dyn std::error::Error>>,
+{
+    if !script.dangerous || yes {
+        return Ok(());
+    }
+    if !interactive {
+        return Err(format!(
+            "Script '{name}' is marked dangerous; pass --yes to run it without a terminal"
+        )
+        .into());
+    }
+
+    match &script.confirm_message {
+        Some(message) => writeln!(writer, "{}", message.trim_end())?,
+        None => writeln!(writer, "Script '{name}' is marked dangerous.")?,
+    }
+    let answer = prompt(&format!("Type '{name}' to run it"))?;
+    if answer.trim() != name {
+        return Err(format!("'{name}' was not confirmed; nothing was run").into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dangerous(confirm_message: Option<&str>) -> Script {
+        Script {
+            name: "Wipe production".to_string(),
+            dangerous: true,
+            confirm_message: confirm_message.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_typing_the_name_confirms() {
+        let mut buffer = Vec::new();
+        let mut asked = None;
+        confirm_dangerous(
+            &mut buffer,
+            &dangerous(Some("This drops the production database.\n")),
+            "wipe-prod",
+            false,
+            true,
+            |prompt| {
+                asked = Some(prompt.to_string());
+                Ok(" wipe-prod\n".to_string())
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "This drops the production database.\n"
+        );
+        assert_eq!(asked.as_deref(), Some("Type 'wipe-prod' to run it"));
+    }
+
+    #[test]
+    fn test_anything_else_rejects() {
+        let mut buffer = Vec::new();
+        let error = confirm_dangerous(
+            &mut buffer,
+            &dangerous(None),
+            "wipe-prod",
+            false,
+            true,
+            |_| Ok("Wipe production".to_string()),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "'wipe-prod' was not confirmed; nothing was run"
+        );
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "Script 'wipe-prod' is marked dangerous.\n"
+        );
+    }
+
+    #[test]
+    fn test_without_a_terminal_only_yes_runs() {
+        let refuse =
+            |_: &str| -> Result<String, Box<dyn std::error::Error>> { panic!("must not prompt") };
+        let mut buffer = Vec::new();
+        let error = confirm_dangerous(
+            &mut buffer,
+            &dangerous(None),
+            "wipe-prod",
+            false,
+            false,
+            refuse,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("pass --yes"), "{error}");
+        assert!(buffer.is_empty());
+
+        confirm_dangerous(
+            &mut buffer,
+            &dangerous(None),
+            "wipe-prod",
+            true,
+            false,
+            refuse,
+        )
+        .unwrap();
+        let safe = Script {
+            name: "build".to_string(),
+            ..Default::default()
+        };
+        confirm_dangerous(&mut buffer, &safe, "build", false, false, refuse).unwrap();
+        assert!(buffer.is_empty());
+    }
+}
@@ -0,0 +1,284 @@
+use std::{fs, io::Write, path::Path, process::Command};
+
+use super::{
+    create::{ScriptFormat, validate_name},
+    validate::script_problems,
+};
+use crate::{
+    error::ZirvError,
+    utils::{ScriptLocation, Vars},
+};
+
+/// The editor to open scripts with: `$VISUAL`, then `$EDITOR`, then the
+/// platform's default.
+pub fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| {
+            if cfg!(windows) {
+                "notepad".to_string()
+            } else {
+                "vi".to_string()
+            }
+        })
+}
+
+/// Opens the script `name` in `editor` and checks it once the editor exits,
+/// allowing placeholders for `vars`.
+///
+/// `resolved` is the result of looking the name up like a run would. When
+/// no script was found, `confirm` is asked whether to create `<name>.yaml`
+/// in `new_dir` from the default template first. Each part of a namespaced
+/// name must be a name `create` accepts.
+pub fn edit_script<W: Write>(
+    writer: &mut W,
+    name: &str,
+    resolved: Result<ScriptLocation, ZirvError>,
+    new_dir: &Path,
+    editor: &str,
+    vars: &Vars,
+    confirm: impl FnOnce(&str) -> Result<bool, Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let location = match resolved {
+        Ok(location) => location,
+        Err(e @ ZirvError::ScriptNotFound { .. }) => {
+            for part in name.split('/') {
+                if matches!(part, "." | "..") {
+                    return Err(format!("Script name '{name}' must not contain '{part}'").into());
+                }
+                validate_name(part)?;
+            }
+            let path = new_dir.join(format!("{name}.yaml"));
+            if !confirm(&format!(
+                "{e}. Create {} from the template?",
+                path.display()
+            ))? {
+                return Err(e.into());
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, ScriptFormat::Yaml.template(name)?)?;
+            writeln!(writer, "Created script file: {}", path.display())?;
+            ScriptLocation::File(path)
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    // A script in `.zirv.yaml` is edited in place, in that file.
+    run_editor(editor, location.file())?;
+
+    let (problems, warnings) = script_problems(&location, None, vars);
+    for warning in &warnings {
+        writeln!(writer, "Warning: {warning}")?;
+    }
+    if problems.is_empty() {
+        writeln!(writer, "{location} is valid.")?;
+        return Ok(());
+    }
+    writeln!(writer, "{location}:")?;
+    for problem in &problems {
+        writeln!(writer, "  - {problem}")?;
+    }
+    Err(format!(
+        "{location} has {} problem(s) and may not run",
+        problems.len()
+    )
+    .into())
+}
+
+/// Runs `editor` on `path` and waits for it. The editor may carry its own
+/// arguments, e.g. `code --wait`.
+fn run_editor(editor: &str, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().ok_or("No editor configured")?;
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .map_err(|e| format!("Failed to start editor '{editor}': {e}"))?;
+    if !status.success() {
+        return Err(format!("Editor '{editor}' exited with {status}").into());
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs::{read_to_string, write};
+    use tempfile::tempdir;
+
+    /// An editor command that appends `line` to the file it is given.
+    fn fake_editor(dir: &Path, line: &str) -> String {
+        let path = dir.join("fake-editor.sh");
+        write(&path, format!("echo '{line}' >> \"$1\"\n")).unwrap();
+        format!("sh {}", path.display())
+    }
+
+    fn edit(
+        name: &str,
+        resolved: Result<ScriptLocation, ZirvError>,
+        dir: &Path,
+        editor: &str,
+        answer: bool,
+    ) -> (String, Result<(), String>) {
+        let mut buffer = Vec::new();
+        let result = edit_script(
+            &mut buffer,
+            name,
+            resolved,
+            dir,
+            editor,
+            &Vars::new(),
+            |_| Ok(answer),
+        )
+        .map_err(|e| e.to_string());
+        (String::from_utf8(buffer).unwrap(), result)
+    }
+
+    #[test]
+    fn test_edit_existing_script() {
+        let dir = tempdir().unwrap();
+        let script = dir.path().join("build.yaml");
+        write(&script, "name: Build\ncommands: []\n").unwrap();
+        let editor = fake_editor(dir.path(), "description: Edited");
+
+        let (output, result) = edit(
+            "build",
+            Ok(ScriptLocation::File(script.clone())),
+            dir.path(),
+            &editor,
+            false,
+        );
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(output, format!("{} is valid.\n", script.display()));
+        assert!(
+            read_to_string(&script)
+                .unwrap()
+                .ends_with("description: Edited\n")
+        );
+    }
+
+    #[test]
+    fn test_problems_are_reported_after_editing() {
+        let dir = tempdir().unwrap();
+        let script = dir.path().join("build.yaml");
+        write(&script, "name: Build\ncommands:\n").unwrap();
+        let editor = fake_editor(dir.path(), "  - command: echo ${typo}");
+
+        let (output, result) = edit(
+            "build",
+            Ok(ScriptLocation::File(script.clone())),
+            dir.path(),
+            &editor,
+            false,
+        );
+
+        assert!(result.unwrap_err().contains("has 1 problem(s)"));
+        assert!(
+            output.contains("step 1: unknown variable '${typo}'"),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn test_missing_script_is_created_from_template() {
+        let dir = tempdir().unwrap();
+        let editor = fake_editor(dir.path(), "# edited");
+        let zirv_dir = dir.path().join(".zirv");
+
+        let (output, result) = edit(
+            "db/seed",
+            Err(ZirvError::not_found(
+                "db/seed",
+                "No script or shortcut found for 'db/seed'".to_string(),
+            )),
+            &zirv_dir,
+            &editor,
+            true,
+        );
+
+        let script = zirv_dir.join("db").join("seed.yaml");
+        assert_eq!(result, Ok(()), "{output}");
+        assert!(output.starts_with(&format!("Created script file: {}\n", script.display())));
+        assert_eq!(
+            read_to_string(&script).unwrap(),
+            format!(
+                "{}# edited\n",
+                ScriptFormat::Yaml.template("db/seed").unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_invalid_names_are_not_created() {
+        let dir = tempdir().unwrap();
+        let editor = fake_editor(dir.path(), "# edited");
+
+        for (name, expected) in [
+            (
+                "list",
+                "'list' is a built-in command and cannot be a script name",
+            ),
+            ("db//seed", "Script name must not be empty"),
+            ("../seed", "Script name '../seed' must not contain '..'"),
+        ] {
+            let (_, result) = edit(
+                name,
+                Err(ZirvError::not_found(name, format!("No script '{name}'"))),
+                dir.path(),
+                &editor,
+                true,
+            );
+            assert_eq!(result, Err(expected.to_string()));
+        }
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_declining_creation_keeps_the_error() {
+        let dir = tempdir().unwrap();
+        let editor = fake_editor(dir.path(), "# edited");
+
+        let (_, result) = edit(
+            "deploy",
+            Err(ZirvError::not_found(
+                "deploy",
+                "No script or shortcut found for 'deploy'".to_string(),
+            )),
+            dir.path(),
+            &editor,
+            false,
+        );
+
+        assert_eq!(
+            result,
+            Err("No script or shortcut found for 'deploy'".to_string())
+        );
+        assert!(!dir.path().join("deploy.yaml").exists());
+    }
+
+    #[test]
+    fn test_other_errors_are_not_offered_creation() {
+        let dir = tempdir().unwrap();
+        let editor = fake_editor(dir.path(), "# edited");
+
+        let (_, result) = edit(
+            "deploy",
+            Err("--local and --global cannot be used together".into()),
+            dir.path(),
+            &editor,
+            true,
+        );
+
+        assert_eq!(
+            result,
+            Err("--local and --global cannot be used together".to_string())
+        );
+        assert!(!dir.path().join("deploy.yaml").exists());
+    }
+}
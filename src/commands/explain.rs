@@ -0,0 +1,70 @@
+use std::io::Write;
+
+use crate::{
+    script_runner::{plan::plan, run_options::RunOptions, script::Script},
+    utils::Overlay,
+};
+
+/// Prints what running `script` with `params` would do, without running
+/// it: as text, or as JSON with `json`. With the `overlay` merged into
+/// `script`, every value it set is pointed out.
+pub fn explain_script<W: Write>(
+    writer: &mut W,
+    script: &Script,
+    overlay: Option<&Overlay>,
+    params: &[String],
+    options: &RunOptions,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut plan = plan(script, params, options)?;
+    if let Some(overlay) = overlay {
+        plan.annotate(overlay);
+    }
+    if json {
+        serde_json::to_writer_pretty(&mut *writer, &plan)?;
+        writeln!(writer)?;
+    } else {
+        plan.write_text(writer)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_as_json() {
+        let script: Script = serde_yaml::from_str(
+            "name: Greet\nparams: [who]\ncommands:\n  - command: echo hi ${who}\n",
+        )
+        .unwrap();
+
+        let mut buffer = Vec::new();
+        explain_script(
+            &mut buffer,
+            &script,
+            None,
+            &["there".to_string()],
+            &RunOptions::default(),
+            true,
+        )
+        .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["script"], "Greet");
+        assert_eq!(json["steps"][0]["command"], "echo hi there");
+        assert_eq!(json["steps"][0]["index"], 1);
+
+        let error = explain_script(
+            &mut buffer,
+            &script,
+            None,
+            &[],
+            &RunOptions::default(),
+            false,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.starts_with("missing params: who"), "{error}");
+    }
+}
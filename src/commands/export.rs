@@ -0,0 +1,680 @@
+use std::io::Write;
+
+use hashbrown::HashMap;
+
+use crate::script_runner::{
+    PROJECT_ROOT_VAR,
+    command::{Command, Piece, pieces},
+    command_types::CommandTypes,
+    operating_system::OperatingSystem,
+    script::Script,
+};
+
+/// The shell `export` writes a script for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// A POSIX shell script.
+    Sh,
+    /// A PowerShell script.
+    Ps1,
+}
+
+impl Default for ExportFormat {
+    fn default() -> Self {
+        if cfg!(windows) {
+            ExportFormat::Ps1
+        } else {
+            ExportFormat::Sh
+        }
+    }
+}
+
+/// PowerShell variables that must not be overwritten by script variables.
+/// PowerShell variable names are case-insensitive.
+const PS1_RESERVED: &[&str] = &[
+    "args", "env", "error", "false", "home", "host", "input", "matches", "null", "pid", "profile",
+    "pwd", "this", "true",
+];
+
+/// A shell variable name for the zirv variable `name`.
+fn variable(name: &str, format: ExportFormat) -> String {
+    let mut variable: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if variable.is_empty() || variable.starts_with(|c: char| c.is_ascii_digit()) {
+        variable.insert(0, '_');
+    }
+    if format == ExportFormat::Ps1 && PS1_RESERVED.contains(&variable.to_lowercase().as_str()) {
+        variable.push('_');
+    }
+    variable
+}
+
+/// Quotes `text` as a single-quoted string literal, which neither shell
+/// expands.
+fn quote(text: &str, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Sh => format!("'{}'", text.replace('\'', r"'\''")),
+        ExportFormat::Ps1 => format!("'{}'", text.replace('\'', "''")),
+    }
+}
+
+/// Writes the lines of `text` as comments.
+fn comment<W: Write>(writer: &mut W, text: &str) -> std::io::Result<()> {
+    for line in text.lines() {
+        writeln!(writer, "# {line}")?;
+    }
+    Ok(())
+}
+
+/// Indents every line of `text` by `indent`.
+fn indented(text: &str, indent: &str) -> String {
+    text.lines()
+        .map(|line| format!("{indent}{line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Writes `script` as a standalone `format` script that runs its steps
+/// without zirv.
+///
+/// Params become positional arguments and secrets required environment
+/// variables. A failing step stops the script unless it proceeds on
+/// failure, after its fallbacks ran. Steps for another operating system are
+/// wrapped in a check, or left out where the shell cannot run on it. What
+/// cannot be translated is pointed out in `Warning:` comments.
+pub fn export_script<W: Write>(
+    writer: &mut W,
+    script: &Script,
+    format: ExportFormat,
+) -> std::io::Result<()> {
+    let params = script.params.clone().unwrap_or_default();
+    let secrets = script.secrets.clone().unwrap_or_default();
+    let mut variables: HashMap<String, String> = HashMap::new();
+    for name in params
+        .iter()
+        .chain(secrets.iter().map(|s| &s.name))
+        .map(String::as_str)
+        .chain(script.commands.iter().flat_map(|step| step.variables()))
+        .chain([PROJECT_ROOT_VAR])
+    {
+        variables.insert(name.to_string(), variable(name, format));
+    }
+    let exporter = Exporter { format, variables };
+
+    exporter.header(writer, script)?;
+    for (i, name) in params.iter().enumerate() {
+        let var = &exporter.variables[name];
+        match format {
+            ExportFormat::Sh => writeln!(writer, "{var}=\"${}\"", i + 1)?,
+            ExportFormat::Ps1 => writeln!(writer, "${var} = $args[{i}]")?,
+        }
+    }
+    for secret in &secrets {
+        let var = &exporter.variables[&secret.name];
+        let env_var = &secret.env_var;
+        let message = quote(
+            &format!("Secret '{}' must be set in {env_var}", secret.name),
+            format,
+        );
+        match format {
+            ExportFormat::Sh => {
+                writeln!(writer, "if [ -z \"${{{env_var}+x}}\" ]; then")?;
+                writeln!(writer, "  echo {message} >&2")?;
+                writeln!(writer, "  exit 1")?;
+                writeln!(writer, "fi")?;
+                writeln!(writer, "{var}=\"${env_var}\"")?;
+            }
+            ExportFormat::Ps1 => {
+                writeln!(writer, "if ($null -eq $env:{env_var}) {{")?;
+                writeln!(writer, "    [Console]::Error.WriteLine({message})")?;
+                writeln!(writer, "    exit 1")?;
+                writeln!(writer, "}}")?;
+                writeln!(writer, "${var} = $env:{env_var}")?;
+            }
+        }
+    }
+    let uses_project_root = script
+        .commands
+        .iter()
+        .any(|step| step.placeholders().iter().any(|p| p == PROJECT_ROOT_VAR));
+    if uses_project_root {
+        let var = &exporter.variables[PROJECT_ROOT_VAR];
+        match format {
+            ExportFormat::Sh => writeln!(writer, "{var}=\"$PWD\"")?,
+            ExportFormat::Ps1 => writeln!(writer, "${var} = (Get-Location).Path")?,
+        }
+    }
+
+    let mut group = None;
+    for (i, step) in script.commands.iter().enumerate() {
+        writeln!(writer)?;
+        if step.group() != group {
+            group = step.group();
+            if let Some(name) = group
+                && let Some(when) = script.groups.get(name).and_then(|g| g.when.as_deref())
+            {
+                writeln!(
+                    writer,
+                    "# Warning: zirv only runs group '{name}' when `{when}`; the condition is not exported."
+                )?;
+            }
+        }
+        match step {
+            CommandTypes::Command(command) => exporter.command(writer, i + 1, command)?,
+            CommandTypes::Commands(commands) => exporter.group(writer, i + 1, commands)?,
+        }
+    }
+    Ok(())
+}
+
+struct Exporter {
+    format: ExportFormat,
+    /// Shell variable names by zirv variable name.
+    variables: HashMap<String, String>,
+}
+
+impl Exporter {
+    /// The shebang, a description of the script, the usage check and the
+    /// `fail` helper.
+    fn header<W: Write>(&self, writer: &mut W, script: &Script) -> std::io::Result<()> {
+        let params = script.params.as_deref().unwrap_or_default();
+        let usage: String = params.iter().map(|p| format!(" <{p}>")).collect();
+        match self.format {
+            ExportFormat::Sh => {
+                writeln!(writer, "#!/bin/sh")?;
+                writeln!(writer, "# {}, exported by zirv.", script.name)?;
+                if let Some(description) = &script.description {
+                    comment(writer, description)?;
+                }
+                writeln!(writer)?;
+                writeln!(writer, "fail() {{")?;
+                writeln!(writer, "  echo \"Step $1 failed\" >&2")?;
+                writeln!(writer, "  exit 1")?;
+                writeln!(writer, "}}")?;
+                writeln!(writer)?;
+                writeln!(writer, "if [ \"$#\" -ne {} ]; then", params.len())?;
+                writeln!(writer, "  echo \"Usage: $0{usage}\" >&2")?;
+                writeln!(writer, "  exit 1")?;
+                writeln!(writer, "fi")?;
+            }
+            ExportFormat::Ps1 => {
+                writeln!(writer, "# {}, exported by zirv.", script.name)?;
+                if let Some(description) = &script.description {
+                    comment(writer, description)?;
+                }
+                writeln!(writer)?;
+                writeln!(writer, "function Fail($Step) {{")?;
+                writeln!(
+                    writer,
+                    "    [Console]::Error.WriteLine(\"Step $Step failed\")"
+                )?;
+                writeln!(writer, "    exit 1")?;
+                writeln!(writer, "}}")?;
+                writeln!(writer)?;
+                writeln!(writer, "if ($args.Count -ne {}) {{", params.len())?;
+                writeln!(
+                    writer,
+                    "    [Console]::Error.WriteLine(\"Usage: $($MyInvocation.MyCommand.Name){usage}\")"
+                )?;
+                writeln!(writer, "    exit 1")?;
+                writeln!(writer, "}}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `command` with zirv's placeholders turned into shell variables,
+    /// double-quoted for `${name:q}`, and its escaped `$$` into `$`.
+    fn substituted(&self, command: &str) -> String {
+        pieces(command)
+            .into_iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => text.to_string(),
+                Piece::Placeholder(name) => match self.variables.get(name) {
+                    Some(variable) => format!("${{{variable}}}"),
+                    None => format!("${{{name}}}"),
+                },
+                // Both shells expand a variable in double quotes as a
+                // single argument.
+                Piece::Quoted(name) => match self.variables.get(name) {
+                    Some(variable) => format!("\"${{{variable}}}\""),
+                    None => format!("${{{name}:q}}"),
+                },
+            })
+            .collect()
+    }
+
+    /// The check that limits a step to `os`, or `None` if this shell never
+    /// runs there.
+    fn os_condition(&self, os: &OperatingSystem) -> Option<&'static str> {
+        match (self.format, os) {
+            (ExportFormat::Sh, OperatingSystem::Linux) => Some("[ \"$(uname -s)\" = Linux ]"),
+            (ExportFormat::Sh, OperatingSystem::MacOS) => Some("[ \"$(uname -s)\" = Darwin ]"),
+            (ExportFormat::Sh, OperatingSystem::Windows) => None,
+            (ExportFormat::Ps1, OperatingSystem::Linux) => Some("$IsLinux"),
+            (ExportFormat::Ps1, OperatingSystem::MacOS) => Some("$IsMacOS"),
+            (ExportFormat::Ps1, OperatingSystem::Windows) => Some("$env:OS -eq 'Windows_NT'"),
+        }
+    }
+
+    fn command<W: Write>(
+        &self,
+        writer: &mut W,
+        index: usize,
+        command: &Command,
+    ) -> std::io::Result<()> {
+        let options = command.options.clone().unwrap_or_default();
+        match &command.name {
+            Some(name) => writeln!(writer, "# Step {index}: {name}")?,
+            None => writeln!(writer, "# Step {index}")?,
+        }
+        if let Some(description) = &command.description {
+            comment(writer, description)?;
+        }
+        if options.interactive() {
+            writeln!(
+                writer,
+                "# Warning: zirv runs this step interactively; run the exported script from a terminal."
+            )?;
+        }
+        if options.export_context.unwrap_or(false) {
+            writeln!(
+                writer,
+                "# Warning: the context is not exported as environment variables here."
+            )?;
+        }
+        if let Some(when) = &command.when {
+            writeln!(
+                writer,
+                "# Warning: zirv only runs this step when `{when}`; the condition is not exported."
+            )?;
+        }
+        if let Some(export) = &command.export {
+            let names: Vec<&str> = export.values().map(String::as_str).collect();
+            writeln!(
+                writer,
+                "# Warning: zirv sets {} from the called script here; this is not exported.",
+                names.join(", ")
+            )?;
+        }
+
+        let body = self.step_body(index, command, &options);
+        let Some(os) = &options.operating_system else {
+            return writeln!(writer, "{body}");
+        };
+        let os_name = serde_yaml::to_string(os).unwrap_or_default();
+        let os_name = os_name.trim();
+        match self.os_condition(os) {
+            Some(condition) => {
+                writeln!(writer, "# Only runs on {os_name}.")?;
+                let indent = match self.format {
+                    ExportFormat::Sh => "  ",
+                    ExportFormat::Ps1 => "    ",
+                };
+                let body = indented(&body, indent);
+                match self.format {
+                    ExportFormat::Sh => writeln!(writer, "if {condition}; then\n{body}\nfi"),
+                    ExportFormat::Ps1 => writeln!(writer, "if ({condition}) {{\n{body}\n}}"),
+                }
+            }
+            None => {
+                writeln!(
+                    writer,
+                    "# Only runs on {os_name}, where this script does not; left out:"
+                )?;
+                comment(writer, &body)
+            }
+        }
+    }
+
+    /// Runs the step's command, then on failure its fallbacks, the retry and
+    /// either a warning or `fail`.
+    fn step_body(
+        &self,
+        index: usize,
+        command: &Command,
+        options: &crate::script_runner::options::Options,
+    ) -> String {
+        let text = self.substituted(&command.command);
+        // zirv does not let `cd` fail quietly, whatever the options say.
+        let is_cd = command.command.trim_start().starts_with("cd ");
+        let run = match (&command.capture, self.format) {
+            (Some(capture), ExportFormat::Sh) => {
+                format!("{}=$({text})", self.variables[capture])
+            }
+            (Some(capture), ExportFormat::Ps1) => {
+                format!("${} = {text}", self.variables[capture])
+            }
+            // A `cd` changes the directory of the steps after it.
+            (None, _) if is_cd => text,
+            // Like zirv's `sh -c`, keep `exit` and the like to the step.
+            (None, ExportFormat::Sh) if text.contains('\n') => {
+                format!("(\n{}\n)", indented(&text, "  "))
+            }
+            (None, ExportFormat::Sh) => format!("( {text} )"),
+            (None, ExportFormat::Ps1) if text.contains('\n') => {
+                format!("& {{\n{}\n}}", indented(&text, "    "))
+            }
+            (None, ExportFormat::Ps1) => format!("& {{ {text} }}"),
+        };
+        let trim_capture = match (&command.capture, self.format) {
+            (Some(capture), ExportFormat::Ps1) => {
+                let var = &self.variables[capture];
+                format!("\n${var} = (${var} | Out-String).Trim()")
+            }
+            _ => String::new(),
+        };
+        let fail = match self.format {
+            ExportFormat::Sh => format!("fail {index}"),
+            ExportFormat::Ps1 => format!("Fail {index}"),
+        };
+        let give_up = if options.proceed_on_failure() && !is_cd {
+            match self.format {
+                ExportFormat::Sh => {
+                    format!("echo \"Step {index} failed, continuing\" >&2")
+                }
+                ExportFormat::Ps1 => {
+                    format!("[Console]::Error.WriteLine(\"Step {index} failed, continuing\")")
+                }
+            }
+        } else {
+            fail.clone()
+        };
+
+        let mut on_failure: Vec<String> = Vec::new();
+        for fallback in options.fallback.iter().flatten() {
+            on_failure.push(self.checked(&fallback.command, &fail));
+        }
+        let fallbacks = !on_failure.is_empty();
+        if fallbacks && options.retry_after_fallback() {
+            on_failure.push(self.checked(&run, &give_up));
+        } else {
+            on_failure.push(give_up);
+        }
+
+        let mut body = match (self.format, fallbacks) {
+            (ExportFormat::Sh, false) => format!("{run} || {}", on_failure[0]),
+            (ExportFormat::Sh, true) => format!(
+                "{run} || {{\n{}\n}}",
+                indented(&on_failure.join("\n"), "  ")
+            ),
+            (ExportFormat::Ps1, _) => format!(
+                "{run}\nif (-not $?) {{\n{}\n}}",
+                indented(&on_failure.join("\n"), "    ")
+            ),
+        };
+        body.push_str(&trim_capture);
+        if let Some(delay) = options.delay_ms {
+            match self.format {
+                ExportFormat::Sh => body.push_str(&format!("\nsleep {}", delay as f64 / 1000.0)),
+                ExportFormat::Ps1 => body.push_str(&format!("\nStart-Sleep -Milliseconds {delay}")),
+            }
+        }
+        body
+    }
+
+    /// `command` followed by `on_failure` if it fails.
+    fn checked(&self, command: &str, on_failure: &str) -> String {
+        match self.format {
+            ExportFormat::Sh => format!("{command} || {on_failure}"),
+            ExportFormat::Ps1 => format!("{command}\nif (-not $?) {{ {on_failure} }}"),
+        }
+    }
+
+    /// A parallel group, which zirv opens in a new terminal window, started
+    /// in the background instead.
+    fn group<W: Write>(
+        &self,
+        writer: &mut W,
+        index: usize,
+        commands: &[Command],
+    ) -> std::io::Result<()> {
+        writeln!(writer, "# Step {index}")?;
+        writeln!(
+            writer,
+            "# Warning: zirv opens this group in a new terminal window; here it runs in the background."
+        )?;
+        let joined = commands
+            .iter()
+            .map(|c| self.substituted(&c.command))
+            .collect::<Vec<_>>()
+            .join(" && ");
+        match self.format {
+            ExportFormat::Sh => writeln!(writer, "( {joined} ) &"),
+            ExportFormat::Ps1 => writeln!(
+                writer,
+                "Start-Process powershell -ArgumentList '-NoExit', '-Command', {}",
+                quote(&joined, self.format)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use crate::script_runner::{execute, run_options::RunOptions};
+
+    fn export(script: &str, format: ExportFormat) -> String {
+        let script: Script = serde_yaml::from_str(script).unwrap();
+        let mut buffer = Vec::new();
+        export_script(&mut buffer, &script, format).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    /// Every file under `dir` with its content, sorted by path.
+    #[cfg(unix)]
+    fn files(dir: &std::path::Path) -> Vec<(String, String)> {
+        let mut found = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            if path.is_dir() {
+                for (inner, content) in files(&path) {
+                    found.push((format!("{name}/{inner}"), content));
+                }
+            } else {
+                found.push((name, std::fs::read_to_string(&path).unwrap()));
+            }
+        }
+        found.sort();
+        found
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exported_sh_matches_zirv_run() {
+        let other_os = if cfg!(target_os = "linux") {
+            "macos"
+        } else {
+            "linux"
+        };
+        let yaml = format!(
+            r#"
+name: Fixture
+params: [greeting]
+secrets:
+  - name: word
+    env_var: ZIRV_EXPORT_TEST_WORD
+commands:
+  - command: mkdir -p out
+  - command: cd out
+  - command: printf '%s' "${{greeting}}"
+    capture: text
+  - command: printf '%s %s' "${{text}}" "${{word}}" > hello.txt
+  - command: "false"
+    options:
+      proceed_on_failure: true
+      fallback:
+        - command: echo recovering
+  - command: touch elsewhere
+    options:
+      operating_system: {other_os}
+  - command: printf '%s %s' '$${{greeting}}' "$$((1 + 2))" > escaped.txt
+  - command: printf '%s|' ${{greeting:q}} "${{text}} ${{word}}" > quoted.txt
+  - command: touch done
+"#
+        );
+        unsafe {
+            std::env::set_var("ZIRV_EXPORT_TEST_WORD", "world");
+        }
+
+        let zirv_dir = tempfile::tempdir().unwrap();
+        let script: Script = serde_yaml::from_str(&yaml).unwrap();
+        let options = RunOptions {
+            no_summary: true,
+            project_root: Some(zirv_dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        execute(&script, &["hello".to_string()], &options)
+            .await
+            .unwrap();
+
+        let sh_dir = tempfile::tempdir().unwrap();
+        let exported = sh_dir.path().join("fixture.sh");
+        std::fs::write(&exported, export(&yaml, ExportFormat::Sh)).unwrap();
+        let status = std::process::Command::new("sh")
+            .arg(&exported)
+            .arg("hello")
+            .current_dir(sh_dir.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        std::fs::remove_file(&exported).unwrap();
+
+        let expected = files(zirv_dir.path());
+        assert!(expected.contains(&("out/hello.txt".to_string(), "hello world".to_string())));
+        assert!(expected.contains(&("out/done".to_string(), String::new())));
+        assert!(expected.contains(&("out/escaped.txt".to_string(), "${greeting} 3".to_string())));
+        assert!(expected.contains(&(
+            "out/quoted.txt".to_string(),
+            "hello|hello world|".to_string()
+        )));
+        assert_eq!(files(sh_dir.path()), expected);
+
+        // Missing arguments and secrets stop the exported script up front.
+        std::fs::write(&exported, export(&yaml, ExportFormat::Sh)).unwrap();
+        let output = std::process::Command::new("sh")
+            .arg(&exported)
+            .current_dir(sh_dir.path())
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Usage: "));
+        let output = std::process::Command::new("sh")
+            .arg(&exported)
+            .arg("hello")
+            .env_remove("ZIRV_EXPORT_TEST_WORD")
+            .current_dir(sh_dir.path())
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            "Secret 'word' must be set in ZIRV_EXPORT_TEST_WORD\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exported_sh_stops_on_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let exported = dir.path().join("fail.sh");
+        let yaml =
+            "name: Fail\ncommands:\n  - command: touch first; exit 3\n  - command: touch second\n";
+        std::fs::write(&exported, export(yaml, ExportFormat::Sh)).unwrap();
+
+        let output = std::process::Command::new("sh")
+            .arg(&exported)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "Step 1 failed\n");
+        assert!(dir.path().join("first").exists());
+        assert!(!dir.path().join("second").exists());
+    }
+
+    /// `exit` in a step ends the step, as under zirv, and not the exported
+    /// script, so its fallback and the steps after it still run.
+    #[cfg(unix)]
+    #[test]
+    fn test_exported_sh_keeps_exit_to_the_step() {
+        let dir = tempfile::tempdir().unwrap();
+        let exported = dir.path().join("setup.sh");
+        let yaml = r#"
+name: Setup
+commands:
+  - command: test -f configured || exit 1
+    options:
+      fallback:
+        - command: touch configured
+  - command: exit 0
+  - command: touch done
+"#;
+        std::fs::write(&exported, export(yaml, ExportFormat::Sh)).unwrap();
+
+        let output = std::process::Command::new("sh")
+            .arg(&exported)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        assert!(dir.path().join("configured").exists());
+        assert!(dir.path().join("done").exists());
+    }
+
+    #[test]
+    fn test_export_ps1() {
+        let yaml = r#"
+name: Deploy
+params: [env]
+commands:
+  - command: dotnet publish -c ${env}
+    capture: out
+    options:
+      fallback:
+        - command: dotnet restore
+      retry_after_fallback: true
+  - command: ./install.sh
+    options:
+      operating_system: linux
+  - - command: npm run watch
+    - command: npm run serve
+"#;
+        let exported = export(yaml, ExportFormat::Ps1);
+        assert!(exported.contains("$env_ = $args[0]\n"), "{exported}");
+        assert!(
+            exported.contains(
+                "$out = dotnet publish -c ${env_}\nif (-not $?) {\n    dotnet restore\n    if (-not $?) { Fail 1 }\n    $out = dotnet publish -c ${env_}\n    if (-not $?) { Fail 1 }\n}\n$out = ($out | Out-String).Trim()\n"
+            ),
+            "{exported}"
+        );
+        assert!(
+            exported.contains("if ($IsLinux) {\n    & { ./install.sh }\n"),
+            "{exported}"
+        );
+        assert!(
+            exported.contains("# Warning: zirv opens this group in a new terminal window"),
+            "{exported}"
+        );
+        assert!(
+            exported.contains("'-Command', 'npm run watch && npm run serve'"),
+            "{exported}"
+        );
+    }
+
+    #[test]
+    fn test_windows_steps_left_out_of_sh() {
+        let yaml = "name: Win\ncommands:\n  - command: dir\n    options:\n      operating_system: windows\n";
+        let exported = export(yaml, ExportFormat::Sh);
+        assert!(
+            exported.ends_with(
+                "# Step 1\n# Only runs on windows, where this script does not; left out:\n# ( dir ) || fail 1\n"
+            ),
+            "{exported}"
+        );
+    }
+}
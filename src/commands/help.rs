@@ -1,103 +1,266 @@
-use std::{fs, io::Write, path::Path, path::PathBuf};
-
-use crate::utils::{
-    SCRIPT_DIR_NAME, SUPPORTED_EXTENSIONS, Shortcuts, home_dir, parse_script_content,
-};
-
-fn write_scripts<W: Write>(writer: &mut W, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    for entry in fs::read_dir(dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_file()
-            && let Some(ext) = path.extension().and_then(|s| s.to_str())
-            && SUPPORTED_EXTENSIONS.contains(&ext)
-            && path.file_name().unwrap() != ".shortcuts.yaml"
-        {
-            let content = fs::read_to_string(&path)?;
-            let script = parse_script_content(&content, ext)?;
-
-            let file_name = path.file_name().unwrap().to_string_lossy();
-            writeln!(writer, "-------------------------------------------------")?;
-            writeln!(writer, "File: {file_name}")?;
-            writeln!(writer, "  Name: {}", script.name)?;
-            if let Some(desc) = script.description {
-                writeln!(writer, "  Description: {desc}")?;
-            }
-            if let Some(params) = &script.params {
-                writeln!(writer, "  Required Parameters:")?;
-                for param in params {
-                    writeln!(writer, "    {param}")?;
+use std::{io::Write, path::PathBuf};
+
+use crate::catalog::{CatalogScript, effective_scripts, effective_shortcuts};
+use crate::script_runner::{command::Command, command_types::CommandTypes, script::Script};
+use crate::utils::{ScriptLocation, ScriptSource, ZIRV_PATH_VAR, scripts_file};
+
+/// Renders one script. The listing shows its name, description and
+/// parameters; `detailed` adds secrets and every step with its options.
+fn write_script<W: Write>(
+    writer: &mut W,
+    file: &str,
+    script: &Script,
+    detailed: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    writeln!(writer, "-------------------------------------------------")?;
+    writeln!(writer, "File: {file}")?;
+    writeln!(writer, "  Name: {}", script.name)?;
+    if !script.aliases.is_empty() {
+        writeln!(writer, "  Aliases: {}", script.aliases.join(", "))?;
+    }
+    if let Some(desc) = &script.description {
+        writeln!(writer, "  Description: {desc}")?;
+    }
+    if let Some(params) = &script.params {
+        writeln!(writer, "  Required Parameters:")?;
+        for param in params {
+            writeln!(writer, "    {param}")?;
+        }
+    }
+    if !detailed {
+        return Ok(());
+    }
+
+    if let Some(secrets) = script.secrets.as_ref().filter(|s| !s.is_empty()) {
+        writeln!(writer, "  Secrets:")?;
+        for secret in secrets {
+            writeln!(writer, "    {} (from ${})", secret.name, secret.env_var)?;
+        }
+    }
+    writeln!(writer, "  Commands:")?;
+    for (i, step) in script.commands.iter().enumerate() {
+        match step {
+            CommandTypes::Command(cmd) => write_command(writer, &format!("{}.", i + 1), cmd)?,
+            CommandTypes::Commands(cmds) => {
+                writeln!(writer, "    {}. In parallel terminals:", i + 1)?;
+                for cmd in cmds {
+                    write_command(writer, "  -", cmd)?;
                 }
             }
         }
     }
-
     Ok(())
 }
 
-fn write_shortcuts<W: Write>(writer: &mut W, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    let shortcuts_path = dir.join(".shortcuts.yaml");
-    if shortcuts_path.exists() {
-        let content = fs::read_to_string(shortcuts_path)?;
-        let shortcuts: Shortcuts = serde_yaml::from_str(&content)?;
-        for (key, value) in shortcuts.shortcuts {
-            writeln!(writer, "  {key} -> {value}")?;
+fn write_command<W: Write>(
+    writer: &mut W,
+    marker: &str,
+    cmd: &Command,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let indent = " ".repeat(marker.len() + 5);
+    match &cmd.name {
+        Some(name) => writeln!(writer, "    {marker} [{name}] {}", cmd.command)?,
+        None => writeln!(writer, "    {marker} {}", cmd.command)?,
+    }
+    if let Some(description) = &cmd.description {
+        writeln!(writer, "{indent}Description: {description}")?;
+    }
+    if let Some(var) = &cmd.capture {
+        writeln!(writer, "{indent}Captures: ${{{var}}}")?;
+    }
+    if let Some(tags) = cmd.tags.as_ref().filter(|t| !t.is_empty()) {
+        writeln!(writer, "{indent}Tags: {}", tags.join(", "))?;
+    }
+
+    let Some(options) = &cmd.options else {
+        return Ok(());
+    };
+    let mut flags = Vec::new();
+    if let Some(os) = &options.operating_system {
+        flags.push(format!("only on {}", format!("{os:?}").to_lowercase()));
+    }
+    if options.interactive() {
+        flags.push("interactive".to_string());
+    }
+    if options.proceed_on_failure() {
+        flags.push("proceeds on failure".to_string());
+    }
+    if let Some(delay) = options.delay_ms {
+        flags.push(format!("{delay}ms delay"));
+    }
+    if !flags.is_empty() {
+        writeln!(writer, "{indent}Options: {}", flags.join(", "))?;
+    }
+    if let Some(fallback) = options.fallback.as_ref().filter(|f| !f.is_empty()) {
+        let retry = if options.retry_after_fallback() {
+            "then retries"
+        } else {
+            "no retry"
+        };
+        writeln!(writer, "{indent}Fallback ({retry}):")?;
+        for cmd in fallback {
+            writeln!(writer, "{indent}  - {}", cmd.command)?;
         }
     }
     Ok(())
 }
 
-pub fn show_help<W: Write>(writer: &mut W) -> Result<(), Box<dyn std::error::Error>> {
-    let base_dir = PathBuf::from(SCRIPT_DIR_NAME);
-
-    if base_dir.exists() {
-        writeln!(writer, "\nAvailable Scripts:")?;
-        write_scripts(writer, &base_dir)?;
-
-        let shortcuts_path = base_dir.join(".shortcuts.yaml");
-        if shortcuts_path.exists() {
-            writeln!(writer, "\nAvailable Shortcuts:")?;
-            write_shortcuts(writer, &base_dir)?;
-            writeln!(writer, "  i -> init")?;
-            writeln!(writer, "  c -> create")?;
-            writeln!(writer, "  v -> version")?;
-            writeln!(writer, "  h -> help")?;
+/// Shows everything about the script at `location`.
+pub fn show_script<W: Write>(
+    writer: &mut W,
+    location: &ScriptLocation,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let script = location.load()?;
+    write_script(writer, &location.to_string(), &script, true)
+}
+
+/// Lists the `ZIRV_PATH` directories, in lookup order.
+fn write_script_paths<W: Write>(
+    writer: &mut W,
+    dirs: &[PathBuf],
+) -> Result<(), Box<dyn std::error::Error>> {
+    if dirs.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(writer, "\nScript Path Directories ({ZIRV_PATH_VAR}):")?;
+    for dir in dirs {
+        if dir.is_dir() {
+            writeln!(writer, "  {dir:?}")?;
+        } else {
+            writeln!(writer, "  {dir:?} (not found)")?;
         }
     }
+    Ok(())
+}
 
-    let root = home_dir()?.join(SCRIPT_DIR_NAME);
+/// Where a script comes from and which scripts it hides, e.g.
+/// `local, overrides global`.
+fn origin(script: &CatalogScript) -> String {
+    let mut hidden: Vec<&str> = Vec::new();
+    for (source, _) in &script.overrides {
+        if !hidden.contains(&source.as_str()) {
+            hidden.push(source.as_str());
+        }
+    }
+    if hidden.is_empty() {
+        script.source.as_str().to_string()
+    } else {
+        format!(
+            "{}, overrides {}",
+            script.source.as_str(),
+            hidden.join(", ")
+        )
+    }
+}
 
-    if root.exists() {
-        writeln!(writer, "\nGlobal Base Scripts:")?;
-        writeln!(
+/// Lists every script and shortcut visible through `sources`, once each and
+/// alphabetically, grouped by namespace. Scripts and shortcuts hidden by an
+/// earlier source are noted separately.
+pub fn show_help<W: Write>(
+    writer: &mut W,
+    sources: &[(ScriptSource, PathBuf)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let global = sources
+        .iter()
+        .find(|(source, _)| *source == ScriptSource::Global)
+        .map(|(_, dir)| dir);
+
+    match global {
+        Some(global) => writeln!(
             writer,
-            "Global scripts are overwritten by above mentioned scripts if they share name."
-        )?;
-        writeln!(writer, "Home Directory: {root:?}")?;
-        write_scripts(writer, &root)?;
+            "Scripts are looked up in the nearest .zirv directory, then in each {ZIRV_PATH_VAR} directory in order, then in {global:?}; the first match wins."
+        )?,
+        None => writeln!(
+            writer,
+            "Scripts are looked up in the nearest .zirv directory, then in each {ZIRV_PATH_VAR} directory in order; the first match wins."
+        )?,
+    }
 
-        let shortcuts_path = root.join(".shortcuts.yaml");
-        if shortcuts_path.exists() {
-            writeln!(writer, "\nGlobal Shortcuts:")?;
-            write_shortcuts(writer, &root)?;
+    if !sources
+        .iter()
+        .any(|(_, dir)| dir.is_dir() || scripts_file(dir).is_some())
+    {
+        match global {
+            Some(global) => writeln!(
+                writer,
+                "No scripts found. Please create a .zirv directory in {global:?}."
+            )?,
+            None => writeln!(
+                writer,
+                "No scripts found. Please create a .zirv directory in your project."
+            )?,
         }
-    } else {
-        writeln!(
-            writer,
-            "No scripts found. Please create a .zirv directory in {root:?}."
-        )?;
+        return Ok(());
     }
 
+    let mut scripts = effective_scripts(sources)?;
+    let namespace = |name: &str| name.rsplit_once('/').map_or("", |(ns, _)| ns).to_string();
+    scripts.sort_by_key(|s| (namespace(&s.name), s.name.clone()));
+
+    writeln!(writer, "\nAvailable Scripts:")?;
+    let mut current_namespace = String::new();
+    for entry in &scripts {
+        let script = match entry.path.load() {
+            Ok(script) => script,
+            Err(e) => {
+                eprintln!("Warning: skipping {}: {e}", entry.file_name());
+                continue;
+            }
+        };
+
+        let entry_namespace = namespace(&entry.name);
+        if entry_namespace != current_namespace {
+            writeln!(writer, "\nNamespace '{entry_namespace}':")?;
+            current_namespace = entry_namespace;
+        }
+        let label = format!("{} ({})", entry.file_name(), origin(entry));
+        write_script(writer, &label, &script, false)?;
+    }
+
+    let overridden: Vec<(&CatalogScript, &(ScriptSource, ScriptLocation))> = scripts
+        .iter()
+        .flat_map(|s| s.overrides.iter().map(move |o| (s, o)))
+        .collect();
+    if !overridden.is_empty() {
+        writeln!(writer, "\nOverridden (not run by name):")?;
+        for (script, (source, path)) in overridden {
+            writeln!(writer, "  {} ({}, {path})", script.name, source.as_str())?;
+        }
+    }
+
+    let extra: Vec<PathBuf> = sources
+        .iter()
+        .filter(|(source, _)| *source == ScriptSource::Extra)
+        .map(|(_, dir)| dir.clone())
+        .collect();
+    write_script_paths(writer, &extra)?;
+
+    writeln!(writer, "\nAvailable Shortcuts:")?;
+    for shortcut in effective_shortcuts(sources)? {
+        let mut line = format!(
+            "  {} -> {} ({}",
+            shortcut.key,
+            shortcut.target,
+            shortcut.source.as_str()
+        );
+        for (source, target) in &shortcut.conflicts {
+            line.push_str(&format!("; {} maps it to '{target}'", source.as_str()));
+        }
+        writeln!(writer, "{line})")?;
+    }
+    writeln!(writer, "  i -> init")?;
+    writeln!(writer, "  c -> create")?;
+    writeln!(writer, "  v -> version")?;
+    writeln!(writer, "  h -> help")?;
+
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env;
     use std::fs::{create_dir_all, write};
-    use std::io::Cursor;
     use std::path::{Path, PathBuf};
     use tempfile::tempdir;
 
@@ -108,31 +271,34 @@ mod tests {
         zirv_dir
     }
 
+    fn help(sources: &[(ScriptSource, PathBuf)]) -> String {
+        let mut buffer = Vec::new();
+        show_help(&mut buffer, sources).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
     /// Test that a local script file is listed correctly.
     #[test]
     fn test_show_help_with_script() -> Result<(), Box<dyn std::error::Error>> {
         let temp_dir = tempdir()?;
-        let temp_path = temp_dir.path().to_path_buf();
-        let zirv_dir = setup_zirv_dir(&temp_path);
+        let zirv_dir = setup_zirv_dir(temp_dir.path());
 
         // Create a dummy script file (YAML) in .zirv.
         let script_content = r#"
 name: "Test Script"
 description: "A dummy script for testing."
+aliases: [t, tst]
 params: []
 commands: []
         "#;
-        let script_file = zirv_dir.join("test.yaml");
-        write(&script_file, script_content)?;
-
-        let original_dir = env::current_dir()?;
-        env::set_current_dir(&temp_path)?;
+        write(zirv_dir.join("test.yaml"), script_content)?;
 
-        let mut buffer = Cursor::new(Vec::new());
-        show_help(&mut buffer)?;
-        let output = String::from_utf8(buffer.into_inner())?;
+        let output = help(&[(ScriptSource::Local, zirv_dir)]);
 
-        assert!(output.contains("File:"), "Output should contain 'File:'");
+        assert!(
+            output.contains("File: test.yaml (local)"),
+            "Output should contain the file and its source"
+        );
 
         assert!(
             output.contains("Test Script"),
@@ -144,7 +310,10 @@ commands: []
             "Output should contain 'Description:'"
         );
 
-        env::set_current_dir(original_dir)?;
+        assert!(
+            output.contains("  Aliases: t, tst\n"),
+            "Output should list the script's aliases"
+        );
 
         Ok(())
     }
@@ -153,8 +322,7 @@ commands: []
     #[test]
     fn test_show_help_with_shortcuts() -> Result<(), Box<dyn std::error::Error>> {
         let temp_dir = tempdir()?;
-        let temp_path = temp_dir.path().to_path_buf();
-        let zirv_dir = setup_zirv_dir(&temp_path);
+        let zirv_dir = setup_zirv_dir(temp_dir.path());
 
         // Create a dummy script file.
         let script_content = r#"
@@ -163,23 +331,16 @@ description: "A dummy script for testing shortcuts."
 params: []
 commands: []
         "#;
-        let script_file = zirv_dir.join("test.yaml");
-        write(&script_file, script_content)?;
+        write(zirv_dir.join("test.yaml"), script_content)?;
 
         // Create a shortcuts file mapping "t" to "test.yaml".
         let shortcuts_content = r#"
 shortcuts:
   t: "test.yaml"
         "#;
-        let shortcuts_file = zirv_dir.join(".shortcuts.yaml");
-        write(&shortcuts_file, shortcuts_content)?;
+        write(zirv_dir.join(".shortcuts.yaml"), shortcuts_content)?;
 
-        let original_dir = env::current_dir()?;
-        env::set_current_dir(&temp_path)?;
-
-        let mut buffer = Cursor::new(Vec::new());
-        show_help(&mut buffer)?;
-        let output = String::from_utf8(buffer.into_inner())?;
+        let output = help(&[(ScriptSource::Local, zirv_dir)]);
 
         assert!(
             output.contains("Available Shortcuts:"),
@@ -187,7 +348,7 @@ shortcuts:
         );
 
         assert!(
-            output.contains("t -> test.yaml"),
+            output.contains("t -> test.yaml (local)"),
             "Output should contain the shortcut mapping 't -> test.yaml'"
         );
 
@@ -196,7 +357,276 @@ shortcuts:
             "Output should include a help shortcut"
         );
 
-        env::set_current_dir(original_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_scripts_are_grouped_by_namespace() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let zirv_dir = setup_zirv_dir(temp_dir.path());
+        let script = |name: &str| format!("name: {name}\ncommands: []\n");
+        create_dir_all(zirv_dir.join("db").join("seed"))?;
+        write(zirv_dir.join("build.yaml"), script("Build"))?;
+        write(zirv_dir.join("zip.yaml"), script("Zip"))?;
+        write(zirv_dir.join("db").join("migrate.yaml"), script("Migrate"))?;
+        write(
+            zirv_dir.join("db").join("seed").join("users.yaml"),
+            script("Users"),
+        )?;
+
+        let output = help(&[(ScriptSource::Local, zirv_dir)]);
+
+        let position = |text: &str| {
+            output
+                .find(text)
+                .unwrap_or_else(|| panic!("'{text}' missing from:\n{output}"))
+        };
+        assert!(position("File: build.yaml") < position("File: zip.yaml"));
+        assert!(position("File: zip.yaml") < position("Namespace 'db':"));
+        assert!(position("Namespace 'db':") < position("File: db/migrate.yaml"));
+        assert!(position("File: db/migrate.yaml") < position("Namespace 'db/seed':"));
+        assert!(position("Namespace 'db/seed':") < position("File: db/seed/users.yaml"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_overridden_scripts_are_listed_once() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let local = setup_zirv_dir(&temp_dir.path().join("project"));
+        let global = setup_zirv_dir(&temp_dir.path().join("home"));
+        write(
+            local.join("build.yaml"),
+            "name: Local Build\ncommands: []\n",
+        )?;
+        write(
+            global.join("build.json"),
+            r#"{"name": "Global Build", "commands": []}"#,
+        )?;
+        write(global.join("deploy.yaml"), "name: Deploy\ncommands: []\n")?;
+        write(local.join(".shortcuts.yaml"), "shortcuts:\n  b: build\n")?;
+        write(
+            global.join(".shortcuts.yaml"),
+            "shortcuts:\n  b: bump\n  d: deploy\n",
+        )?;
+
+        let output = help(&[
+            (ScriptSource::Local, local),
+            (ScriptSource::Global, global.clone()),
+        ]);
+
+        assert_eq!(output.matches("File: build").count(), 1, "{output}");
+        assert!(
+            output.contains("File: build.yaml (local, overrides global)"),
+            "{output}"
+        );
+        assert!(!output.contains("Global Build"), "{output}");
+        assert!(output.contains("File: deploy.yaml (global)"), "{output}");
+        assert!(
+            output.contains(&format!(
+                "Overridden (not run by name):\n  build (global, {})\n",
+                global.join("build.json").display()
+            )),
+            "{output}"
+        );
+        assert!(
+            output.contains(
+                "  b -> build (local; global maps it to 'bump')\n  d -> deploy (global)\n"
+            ),
+            "{output}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_script_paths_are_listed_in_order() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let first = temp_dir.path().join("first");
+        let second = temp_dir.path().join("second");
+        create_dir_all(&first)?;
+        write(first.join("lint.yaml"), "name: Shared Lint\ncommands: []\n")?;
+
+        let output = help(&[
+            (ScriptSource::Extra, first.clone()),
+            (ScriptSource::Extra, second.clone()),
+        ]);
+
+        assert!(output.contains("File: lint.yaml (extra)"), "{output}");
+        assert!(output.contains(&format!(
+            "Script Path Directories (ZIRV_PATH):\n  {first:?}\n  {second:?} (not found)\n"
+        )));
+
+        let mut buffer = Vec::new();
+        write_script_paths(&mut buffer, &[])?;
+        assert!(buffer.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_without_scripts() {
+        let temp_dir = tempdir().unwrap();
+        let global = temp_dir.path().join(".zirv");
+
+        let output = help(&[(ScriptSource::Global, global.clone())]);
+        assert!(output.ends_with(&format!(
+            "No scripts found. Please create a .zirv directory in {global:?}.\n"
+        )));
+    }
+
+    #[test]
+    fn test_without_home_directory() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let zirv_dir = setup_zirv_dir(temp_dir.path());
+
+        let output = help(&[(ScriptSource::Local, zirv_dir.clone())]);
+        assert!(
+            output.starts_with(
+                "Scripts are looked up in the nearest .zirv directory, then in each ZIRV_PATH directory in order; the first match wins.\n"
+            ),
+            "{output}"
+        );
+        assert!(output.contains("Available Scripts:"), "{output}");
+
+        std::fs::remove_dir(&zirv_dir)?;
+        let output = help(&[(ScriptSource::Local, zirv_dir)]);
+        assert!(
+            output
+                .ends_with("No scripts found. Please create a .zirv directory in your project.\n")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scripts_and_shortcuts_are_sorted() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let zirv_dir = setup_zirv_dir(temp_dir.path());
+        for name in ["zeta", "alpha", "mid"] {
+            write(
+                zirv_dir.join(format!("{name}.yaml")),
+                format!("name: {name}\ncommands: []\n"),
+            )?;
+        }
+        write(
+            zirv_dir.join(".shortcuts.yaml"),
+            "shortcuts:\n  z: zeta\n  a: alpha\n  m: mid\n",
+        )?;
+
+        let output = help(&[(ScriptSource::Local, zirv_dir)]);
+
+        let files: Vec<&str> = output
+            .lines()
+            .filter(|line| line.starts_with("File: "))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                "File: alpha.yaml (local)",
+                "File: mid.yaml (local)",
+                "File: zeta.yaml (local)"
+            ]
+        );
+        assert!(output.contains("  a -> alpha (local)\n  m -> mid (local)\n  z -> zeta (local)\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unparseable_scripts_are_skipped() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let zirv_dir = setup_zirv_dir(temp_dir.path());
+        write(zirv_dir.join("broken.yaml"), "name: [\n")?;
+        write(zirv_dir.join("build.yaml"), "name: Build\ncommands: []\n")?;
+
+        let output = help(&[(ScriptSource::Local, zirv_dir)]);
+
+        assert!(!output.contains("broken.yaml"), "{output}");
+        assert!(output.contains("File: build.yaml"), "{output}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_script_details() -> Result<(), Box<dyn std::error::Error>> {
+        let script: Script = serde_yaml::from_str(
+            r#"
+name: Deploy
+description: Ship it.
+params: [version]
+secrets:
+  - name: token
+    env_var: DEPLOY_TOKEN
+commands:
+  - name: build
+    command: cargo build --release
+    capture: artifact
+    options:
+      operating_system: linux
+      interactive: true
+      fallback:
+        - command: cargo clean
+  - - command: npm run watch
+    - command: cargo watch
+"#,
+        )?;
+
+        let mut listing = Vec::new();
+        write_script(&mut listing, "deploy.yaml", &script, false)?;
+        let listing = String::from_utf8(listing)?;
+        assert_eq!(
+            listing,
+            "-------------------------------------------------\n\
+             File: deploy.yaml\n  Name: Deploy\n  Description: Ship it.\n\
+             \x20 Required Parameters:\n    version\n"
+        );
+
+        let mut details = Vec::new();
+        write_script(&mut details, "deploy.yaml", &script, true)?;
+        let details = String::from_utf8(details)?;
+        assert!(details.starts_with(&listing));
+        assert_eq!(
+            &details[listing.len()..],
+            "  Secrets:\n    token (from $DEPLOY_TOKEN)\n  Commands:\n\
+             \x20   1. [build] cargo build --release\n\
+             \x20      Captures: ${artifact}\n\
+             \x20      Options: only on linux, interactive\n\
+             \x20      Fallback (then retries):\n\
+             \x20        - cargo clean\n\
+             \x20   2. In parallel terminals:\n\
+             \x20     - npm run watch\n\
+             \x20     - cargo watch\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scripts_file_entries_are_listed() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let zirv_dir = temp_dir.path().join(".zirv");
+        write(
+            temp_dir.path().join(".zirv.yaml"),
+            "scripts:\n  build:\n    name: Build\n    description: Builds it\n    commands: []\n  test:\n    name: Test\n    commands: []\n",
+        )?;
+
+        let output = help(&[(ScriptSource::Local, zirv_dir.clone())]);
+        assert!(
+            output.contains("File: .zirv.yaml#build (local)"),
+            "{output}"
+        );
+        assert!(output.contains("Builds it"), "{output}");
+        assert!(output.contains("File: .zirv.yaml#test (local)"), "{output}");
+
+        create_dir_all(&zirv_dir)?;
+        write(zirv_dir.join("test.yaml"), "name: Dir Test\ncommands: []\n")?;
+        let output = help(&[(ScriptSource::Local, zirv_dir)]);
+        assert!(
+            output.contains("File: test.yaml (local, overrides local)"),
+            "{output}"
+        );
+        assert!(output.contains("Dir Test"), "{output}");
 
         Ok(())
     }
@@ -0,0 +1,256 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::utils::home_dir;
+
+/// The shells whose history files `create --from-history` can read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryFormat {
+    /// One command per line, optionally preceded by `#<epoch>` lines when
+    /// `HISTTIMEFORMAT` is set.
+    Bash,
+    /// Lines may carry the extended-history prefix `: <epoch>:<duration>;`,
+    /// and multi-line commands continue with a trailing `\`.
+    Zsh,
+    /// PSReadLine's `ConsoleHost_history.txt`, where multi-line commands
+    /// continue with a trailing backtick.
+    PowerShell,
+}
+
+impl HistoryFormat {
+    /// Guesses the format from the history file's name, or from the user's
+    /// `shell` for names like zsh's `~/.histfile`.
+    fn detect(path: &Path, shell: &str) -> Self {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name.contains("consolehost_history") {
+            HistoryFormat::PowerShell
+        } else if name.contains("zsh") || (!name.contains("bash") && shell.ends_with("zsh")) {
+            HistoryFormat::Zsh
+        } else {
+            HistoryFormat::Bash
+        }
+    }
+
+    fn continuation(&self) -> char {
+        match self {
+            HistoryFormat::Bash | HistoryFormat::Zsh => '\\',
+            HistoryFormat::PowerShell => '`',
+        }
+    }
+}
+
+/// The user's shell history file: `$HISTFILE`, then PSReadLine's history on
+/// Windows, then the history of `$SHELL`, then whichever of
+/// `~/.zsh_history` and `~/.bash_history` exists.
+pub fn history_file() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("HISTFILE").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    if cfg!(windows)
+        && let Some(app_data) = std::env::var_os("APPDATA")
+    {
+        let path = PathBuf::from(app_data)
+            .join("Microsoft")
+            .join("Windows")
+            .join("PowerShell")
+            .join("PSReadLine")
+            .join("ConsoleHost_history.txt");
+        if path.is_file() {
+            return Some(path);
+        }
+    }
+
+    let home = home_dir().ok()?;
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    let mut candidates = vec![home.join(".zsh_history"), home.join(".bash_history")];
+    if shell.ends_with("bash") {
+        candidates.reverse();
+    }
+    candidates.into_iter().find(|path| path.is_file())
+}
+
+/// Splits a history file into its commands, oldest first, joining
+/// multi-line commands back together.
+pub fn parse_history(content: &str, format: HistoryFormat) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut pending: Option<String> = None;
+    for line in content.lines() {
+        let line = match pending {
+            Some(_) => line,
+            None => match format {
+                HistoryFormat::Bash if is_bash_timestamp(line) => continue,
+                HistoryFormat::Zsh => strip_zsh_timestamp(line),
+                _ => line,
+            },
+        };
+        let (text, continues) = match line.strip_suffix(format.continuation()) {
+            Some(text) => (text, true),
+            None => (line, false),
+        };
+        let command = match pending.take() {
+            Some(mut command) => {
+                command.push('\n');
+                command.push_str(text);
+                command
+            }
+            None => text.to_string(),
+        };
+        if continues {
+            pending = Some(command);
+        } else if !command.trim().is_empty() {
+            commands.push(command.trim().to_string());
+        }
+    }
+    if let Some(command) = pending.filter(|command| !command.trim().is_empty()) {
+        commands.push(command.trim().to_string());
+    }
+    commands
+}
+
+fn is_bash_timestamp(line: &str) -> bool {
+    line.strip_prefix('#')
+        .is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Removes the `: <epoch>:<duration>;` prefix zsh writes with
+/// `EXTENDED_HISTORY`.
+fn strip_zsh_timestamp(line: &str) -> &str {
+    let Some(rest) = line.strip_prefix(": ") else {
+        return line;
+    };
+    let Some((stamp, command)) = rest.split_once(';') else {
+        return line;
+    };
+    let is_stamp = stamp.split_once(':').is_some_and(|(epoch, duration)| {
+        [epoch, duration]
+            .iter()
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+    });
+    if is_stamp { command } else { line }
+}
+
+/// Whether `command` runs zirv itself, which makes no sense inside a script.
+fn is_zirv_invocation(command: &str) -> bool {
+    command.split_whitespace().next().is_some_and(|program| {
+        let program = program.rsplit(['/', '\\']).next().unwrap_or(program);
+        program == "zirv" || program.eq_ignore_ascii_case("zirv.exe")
+    })
+}
+
+/// The last `limit` distinct commands in `commands`, oldest first, leaving
+/// out zirv invocations. Repeating a command moves it to the end.
+pub fn recent_commands(commands: Vec<String>, limit: usize) -> Vec<String> {
+    let mut recent: Vec<String> = Vec::new();
+    for command in commands.into_iter().rev() {
+        if recent.len() == limit {
+            break;
+        }
+        if !is_zirv_invocation(&command) && !recent.contains(&command) {
+            recent.push(command);
+        }
+    }
+    recent.reverse();
+    recent
+}
+
+/// Reads the last `limit` commands from the user's shell history.
+pub fn read_recent_history(limit: usize) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let path =
+        history_file().ok_or("Could not find a shell history file; set HISTFILE to point at it")?;
+    // zsh stores some characters in its own encoding, so the file is not
+    // always valid UTF-8.
+    let content = fs::read(&path).map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+    let content = String::from_utf8_lossy(&content).into_owned();
+    let commands = recent_commands(
+        parse_history(
+            &content,
+            HistoryFormat::detect(&path, &std::env::var("SHELL").unwrap_or_default()),
+        ),
+        limit,
+    );
+    if commands.is_empty() {
+        return Err(format!("No commands found in {}", path.display()).into());
+    }
+    Ok(commands)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bash_history() {
+        let content = "#1700000000\ncargo build\n#1700000005\ncargo test\n\nls -la\n";
+        assert_eq!(
+            parse_history(content, HistoryFormat::Bash),
+            vec!["cargo build", "cargo test", "ls -la"]
+        );
+    }
+
+    #[test]
+    fn test_parse_zsh_extended_history() {
+        let content = ": 1700000000:0;git status\n: 1700000003:2;for f in *; do\\\n  echo $f\\\ndone\ngit push\n: not a stamp\n";
+        assert_eq!(
+            parse_history(content, HistoryFormat::Zsh),
+            vec![
+                "git status",
+                "for f in *; do\n  echo $f\ndone",
+                "git push",
+                ": not a stamp"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_powershell_history() {
+        let content = "dotnet build\r\nGet-ChildItem `\r\n  -Recurse\r\ndotnet test\r\n";
+        assert_eq!(
+            parse_history(content, HistoryFormat::PowerShell),
+            vec!["dotnet build", "Get-ChildItem \n  -Recurse", "dotnet test"]
+        );
+    }
+
+    #[test]
+    fn test_detect_format() {
+        let format = |name: &str, shell: &str| HistoryFormat::detect(Path::new(name), shell);
+        assert_eq!(format("/home/me/.zsh_history", ""), HistoryFormat::Zsh);
+        assert_eq!(
+            format("/home/me/.bash_history", "/bin/zsh"),
+            HistoryFormat::Bash
+        );
+        assert_eq!(format("/home/me/.histfile", "/bin/zsh"), HistoryFormat::Zsh);
+        assert_eq!(
+            format("/home/me/.histfile", "/bin/bash"),
+            HistoryFormat::Bash
+        );
+        assert_eq!(
+            format("C:\\PSReadLine\\ConsoleHost_history.txt", ""),
+            HistoryFormat::PowerShell
+        );
+    }
+
+    #[test]
+    fn test_recent_commands_skip_zirv_and_repeats() {
+        let commands = [
+            "cargo build",
+            "zirv deploy",
+            "cargo test",
+            "/usr/local/bin/zirv help",
+            "cargo build",
+            "zirvish",
+            "git push",
+        ]
+        .map(String::from)
+        .to_vec();
+        assert_eq!(
+            recent_commands(commands.clone(), 10),
+            vec!["cargo test", "cargo build", "zirvish", "git push"]
+        );
+        assert_eq!(recent_commands(commands, 2), vec!["zirvish", "git push"]);
+    }
+}
@@ -0,0 +1,328 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::script_runner::quoting::Quoting;
+
+/// The hooks git runs, from `git help hooks`.
+pub const GIT_HOOKS: &[&str] = &[
+    "applypatch-msg",
+    "pre-applypatch",
+    "post-applypatch",
+    "pre-commit",
+    "pre-merge-commit",
+    "prepare-commit-msg",
+    "commit-msg",
+    "post-commit",
+    "pre-rebase",
+    "post-checkout",
+    "post-merge",
+    "pre-push",
+    "pre-receive",
+    "update",
+    "proc-receive",
+    "post-receive",
+    "post-update",
+    "reference-transaction",
+    "push-to-checkout",
+    "pre-auto-gc",
+    "post-rewrite",
+    "sendemail-validate",
+    "fsmonitor-watchman",
+    "post-index-change",
+];
+
+/// The line that marks a hook as written by `zirv install-hook`.
+const SHIM_MARKER: &str = "# Installed by zirv install-hook.";
+
+/// The hook script that runs the zirv script `script` with git's arguments.
+fn shim(script: &str) -> String {
+    format!(
+        "#!/bin/sh\n{SHIM_MARKER}\nexec zirv --plain {} \"$@\"\n",
+        Quoting::Posix.quote(script)
+    )
+}
+
+fn check_hook_name(hook: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if GIT_HOOKS.contains(&hook) {
+        return Ok(());
+    }
+    Err(format!(
+        "Unknown git hook '{hook}'; expected one of: {}",
+        GIT_HOOKS.join(", ")
+    )
+    .into())
+}
+
+/// The directory git runs the hooks of the repository containing `dir`
+/// from: `.git/hooks`, or `core.hooksPath` when set.
+pub fn hooks_dir(dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output()
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    if !output.status.success() {
+        return Err(format!("{} is not inside a git repository", dir.display()).into());
+    }
+    let path = String::from_utf8_lossy(&output.stdout);
+    Ok(dir.join(path.trim_end()))
+}
+
+/// Installs a `hook` of the git repository containing `dir` that runs the
+/// zirv script `script`. An existing hook not installed by zirv is moved to
+/// `<hook>.bak` if `confirm` agrees, and left alone otherwise. An existing
+/// `<hook>.bak` is never overwritten.
+pub fn install_hook<W: Write>(
+    writer: &mut W,
+    dir: &Path,
+    hook: &str,
+    script: &str,
+    confirm: impl FnOnce(&str) -> Result<bool, Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    check_hook_name(hook)?;
+    let hooks = hooks_dir(dir)?;
+    let path = hooks.join(hook);
+
+    if path.exists() && !is_shim(&path) {
+        let backup = hooks.join(format!("{hook}.bak"));
+        if backup.exists() {
+            return Err(format!(
+                "Cannot move the existing {hook} hook to {}, which exists already; move or remove one of them first",
+                backup.display()
+            )
+            .into());
+        }
+        if !confirm(&format!(
+            "Move the existing {hook} hook to {} and replace it?",
+            backup.display()
+        ))? {
+            writeln!(writer, "Nothing installed.")?;
+            return Ok(());
+        }
+        fs::rename(&path, &backup)?;
+        writeln!(writer, "Moved the existing hook to {}", backup.display())?;
+    }
+
+    fs::create_dir_all(&hooks)?;
+    fs::write(&path, shim(script))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+    writeln!(
+        writer,
+        "Installed the {hook} hook at {}, running `zirv {script}`",
+        path.display()
+    )?;
+    Ok(())
+}
+
+/// Removes a `hook` installed by `zirv install-hook`, restoring the hook it
+/// replaced, if any. Hooks zirv did not install are left alone.
+pub fn uninstall_hook<W: Write>(
+    writer: &mut W,
+    dir: &Path,
+    hook: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    check_hook_name(hook)?;
+    let hooks = hooks_dir(dir)?;
+    let path = hooks.join(hook);
+
+    if !path.exists() {
+        return Err(format!("No {hook} hook is installed").into());
+    }
+    if !is_shim(&path) {
+        return Err(format!(
+            "The {hook} hook at {} was not installed by zirv; leaving it alone",
+            path.display()
+        )
+        .into());
+    }
+    fs::remove_file(&path)?;
+    writeln!(writer, "Removed the {hook} hook at {}", path.display())?;
+
+    let backup = hooks.join(format!("{hook}.bak"));
+    if backup.exists() {
+        fs::rename(&backup, &path)?;
+        writeln!(
+            writer,
+            "Restored the previous hook from {}",
+            backup.display()
+        )?;
+    }
+    Ok(())
+}
+
+fn is_shim(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|content| content.lines().nth(1) == Some(SHIM_MARKER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git_init(dir: &Path) {
+        let status = Command::new("git")
+            .args(["init", "--quiet"])
+            .arg(dir)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    fn install(
+        dir: &Path,
+        hook: &str,
+        script: &str,
+        answer: bool,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let mut buffer = Vec::new();
+        install_hook(&mut buffer, dir, hook, script, |_| Ok(answer))?;
+        Ok(String::from_utf8(buffer).unwrap())
+    }
+
+    #[test]
+    fn test_install_and_uninstall() {
+        let repo = tempfile::tempdir().unwrap();
+        git_init(repo.path());
+        let subdir = repo.path().join("src");
+        fs::create_dir(&subdir).unwrap();
+
+        install(&subdir, "pre-push", "lint", false).unwrap();
+        let hook = repo.path().join(".git").join("hooks").join("pre-push");
+        assert_eq!(
+            fs::read_to_string(&hook).unwrap(),
+            format!("#!/bin/sh\n{SHIM_MARKER}\nexec zirv --plain 'lint' \"$@\"\n")
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&hook).unwrap().permissions().mode();
+            assert_eq!(mode & 0o111, 0o111);
+        }
+
+        // Reinstalling over zirv's own hook does not ask.
+        install(repo.path(), "pre-push", "check", false).unwrap();
+        assert!(
+            fs::read_to_string(&hook)
+                .unwrap()
+                .contains("zirv --plain 'check'")
+        );
+
+        let mut buffer = Vec::new();
+        uninstall_hook(&mut buffer, repo.path(), "pre-push").unwrap();
+        assert!(!hook.exists());
+        let error = uninstall_hook(&mut buffer, repo.path(), "pre-push").unwrap_err();
+        assert_eq!(error.to_string(), "No pre-push hook is installed");
+    }
+
+    #[test]
+    fn test_existing_hook_is_backed_up_after_confirmation() {
+        let repo = tempfile::tempdir().unwrap();
+        git_init(repo.path());
+        let hooks = repo.path().join(".git").join("hooks");
+        fs::create_dir_all(&hooks).unwrap();
+        let hook = hooks.join("pre-commit");
+        fs::write(&hook, "#!/bin/sh\nmake fmt\n").unwrap();
+
+        let output = install(repo.path(), "pre-commit", "fmt", false).unwrap();
+        assert_eq!(output, "Nothing installed.\n");
+        assert_eq!(fs::read_to_string(&hook).unwrap(), "#!/bin/sh\nmake fmt\n");
+
+        let error = uninstall_hook(&mut Vec::new(), repo.path(), "pre-commit").unwrap_err();
+        assert!(error.to_string().contains("was not installed by zirv"));
+
+        install(repo.path(), "pre-commit", "fmt", true).unwrap();
+        let backup = hooks.join("pre-commit.bak");
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "#!/bin/sh\nmake fmt\n"
+        );
+        assert!(is_shim(&hook));
+
+        uninstall_hook(&mut Vec::new(), repo.path(), "pre-commit").unwrap();
+        assert_eq!(fs::read_to_string(&hook).unwrap(), "#!/bin/sh\nmake fmt\n");
+        assert!(!backup.exists());
+
+        // A backup from before is kept, along with the hook.
+        fs::write(&backup, "#!/bin/sh\nmake lint\n").unwrap();
+        let error = install(repo.path(), "pre-commit", "fmt", true).unwrap_err();
+        assert!(error.to_string().contains("exists already"), "{error}");
+        assert_eq!(fs::read_to_string(&hook).unwrap(), "#!/bin/sh\nmake fmt\n");
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "#!/bin/sh\nmake lint\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_script_name_is_quoted() {
+        let repo = tempfile::tempdir().unwrap();
+        git_init(repo.path());
+        let script = "lint; echo it's $(id)";
+        install(repo.path(), "pre-push", script, false).unwrap();
+
+        // A zirv that prints its arguments, one per line.
+        let bin = tempfile::tempdir().unwrap();
+        let zirv = bin.path().join("zirv");
+        fs::write(&zirv, "#!/bin/sh\nprintf '%s\\n' \"$@\"\n").unwrap();
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&zirv, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let path = std::env::join_paths(
+            std::iter::once(bin.path().to_path_buf())
+                .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+        )
+        .unwrap();
+        let output = Command::new(repo.path().join(".git").join("hooks").join("pre-push"))
+            .env("PATH", path)
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            format!("--plain\n{script}\n")
+        );
+    }
+
+    #[test]
+    fn test_core_hooks_path_is_respected() {
+        let repo = tempfile::tempdir().unwrap();
+        git_init(repo.path());
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(repo.path())
+            .args(["config", "core.hooksPath", "githooks"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        install(repo.path(), "commit-msg", "lint-message", false).unwrap();
+        assert!(is_shim(&repo.path().join("githooks").join("commit-msg")));
+    }
+
+    #[test]
+    fn test_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = install(dir.path(), "pre-push", "lint", true).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("{} is not inside a git repository", dir.path().display())
+        );
+
+        git_init(dir.path());
+        let error = install(dir.path(), "pre-pusj", "lint", true).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Unknown git hook 'pre-pusj'; expected one of: applypatch-msg,"),
+            "{error}"
+        );
+    }
+}
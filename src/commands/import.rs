@@ -0,0 +1,805 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    catalog::{load_shortcuts, save_shortcuts, script_names},
+    commands::BUILTIN_COMMANDS,
+    script_runner::{
+        command::{Command, escape},
+        command_types::CommandTypes,
+        options::Options,
+        script::Script,
+    },
+    utils::Shortcut,
+};
+
+/// The kinds of files `import` reads targets from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    /// The `scripts` of a `package.json`.
+    Npm,
+    /// The rules of a Makefile.
+    Make,
+    /// The recipes of a justfile.
+    Just,
+}
+
+impl ImportSource {
+    /// Tells the kind of file from its name.
+    pub fn detect(path: &Path) -> Result<Self, String> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match name.as_str() {
+            "package.json" => Ok(ImportSource::Npm),
+            "makefile" | "gnumakefile" => Ok(ImportSource::Make),
+            _ if name.ends_with(".mk") => Ok(ImportSource::Make),
+            "justfile" | ".justfile" => Ok(ImportSource::Just),
+            _ => Err(format!(
+                "Cannot import from '{}'; expected a package.json, Makefile or justfile",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// A target, script or recipe read from a file, before it becomes a script.
+#[derive(Debug, Default, PartialEq)]
+pub struct Target {
+    pub name: String,
+    pub description: Option<String>,
+    pub params: Vec<String>,
+    /// Other targets that run first.
+    pub dependencies: Vec<String>,
+    /// The commands, each with whether its failure is ignored.
+    pub commands: Vec<(String, bool)>,
+}
+
+/// What a file provided: the targets that can be imported, and the names of
+/// those that cannot with the reason why.
+#[derive(Debug, Default)]
+pub struct Parsed {
+    pub targets: Vec<Target>,
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Splits `command` at `&&` outside of quotes.
+fn split_and(command: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '&') if chars.peek() == Some(&'&') => {
+                chars.next();
+                parts.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    parts.push(current.trim().to_string());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Reads the `scripts` of a `package.json`, splitting each into its
+/// `&&`-chained commands.
+pub fn parse_npm(content: &str) -> Result<Parsed, Box<dyn std::error::Error>> {
+    let package: serde_json::Value = serde_json::from_str(content)?;
+    let mut parsed = Parsed::default();
+    let Some(scripts) = package.get("scripts").and_then(|s| s.as_object()) else {
+        return Ok(parsed);
+    };
+    for (name, command) in scripts {
+        let commands = match command.as_str() {
+            Some(command) => split_and(command),
+            None => {
+                parsed
+                    .skipped
+                    .push((name.clone(), "is not a string".to_string()));
+                continue;
+            }
+        };
+        if commands.is_empty() {
+            parsed.skipped.push((name.clone(), "is empty".to_string()));
+            continue;
+        }
+        parsed.targets.push(Target {
+            name: name.clone(),
+            commands: commands.into_iter().map(|c| (c, false)).collect(),
+            ..Default::default()
+        });
+    }
+    Ok(parsed)
+}
+
+/// Joins lines ending in `\` with the next one.
+fn logical_lines(content: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut pending = String::new();
+    for line in content.lines() {
+        match line.strip_suffix('\\') {
+            Some(start) => {
+                pending.push_str(start);
+                pending.push(' ');
+            }
+            None => {
+                pending.push_str(line);
+                lines.push(std::mem::take(&mut pending));
+            }
+        }
+    }
+    if !pending.is_empty() {
+        lines.push(pending);
+    }
+    lines
+}
+
+/// The text of a comment line, without its `#`s.
+fn comment_text(line: &str) -> Option<String> {
+    let text = line.trim().strip_prefix('#')?;
+    Some(text.trim_start_matches('#').trim().to_string())
+}
+
+/// Strips the `@`, `-` and `+` prefixes of a recipe line, returning whether
+/// its failure is ignored.
+fn recipe_line(line: &str) -> (String, bool) {
+    let line = line.trim();
+    let body = line.trim_start_matches(['@', '-', '+', ' ']);
+    let prefix = &line[..line.len() - body.len()];
+    (body.to_string(), prefix.contains('-'))
+}
+
+/// Collects `target` into `parsed`, unless a problem was found with it.
+fn finish(parsed: &mut Parsed, target: Option<Target>, problem: Option<String>) {
+    let Some(target) = target else { return };
+    match problem {
+        Some(problem) => parsed.skipped.push((target.name, problem)),
+        None if target.commands.is_empty() && target.dependencies.is_empty() => parsed
+            .skipped
+            .push((target.name, "has no recipe".to_string())),
+        None => parsed.targets.push(target),
+    }
+}
+
+/// Reads the simple rules of a Makefile: one explicit target per rule,
+/// recipes without make variables. The comment above a rule, or a `##`
+/// comment after it, becomes the description.
+pub fn parse_makefile(content: &str) -> Parsed {
+    let mut parsed = Parsed::default();
+    let mut current: Option<Target> = None;
+    let mut problem: Option<String> = None;
+    let mut description: Option<String> = None;
+    let mut in_define = false;
+
+    for line in logical_lines(content) {
+        if in_define {
+            in_define = line.trim() != "endef";
+            continue;
+        }
+        if let Some(recipe) = line.strip_prefix('\t') {
+            if let Some(target) = current.as_mut() {
+                let (command, ignore_errors) = recipe_line(recipe);
+                if command.replace("$$", "").contains('$') {
+                    problem.get_or_insert("uses make variables".to_string());
+                } else if !command.is_empty() {
+                    let command = escape(&command.replace("$$", "$"));
+                    target.commands.push((command, ignore_errors));
+                }
+            }
+            continue;
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(text) = comment_text(trimmed) {
+            description = Some(text);
+            continue;
+        }
+
+        finish(&mut parsed, current.take(), problem.take());
+        let comment = description.take();
+        let first_word = trimmed.split_whitespace().next().unwrap_or_default();
+        if first_word == "define" {
+            in_define = true;
+            continue;
+        }
+        let is_assignment = match (trimmed.find('='), trimmed.find(':')) {
+            (Some(eq), Some(colon)) => eq < colon || trimmed[colon..].starts_with(":="),
+            (Some(_), None) => true,
+            _ => false,
+        };
+        let Some((targets, rest)) = trimmed.split_once(':') else {
+            continue;
+        };
+        if is_assignment
+            || matches!(
+                first_word,
+                "ifeq" | "ifneq" | "ifdef" | "ifndef" | "else" | "endif" | "include" | "-include"
+            )
+        {
+            continue;
+        }
+
+        let names: Vec<&str> = targets.split_whitespace().collect();
+        let rest = rest.trim_start_matches(':');
+        let (rest, inline_comment) = match rest.split_once('#') {
+            Some((rest, comment)) => (rest, comment_text(&format!("#{comment}"))),
+            None => (rest, None),
+        };
+        let (dependencies, inline_recipe) = match rest.split_once(';') {
+            Some((dependencies, recipe)) => (dependencies, Some(recipe)),
+            None => (rest, None),
+        };
+        let [name] = names[..] else {
+            for name in names.iter().filter(|name| !name.starts_with('.')) {
+                parsed.skipped.push((
+                    name.to_string(),
+                    "is defined together with other targets".to_string(),
+                ));
+            }
+            continue;
+        };
+        if name.starts_with('.') {
+            continue;
+        }
+
+        let existing = parsed.targets.iter().position(|t| t.name == name);
+        let mut target = match existing {
+            Some(index) => parsed.targets.remove(index),
+            None => Target {
+                name: name.to_string(),
+                ..Default::default()
+            },
+        };
+        target.description = inline_comment.or(comment).or(target.description);
+        target
+            .dependencies
+            .extend(dependencies.split_whitespace().map(String::from));
+        if name.contains('%') {
+            problem = Some("is a pattern rule".to_string());
+        } else if name.contains('$') || dependencies.contains('$') {
+            problem = Some("uses make variables".to_string());
+        } else if dependencies.contains('=') {
+            problem = Some("sets target-specific variables".to_string());
+        }
+        if let Some(recipe) = inline_recipe {
+            let (command, ignore_errors) = recipe_line(recipe);
+            target.commands.push((command, ignore_errors));
+        }
+        current = Some(target);
+    }
+    finish(&mut parsed, current, problem);
+    parsed
+}
+
+/// Replaces `{{param}}` interpolations with zirv placeholders, or returns
+/// `None` if the line interpolates anything else. `$` in the rest of the
+/// line is escaped where zirv would not take it literally.
+fn just_interpolations(line: &str, params: &[String]) -> Option<String> {
+    let mut result = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..].find("}}")? + start;
+        let expression = rest[start + 2..end].trim();
+        if !params.iter().any(|p| p == expression) {
+            return None;
+        }
+        result.push_str(&escape(&rest[..start]));
+        if result.ends_with('$') {
+            // Or `$` and the placeholder would read as `$$`.
+            result.push('$');
+        }
+        result.push_str(&format!("${{{expression}}}"));
+        rest = &rest[end + 2..];
+    }
+    result.push_str(&escape(rest));
+    Some(result)
+}
+
+/// Reads the simple recipes of a justfile: plain parameters, dependencies
+/// without arguments, and bodies that only interpolate parameters. The
+/// comment above a recipe becomes the description.
+pub fn parse_justfile(content: &str) -> Parsed {
+    let mut parsed = Parsed::default();
+    let mut current: Option<Target> = None;
+    let mut problem: Option<String> = None;
+    let mut description: Option<String> = None;
+
+    for line in logical_lines(content) {
+        if line.starts_with([' ', '\t']) {
+            let Some(target) = current.as_mut() else {
+                continue;
+            };
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            if target.commands.is_empty() && trimmed.starts_with("#!") {
+                problem.get_or_insert("is a shebang recipe".to_string());
+                continue;
+            }
+            let (command, ignore_errors) = recipe_line(trimmed);
+            match just_interpolations(&command, &target.params) {
+                Some(command) => target.commands.push((command, ignore_errors)),
+                None => {
+                    problem.get_or_insert("uses just expressions or variables".to_string());
+                }
+            }
+            continue;
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(text) = comment_text(trimmed) {
+            description = Some(text);
+            continue;
+        }
+        if trimmed.starts_with('[') {
+            // Attributes such as `[private]` sit between a comment and its recipe.
+            continue;
+        }
+
+        finish(&mut parsed, current.take(), problem.take());
+        let comment = description.take();
+        let first_word = trimmed.split_whitespace().next().unwrap_or_default();
+        if matches!(
+            first_word,
+            "set" | "alias" | "import" | "mod" | "export" | "import?" | "mod?"
+        ) || trimmed.contains(":=")
+        {
+            continue;
+        }
+        let Some((header, dependencies)) = trimmed.trim_start_matches('@').split_once(':') else {
+            continue;
+        };
+        let mut words = header.split_whitespace();
+        let Some(name) = words.next() else { continue };
+        let params: Vec<String> = words.map(String::from).collect();
+        let dependencies = dependencies.split('#').next().unwrap_or_default();
+
+        if params
+            .iter()
+            .any(|p| p.contains('=') || p.starts_with(['+', '*', '$']))
+        {
+            problem = Some("has default, variadic or exported parameters".to_string());
+        } else if dependencies.contains(['(', '&']) {
+            problem = Some("passes arguments to or runs after dependencies".to_string());
+        }
+        current = Some(Target {
+            name: name.to_string(),
+            description: comment,
+            params,
+            dependencies: dependencies.split_whitespace().map(String::from).collect(),
+            commands: Vec::new(),
+        });
+    }
+    finish(&mut parsed, current, problem);
+    parsed
+}
+
+/// The file name, without extension, of the script for the target `name`.
+fn script_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '-',
+        })
+        .collect()
+}
+
+/// Leaves out the targets whose dependencies were skipped or cannot be
+/// imported, until every remaining dependency is imported or unknown.
+/// Unknown dependencies are files (make) and are dropped.
+fn resolve_dependencies(parsed: &mut Parsed) {
+    loop {
+        let skipped: Vec<String> = parsed.skipped.iter().map(|(n, _)| n.clone()).collect();
+        let Some(index) = parsed
+            .targets
+            .iter()
+            .position(|t| t.dependencies.iter().any(|d| skipped.contains(d)))
+        else {
+            break;
+        };
+        let target = parsed.targets.remove(index);
+        let dependency = target
+            .dependencies
+            .iter()
+            .find(|d| skipped.contains(d))
+            .cloned()
+            .unwrap_or_default();
+        parsed.skipped.push((
+            target.name,
+            format!("depends on '{dependency}', which is skipped"),
+        ));
+    }
+    let names: Vec<String> = parsed.targets.iter().map(|t| t.name.clone()).collect();
+    for target in &mut parsed.targets {
+        target.dependencies.retain(|d| names.contains(d));
+    }
+}
+
+/// The script for `target`: `zirv <dependency>` for each dependency, then
+/// its commands.
+fn to_script(target: &Target) -> Script {
+    let mut commands: Vec<CommandTypes> = target
+        .dependencies
+        .iter()
+        .map(|dependency| {
+            CommandTypes::Command(Command {
+                command: format!("zirv {}", script_name(dependency)),
+                ..Default::default()
+            })
+        })
+        .collect();
+    for (command, ignore_errors) in &target.commands {
+        commands.push(CommandTypes::Command(Command {
+            command: command.clone(),
+            options: ignore_errors.then(|| Options {
+                proceed_on_failure: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }));
+    }
+    Script {
+        name: target.name.clone(),
+        description: target.description.clone(),
+        params: (!target.params.is_empty()).then(|| target.params.clone()),
+        commands,
+        ..Default::default()
+    }
+}
+
+/// Writes one script into `dir` for every target in the package.json,
+/// Makefile or justfile at `from`, and reports the targets it had to skip.
+///
+/// A target whose name is not a usable file name gets a shortcut under its
+/// own name. Existing scripts of the same name are only replaced with
+/// `force` or when `confirm_fn` agrees. Returns the paths written.
+pub fn import_scripts<W, F>(
+    writer: &mut W,
+    from: &Path,
+    dir: &Path,
+    force: bool,
+    confirm_fn: F,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>>
+where
+    W: Write,
+    F: Fn(&str) -> Result<bool, Box<dyn std::error::Error>>,
+{
+    let source = ImportSource::detect(from)?;
+    let content =
+        fs::read_to_string(from).map_err(|e| format!("Could not read {}: {e}", from.display()))?;
+    let mut parsed = match source {
+        ImportSource::Npm => parse_npm(&content)?,
+        ImportSource::Make => parse_makefile(&content),
+        ImportSource::Just => parse_justfile(&content),
+    };
+    parsed.targets.retain(|target| {
+        if BUILTIN_COMMANDS.contains(&script_name(&target.name).as_str()) {
+            parsed.skipped.push((
+                target.name.clone(),
+                "has the name of a zirv command".to_string(),
+            ));
+            false
+        } else {
+            true
+        }
+    });
+    resolve_dependencies(&mut parsed);
+
+    fs::create_dir_all(dir)?;
+    let existing = script_names(dir)?;
+    let mut shortcuts = load_shortcuts(dir)?;
+    let mut written = Vec::new();
+    for target in &parsed.targets {
+        let name = script_name(&target.name);
+        let same_named: Vec<&PathBuf> = existing
+            .iter()
+            .filter(|(n, _)| *n == name)
+            .map(|(_, path)| path)
+            .collect();
+        if let Some(path) = same_named.first()
+            && !force
+            && !confirm_fn(&format!("{} already exists. Overwrite it?", path.display()))?
+        {
+            parsed
+                .skipped
+                .push((target.name.clone(), "kept the existing script".to_string()));
+            continue;
+        }
+        // A same-named script in another format would keep shadowing the new one.
+        for path in same_named {
+            fs::remove_file(path)?;
+        }
+
+        let file_name = format!("{name}.yaml");
+        let path = dir.join(&file_name);
+        fs::write(&path, serde_yaml::to_string(&to_script(target))?)?;
+        writeln!(writer, "Created script file: {}", path.display())?;
+        if target.name != name && !BUILTIN_COMMANDS.contains(&target.name.as_str()) {
+            shortcuts
+                .shortcuts
+                .insert(target.name.clone(), Shortcut::from(file_name.as_str()));
+            writeln!(writer, "Added shortcut '{}' -> {file_name}", target.name)?;
+        }
+        written.push(path);
+    }
+    save_shortcuts(dir, &shortcuts)?;
+
+    writeln!(
+        writer,
+        "Imported {} of {} targets from {}",
+        written.len(),
+        written.len() + parsed.skipped.len(),
+        from.display()
+    )?;
+    if !parsed.skipped.is_empty() {
+        writeln!(writer, "Skipped:")?;
+        for (name, reason) in &parsed.skipped {
+            writeln!(writer, "  {name}: {reason}")?;
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::list::list_scripts, utils::ScriptSource, utils::file_to_script};
+    use std::fs::write;
+    use tempfile::tempdir;
+
+    const PACKAGE_JSON: &str = r#"{
+  "name": "app",
+  "scripts": {
+    "build": "tsc -p . && vite build",
+    "test:unit": "vitest run --reporter \"dot && verbose\"",
+    "lint": ""
+  }
+}"#;
+
+    const MAKEFILE: &str = "\
+VERSION := 1.0
+.PHONY: all build test clean
+
+# Build everything
+all: build test
+
+build: src/main.c ## Compile the binary
+\t@mkdir -p out
+\tcc -o out/app src/main.c \\
+\t  -Wall
+
+test: build
+\t-./out/app --selftest
+\techo \"$$HOME\"
+
+clean:
+\trm -rf out $(BUILD_DIR)
+
+%.o: %.c
+\tcc -c $<
+
+release:
+";
+
+    const JUSTFILE: &str = "\
+set shell := [\"bash\", \"-c\"]
+
+# Deploy to an environment
+[no-cd]
+deploy env: build
+    @echo deploying to {{env}}
+    -./deploy.sh {{ env }}
+    echo $HOME ${USER} $$ {{env}}
+
+build:
+    cargo build
+
+greet name='world':
+    echo hi {{name}}
+
+script:
+    #!/usr/bin/env python3
+    print('hi')
+";
+
+    fn import(from: &Path, dir: &Path) -> String {
+        let mut buffer = Vec::new();
+        import_scripts(&mut buffer, from, dir, false, |_| Ok(false)).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_import_npm_scripts() {
+        let dir = tempdir().unwrap();
+        let from = dir.path().join("package.json");
+        write(&from, PACKAGE_JSON).unwrap();
+        let target = dir.path().join(".zirv");
+
+        let output = import(&from, &target);
+        assert!(output.contains("Imported 2 of 3 targets"), "{output}");
+        assert!(output.contains("  lint: is empty\n"), "{output}");
+
+        let build = file_to_script(&target.join("build.yaml")).unwrap();
+        let commands: Vec<String> = build.commands.iter().map(|c| c.label()).collect();
+        assert_eq!(commands, ["tsc -p .", "vite build"]);
+        let unit = file_to_script(&target.join("test-unit.yaml")).unwrap();
+        assert_eq!(
+            unit.commands[0].label(),
+            "vitest run --reporter \"dot && verbose\""
+        );
+
+        let mut listing = Vec::new();
+        list_scripts(&mut listing, &[(ScriptSource::Local, target)], false).unwrap();
+        let listing = String::from_utf8(listing).unwrap();
+        assert!(listing.contains("build"), "{listing}");
+        assert!(listing.contains("test:unit"), "{listing}");
+    }
+
+    #[test]
+    fn test_parse_makefile() {
+        let parsed = parse_makefile(MAKEFILE);
+        let names: Vec<&str> = parsed.targets.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["all", "build", "test"]);
+
+        assert_eq!(
+            parsed.targets[0].description.as_deref(),
+            Some("Build everything")
+        );
+        assert_eq!(parsed.targets[0].dependencies, ["build", "test"]);
+        assert_eq!(
+            parsed.targets[1].description.as_deref(),
+            Some("Compile the binary")
+        );
+        assert_eq!(
+            parsed.targets[1].commands,
+            [
+                ("mkdir -p out".to_string(), false),
+                ("cc -o out/app src/main.c  \t  -Wall".to_string(), false)
+            ]
+        );
+        assert_eq!(
+            parsed.targets[2].commands,
+            [
+                ("./out/app --selftest".to_string(), true),
+                ("echo \"$HOME\"".to_string(), false)
+            ]
+        );
+
+        let skipped: Vec<(&str, &str)> = parsed
+            .skipped
+            .iter()
+            .map(|(n, r)| (n.as_str(), r.as_str()))
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                ("clean", "uses make variables"),
+                ("%.o", "is a pattern rule"),
+                ("release", "has no recipe")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_makefile() {
+        let dir = tempdir().unwrap();
+        let from = dir.path().join("Makefile");
+        write(&from, MAKEFILE).unwrap();
+        let target = dir.path().join(".zirv");
+
+        let output = import(&from, &target);
+        assert!(output.contains("Imported 3 of 6 targets"), "{output}");
+
+        let all = file_to_script(&target.join("all.yaml")).unwrap();
+        let commands: Vec<String> = all.commands.iter().map(|c| c.label()).collect();
+        assert_eq!(commands, ["zirv build", "zirv test"]);
+
+        let test = file_to_script(&target.join("test.yaml")).unwrap();
+        // `build` is a prerequisite target; `src/main.c` of `build` is a file.
+        assert_eq!(test.commands[0].label(), "zirv build");
+        let build = file_to_script(&target.join("build.yaml")).unwrap();
+        assert_eq!(build.commands.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_justfile() {
+        let parsed = parse_justfile(JUSTFILE);
+        let names: Vec<&str> = parsed.targets.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["deploy", "build"]);
+
+        let deploy = &parsed.targets[0];
+        assert_eq!(
+            deploy.description.as_deref(),
+            Some("Deploy to an environment")
+        );
+        assert_eq!(deploy.params, ["env"]);
+        assert_eq!(deploy.dependencies, ["build"]);
+        assert_eq!(
+            deploy.commands,
+            [
+                ("echo deploying to ${env}".to_string(), false),
+                ("./deploy.sh ${env}".to_string(), true),
+                ("echo $HOME $${USER} $$$ ${env}".to_string(), false)
+            ]
+        );
+
+        let skipped: Vec<&str> = parsed.skipped.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(skipped, ["greet", "script"]);
+
+        let script = to_script(deploy);
+        assert_eq!(script.params, Some(vec!["env".to_string()]));
+        assert_eq!(script.commands[0].label(), "zirv build");
+        assert_eq!(script.commands[2].label(), "./deploy.sh ${env}");
+    }
+
+    #[test]
+    fn test_existing_scripts_need_confirmation_or_force() {
+        let dir = tempdir().unwrap();
+        let from = dir.path().join("justfile");
+        write(&from, "build:\n    cargo build\n").unwrap();
+        let target = dir.path().join(".zirv");
+        std::fs::create_dir_all(&target).unwrap();
+        write(
+            target.join("build.json"),
+            r#"{"name": "Mine", "commands": []}"#,
+        )
+        .unwrap();
+
+        let output = import(&from, &target);
+        assert!(
+            output.contains("  build: kept the existing script\n"),
+            "{output}"
+        );
+        assert!(target.join("build.json").exists());
+
+        import_scripts(&mut Vec::new(), &from, &target, false, |prompt| {
+            assert!(prompt.ends_with("build.json already exists. Overwrite it?"));
+            Ok(true)
+        })
+        .unwrap();
+        assert!(!target.join("build.json").exists());
+        assert!(target.join("build.yaml").exists());
+
+        import_scripts(&mut Vec::new(), &from, &target, true, |_| {
+            panic!("--force should not ask")
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_detect_source() {
+        assert_eq!(
+            ImportSource::detect(Path::new("web/package.json")),
+            Ok(ImportSource::Npm)
+        );
+        assert_eq!(
+            ImportSource::detect(Path::new("GNUmakefile")),
+            Ok(ImportSource::Make)
+        );
+        assert_eq!(
+            ImportSource::detect(Path::new("rules.mk")),
+            Ok(ImportSource::Make)
+        );
+        assert_eq!(
+            ImportSource::detect(Path::new(".justfile")),
+            Ok(ImportSource::Just)
+        );
+        assert!(ImportSource::detect(Path::new("build.gradle")).is_err());
+    }
+}
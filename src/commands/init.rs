@@ -1,170 +1,204 @@
 use dialoguer::Confirm;
 use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
+
+use crate::utils::{SCRIPT_DIR_NAME, zirv_home};
 
 // Default shortcuts file content.
 const DEFAULT_SHORTCUTS: &str = r#"shortcuts:
   e: "example.yaml"
 "#;
 
-/// Initializes the global .zirv folder in the home directory and (optionally)
-/// the .zirv folder in the current directory based on the confirmation function.
-///
-/// The `confirm_fn` closure is called to determine if the user wants to initialize
-/// in the current directory. In production, you can pass a closure that uses `dialoguer::Confirm`.
-pub fn init_zirv_with<F>(confirm_fn: F) -> Result<(), Box<dyn std::error::Error>>
-where
-    F: Fn() -> Result<bool, Box<dyn std::error::Error>>,
-{
-    // Instead of using dirs::home_dir(), use the HOME or USERPROFILE env variable.
-    let home = std::env::var("HOME")
-        .or_else(|_| std::env::var("USERPROFILE"))
-        .map(PathBuf::from)
-        .map_err(|_| "Could not determine home directory")?;
-    let home_zirv = home.join(".zirv");
-
-    if !home_zirv.exists() {
-        fs::create_dir_all(&home_zirv)?;
-        println!("Created .zirv in home directory: {home_zirv:?}");
+// The script the default `e` shortcut points at.
+const EXAMPLE_SCRIPT: &str = r#"name: Example
+description: An example script created by zirv init. Run it with `zirv e`.
+commands:
+  - command: echo "Hello from zirv"
+    description: Say hello
+"#;
+
+/// Which directories `init` sets up, as given on the command line.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InitOptions {
+    /// Initialize the current directory without asking.
+    pub local: bool,
+    /// Initialize the home directory.
+    pub global: bool,
+    /// Answer every question with yes.
+    pub yes: bool,
+    /// Add `.zirv/` to the project's `.gitignore` without asking.
+    pub gitignore: bool,
+}
+
+/// Creates `dir` with the default `.shortcuts.yaml` and the example script
+/// it points at, leaving existing files alone.
+fn init_dir(dir: &Path, location: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if !dir.exists() {
+        fs::create_dir_all(dir)?;
+        println!("Created .zirv in {location}: {dir:?}");
+    }
+    for (file, content) in [
+        (".shortcuts.yaml", DEFAULT_SHORTCUTS),
+        ("example.yaml", EXAMPLE_SCRIPT),
+    ] {
+        let path = dir.join(file);
+        if !path.exists() {
+            fs::write(&path, content)?;
+            println!("Created default {file} in {location}: {path:?}");
+        }
     }
-    // Create default .shortcuts.yaml in home folder if not present.
-    let home_shortcuts = home_zirv.join(".shortcuts.yaml");
-    if !home_shortcuts.exists() {
-        fs::write(&home_shortcuts, DEFAULT_SHORTCUTS)?;
-        println!("Created default .shortcuts.yaml in home directory: {home_shortcuts:?}");
+    Ok(())
+}
+
+/// Appends `.zirv/` to the `.gitignore` in `project`, unless it already
+/// ignores the directory.
+fn ignore_zirv_dir(project: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let path = project.join(".gitignore");
+    let existing = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let ignored = existing.lines().any(|line| {
+        matches!(
+            line.trim().trim_start_matches('/').trim_end_matches('/'),
+            SCRIPT_DIR_NAME
+        )
+    });
+    if ignored {
+        println!(".zirv is already ignored in {path:?}");
+        return Ok(());
     }
 
-    // Get the current directory.
-    let current_dir = std::env::current_dir()?;
-    let current_zirv = current_dir.join(".zirv");
-
-    if !current_zirv.exists() {
-        let init_current = confirm_fn()?;
-        if init_current {
-            fs::create_dir_all(&current_zirv)?;
-            println!("Created .zirv in current directory: {current_zirv:?}");
-            // Create default .shortcuts.yaml in current dicurrent_zirvrect
-            let current_shortcuts = current_zirv.join(".shortcuts.yaml");
-            if !current_shortcuts.exists() {
-                fs::write(&current_shortcuts, DEFAULT_SHORTCUTS)?;
-                println!(
-                    "Created default .shortcuts.yaml in current directory: {current_shortcuts:?}"
-                );
-            }
+    let mut content = existing;
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&format!("{SCRIPT_DIR_NAME}/\n"));
+    fs::write(&path, content)?;
+    println!("Added {SCRIPT_DIR_NAME}/ to {path:?}");
+    Ok(())
+}
+
+/// Initializes the global .zirv folder in `home_zirv` and (optionally) the
+/// .zirv folder in `current_dir`, each with the default shortcuts and the
+/// example script.
+///
+/// Without `--local` or `--global`, the global folder is always created and
+/// the `confirm_fn` closure is called to determine if the user wants to
+/// initialize in the current directory as well. With either flag, exactly
+/// the chosen folders are created. Initializing locally also offers to add
+/// `.zirv/` to the project's `.gitignore`. With `--yes`, `confirm_fn` is
+/// never called and every question is answered with yes. In production, you
+/// can pass a closure that uses `dialoguer::Confirm`.
+pub fn init_zirv_with<F>(
+    options: &InitOptions,
+    home_zirv: Option<&Path>,
+    current_dir: &Path,
+    confirm_fn: F,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: Fn(&str) -> Result<bool, Box<dyn std::error::Error>>,
+{
+    let confirm = |prompt: &str| -> Result<bool, Box<dyn std::error::Error>> {
+        if options.yes {
+            Ok(true)
         } else {
-            println!(".zirv not created in current directory.");
+            confirm_fn(prompt)
         }
-    } else {
+    };
+
+    if options.global || !options.local {
+        let home_zirv = home_zirv.ok_or("Could not determine home directory")?;
+        init_dir(home_zirv, "home directory")?;
+    }
+
+    let current_zirv = current_dir.join(SCRIPT_DIR_NAME);
+    if current_zirv.exists() && !options.local {
         println!(".zirv already exists in current directory.");
+        return Ok(());
+    }
+    let init_current = options.local
+        || (!options.global
+            && confirm("Would you like to initialize .zirv in the current directory?")?);
+    if !init_current {
+        println!(".zirv not created in current directory.");
+        return Ok(());
     }
+    init_dir(&current_zirv, "current directory")?;
 
+    if options.gitignore || confirm("Add .zirv/ to the project's .gitignore?")? {
+        ignore_zirv_dir(current_dir)?;
+    }
     Ok(())
 }
 
 /// Production version: calls init_zirv_with using dialoguer to ask the user.
-pub fn init_zirv() -> Result<(), Box<dyn std::error::Error>> {
-    init_zirv_with(|| {
-        Confirm::new()
-            .with_prompt("Would you like to initialize .zirv in the current directory?")
-            .default(false)
-            .interact()
-            .map_err(|e| e.into())
-    })
+pub fn init_zirv(options: &InitOptions) -> Result<(), Box<dyn std::error::Error>> {
+    init_zirv_with(
+        options,
+        zirv_home().as_deref(),
+        &std::env::current_dir()?,
+        |prompt| {
+            Confirm::new()
+                .with_prompt(prompt)
+                .default(false)
+                .interact()
+                .map_err(|e| e.into())
+        },
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env;
-    use std::fs::{read_to_string, remove_dir_all};
-    use std::path::PathBuf;
+    use crate::utils::file_to_script;
+    use std::cell::RefCell;
+    use std::fs::{read_to_string, write};
     use tempfile::tempdir;
 
     const DEFAULT_SHORTCUTS_CONTENT: &str = r#"shortcuts:
   e: "example.yaml"
 "#;
 
-    /// Helper function to temporarily override HOME and USERPROFILE.
-    fn with_fake_home<F, R>(fake_home: &PathBuf, test: F) -> R
-    where
-        F: FnOnce() -> R,
-    {
-        let original_home = env::var("HOME").ok();
-        let original_userprofile = env::var("USERPROFILE").ok();
-
-        unsafe {
-            env::set_var("HOME", fake_home);
-            env::set_var("USERPROFILE", fake_home);
-        }
-
-        let result = test();
-
-        unsafe {
-            if let Some(home) = original_home {
-                env::set_var("HOME", home);
-            } else {
-                env::remove_var("HOME");
-            }
-            if let Some(up) = original_userprofile {
-                env::set_var("USERPROFILE", up);
-            } else {
-                env::remove_var("USERPROFILE");
-            }
-        }
-
-        result
-    }
-
     /// Test that only the home directory .zirv folder (with default .shortcuts.yaml) is created,
     /// if the user declines to initialize in the current directory.
     #[test]
     fn test_init_zirv_only_home() -> Result<(), Box<dyn std::error::Error>> {
         // Create temporary directories for fake home and current directory.
         let fake_home_dir = tempdir()?;
-        let fake_home_path = fake_home_dir.path().to_path_buf();
+        let home_zirv = fake_home_dir.path().join(".zirv");
         let fake_current_dir = tempdir()?;
-        let fake_current_path = fake_current_dir.path().to_path_buf();
-
-        // Override HOME and USERPROFILE.
-        with_fake_home(&fake_home_path, || {
-            // Change current directory to fake_current.
-            let original_dir = env::current_dir().unwrap();
-            env::set_current_dir(&fake_current_path).unwrap();
-
-            // Ensure no .zirv exists in current directory.
-            let current_zirv = fake_current_path.join(".zirv");
-            if current_zirv.exists() {
-                remove_dir_all(&current_zirv).unwrap();
-            }
-
-            // Call init_zirv_with with a confirmation function that returns false.
-            init_zirv_with(|| Ok(false)).unwrap();
-
-            // Verify that .zirv exists in the fake home directory.
-            let home_zirv = fake_home_path.join(".zirv");
-
-            assert!(
-                home_zirv.exists(),
-                ".zirv should be created in the home directory"
-            );
-
-            let home_shortcuts = home_zirv.join(".shortcuts.yaml");
-            assert!(
-                home_shortcuts.exists(),
-                ".shortcuts.yaml should be created in the home directory"
-            );
-
-            let content = read_to_string(&home_shortcuts).unwrap();
-            assert_eq!(content, DEFAULT_SHORTCUTS_CONTENT);
-
-            // Verify that .zirv was NOT created in the current directory.
-            assert!(
-                !current_zirv.exists(),
-                ".zirv should not be created in the current directory"
-            );
-
-            env::set_current_dir(&original_dir).unwrap();
-        });
+        let current_zirv = fake_current_dir.path().join(".zirv");
+
+        // Call init_zirv_with with a confirmation function that returns false.
+        init_zirv_with(
+            &InitOptions::default(),
+            Some(&home_zirv),
+            fake_current_dir.path(),
+            |_| Ok(false),
+        )?;
+
+        // Verify that .zirv exists in the fake home directory.
+        assert!(
+            home_zirv.exists(),
+            ".zirv should be created in the home directory"
+        );
+
+        let home_shortcuts = home_zirv.join(".shortcuts.yaml");
+        assert!(
+            home_shortcuts.exists(),
+            ".shortcuts.yaml should be created in the home directory"
+        );
+
+        let content = read_to_string(&home_shortcuts)?;
+        assert_eq!(content, DEFAULT_SHORTCUTS_CONTENT);
+
+        // Verify that .zirv was NOT created in the current directory.
+        assert!(
+            !current_zirv.exists(),
+            ".zirv should not be created in the current directory"
+        );
 
         Ok(())
     }
@@ -174,60 +208,135 @@ mod tests {
     #[test]
     fn test_init_zirv_home_and_current() -> Result<(), Box<dyn std::error::Error>> {
         let fake_home_dir = tempdir()?;
-        let fake_home_path = fake_home_dir.path().to_path_buf();
+        let home_zirv = fake_home_dir.path().join(".zirv");
         let fake_current_dir = tempdir()?;
-        let fake_current_path = fake_current_dir.path().to_path_buf();
+        let current_zirv = fake_current_dir.path().join(".zirv");
 
-        with_fake_home(&fake_home_path, || {
-            let original_dir = env::current_dir().unwrap();
-            env::set_current_dir(&fake_current_path).unwrap();
+        // Agree to initialize the current directory, but not to touch .gitignore.
+        init_zirv_with(
+            &InitOptions::default(),
+            Some(&home_zirv),
+            fake_current_dir.path(),
+            |prompt| Ok(prompt.contains("initialize")),
+        )?;
 
-            // Ensure no .zirv exists in the current directory.
-            let current_zirv = fake_current_path.join(".zirv");
-            if current_zirv.exists() {
-                remove_dir_all(&current_zirv).unwrap();
-            }
+        // Verify that .zirv exists in the home directory.
+        assert!(
+            home_zirv.exists(),
+            ".zirv should be created in the home directory"
+        );
 
-            // Call init_zirv_with with a confirmation function that returns true.
-            init_zirv_with(|| Ok(true)).unwrap();
+        let home_shortcuts = home_zirv.join(".shortcuts.yaml");
 
-            // Verify that .zirv exists in the home directory.
-            let home_zirv = fake_home_path.join(".zirv");
+        assert!(
+            home_shortcuts.exists(),
+            "Global .shortcuts.yaml should be created"
+        );
 
-            assert!(
-                home_zirv.exists(),
-                ".zirv should be created in the home directory"
-            );
+        let home_content = read_to_string(home_shortcuts)?;
+        assert_eq!(home_content, DEFAULT_SHORTCUTS_CONTENT);
 
-            let home_shortcuts = home_zirv.join(".shortcuts.yaml");
+        // Verify that .zirv exists in the current directory.
+        assert!(
+            current_zirv.exists(),
+            ".zirv should be created in the current directory"
+        );
 
-            assert!(
-                home_shortcuts.exists(),
-                "Global .shortcuts.yaml should be created"
-            );
+        let current_shortcuts = current_zirv.join(".shortcuts.yaml");
 
-            let home_content = read_to_string(home_shortcuts).unwrap();
-            assert_eq!(home_content, DEFAULT_SHORTCUTS_CONTENT);
+        assert!(
+            current_shortcuts.exists(),
+            "Local .shortcuts.yaml should be created"
+        );
 
-            // Verify that .zirv exists in the current directory.
-            assert!(
-                current_zirv.exists(),
-                ".zirv should be created in the current directory"
-            );
+        let current_content = read_to_string(current_shortcuts)?;
+        assert_eq!(current_content, DEFAULT_SHORTCUTS_CONTENT);
+        assert!(!fake_current_dir.path().join(".gitignore").exists());
 
-            let current_shortcuts = current_zirv.join(".shortcuts.yaml");
+        Ok(())
+    }
 
-            assert!(
-                current_shortcuts.exists(),
-                "Local .shortcuts.yaml should be created"
-            );
+    #[test]
+    fn test_flags_skip_prompts() -> Result<(), Box<dyn std::error::Error>> {
+        let never = |prompt: &str| -> Result<bool, Box<dyn std::error::Error>> {
+            panic!("unexpected prompt: {prompt}")
+        };
+        let cases = [
+            (true, true, true),
+            (true, false, false),
+            (false, true, true),
+        ];
+        for (local, global, yes) in cases {
+            let home = tempdir()?;
+            let current = tempdir()?;
+            let home_zirv = home.path().join(".zirv");
+            let options = InitOptions {
+                local,
+                global,
+                yes,
+                gitignore: local && !yes,
+            };
+            init_zirv_with(&options, Some(&home_zirv), current.path(), never)?;
+
+            assert_eq!(home_zirv.join("example.yaml").exists(), global);
+            assert_eq!(current.path().join(".zirv/example.yaml").exists(), local);
+            assert_eq!(current.path().join(".gitignore").exists(), local);
+        }
 
-            let current_content = read_to_string(current_shortcuts).unwrap();
-            assert_eq!(current_content, DEFAULT_SHORTCUTS_CONTENT);
+        // Only the local directory needs no home directory.
+        let current = tempdir()?;
+        let options = InitOptions {
+            local: true,
+            gitignore: true,
+            ..Default::default()
+        };
+        init_zirv_with(&options, None, current.path(), never)?;
+        assert!(current.path().join(".zirv").is_dir());
+        Ok(())
+    }
 
-            env::set_current_dir(&original_dir).unwrap();
-        });
+    #[test]
+    fn test_gitignore_is_appended_once() -> Result<(), Box<dyn std::error::Error>> {
+        let current = tempdir()?;
+        write(current.path().join(".gitignore"), "target")?;
+        let asked = RefCell::new(Vec::new());
+        let options = InitOptions {
+            local: true,
+            ..Default::default()
+        };
+        let confirm = |prompt: &str| {
+            asked.borrow_mut().push(prompt.to_string());
+            Ok(true)
+        };
+
+        init_zirv_with(&options, None, current.path(), confirm)?;
+        init_zirv_with(&options, None, current.path(), confirm)?;
+        assert_eq!(
+            read_to_string(current.path().join(".gitignore"))?,
+            "target\n.zirv/\n"
+        );
+        assert_eq!(asked.borrow().len(), 2);
+        Ok(())
+    }
 
+    #[test]
+    fn test_example_script_parses() -> Result<(), Box<dyn std::error::Error>> {
+        let home = tempdir()?;
+        let home_zirv = home.path().join(".zirv");
+        let current = tempdir()?;
+        init_zirv_with(
+            &InitOptions {
+                global: true,
+                ..Default::default()
+            },
+            Some(&home_zirv),
+            current.path(),
+            |_| Ok(false),
+        )?;
+
+        let script = file_to_script(&home_zirv.join("example.yaml"))?;
+        assert_eq!(script.name, "Example");
+        assert_eq!(script.commands.len(), 1);
         Ok(())
     }
 }
@@ -0,0 +1,326 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ZirvError,
+    script_runner::{RunReport, Script, StepStatus},
+    utils::{ScriptLocation, file_to_script_with_overlay, zirv_home},
+};
+
+/// The file in `~/.zirv` each run is recorded in, one JSON object a line.
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// One invocation of a script, as recorded in the history file. Secrets are
+/// read from the environment or supplied by the caller, never passed as
+/// params, so they are not recorded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// The script that was run.
+    pub script: ScriptLocation,
+    /// The `--env` overlay it was run with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+    /// The params the script received, bundled ones included.
+    #[serde(default)]
+    pub params: Vec<String>,
+    /// When the run started, in RFC 3339.
+    pub timestamp: String,
+    /// The exit code zirv ended with.
+    pub exit_code: u8,
+    /// How long the run took, in milliseconds. Only recorded for a single
+    /// run, not for `--repeat` or `--watch`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// How long each step that ran took, in execution order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<StepTiming>,
+}
+
+/// How long a step of a recorded run took.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepTiming {
+    /// 1-based position of the step in the script.
+    pub index: usize,
+    /// The (unsubstituted) command of the step.
+    pub command: String,
+    pub duration_ms: u64,
+}
+
+impl RunRecord {
+    /// The record of running `script` now, which ended with `exit_code`.
+    pub fn new(
+        script: &ScriptLocation,
+        env: Option<&str>,
+        params: &[String],
+        exit_code: u8,
+    ) -> RunRecord {
+        RunRecord {
+            script: script.canonicalize().unwrap_or_else(|_| script.clone()),
+            env: env.map(str::to_string),
+            params: params.to_vec(),
+            timestamp: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            exit_code,
+            duration_ms: None,
+            steps: Vec::new(),
+        }
+    }
+
+    /// The same record, with the durations of the run `report` describes.
+    pub fn with_report(self, report: &RunReport) -> RunRecord {
+        RunRecord {
+            duration_ms: Some(report.duration.as_millis() as u64),
+            steps: report
+                .steps
+                .iter()
+                .filter(|step| step.status != StepStatus::Skipped)
+                .map(|step| StepTiming {
+                    index: step.index,
+                    command: step.command.clone(),
+                    duration_ms: step.duration.as_millis() as u64,
+                })
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Loads the recorded script, with its overlay merged over it.
+    pub fn load_script(&self) -> Result<Script, ZirvError> {
+        match &self.env {
+            Some(env) => Ok(file_to_script_with_overlay(&self.script, env)?.0),
+            None => self.script.load(),
+        }
+    }
+
+    /// The run on one line: the script followed by its params.
+    fn command_line(&self) -> String {
+        let mut line = self.script.to_string();
+        if let Some(env) = &self.env {
+            line.push_str(&format!(" --env {env}"));
+        }
+        for param in &self.params {
+            line.push(' ');
+            line.push_str(param);
+        }
+        line
+    }
+}
+
+/// The history file, `~/.zirv/history.jsonl`.
+pub fn history_path() -> Option<PathBuf> {
+    zirv_home().map(|dir| dir.join(HISTORY_FILE))
+}
+
+/// Appends `record` to the history file at `path`, creating it if needed.
+pub fn append_record(path: &Path, record: &RunRecord) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// Records a run in the history file. Recording is best-effort: a history
+/// that cannot be written must never fail the run itself.
+pub fn record_run(record: &RunRecord) {
+    if let Some(path) = history_path() {
+        let _ = append_record(&path, record);
+    }
+}
+
+/// The runs recorded at `path`, oldest first. A missing file holds no runs,
+/// and lines that cannot be read, such as one cut short, are skipped.
+pub fn read_records(path: &Path) -> Result<Vec<RunRecord>, ZirvError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Prints the last `limit` runs, newest first.
+pub fn show_history<W: Write>(
+    writer: &mut W,
+    records: &[RunRecord],
+    limit: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if records.is_empty() {
+        writeln!(writer, "No runs recorded yet.")?;
+        return Ok(());
+    }
+    for (n, record) in records.iter().rev().take(limit).enumerate() {
+        writeln!(
+            writer,
+            "{:>3}  {}  exit {:<3}  {}",
+            n + 1,
+            record.timestamp,
+            record.exit_code,
+            record.command_line()
+        )?;
+    }
+    Ok(())
+}
+
+/// The run `zirv last` repeats: the most recent one, or with `pick`, the
+/// one `select` chooses among the last `pick`, which it is given newest
+/// first.
+pub fn choose_record<F>(
+    records: &[RunRecord],
+    pick: Option<usize>,
+    select: F,
+) -> Result<&RunRecord, ZirvError>
+where
+    F: FnOnce(&[String]) -> Result<usize, Box<dyn std::error::Error>>,
+{
+    let Some(last) = records.last() else {
+        return Err("No runs recorded yet".into());
+    };
+    let Some(limit) = pick else {
+        return Ok(last);
+    };
+    let recent: Vec<&RunRecord> = records.iter().rev().take(limit.max(1)).collect();
+    let items: Vec<String> = recent
+        .iter()
+        .map(|record| format!("{}  {}", record.timestamp, record.command_line()))
+        .collect();
+    let index = select(&items)?;
+    recent
+        .get(index)
+        .copied()
+        .ok_or_else(|| format!("No run at position {}", index + 1).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script_runner::{RunOptions, execute};
+
+    fn record(script: &str, params: &[&str], exit_code: u8) -> RunRecord {
+        RunRecord {
+            script: ScriptLocation::File(PathBuf::from(script)),
+            env: None,
+            params: params.iter().map(|p| p.to_string()).collect(),
+            timestamp: "2026-10-16T12:00:00+02:00".to_string(),
+            exit_code,
+            duration_ms: None,
+            steps: Vec::new(),
+        }
+    }
+
+    fn write_script(dir: &Path, name: &str, marker: &Path) -> PathBuf {
+        let path = dir.join(format!("{name}.yaml"));
+        let content = format!(
+            "name: {name}\nparams:\n  - word\ncommands:\n  - command: echo ${{word}} >> {}\n",
+            marker.display()
+        );
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_last_reruns_the_most_recent_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = dir.path().join(".zirv").join(HISTORY_FILE);
+        let options = RunOptions {
+            no_summary: true,
+            ..Default::default()
+        };
+        let markers = [dir.path().join("first.txt"), dir.path().join("second.txt")];
+        for (name, marker) in ["first", "second"].iter().zip(&markers) {
+            let path = write_script(dir.path(), name, marker);
+            let params = vec![name.to_string()];
+            let location = ScriptLocation::File(path);
+            let script = location.load().unwrap();
+            execute(&script, &params, &options).await.unwrap();
+            append_record(&history, &RunRecord::new(&location, None, &params, 0)).unwrap();
+        }
+
+        let records = read_records(&history).unwrap();
+        assert_eq!(records.len(), 2);
+        let last = choose_record(&records, None, |_| unreachable!()).unwrap();
+        let script = last.load_script().unwrap();
+        execute(&script, &last.params, &options).await.unwrap();
+
+        assert_eq!(fs::read_to_string(&markers[0]).unwrap(), "first\n");
+        assert_eq!(fs::read_to_string(&markers[1]).unwrap(), "second\nsecond\n");
+    }
+
+    #[test]
+    fn test_pick_offers_recent_runs_newest_first() {
+        let records = [
+            record("/p/a.yaml", &[], 0),
+            record("/p/b.yaml", &["x"], 1),
+            record("/p/c.yaml", &[], 0),
+        ];
+        let picked = choose_record(&records, Some(2), |items| {
+            assert_eq!(
+                items,
+                [
+                    "2026-10-16T12:00:00+02:00  /p/c.yaml",
+                    "2026-10-16T12:00:00+02:00  /p/b.yaml x"
+                ]
+            );
+            Ok(1)
+        })
+        .unwrap();
+        assert_eq!(picked, &records[1]);
+
+        let error = choose_record(&[], None, |_| Ok(0)).unwrap_err();
+        assert_eq!(error.to_string(), "No runs recorded yet");
+    }
+
+    #[test]
+    fn test_unreadable_lines_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = dir.path().join(HISTORY_FILE);
+        assert!(read_records(&history).unwrap().is_empty());
+
+        append_record(&history, &record("/p/a.yaml", &[], 0)).unwrap();
+        let mut file = fs::OpenOptions::new().append(true).open(&history).unwrap();
+        writeln!(file, "{{\"script\": \"/p/b.ya").unwrap();
+        append_record(&history, &record("/p/c.yaml", &["1"], 2)).unwrap();
+
+        let records = read_records(&history).unwrap();
+        assert_eq!(
+            records,
+            [record("/p/a.yaml", &[], 0), record("/p/c.yaml", &["1"], 2)]
+        );
+    }
+
+    #[test]
+    fn test_show_history() {
+        let mut buffer = Vec::new();
+        let records = [
+            record("/p/a.yaml", &[], 0),
+            RunRecord {
+                env: Some("prod".to_string()),
+                ..record("/p/b.yaml", &["x", "y"], 127)
+            },
+        ];
+        show_history(&mut buffer, &records, 20).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "  1  2026-10-16T12:00:00+02:00  exit 127  /p/b.yaml --env prod x y\n  \
+             2  2026-10-16T12:00:00+02:00  exit 0    /p/a.yaml\n"
+        );
+
+        let mut buffer = Vec::new();
+        show_history(&mut buffer, &[], 20).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "No runs recorded yet.\n"
+        );
+    }
+}
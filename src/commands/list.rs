@@ -0,0 +1,248 @@
+use std::{io::Write, path::PathBuf};
+
+use serde::Serialize;
+
+use crate::{
+    catalog::{effective_scripts, effective_shortcuts},
+    commands::BUILTIN_COMMANDS,
+    utils::{ScriptLocation, ScriptSource, Shortcut, ShortcutParam},
+};
+
+/// One line of `zirv list`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Entry {
+    Script {
+        /// The name the script is run with, e.g. `db/migrate`.
+        name: String,
+        source: ScriptSource,
+        /// The file the script is in, with its name in that file for a
+        /// script in `.zirv.yaml`.
+        path: ScriptLocation,
+        /// The first line of the script's description.
+        description: Option<String>,
+        /// Other names the script declares for itself.
+        aliases: Vec<String>,
+        /// Sources of same-named scripts hidden by this one.
+        overrides: Vec<ScriptSource>,
+        /// Whether a built-in command of the same name runs instead.
+        hidden_by_builtin: bool,
+    },
+    Shortcut {
+        name: String,
+        /// The script the shortcut runs, as written in the shortcuts file.
+        target: String,
+        /// Params bundled into the shortcut.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        params: Vec<ShortcutParam>,
+        source: ScriptSource,
+    },
+}
+
+/// Prints one line per available script and shortcut, or a JSON array of
+/// them with `json`. A script hides same-named scripts from later sources.
+pub fn list_scripts<W: Write>(
+    writer: &mut W,
+    sources: &[(ScriptSource, PathBuf)],
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = collect_entries(sources)?;
+
+    if json {
+        serde_json::to_writer_pretty(&mut *writer, &entries)?;
+        writeln!(writer)?;
+        return Ok(());
+    }
+
+    let width = entries
+        .iter()
+        .map(|entry| match entry {
+            Entry::Script { name, .. } => name.len(),
+            Entry::Shortcut {
+                name,
+                target,
+                params,
+                ..
+            } => name.len() + command_line(target, params).len() + 4,
+        })
+        .max()
+        .unwrap_or(0);
+
+    let mut shortcuts_started = false;
+    for entry in &entries {
+        match entry {
+            Entry::Script {
+                name,
+                source,
+                description,
+                aliases,
+                overrides,
+                hidden_by_builtin,
+                ..
+            } => {
+                let mut line = format!("{name:<width$}  {:<6}", source.as_str());
+                if let Some(description) = description {
+                    line.push_str("  ");
+                    line.push_str(description);
+                }
+                if !aliases.is_empty() {
+                    line.push_str(&format!(" (aliases: {})", aliases.join(", ")));
+                }
+                if !overrides.is_empty() {
+                    let hidden: Vec<&str> = overrides.iter().map(ScriptSource::as_str).collect();
+                    line.push_str(&format!(" (overrides {})", hidden.join(", ")));
+                }
+                if *hidden_by_builtin {
+                    line.push_str(" (hidden by the built-in command; rename it to run it)");
+                }
+                writeln!(writer, "{}", line.trim_end())?;
+            }
+            Entry::Shortcut {
+                name,
+                target,
+                params,
+                source,
+            } => {
+                if !shortcuts_started {
+                    shortcuts_started = true;
+                    writeln!(writer, "\nShortcuts:")?;
+                }
+                let mapping = format!("{name} -> {}", command_line(target, params));
+                writeln!(writer, "{mapping:<width$}  {}", source.as_str())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_entries(
+    sources: &[(ScriptSource, PathBuf)],
+) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    let scripts = effective_scripts(sources)?.into_iter().map(|script| {
+        let mut overrides: Vec<ScriptSource> = Vec::new();
+        for (source, _) in script.overrides {
+            if !overrides.contains(&source) {
+                overrides.push(source);
+            }
+        }
+        let (description, aliases) = describe(&script.path);
+        Entry::Script {
+            description,
+            aliases,
+            hidden_by_builtin: BUILTIN_COMMANDS.contains(&script.name.as_str()),
+            name: script.name,
+            source: script.source,
+            path: script.path,
+            overrides,
+        }
+    });
+    let shortcuts = effective_shortcuts(sources)?
+        .into_iter()
+        .map(|shortcut| Entry::Shortcut {
+            name: shortcut.key,
+            target: shortcut.target.script().to_string(),
+            params: shortcut.target.params().to_vec(),
+            source: shortcut.source,
+        });
+    Ok(scripts.chain(shortcuts).collect())
+}
+
+/// What a shortcut runs, e.g. `deploy prod region=eu`.
+fn command_line(target: &str, params: &[ShortcutParam]) -> String {
+    Shortcut::WithParams {
+        script: target.to_string(),
+        params: params.to_vec(),
+    }
+    .to_string()
+}
+
+/// The first line of the script's description and its aliases, if it
+/// parses.
+fn describe(location: &ScriptLocation) -> (Option<String>, Vec<String>) {
+    let Ok(script) = location.load() else {
+        return (None, Vec::new());
+    };
+    let description = script
+        .description
+        .and_then(|d| d.lines().next().map(|line| line.trim().to_string()));
+    (description, script.aliases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+    use tempfile::tempdir;
+
+    fn fixture() -> (tempfile::TempDir, Vec<(ScriptSource, PathBuf)>) {
+        let temp_dir = tempdir().unwrap();
+        let local = temp_dir.path().join("project").join(".zirv");
+        let global = temp_dir.path().join("home").join(".zirv");
+        create_dir_all(local.join("db")).unwrap();
+        create_dir_all(&global).unwrap();
+
+        let script =
+            |description: &str| format!("name: x\ndescription: \"{description}\"\ncommands: []\n");
+        write(local.join("build.yaml"), script("Build it\\nwith details")).unwrap();
+        write(
+            local.join("db").join("migrate.yaml"),
+            "name: x\ndescription: Migrate\naliases: [m, mig]\ncommands: []\n",
+        )
+        .unwrap();
+        write(
+            local.join(".shortcuts.yaml"),
+            "shortcuts:\n  b: build\n  bp:\n    script: build\n    params: [release]\n",
+        )
+        .unwrap();
+        write(global.join("build.yaml"), script("Global build")).unwrap();
+        write(global.join("deploy.toml"), "name = \"x\"\ncommands = []\n").unwrap();
+        write(global.join("stats.yaml"), script("Counts lines")).unwrap();
+
+        let sources = vec![(ScriptSource::Local, local), (ScriptSource::Global, global)];
+        (temp_dir, sources)
+    }
+
+    #[test]
+    fn test_list_scripts_plain() {
+        let (_dir, sources) = fixture();
+        let mut buffer = Vec::new();
+        list_scripts(&mut buffer, &sources, false).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "build                local   Build it (overrides global)\n\
+             db/migrate           local   Migrate (aliases: m, mig)\n\
+             deploy               global\n\
+             stats                global  Counts lines (hidden by the built-in command; rename it to run it)\n\
+             \n\
+             Shortcuts:\n\
+             b -> build           local\n\
+             bp -> build release  local\n"
+        );
+    }
+
+    #[test]
+    fn test_list_scripts_json() {
+        let (_dir, sources) = fixture();
+        let mut buffer = Vec::new();
+        list_scripts(&mut buffer, &sources, true).unwrap();
+
+        let entries: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        let entries = entries.as_array().unwrap();
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries[0]["kind"], "script");
+        assert_eq!(entries[0]["name"], "build");
+        assert_eq!(entries[0]["source"], "local");
+        assert_eq!(entries[0]["overrides"], serde_json::json!(["global"]));
+        assert_eq!(entries[1]["aliases"], serde_json::json!(["m", "mig"]));
+        assert_eq!(entries[2]["name"], "deploy");
+        assert_eq!(entries[2]["description"], serde_json::Value::Null);
+        assert_eq!(entries[2]["hidden_by_builtin"], false);
+        assert_eq!(entries[3]["hidden_by_builtin"], true);
+        assert_eq!(entries[4]["kind"], "shortcut");
+        assert_eq!(entries[4]["target"], "build");
+        assert_eq!(entries[4].get("params"), None);
+        assert_eq!(entries[5]["params"], serde_json::json!(["release"]));
+    }
+}
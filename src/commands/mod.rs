@@ -1,4 +1,57 @@
+pub mod clean;
+pub mod config;
 pub mod create;
+pub mod dangerous;
+pub mod edit;
+pub mod explain;
+pub mod export;
 pub mod help;
+pub mod history;
+pub mod hook;
+pub mod import;
 pub mod init;
+pub mod last;
+pub mod list;
+pub mod promote;
+pub mod remove;
+pub mod rename;
+pub mod run_url;
+pub mod schema;
+pub mod shortcut;
+pub mod stats;
+pub mod template;
+pub mod validate;
 pub mod version;
+
+/// Commands and aliases handled by zirv itself. They take precedence over
+/// scripts and shortcuts of the same name.
+pub const BUILTIN_COMMANDS: &[&str] = &[
+    "help",
+    "h",
+    "version",
+    "v",
+    "list",
+    "schema",
+    "validate",
+    "edit",
+    "remove",
+    "rename",
+    "promote",
+    "demote",
+    "shortcut",
+    "init",
+    "i",
+    "create",
+    "c",
+    "explain",
+    "export",
+    "import",
+    "run-url",
+    "config",
+    "last",
+    "history",
+    "stats",
+    "clean",
+    "install-hook",
+    "uninstall-hook",
+];
@@ -0,0 +1,248 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    catalog::{load_shortcuts, locate_script, save_shortcuts, shortcuts_to},
+    utils::{SUPPORTED_EXTENSIONS, ScriptSource},
+};
+
+/// Copies the `from` script `name` refers to into `to_dir` under the same
+/// name, along with the shortcuts pointing at it. Used by `promote` (local
+/// to global) and `demote` (global to local).
+///
+/// `confirm` is asked before replacing the scripts of the same name in
+/// `to_dir`, in any format. With `move_source`, the original and its shortcuts are removed
+/// afterwards.
+pub fn copy_script<W: Write>(
+    writer: &mut W,
+    sources: &[(ScriptSource, PathBuf)],
+    name: &str,
+    from: ScriptSource,
+    to_dir: &Path,
+    move_source: bool,
+    confirm: impl FnOnce(&str) -> Result<bool, Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let script = locate_script(sources, name, Some(from))?;
+    let file_name = script.path.strip_prefix(&script.dir)?;
+    let target = to_dir.join(file_name);
+
+    // Lookup only sees one of several formats, but each would keep
+    // shadowing the copy.
+    let existing: Vec<PathBuf> = SUPPORTED_EXTENSIONS
+        .iter()
+        .map(|ext| to_dir.join(format!("{}.{ext}", script.name)))
+        .filter(|path| path.is_file())
+        .collect();
+    if !existing.is_empty() {
+        let shown: Vec<String> = existing.iter().map(|p| p.display().to_string()).collect();
+        let question = match shown.as_slice() {
+            [one] => format!("{one} already exists. Overwrite it?"),
+            _ => format!("{} already exist. Overwrite them?", shown.join(", ")),
+        };
+        if !confirm(&question)? {
+            writeln!(writer, "Nothing copied.")?;
+            return Ok(());
+        }
+        for path in &existing {
+            fs::remove_file(path)?;
+        }
+    }
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(&script.path, &target)?;
+    writeln!(writer, "Copied {} to {}", script.name, target.display())?;
+
+    let mut source_shortcuts = load_shortcuts(&script.dir)?;
+    let carried = shortcuts_to(&source_shortcuts, &script.name);
+    if !carried.is_empty() {
+        let mut target_shortcuts = load_shortcuts(to_dir)?;
+        for (key, target) in &carried {
+            match target_shortcuts.shortcuts.get(key) {
+                Some(other) if other != target => writeln!(
+                    writer,
+                    "Shortcut '{key}' already points at '{other}' in {}; left unchanged",
+                    to_dir.display()
+                )?,
+                Some(_) => {}
+                None => {
+                    target_shortcuts
+                        .shortcuts
+                        .insert(key.clone(), target.clone());
+                    writeln!(writer, "Added shortcut '{key}' -> {target}")?;
+                }
+            }
+        }
+        save_shortcuts(to_dir, &target_shortcuts)?;
+    }
+
+    if move_source {
+        fs::remove_file(&script.path)?;
+        writeln!(writer, "Removed {}", script.path.display())?;
+        if !carried.is_empty() {
+            for (key, _) in &carried {
+                source_shortcuts.shortcuts.remove(key);
+            }
+            save_shortcuts(&script.dir, &source_shortcuts)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::fs::{create_dir_all, read_to_string, write};
+    use tempfile::tempdir;
+
+    fn setup(root: &Path) -> Vec<(ScriptSource, PathBuf)> {
+        let local = root.join("project").join(".zirv");
+        let global = root.join("home").join(".zirv");
+        create_dir_all(local.join("db")).unwrap();
+        write(local.join("db").join("seed.yaml"), "name: Local Seed\n").unwrap();
+        write(local.join("lint.json"), "{\"name\": \"Local Lint\"}").unwrap();
+        write(
+            local.join(".shortcuts.yaml"),
+            "shortcuts:\n  l: lint\n  s: db/seed\n",
+        )
+        .unwrap();
+        vec![(ScriptSource::Local, local), (ScriptSource::Global, global)]
+    }
+
+    #[test]
+    fn test_promote_copies_script_and_shortcuts() {
+        let dir = tempdir().unwrap();
+        let sources = setup(dir.path());
+        let (local, global) = (&sources[0].1, &sources[1].1);
+
+        let mut buffer = Vec::new();
+        copy_script(
+            &mut buffer,
+            &sources,
+            "s",
+            ScriptSource::Local,
+            global,
+            false,
+            |_| panic!("nothing to overwrite"),
+        )
+        .unwrap();
+
+        let target = global.join("db").join("seed.yaml");
+        assert_eq!(read_to_string(&target).unwrap(), "name: Local Seed\n");
+        assert!(local.join("db").join("seed.yaml").exists());
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            format!(
+                "Copied db/seed to {}\nAdded shortcut 's' -> db/seed\n",
+                target.display()
+            )
+        );
+        assert_eq!(
+            read_to_string(global.join(".shortcuts.yaml")).unwrap(),
+            "shortcuts:\n  s: db/seed\n"
+        );
+    }
+
+    #[test]
+    fn test_demote_with_move() {
+        let dir = tempdir().unwrap();
+        let sources = setup(dir.path());
+        let (local, global) = (&sources[0].1, &sources[1].1);
+        create_dir_all(global).unwrap();
+        write(global.join("deploy.toml"), "name = \"Deploy\"\n").unwrap();
+        write(global.join(".shortcuts.yaml"), "shortcuts:\n  d: deploy\n").unwrap();
+
+        let mut buffer = Vec::new();
+        copy_script(
+            &mut buffer,
+            &sources,
+            "deploy",
+            ScriptSource::Global,
+            local,
+            true,
+            |_| panic!("nothing to overwrite"),
+        )
+        .unwrap();
+
+        assert!(local.join("deploy.toml").is_file());
+        assert!(!global.join("deploy.toml").exists());
+        assert_eq!(
+            read_to_string(local.join(".shortcuts.yaml")).unwrap(),
+            "shortcuts:\n  d: deploy\n  l: lint\n  s: db/seed\n"
+        );
+        assert_eq!(
+            read_to_string(global.join(".shortcuts.yaml")).unwrap(),
+            "shortcuts: {}\n"
+        );
+    }
+
+    #[test]
+    fn test_overwrite_is_confirmed() {
+        let dir = tempdir().unwrap();
+        let sources = setup(dir.path());
+        let global = &sources[1].1;
+        create_dir_all(global).unwrap();
+        write(global.join("lint.yaml"), "name: Global Lint\n").unwrap();
+        write(global.join("lint.toml"), "name = \"Global Lint\"\n").unwrap();
+        write(
+            global.join(".shortcuts.yaml"),
+            "shortcuts:\n  l: lint-all\n",
+        )
+        .unwrap();
+
+        let asked = Cell::new(0);
+        let mut buffer = Vec::new();
+        copy_script(
+            &mut buffer,
+            &sources,
+            "lint",
+            ScriptSource::Local,
+            global,
+            false,
+            |prompt| {
+                asked.set(asked.get() + 1);
+                assert!(prompt.contains("lint.yaml, "), "{prompt}");
+                assert!(prompt.contains("lint.toml already exist."), "{prompt}");
+                Ok(false)
+            },
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), "Nothing copied.\n");
+        assert_eq!(
+            read_to_string(global.join("lint.yaml")).unwrap(),
+            "name: Global Lint\n"
+        );
+
+        let mut buffer = Vec::new();
+        copy_script(
+            &mut buffer,
+            &sources,
+            "lint",
+            ScriptSource::Local,
+            global,
+            false,
+            |_| {
+                asked.set(asked.get() + 1);
+                Ok(true)
+            },
+        )
+        .unwrap();
+        assert_eq!(asked.get(), 2);
+        assert!(!global.join("lint.yaml").exists());
+        assert!(!global.join("lint.toml").exists());
+        assert_eq!(
+            read_to_string(global.join("lint.json")).unwrap(),
+            "{\"name\": \"Local Lint\"}"
+        );
+        assert!(
+            String::from_utf8(buffer)
+                .unwrap()
+                .contains("Shortcut 'l' already points at 'lint-all'"),
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use std::{fs, io::Write, path::PathBuf};
+
+use crate::{
+    catalog::{load_shortcuts, locate_script, save_shortcuts, shortcuts_to},
+    utils::ScriptSource,
+};
+
+/// Deletes the script `name` refers to, along with the shortcuts pointing at
+/// it in the same directory. `confirm` is asked first; `scope` picks the
+/// source when the script exists in several.
+pub fn remove_script<W: Write>(
+    writer: &mut W,
+    sources: &[(ScriptSource, PathBuf)],
+    name: &str,
+    scope: Option<ScriptSource>,
+    confirm: impl FnOnce(&str) -> Result<bool, Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let script = locate_script(sources, name, scope)?;
+    if !confirm(&format!(
+        "Remove the {} script {}?",
+        script.source.as_str(),
+        script.path.display()
+    ))? {
+        writeln!(writer, "Nothing removed.")?;
+        return Ok(());
+    }
+
+    fs::remove_file(&script.path)?;
+    writeln!(
+        writer,
+        "Removed {} script {} ({})",
+        script.source.as_str(),
+        script.name,
+        script.path.display()
+    )?;
+
+    let mut shortcuts = load_shortcuts(&script.dir)?;
+    let removed = shortcuts_to(&shortcuts, &script.name);
+    if removed.is_empty() {
+        return Ok(());
+    }
+    for (key, _) in &removed {
+        shortcuts.shortcuts.remove(key);
+        writeln!(writer, "Removed shortcut '{key}'")?;
+    }
+    save_shortcuts(&script.dir, &shortcuts)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, read_to_string, write};
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    fn setup(root: &Path) -> Vec<(ScriptSource, PathBuf)> {
+        let local = root.join("project").join(".zirv");
+        let global = root.join("home").join(".zirv");
+        create_dir_all(local.join("db")).unwrap();
+        create_dir_all(&global).unwrap();
+        for path in [
+            local.join("build.yaml"),
+            local.join("db").join("migrate.yaml"),
+            global.join("build.json"),
+        ] {
+            write(path, "name: x\ncommands: []\n").unwrap();
+        }
+        write(
+            local.join(".shortcuts.yaml"),
+            "shortcuts:\n  b: build\n  m: db/migrate.yaml\n  mm: db/migrate\n",
+        )
+        .unwrap();
+        vec![(ScriptSource::Local, local), (ScriptSource::Global, global)]
+    }
+
+    #[test]
+    fn test_remove_strips_shortcuts() {
+        let dir = tempdir().unwrap();
+        let sources = setup(dir.path());
+        let local = &sources[0].1;
+
+        let mut buffer = Vec::new();
+        remove_script(&mut buffer, &sources, "m", None, |_| Ok(true)).unwrap();
+
+        assert!(!local.join("db").join("migrate.yaml").exists());
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            format!(
+                "Removed local script db/migrate ({})\nRemoved shortcut 'm'\nRemoved shortcut 'mm'\n",
+                local.join("db").join("migrate.yaml").display()
+            )
+        );
+        assert_eq!(
+            read_to_string(local.join(".shortcuts.yaml")).unwrap(),
+            "shortcuts:\n  b: build\n"
+        );
+    }
+
+    #[test]
+    fn test_remove_refuses_ambiguous_names() {
+        let dir = tempdir().unwrap();
+        let sources = setup(dir.path());
+        let (local, global) = (&sources[0].1, &sources[1].1);
+
+        let mut buffer = Vec::new();
+        let error = remove_script(&mut buffer, &sources, "build", None, |_| Ok(true))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("pass --local or --global"), "{error}");
+        assert!(local.join("build.yaml").exists());
+        assert!(global.join("build.json").exists());
+
+        remove_script(
+            &mut buffer,
+            &sources,
+            "build",
+            Some(ScriptSource::Global),
+            |_| Ok(true),
+        )
+        .unwrap();
+        assert!(local.join("build.yaml").exists());
+        assert!(!global.join("build.json").exists());
+    }
+
+    #[test]
+    fn test_remove_can_be_declined() {
+        let dir = tempdir().unwrap();
+        let sources = setup(dir.path());
+
+        let mut buffer = Vec::new();
+        remove_script(&mut buffer, &sources, "db/migrate", None, |_| Ok(false)).unwrap();
+
+        assert_eq!(String::from_utf8(buffer).unwrap(), "Nothing removed.\n");
+        assert!(sources[0].1.join("db").join("migrate.yaml").exists());
+    }
+}
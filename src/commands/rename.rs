@@ -0,0 +1,155 @@
+use std::{fs, io::Write, path::PathBuf};
+
+use crate::{
+    catalog::{load_shortcuts, locate_script, save_shortcuts, script_name, script_names},
+    utils::{ScriptSource, Shortcut},
+};
+
+/// Renames the script `old` refers to as `new`, keeping its format, and
+/// points the shortcuts of the same directory at the new name. `scope`
+/// picks the source when the script exists in several.
+pub fn rename_script<W: Write>(
+    writer: &mut W,
+    sources: &[(ScriptSource, PathBuf)],
+    old: &str,
+    new: &str,
+    scope: Option<ScriptSource>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let script = locate_script(sources, old, scope)?;
+    let new = script_name(new);
+    if let Some((_, existing)) = script_names(&script.dir)?
+        .into_iter()
+        .find(|(n, _)| n == new)
+    {
+        return Err(format!(
+            "A script named '{new}' already exists: {}",
+            existing.display()
+        )
+        .into());
+    }
+
+    let ext = script
+        .path
+        .extension()
+        .map(|ext| ext.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let path = script.dir.join(format!("{new}.{ext}"));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(&script.path, &path)?;
+    writeln!(
+        writer,
+        "Renamed {} script {} to {new} ({})",
+        script.source.as_str(),
+        script.name,
+        path.display()
+    )?;
+
+    let mut shortcuts = load_shortcuts(&script.dir)?;
+    let mut updated: Vec<(&String, &mut Shortcut)> = shortcuts
+        .shortcuts
+        .iter_mut()
+        .filter(|(_, target)| script_name(target.script()) == script.name)
+        .collect();
+    if updated.is_empty() {
+        return Ok(());
+    }
+    updated.sort_by(|a, b| a.0.cmp(b.0));
+    for (key, target) in updated {
+        // Keep the extension if the shortcut spelled one out.
+        *target = if script_name(target.script()) == target.script() {
+            target.retarget(new.to_string())
+        } else {
+            target.retarget(format!("{new}.{ext}"))
+        };
+        writeln!(writer, "Updated shortcut '{key}' -> {target}")?;
+    }
+    save_shortcuts(&script.dir, &shortcuts)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, read_to_string, write};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_rename_rewrites_shortcuts() {
+        let dir = tempdir().unwrap();
+        let local = dir.path().join(".zirv");
+        create_dir_all(&local).unwrap();
+        write(local.join("build.toml"), "name = \"x\"\ncommands = []\n").unwrap();
+        write(local.join("test.yaml"), "name: x\ncommands: []\n").unwrap();
+        write(
+            local.join(".shortcuts.yaml"),
+            "shortcuts:\n  b: build\n  bt: build.toml\n  t: test\n",
+        )
+        .unwrap();
+        let sources = vec![(ScriptSource::Local, local.clone())];
+
+        let mut buffer = Vec::new();
+        rename_script(&mut buffer, &sources, "b", "ci/make", None).unwrap();
+
+        let path = local.join("ci").join("make.toml");
+        assert!(path.is_file());
+        assert!(!local.join("build.toml").exists());
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            format!(
+                "Renamed local script build to ci/make ({})\n\
+                 Updated shortcut 'b' -> ci/make\n\
+                 Updated shortcut 'bt' -> ci/make.toml\n",
+                path.display()
+            )
+        );
+        assert_eq!(
+            read_to_string(local.join(".shortcuts.yaml")).unwrap(),
+            "shortcuts:\n  b: ci/make\n  bt: ci/make.toml\n  t: test\n"
+        );
+
+        let error = rename_script(&mut Vec::new(), &sources, "test", "ci/make", None)
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("A script named 'ci/make' already exists"));
+    }
+
+    #[test]
+    fn test_rename_refuses_ambiguous_names() {
+        let dir = tempdir().unwrap();
+        let local = dir.path().join("local");
+        let global = dir.path().join("global");
+        create_dir_all(&local).unwrap();
+        create_dir_all(&global).unwrap();
+        write(local.join("build.yaml"), "").unwrap();
+        write(global.join("build.yaml"), "").unwrap();
+        let sources = vec![
+            (ScriptSource::Local, local.clone()),
+            (ScriptSource::Global, global.clone()),
+        ];
+
+        let error = rename_script(&mut Vec::new(), &sources, "build", "make", None)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            format!(
+                "'build' exists in local ({}) and global ({}); pass --local or --global to choose",
+                local.join("build.yaml").display(),
+                global.join("build.yaml").display()
+            )
+        );
+
+        rename_script(
+            &mut Vec::new(),
+            &sources,
+            "build",
+            "make",
+            Some(ScriptSource::Local),
+        )
+        .unwrap();
+        assert!(local.join("make.yaml").is_file());
+        assert!(global.join("build.yaml").is_file());
+    }
+}
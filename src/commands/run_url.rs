@@ -0,0 +1,209 @@
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
+use crate::{script_runner::script::Script, utils::parse_script_content};
+
+/// How long a download may take in total.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The largest script `run-url` downloads.
+const MAX_SCRIPT_BYTES: u64 = 1024 * 1024;
+
+/// Checks that `url` is safe to fetch a script from: HTTPS, or plain HTTP
+/// to this machine.
+fn check_url(url: &str) -> Result<(), String> {
+    if url.starts_with("https://") {
+        return Ok(());
+    }
+    if let Some(rest) = url.strip_prefix("http://") {
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+        // The colons of an IPv6 address are inside its brackets.
+        let host = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => host,
+            _ => authority,
+        };
+        if !authority.contains('@') && matches!(host, "localhost" | "127.0.0.1" | "[::1]") {
+            return Ok(());
+        }
+    }
+    Err(format!(
+        "Refusing to fetch '{url}'; only https:// URLs are supported"
+    ))
+}
+
+/// The script format of a download: the extension of the URL's path, else
+/// its `Content-Type`, else YAML.
+fn script_format(url: &str, content_type: Option<&str>) -> &'static str {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let file_name = path.rsplit('/').next().unwrap_or_default().to_lowercase();
+    match file_name.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("yaml" | "yml") => return "yaml",
+        Some("json") => return "json",
+        Some("toml") => return "toml",
+        _ => {}
+    }
+    let content_type = content_type.unwrap_or_default().to_lowercase();
+    if content_type.contains("json") {
+        "json"
+    } else if content_type.contains("toml") {
+        "toml"
+    } else {
+        "yaml"
+    }
+}
+
+/// Checks `content` against the hex-encoded SHA-256 hash `expected`.
+fn verify_sha256(url: &str, content: &[u8], expected: &str) -> Result<(), String> {
+    let actual: String = Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(format!(
+            "Checksum mismatch for {url}: expected {}, got {actual}. Nothing was run.",
+            expected.trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Downloads the script at `url` and parses it, after checking that its
+/// SHA-256 hash is `sha256`.
+///
+/// Downloads time out after 15 seconds and may be at most 1 MiB.
+pub fn fetch_script(url: &str, sha256: &str) -> Result<Script, Box<dyn std::error::Error>> {
+    check_url(url)?;
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(DOWNLOAD_TIMEOUT))
+        .build()
+        .into();
+    let mut response = agent
+        .get(url)
+        .header(
+            "User-Agent",
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+        )
+        .call()
+        .map_err(|e| format!("Failed to download {url}: {e}"))?;
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+    let content = response
+        .body_mut()
+        .with_config()
+        .limit(MAX_SCRIPT_BYTES)
+        .read_to_vec()
+        .map_err(|e| format!("Failed to download {url}: {e}"))?;
+
+    verify_sha256(url, &content, sha256)?;
+    let content = String::from_utf8(content).map_err(|_| format!("{url} is not UTF-8 text"))?;
+    Ok(parse_script_content(
+        &content,
+        script_format(url, content_type.as_deref()),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+
+    /// Serves `body` with `content_type` to a single request, returning the
+    /// server's base URL.
+    fn serve(body: Vec<u8>, content_type: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request);
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(&body);
+        });
+        format!("http://{address}")
+    }
+
+    fn sha256(content: &[u8]) -> String {
+        Sha256::digest(content)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    #[test]
+    fn test_fetch_verified_script() {
+        let body = br#"{"name": "Bootstrap", "commands": [{"command": "echo hi"}]}"#;
+        let url = format!("{}/bootstrap", serve(body.to_vec(), "application/json"));
+
+        let script = fetch_script(&url, &sha256(body).to_uppercase()).unwrap();
+        assert_eq!(script.name, "Bootstrap");
+        assert_eq!(script.commands.len(), 1);
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let body = b"name: Evil\ncommands:\n  - command: rm -rf /\n";
+        let url = format!("{}/bootstrap.yaml", serve(body.to_vec(), "text/plain"));
+
+        let error = fetch_script(&url, &sha256(b"something else"))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with(&format!("Checksum mismatch for {url}: expected ")),
+            "{error}"
+        );
+        assert!(error.ends_with(&format!("got {}. Nothing was run.", sha256(body))));
+    }
+
+    #[test]
+    fn test_download_size_cap() {
+        let body = vec![b'#'; MAX_SCRIPT_BYTES as usize + 1];
+        let url = format!("{}/big.yaml", serve(body.clone(), "text/yaml"));
+
+        let error = fetch_script(&url, &sha256(&body)).unwrap_err().to_string();
+        assert!(error.starts_with("Failed to download"), "{error}");
+    }
+
+    #[test]
+    fn test_only_https_or_loopback() {
+        let error = fetch_script("http://example.com/x.yaml", "00")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "Refusing to fetch 'http://example.com/x.yaml'; only https:// URLs are supported"
+        );
+        assert!(check_url("http://localhost:8080/x.yaml").is_ok());
+        assert!(check_url("http://[::1]/x.yaml").is_ok());
+        assert!(check_url("http://[::1]:8080/x.yaml").is_ok());
+        assert!(check_url("http://[::2]/x.yaml").is_err());
+        assert!(check_url("http://localhost.evil.com/x.yaml").is_err());
+        assert!(check_url("http://127.0.0.1:80@evil.com/x.yaml").is_err());
+        assert!(check_url("ftp://example.com/x.yaml").is_err());
+    }
+
+    #[test]
+    fn test_script_format() {
+        assert_eq!(script_format("https://a/b.TOML?raw=1", None), "toml");
+        assert_eq!(script_format("https://a/b.yml", Some("text/plain")), "yaml");
+        assert_eq!(
+            script_format("https://a/b", Some("application/json; charset=utf-8")),
+            "json"
+        );
+        assert_eq!(
+            script_format("https://a/raw", Some("application/toml")),
+            "toml"
+        );
+        assert_eq!(script_format("https://a/raw", None), "yaml");
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::Write;
+
+use crate::script_runner::schema::script_schema;
+
+/// Prints the script schema, e.g. for `yaml-language-server`.
+pub fn print_schema<W: Write>(writer: &mut W) -> Result<(), Box<dyn std::error::Error>> {
+    serde_json::to_writer_pretty(&mut *writer, &script_schema())?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::create::ScriptFormat;
+    use serde_json::Value;
+
+    fn assert_valid(document: Value) {
+        let validator = jsonschema::validator_for(&script_schema()).unwrap();
+        let errors: Vec<String> = validator
+            .iter_errors(&document)
+            .map(|e| format!("{}: {e}", e.instance_path()))
+            .collect();
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[test]
+    fn test_default_template_matches_schema() {
+        for format in [ScriptFormat::Yaml, ScriptFormat::Json, ScriptFormat::Toml] {
+            let template = format.template("deploy").unwrap();
+            assert_valid(match format {
+                ScriptFormat::Yaml => serde_yaml::from_str(&template).unwrap(),
+                ScriptFormat::Json => serde_json::from_str(&template).unwrap(),
+                ScriptFormat::Toml => toml::from_str(&template).unwrap(),
+            });
+        }
+    }
+
+    #[test]
+    fn test_full_script_matches_schema() {
+        assert_valid(
+            serde_yaml::from_str(
+                r#"
+name: Release
+description: Build and publish.
+params: [version]
+secrets:
+  - name: token
+    env_var: TOKEN
+export_context: true
+commands:
+  - name: build
+    command: cargo build --release
+    capture: out
+    tags: [build]
+    options:
+      operating_system: linux
+      proceed_on_failure: false
+      delay_ms: 100
+      fallback:
+        - command: cargo clean
+          description: Start over
+      retry_after_fallback: true
+  - - command: npm run watch
+    - command: cargo watch
+"#,
+            )
+            .unwrap(),
+        );
+    }
+}
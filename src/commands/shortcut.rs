@@ -0,0 +1,256 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use super::BUILTIN_COMMANDS;
+use crate::{
+    catalog::{load_shortcuts, save_shortcuts, script_file, script_name, script_names},
+    utils::{ScriptSource, Shortcut},
+};
+
+/// Adds the shortcut `key` for `target` to the `.shortcuts.yaml` in `dir`.
+///
+/// Shortcuts are resolved in the directory that defines them, so `target`
+/// must be a script in `dir`, or `<file>#<name>` for a script in a file of
+/// scripts such as `.zirv.yaml`. Keys taken by built-in commands or by
+/// another shortcut are rejected.
+pub fn add_shortcut<W: Write>(
+    writer: &mut W,
+    dir: &Path,
+    key: &str,
+    target: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if BUILTIN_COMMANDS.contains(&key) {
+        return Err(format!("'{key}' is a built-in command and cannot be a shortcut").into());
+    }
+    let found = if target.contains('#') {
+        script_file(dir, target).is_some()
+    } else {
+        dir.is_dir()
+            && script_names(dir)?
+                .iter()
+                .any(|(name, _)| name == script_name(target))
+    };
+    if !found {
+        return Err(format!("No script '{target}' found in {}", dir.display()).into());
+    }
+
+    let mut shortcuts = load_shortcuts(dir)?;
+    if let Some(existing) = shortcuts.shortcuts.get(key) {
+        return Err(
+            format!("Shortcut '{key}' already points at '{existing}'; remove it first").into(),
+        );
+    }
+    shortcuts
+        .shortcuts
+        .insert(key.to_string(), Shortcut::from(target));
+    std::fs::create_dir_all(dir)?;
+    save_shortcuts(dir, &shortcuts)?;
+    writeln!(
+        writer,
+        "Added shortcut '{key}' -> {target} in {}",
+        dir.join(".shortcuts.yaml").display()
+    )?;
+    Ok(())
+}
+
+/// Removes the shortcut `key` from the `.shortcuts.yaml` in `dir`.
+pub fn remove_shortcut<W: Write>(
+    writer: &mut W,
+    dir: &Path,
+    key: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut shortcuts = load_shortcuts(dir)?;
+    let Some(target) = shortcuts.shortcuts.remove(key) else {
+        return Err(format!(
+            "No shortcut '{key}' in {}",
+            dir.join(".shortcuts.yaml").display()
+        )
+        .into());
+    };
+    save_shortcuts(dir, &shortcuts)?;
+    writeln!(
+        writer,
+        "Removed shortcut '{key}' -> {target} from {}",
+        dir.join(".shortcuts.yaml").display()
+    )?;
+    Ok(())
+}
+
+/// Prints the shortcuts of every source in lookup order, marking those
+/// hidden by the same key in an earlier source.
+pub fn list_shortcuts<W: Write>(
+    writer: &mut W,
+    sources: &[(ScriptSource, PathBuf)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut seen: Vec<(String, ScriptSource)> = Vec::new();
+    let mut printed = false;
+    for (source, dir) in sources {
+        let mut shortcuts: Vec<(String, Shortcut)> =
+            load_shortcuts(dir)?.shortcuts.into_iter().collect();
+        if shortcuts.is_empty() {
+            continue;
+        }
+        shortcuts.sort_by(|a, b| a.0.cmp(&b.0));
+
+        if printed {
+            writeln!(writer)?;
+        }
+        printed = true;
+        writeln!(
+            writer,
+            "{} ({}):",
+            source.as_str(),
+            dir.join(".shortcuts.yaml").display()
+        )?;
+        for (key, target) in shortcuts {
+            match seen.iter().find(|(k, _)| *k == key) {
+                Some((_, winner)) => writeln!(
+                    writer,
+                    "  {key} -> {target} (overridden by {})",
+                    winner.as_str()
+                )?,
+                None => {
+                    writeln!(writer, "  {key} -> {target}")?;
+                    seen.push((key, *source));
+                }
+            }
+        }
+    }
+
+    if !printed {
+        writeln!(writer, "No shortcuts defined.")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, read_to_string, write};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_add_and_remove_round_trip() {
+        let dir = tempdir().unwrap();
+        write(dir.path().join("build.yaml"), "").unwrap();
+        create_dir_all(dir.path().join("db")).unwrap();
+        write(dir.path().join("db").join("migrate.json"), "").unwrap();
+        let shortcuts_file = dir.path().join(".shortcuts.yaml");
+
+        let mut buffer = Vec::new();
+        add_shortcut(&mut buffer, dir.path(), "b", "build").unwrap();
+        add_shortcut(&mut buffer, dir.path(), "m", "db/migrate.json").unwrap();
+        assert_eq!(
+            read_to_string(&shortcuts_file).unwrap(),
+            "shortcuts:\n  b: build\n  m: db/migrate.json\n"
+        );
+
+        let error = add_shortcut(&mut buffer, dir.path(), "b", "db/migrate")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "Shortcut 'b' already points at 'build'; remove it first"
+        );
+
+        let mut buffer = Vec::new();
+        remove_shortcut(&mut buffer, dir.path(), "b").unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            format!(
+                "Removed shortcut 'b' -> build from {}\n",
+                shortcuts_file.display()
+            )
+        );
+        assert_eq!(
+            read_to_string(&shortcuts_file).unwrap(),
+            "shortcuts:\n  m: db/migrate.json\n"
+        );
+        assert!(remove_shortcut(&mut Vec::new(), dir.path(), "b").is_err());
+    }
+
+    #[test]
+    fn test_add_rejects_builtins_and_missing_scripts() {
+        let dir = tempdir().unwrap();
+        write(dir.path().join("build.yaml"), "").unwrap();
+
+        for key in ["help", "h", "init", "create"] {
+            let error = add_shortcut(&mut Vec::new(), dir.path(), key, "build")
+                .unwrap_err()
+                .to_string();
+            assert_eq!(
+                error,
+                format!("'{key}' is a built-in command and cannot be a shortcut")
+            );
+        }
+        let error = add_shortcut(&mut Vec::new(), dir.path(), "t", "test")
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("No script 'test' found in "), "{error}");
+        assert!(!dir.path().join(".shortcuts.yaml").exists());
+    }
+
+    #[test]
+    fn test_add_shortcut_to_scripts_file_entry() {
+        let dir = tempdir().unwrap();
+        let zirv_dir = dir.path().join(".zirv");
+        write(
+            dir.path().join(".zirv.yaml"),
+            "scripts:\n  lint:\n    name: Lint\n    commands: []\n",
+        )
+        .unwrap();
+
+        add_shortcut(&mut Vec::new(), &zirv_dir, "l", ".zirv.yaml#lint").unwrap();
+        assert_eq!(
+            read_to_string(zirv_dir.join(".shortcuts.yaml")).unwrap(),
+            "shortcuts:\n  l: .zirv.yaml#lint\n"
+        );
+
+        let error = add_shortcut(&mut Vec::new(), &zirv_dir, "t", ".zirv.yaml#test")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("No script '.zirv.yaml#test' found in "),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_list_shows_precedence() {
+        let dir = tempdir().unwrap();
+        let local = dir.path().join("local");
+        let global = dir.path().join("global");
+        create_dir_all(&local).unwrap();
+        create_dir_all(&global).unwrap();
+        write(local.join(".shortcuts.yaml"), "shortcuts:\n  b: build\n").unwrap();
+        write(
+            global.join(".shortcuts.yaml"),
+            "shortcuts:\n  d: deploy\n  b: bump\n",
+        )
+        .unwrap();
+        let sources = [
+            (ScriptSource::Local, local.clone()),
+            (ScriptSource::Global, global.clone()),
+        ];
+
+        let mut buffer = Vec::new();
+        list_shortcuts(&mut buffer, &sources).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            format!(
+                "local ({}):\n  b -> build\n\nglobal ({}):\n  b -> bump (overridden by local)\n  d -> deploy\n",
+                local.join(".shortcuts.yaml").display(),
+                global.join(".shortcuts.yaml").display()
+            )
+        );
+
+        let mut buffer = Vec::new();
+        list_shortcuts(&mut buffer, &[]).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "No shortcuts defined.\n"
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use std::{collections::BTreeMap, io::Write, time::Duration};
+
+use serde::Serialize;
+
+use super::last::RunRecord;
+use crate::script_runner::{
+    RunReport,
+    bench::BenchStats,
+    report::{StepReport, format_duration},
+};
+use crate::utils::ScriptLocation;
+
+/// What the recorded runs of one script add up to.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ScriptStats {
+    pub script: ScriptLocation,
+    pub runs: usize,
+    pub successes: usize,
+    /// The share of runs that succeeded, from 0 to 1.
+    pub success_rate: f64,
+    /// The shortest, median and longest run, over the runs whose duration
+    /// was recorded.
+    pub min_ms: Option<u64>,
+    pub median_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+    /// The average duration of each step, in step order.
+    pub steps: Vec<StepAverage>,
+}
+
+/// The average duration of a step over the runs it ran in.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct StepAverage {
+    pub index: usize,
+    pub command: String,
+    pub average_ms: u64,
+}
+
+/// Aggregates `records` per script, ordered by the script's path.
+pub fn script_stats(records: &[RunRecord]) -> Vec<ScriptStats> {
+    let mut by_script: BTreeMap<&ScriptLocation, Vec<&RunRecord>> = BTreeMap::new();
+    for record in records {
+        by_script.entry(&record.script).or_default().push(record);
+    }
+    by_script
+        .into_iter()
+        .map(|(script, records)| {
+            let successes = records.iter().filter(|r| r.exit_code == 0).count();
+            let reports: Vec<RunReport> = records.iter().filter_map(|r| timed_report(r)).collect();
+            let bench = BenchStats::new("", &reports);
+            let timed = |duration: Duration| (!reports.is_empty()).then(|| millis(duration));
+            ScriptStats {
+                script: script.clone(),
+                runs: records.len(),
+                successes,
+                success_rate: successes as f64 / records.len() as f64,
+                min_ms: timed(bench.min),
+                median_ms: timed(bench.median),
+                max_ms: timed(bench.max),
+                steps: bench
+                    .step_averages
+                    .into_iter()
+                    .map(|(index, command, average)| StepAverage {
+                        index,
+                        command,
+                        average_ms: millis(average),
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// The durations of `record` as a run report, if they were recorded.
+fn timed_report(record: &RunRecord) -> Option<RunReport> {
+    let duration = Duration::from_millis(record.duration_ms?);
+    Some(RunReport {
+        duration,
+        steps: record
+            .steps
+            .iter()
+            .map(|step| StepReport {
+                duration: Duration::from_millis(step.duration_ms),
+                ..StepReport::new(step.index, step.command.clone())
+            })
+            .collect(),
+        ..Default::default()
+    })
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+/// Prints one row per script, and with `steps` the average duration of each
+/// step below it; or all of it as a JSON array with `json`.
+pub fn show_stats<W: Write>(
+    writer: &mut W,
+    stats: &[ScriptStats],
+    steps: bool,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if json {
+        serde_json::to_writer_pretty(&mut *writer, stats)?;
+        writeln!(writer)?;
+        return Ok(());
+    }
+    if stats.is_empty() {
+        writeln!(writer, "No runs recorded yet.")?;
+        return Ok(());
+    }
+
+    let names: Vec<String> = stats.iter().map(|s| s.script.to_string()).collect();
+    let width = names.iter().map(String::len).max().unwrap_or(0).max(6);
+    let duration = |ms: Option<u64>| {
+        ms.map(|ms| format_duration(Duration::from_millis(ms)))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    writeln!(
+        writer,
+        "{:<width$}  {:>5}  {:>7}  {:>8}  {:>8}  {:>8}",
+        "Script", "Runs", "Success", "Min", "Median", "Max"
+    )?;
+    for (name, stats) in names.iter().zip(stats) {
+        writeln!(
+            writer,
+            "{name:<width$}  {:>5}  {:>6.1}%  {:>8}  {:>8}  {:>8}",
+            stats.runs,
+            stats.success_rate * 100.0,
+            duration(stats.min_ms),
+            duration(stats.median_ms),
+            duration(stats.max_ms)
+        )?;
+    }
+
+    if !steps {
+        return Ok(());
+    }
+    for (name, stats) in names.iter().zip(stats) {
+        if stats.steps.is_empty() {
+            continue;
+        }
+        writeln!(writer, "\nSteps of {name}:")?;
+        writeln!(writer, "  {:<4}{:<40}  {:>10}", "#", "Step", "Average")?;
+        for step in &stats.steps {
+            writeln!(
+                writer,
+                "  {:<4}{:<40}  {:>10}",
+                step.index,
+                step.command.lines().next().unwrap_or_default(),
+                duration(Some(step.average_ms))
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::last::{StepTiming, append_record, read_records};
+    use std::path::PathBuf;
+
+    fn record(script: &str, exit_code: u8, durations: Option<(u64, &[u64])>) -> RunRecord {
+        RunRecord {
+            script: ScriptLocation::File(PathBuf::from(script)),
+            env: None,
+            params: Vec::new(),
+            timestamp: "2026-10-16T12:00:00+02:00".to_string(),
+            exit_code,
+            duration_ms: durations.map(|(total, _)| total),
+            steps: durations
+                .map(|(_, steps)| steps)
+                .unwrap_or_default()
+                .iter()
+                .enumerate()
+                .map(|(i, ms)| StepTiming {
+                    index: i + 1,
+                    command: format!("step {}", i + 1),
+                    duration_ms: *ms,
+                })
+                .collect(),
+        }
+    }
+
+    fn history() -> Vec<RunRecord> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        for record in [
+            record("/p/build.yaml", 0, Some((300, &[100, 200]))),
+            record("/p/test.yaml", 0, None),
+            record("/p/build.yaml", 2, Some((100, &[100]))),
+            record("/p/build.yaml", 0, Some((200, &[50, 150]))),
+            record("/p/build.yaml", 0, None),
+        ] {
+            append_record(&path, &record).unwrap();
+        }
+        read_records(&path).unwrap()
+    }
+
+    #[test]
+    fn test_script_stats() {
+        let stats = script_stats(&history());
+        assert_eq!(
+            stats,
+            [
+                ScriptStats {
+                    script: ScriptLocation::File(PathBuf::from("/p/build.yaml")),
+                    runs: 4,
+                    successes: 3,
+                    success_rate: 0.75,
+                    min_ms: Some(100),
+                    median_ms: Some(200),
+                    max_ms: Some(300),
+                    steps: vec![
+                        StepAverage {
+                            index: 1,
+                            command: "step 1".to_string(),
+                            average_ms: 83,
+                        },
+                        StepAverage {
+                            index: 2,
+                            command: "step 2".to_string(),
+                            average_ms: 175,
+                        },
+                    ],
+                },
+                ScriptStats {
+                    script: ScriptLocation::File(PathBuf::from("/p/test.yaml")),
+                    runs: 1,
+                    successes: 1,
+                    success_rate: 1.0,
+                    min_ms: None,
+                    median_ms: None,
+                    max_ms: None,
+                    steps: Vec::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_show_stats() {
+        let stats = script_stats(&history());
+        let mut buffer = Vec::new();
+        show_stats(&mut buffer, &stats, true, false).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "Script          Runs  Success       Min    Median       Max\n\
+             /p/build.yaml      4    75.0%     0.10s     0.20s     0.30s\n\
+             /p/test.yaml       1   100.0%         -         -         -\n\
+             \n\
+             Steps of /p/build.yaml:\n  \
+             #   Step                                         Average\n  \
+             1   step 1                                         0.08s\n  \
+             2   step 2                                         0.17s\n"
+        );
+
+        let mut buffer = Vec::new();
+        show_stats(&mut buffer, &stats[1..], false, true).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json[0]["runs"], 1);
+        assert_eq!(json[0]["success_rate"], 1.0);
+        assert_eq!(json[0]["median_ms"], serde_json::Value::Null);
+
+        let mut buffer = Vec::new();
+        show_stats(&mut buffer, &[], true, false).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "No runs recorded yet.\n"
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{catalog::script_names, utils::SCRIPT_DIR_NAME};
+
+/// Shallow-clones `url` into `dir`, returning git's stderr on failure.
+fn clone(url: &str, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::new("git")
+        .args(["clone", "--depth", "1", "--quiet", "--", url])
+        .arg(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to clone template '{url}':\n{}",
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// The files a template provides, relative to its script directory: its
+/// `.shortcuts.yaml` and every script. A template keeps them in a `.zirv`
+/// directory or at its top level.
+fn template_files(root: &Path) -> Result<(PathBuf, Vec<PathBuf>), Box<dyn std::error::Error>> {
+    let nested = root.join(SCRIPT_DIR_NAME);
+    let dir = if nested.is_dir() {
+        nested
+    } else {
+        root.to_path_buf()
+    };
+
+    let mut files: Vec<PathBuf> = Vec::new();
+    for (_, path) in script_names(&dir)? {
+        files.push(path.strip_prefix(&dir)?.to_path_buf());
+    }
+    if files.is_empty() {
+        return Err(format!("No scripts found in template {}", root.display()).into());
+    }
+    if dir.join(".shortcuts.yaml").is_file() {
+        files.push(PathBuf::from(".shortcuts.yaml"));
+    }
+    Ok((dir, files))
+}
+
+/// Seeds `target` with the scripts and shortcuts of the template at
+/// `source`: a local directory, or a repository `git clone` can fetch.
+///
+/// Nothing is copied if a file would be overwritten, unless `force` is set.
+pub fn init_from_template<W: Write>(
+    writer: &mut W,
+    source: &str,
+    target: &Path,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let clone_dir;
+    let root = if Path::new(source).is_dir() {
+        PathBuf::from(source)
+    } else {
+        clone_dir = tempfile::Builder::new()
+            .prefix("zirv-template-")
+            .tempdir()?;
+        let dir = clone_dir.path().join("template");
+        clone(source, &dir)?;
+        dir
+    };
+
+    let (dir, files) = template_files(&root)?;
+    if !force {
+        let existing: Vec<String> = files
+            .iter()
+            .filter(|file| target.join(file).exists())
+            .map(|file| target.join(file).display().to_string())
+            .collect();
+        if !existing.is_empty() {
+            return Err(format!(
+                "These files already exist; use --force to overwrite them:\n  {}",
+                existing.join("\n  ")
+            )
+            .into());
+        }
+    }
+
+    for file in &files {
+        let to = target.join(file);
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(dir.join(file), &to)?;
+        writeln!(writer, "Copied {}", to.display())?;
+    }
+    writeln!(
+        writer,
+        "Initialized {} from {source} ({} files)",
+        target.display(),
+        files.len()
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, read_to_string, write};
+    use tempfile::tempdir;
+
+    fn template(root: &Path) -> PathBuf {
+        let dir = root.join("templates").join(SCRIPT_DIR_NAME);
+        create_dir_all(dir.join("db")).unwrap();
+        write(dir.join("build.yaml"), "name: Build\ncommands: []\n").unwrap();
+        write(dir.join("db").join("seed.json"), "{}").unwrap();
+        write(dir.join(".shortcuts.yaml"), "shortcuts:\n  b: build\n").unwrap();
+        write(root.join("templates").join("README.toml"), "").unwrap();
+        root.join("templates")
+    }
+
+    #[test]
+    fn test_copies_scripts_and_shortcuts() {
+        let dir = tempdir().unwrap();
+        let source = template(dir.path());
+        let target = dir.path().join("project").join(SCRIPT_DIR_NAME);
+
+        let mut buffer = Vec::new();
+        init_from_template(&mut buffer, source.to_str().unwrap(), &target, false).unwrap();
+
+        assert_eq!(
+            read_to_string(target.join("build.yaml")).unwrap(),
+            "name: Build\ncommands: []\n"
+        );
+        assert!(target.join("db").join("seed.json").is_file());
+        assert_eq!(
+            read_to_string(target.join(".shortcuts.yaml")).unwrap(),
+            "shortcuts:\n  b: build\n"
+        );
+        assert!(!target.join("README.toml").exists());
+        assert!(String::from_utf8(buffer).unwrap().ends_with("(3 files)\n"));
+    }
+
+    #[test]
+    fn test_refuses_to_overwrite_without_force() {
+        let dir = tempdir().unwrap();
+        let source = template(dir.path());
+        let target = dir.path().join("project").join(SCRIPT_DIR_NAME);
+        create_dir_all(&target).unwrap();
+        write(target.join("build.yaml"), "name: Mine\n").unwrap();
+
+        let error = init_from_template(&mut Vec::new(), source.to_str().unwrap(), &target, false)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("use --force to overwrite them"), "{error}");
+        assert!(error.contains("build.yaml"), "{error}");
+        assert!(!target.join(".shortcuts.yaml").exists());
+
+        init_from_template(&mut Vec::new(), source.to_str().unwrap(), &target, true).unwrap();
+        assert_eq!(
+            read_to_string(target.join("build.yaml")).unwrap(),
+            "name: Build\ncommands: []\n"
+        );
+    }
+
+    #[test]
+    fn test_top_level_template_and_empty_template() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("flat");
+        create_dir_all(&source).unwrap();
+        let target = dir.path().join("target");
+
+        let error = init_from_template(&mut Vec::new(), source.to_str().unwrap(), &target, false)
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("No scripts found in template"), "{error}");
+
+        write(source.join("lint.toml"), "name = \"Lint\"\ncommands = []\n").unwrap();
+        init_from_template(&mut Vec::new(), source.to_str().unwrap(), &target, false).unwrap();
+        assert!(target.join("lint.toml").is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_clone_failure_shows_git_output() {
+        let dir = tempdir().unwrap();
+        let missing = dir.path().join("missing.git");
+        let url = format!("file://{}", missing.display());
+
+        let error = init_from_template(&mut Vec::new(), &url, &dir.path().join("target"), false)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with(&format!("Failed to clone template '{url}':\n")),
+            "{error}"
+        );
+        assert!(error.contains("fatal"), "{error}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_source_is_never_a_git_option() {
+        let dir = tempdir().unwrap();
+        let source = "--upload-pack=touch pwned";
+
+        let error = init_from_template(&mut Vec::new(), source, &dir.path().join("target"), false)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with(&format!("Failed to clone template '{source}':\n")),
+            "{error}"
+        );
+        assert!(!Path::new("pwned").exists());
+    }
+}
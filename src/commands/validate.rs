@@ -0,0 +1,875 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use hashbrown::HashSet;
+use jsonschema::{ValidationError, Validator, error::ValidationErrorKind};
+
+use super::BUILTIN_COMMANDS;
+use crate::{
+    catalog::resolve_shortcut,
+    config::Config,
+    script_runner::{
+        BUILTIN_VARIABLES, FAILURE_VARIABLES,
+        command_types::CommandTypes,
+        condition::Condition,
+        reserved_captures,
+        schema::{parse_document, script_schema},
+        shadowing_captures, unquoted_placeholders,
+    },
+    utils::{
+        CONFIG_FILE, SUPPORTED_EXTENSIONS, ScriptLocation, Shortcuts, VARS_FILE, Vars,
+        file_to_script_with_overlay, file_to_scripts, load_vars, overlay_base,
+        parse_script_content, script_unknown_fields, scripts_file,
+    },
+};
+
+/// Checks every script under `paths` (files or directories) without running
+/// anything, printing the problems and warnings found per file. Fields zirv
+/// does not know, such as a misspelled option, are problems. With `strict`,
+/// scripts are also checked against the JSON schema, and warnings about
+/// scripts count as problems.
+/// Placeholders may refer to `vars`, and the `vars.yaml` and `config.yaml`
+/// of a directory are checked too. An overlay is checked by merging it over its script.
+///
+/// Fails with a summary if any problem was found.
+pub fn validate_scripts<W: Write>(
+    writer: &mut W,
+    paths: &[PathBuf],
+    strict: bool,
+    vars: &Vars,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let validator = if strict {
+        Some(jsonschema::validator_for(&script_schema())?)
+    } else {
+        None
+    };
+
+    let mut files = Vec::new();
+    for path in paths {
+        let scripts_file = scripts_file(path);
+        if path.is_dir() {
+            collect_files(path, &mut files)?;
+        } else if path.is_file() {
+            files.push(ScriptLocation::File(path.clone()));
+        } else if scripts_file.is_none() {
+            return Err(format!("No such file or directory: {}", path.display()).into());
+        }
+        // The scripts of `.zirv.yaml` are checked one by one, if it parses.
+        if let Some(file) = scripts_file {
+            match file_to_scripts(&file) {
+                Ok(scripts) => {
+                    files.extend(scripts.into_keys().map(|name| ScriptLocation::Entry {
+                        file: file.clone(),
+                        name,
+                    }))
+                }
+                Err(_) => files.push(ScriptLocation::File(file)),
+            }
+        }
+    }
+
+    let mut problem_count = 0;
+    let mut failed_files = 0;
+    for location in &files {
+        let mut warnings = Vec::new();
+        let other = match location {
+            ScriptLocation::File(file) => other_file_problems(file, paths),
+            ScriptLocation::Entry { .. } => None,
+        };
+        let problems = match other {
+            Some(problems) => problems,
+            None => {
+                let (mut problems, script_warnings) =
+                    script_problems(location, validator.as_ref(), vars);
+                warnings = script_warnings;
+                if let Some(builtin) = shadowing_builtin(location, paths) {
+                    let warning = format!(
+                        "'{builtin}' is a built-in command, so this script cannot be run by name; rename it"
+                    );
+                    if strict {
+                        problems.push(warning);
+                    } else {
+                        warnings.push(warning);
+                    }
+                }
+                problems
+            }
+        };
+        if problems.is_empty() && warnings.is_empty() {
+            continue;
+        }
+
+        writeln!(writer, "{location}:")?;
+        for problem in &problems {
+            writeln!(writer, "  - {problem}")?;
+        }
+        for warning in &warnings {
+            writeln!(writer, "  - warning: {warning}")?;
+        }
+        if !problems.is_empty() {
+            failed_files += 1;
+            problem_count += problems.len();
+        }
+    }
+
+    if problem_count > 0 {
+        return Err(format!(
+            "Found {problem_count} problem(s) in {failed_files} of {} file(s)",
+            files.len()
+        )
+        .into());
+    }
+
+    writeln!(writer, "All {} file(s) are valid.", files.len())?;
+    Ok(())
+}
+
+/// Checks `file` if it is one of the files next to the scripts rather than
+/// a script: the shortcuts, the `vars.yaml` or `config.yaml` of one of
+/// `paths`, an unparsable `.zirv.yaml` or an overlay.
+fn other_file_problems(file: &Path, paths: &[PathBuf]) -> Option<Vec<String>> {
+    let in_paths = || {
+        paths
+            .iter()
+            .any(|path| file.parent() == Some(path.as_path()))
+    };
+    let problems = if file.file_name().is_some_and(|n| n == ".shortcuts.yaml") {
+        shortcut_problems(file)
+    } else if file.file_name().is_some_and(|n| n == VARS_FILE) && in_paths() {
+        load_vars(file)
+            .err()
+            .into_iter()
+            .map(|e| e.to_string())
+            .collect()
+    } else if file.file_name().is_some_and(|n| n == CONFIG_FILE) && in_paths() {
+        Config::load(file)
+            .err()
+            .into_iter()
+            .map(|e| e.to_string())
+            .collect()
+    } else if paths
+        .iter()
+        .any(|path| scripts_file(path).as_deref() == Some(file))
+    {
+        file_to_scripts(file)
+            .err()
+            .into_iter()
+            .map(|e| e.to_string())
+            .collect()
+    } else if let Some((base, env)) = overlay_base(file) {
+        file_to_script_with_overlay(&ScriptLocation::File(base), &env)
+            .err()
+            .into_iter()
+            .map(|e| e.to_string())
+            .collect()
+    } else {
+        return None;
+    };
+    Some(problems)
+}
+
+/// The built-in command that is run instead of the script at `location`,
+/// for a script at the top of one of the directories in `paths` or in its
+/// `.zirv.yaml`.
+fn shadowing_builtin(location: &ScriptLocation, paths: &[PathBuf]) -> Option<&'static str> {
+    let name = match location {
+        ScriptLocation::Entry { name, .. } => name.clone(),
+        ScriptLocation::File(file)
+            if paths
+                .iter()
+                .any(|path| file.parent() == Some(path.as_path())) =>
+        {
+            file.file_stem()?.to_str()?.to_string()
+        }
+        ScriptLocation::File(_) => return None,
+    };
+    BUILTIN_COMMANDS
+        .iter()
+        .find(|builtin| **builtin == name)
+        .copied()
+}
+
+/// Collects script and shortcut files under `dir`, descending into
+/// namespace subdirectories.
+fn collect_files(
+    dir: &Path,
+    files: &mut Vec<ScriptLocation>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    for path in paths {
+        let hidden = path
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+        if path.is_dir() {
+            if !hidden {
+                collect_files(&path, files)?;
+            }
+        } else if path.file_name().is_some_and(|n| n == ".shortcuts.yaml")
+            || (!hidden && has_supported_extension(&path))
+        {
+            files.push(ScriptLocation::File(path));
+        }
+    }
+    Ok(())
+}
+
+fn has_supported_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| SUPPORTED_EXTENSIONS.contains(&ext))
+}
+
+/// Parses the script and checks that every `${name}` placeholder refers to
+/// a param, a secret, a capture of an earlier step, one of `vars` or a
+/// built-in variable. `on_error` commands may also use any capture and the
+/// variables describing the failure.
+///
+/// Also checks for fields zirv does not know, the syntax of every `when`
+/// condition, that every group with settings is the group of some step,
+/// and that nothing is captured into a reserved name.
+///
+/// Upper-case names such as `${HOME}` are left to the shell.
+///
+/// Returns the problems along with warnings, such as a capture overwriting
+/// a param. With a `validator`, i.e. when strict, warnings are problems too.
+pub(crate) fn script_problems(
+    location: &ScriptLocation,
+    validator: Option<&Validator>,
+    vars: &Vars,
+) -> (Vec<String>, Vec<String>) {
+    let mut problems = Vec::new();
+    let script = if let ScriptLocation::File(path) = location {
+        let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => return (vec![e.to_string()], Vec::new()),
+        };
+        let script = match parse_script_content(&content, ext) {
+            Ok(script) => script,
+            Err(mut e) => {
+                e.path = Some(path.to_path_buf());
+                return (vec![e.to_string()], Vec::new());
+            }
+        };
+        if let Some(validator) = validator {
+            problems.extend(schema_problems(&content, ext, validator));
+        }
+        script
+    } else {
+        // A script in `.zirv.yaml` has no document of its own to check
+        // against the schema.
+        match location.load() {
+            Ok(script) => script,
+            Err(e) => return (vec![e.to_string()], Vec::new()),
+        }
+    };
+    problems.extend(script_unknown_fields(location));
+
+    let mut known: HashSet<String> = script
+        .params
+        .iter()
+        .flatten()
+        .cloned()
+        .chain(script.secrets.iter().flatten().map(|s| s.name.clone()))
+        .chain(BUILTIN_VARIABLES.iter().map(|v| v.to_string()))
+        .chain(vars.keys().cloned())
+        .collect();
+
+    for (i, step) in script.commands.iter().enumerate() {
+        for var in step.placeholders() {
+            if known.contains(&var) || is_shell_variable(&var) {
+                continue;
+            }
+            let later = script.commands[i + 1..]
+                .iter()
+                .position(|s| s.variables().contains(&var.as_str()));
+            problems.push(match later {
+                Some(j) => format!(
+                    "step {}: variable '${{{var}}}' is only captured by later step {}",
+                    i + 1,
+                    i + j + 2
+                ),
+                None => format!(
+                    "step {}: unknown variable '${{{var}}}' (not a param, secret, capture, variable or built-in)",
+                    i + 1
+                ),
+            });
+        }
+        if let Some(message) = step.unsupported() {
+            problems.push(format!("step {}: {message}", i + 1));
+        }
+        if let CommandTypes::Command(command) = step {
+            for name in command.export.iter().flat_map(|export| export.values()) {
+                if known.contains(name) {
+                    problems.push(format!(
+                        "step {}: export target '{name}' is already a param, secret, capture or variable",
+                        i + 1
+                    ));
+                }
+            }
+        }
+        known.extend(step.variables().into_iter().map(String::from));
+    }
+
+    let step_conditions = script.commands.iter().enumerate().flat_map(|(i, step)| {
+        step.commands()
+            .iter()
+            .filter_map(move |command| Some((format!("step {}", i + 1), command.when.as_deref()?)))
+    });
+    let group_conditions = script
+        .groups
+        .iter()
+        .filter_map(|(name, group)| Some((format!("group '{name}'"), group.when.as_deref()?)));
+    for (location, when) in step_conditions.chain(group_conditions) {
+        if let Err(e) = Condition::parse(when) {
+            let message = e.to_string();
+            let message = message.lines().next().unwrap_or_default();
+            problems.push(format!("{location}: invalid when condition: {message}"));
+        }
+    }
+    for name in script.groups.keys() {
+        if !script
+            .commands
+            .iter()
+            .any(|step| step.group() == Some(name))
+        {
+            problems.push(format!("group '{name}' is not the group of any step"));
+        }
+    }
+
+    known.extend(FAILURE_VARIABLES.iter().map(|v| v.to_string()));
+    for (i, handler) in script.on_error.iter().enumerate() {
+        for var in handler.placeholders() {
+            if !known.contains(&var) && !is_shell_variable(&var) {
+                problems.push(format!(
+                    "on_error {}: unknown variable '${{{var}}}' (not a param, secret, capture, variable or built-in)",
+                    i + 1
+                ));
+            }
+        }
+    }
+
+    problems.extend(reserved_captures(&script));
+    let mut warnings = shadowing_captures(&script);
+    warnings.extend(unquoted_placeholders(&script));
+    if validator.is_some() {
+        problems.extend(warnings);
+        return (problems, Vec::new());
+    }
+    (problems, warnings)
+}
+
+/// Checks the document against the script schema, reporting each violation
+/// with the path of the offending value (e.g. `/commands/0`).
+fn schema_problems(content: &str, ext: &str, validator: &Validator) -> Vec<String> {
+    match parse_document(content, ext) {
+        Ok(document) => validator
+            .iter_errors(&document)
+            .flat_map(|e| schema_errors(&e))
+            .collect(),
+        Err(e) => vec![e],
+    }
+}
+
+/// Renders a schema violation. A value failing every alternative of an
+/// `anyOf` (a step is a command or a group) is explained by the errors of
+/// the alternative of the right type, instead of a generic message.
+fn schema_errors(error: &ValidationError) -> Vec<String> {
+    if let ValidationErrorKind::AnyOf { context } = error.kind() {
+        let path = error.instance_path();
+        let mut matching = context.iter().filter(|errors| {
+            !errors.iter().any(|e| {
+                e.instance_path() == path && matches!(e.kind(), ValidationErrorKind::Type { .. })
+            })
+        });
+        if let (Some(errors), None) = (matching.next(), matching.next()) {
+            return errors.iter().flat_map(schema_errors).collect();
+        }
+    }
+
+    // Unknown fields are reported on their own; see `unknown_fields`.
+    if let ValidationErrorKind::AdditionalProperties { .. } = error.kind() {
+        return Vec::new();
+    }
+    let location = error.instance_path().to_string();
+    if location.is_empty() {
+        vec![format!("schema: {error}")]
+    } else {
+        vec![format!("schema: {location}: {error}")]
+    }
+}
+
+fn is_shell_variable(name: &str) -> bool {
+    name.chars()
+        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Parses the shortcuts file, rejecting duplicate keys, and checks that
+/// every shortcut leads to an existing script without going in circles.
+fn shortcut_problems(path: &Path) -> Vec<String> {
+    let shortcuts: Shortcuts = match fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        // Going through `Value` rejects duplicate keys, which the map would
+        // silently overwrite.
+        .and_then(|content| {
+            serde_yaml::from_str::<serde_yaml::Value>(&content)
+                .and_then(serde_yaml::from_value)
+                .map_err(|e| e.to_string())
+        }) {
+        Ok(shortcuts) => shortcuts,
+        Err(e) => return vec![e],
+    };
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut keys: Vec<&String> = shortcuts.shortcuts.keys().collect();
+    keys.sort();
+    keys.into_iter()
+        .filter_map(|key| resolve_shortcut(dir, &shortcuts, key).err())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+    use tempfile::tempdir;
+
+    fn validate(dir: &Path) -> (String, Result<(), String>) {
+        let mut buffer = Vec::new();
+        let result = validate_scripts(&mut buffer, &[dir.to_path_buf()], false, &Vars::new())
+            .map_err(|e| e.to_string());
+        (String::from_utf8(buffer).unwrap(), result)
+    }
+
+    #[test]
+    fn test_valid_scripts() {
+        let dir = tempdir().unwrap();
+        create_dir_all(dir.path().join("db")).unwrap();
+        write(
+            dir.path().join("build.yaml"),
+            r#"
+name: Build
+params: [target]
+secrets:
+  - name: token
+    env_var: TOKEN
+commands:
+  - command: git rev-parse HEAD
+    capture: sha
+  - command: echo ${target:q} ${token} ${sha} ${zirv.project_root} ${HOME}
+"#,
+        )
+        .unwrap();
+        write(
+            dir.path().join("db").join("migrate.json"),
+            r#"{"name": "Migrate", "commands": []}"#,
+        )
+        .unwrap();
+        write(
+            dir.path().join(".shortcuts.yaml"),
+            "shortcuts:\n  b: build\n  m: db/migrate.json\n",
+        )
+        .unwrap();
+
+        let (output, result) = validate(dir.path());
+        assert_eq!(result, Ok(()));
+        assert_eq!(output, "All 3 file(s) are valid.\n");
+    }
+
+    #[test]
+    fn test_parse_errors_have_locations() {
+        let dir = tempdir().unwrap();
+        write(
+            dir.path().join("broken.yaml"),
+            "name: Broken\ncommands: [\n",
+        )
+        .unwrap();
+        write(dir.path().join("broken.json"), "{\"name\": \"Broken\",\n}").unwrap();
+
+        let (output, result) = validate(dir.path());
+        assert_eq!(
+            result,
+            Err("Found 2 problem(s) in 2 of 2 file(s)".to_string())
+        );
+        assert!(output.contains("broken.yaml:\n  - "), "{output}");
+        assert!(output.contains("broken.json:\n  - "), "{output}");
+        assert_eq!(output.matches(" at line ").count(), 2, "{output}");
+    }
+
+    #[test]
+    fn test_incompatible_scripts_are_reported() {
+        let dir = tempdir().unwrap();
+        write(
+            dir.path().join("future.yaml"),
+            "name: Future\nzirv_version: \">=999\"\ncommands: []\n",
+        )
+        .unwrap();
+        write(
+            dir.path().join("malformed.yaml"),
+            "name: Malformed\nzirv_version: soon\ncommands: []\n",
+        )
+        .unwrap();
+
+        let (output, result) = validate(dir.path());
+        assert_eq!(
+            result,
+            Err("Found 2 problem(s) in 2 of 2 file(s)".to_string())
+        );
+        assert!(
+            output.contains("this script requires zirv >= 999, you have "),
+            "{output}"
+        );
+        assert!(output.contains("invalid zirv_version 'soon': "), "{output}");
+    }
+
+    #[test]
+    fn test_unquoted_params() {
+        let dir = tempdir().unwrap();
+        write(
+            dir.path().join("commit.yaml"),
+            r#"
+name: Commit
+params: [message, dir]
+commands:
+  - command: cd ${dir}
+  - command: git commit -m ${message}
+  - command: git commit -m ${message:q} --author="${message}"
+  - command: echo "committing ${message} in ${dir}"
+on_error:
+  - command: echo ${message}
+"#,
+        )
+        .unwrap();
+
+        let (output, result) = validate(dir.path());
+        assert_eq!(result, Ok(()), "{output}");
+        assert!(
+            output.ends_with(
+                "  - warning: step 2: '${message}' is not quoted; write '${message:q}' if its value may hold spaces or shell syntax\n  \
+                 - warning: on_error 1: '${message}' is not quoted; write '${message:q}' if its value may hold spaces or shell syntax\n\
+                 All 1 file(s) are valid.\n"
+            ),
+            "{output}"
+        );
+
+        let mut buffer = Vec::new();
+        let result = validate_scripts(&mut buffer, &[dir.path().to_path_buf()], true, &Vars::new());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_unknown_placeholders() {
+        let dir = tempdir().unwrap();
+        write(
+            dir.path().join("deploy.yaml"),
+            r#"
+name: Deploy
+commands:
+  - command: echo ${version} ${typo}
+  - command: cat VERSION
+    capture: version
+    when: ${version} > 1 &&
+    group: Release
+  - command: zirv build ${version}
+    export:
+      artifact: build_artifact
+      sha: version
+  - command: echo ${build_artifact}
+  - - command: zirv pack
+      export:
+        file: packed
+    - command: echo ${packed}
+  - command: echo ${{ github.sha }} $${literal} ${path:-/tmp} ${env:PATH}
+on_error:
+  - command: echo ${zirv.failed_step:q} ${version} ${tpyo} "${zirv.error}"
+groups:
+  Release:
+    when: os == linux
+  Unused: {}
+"#,
+        )
+        .unwrap();
+
+        let (output, result) = validate(dir.path());
+        assert!(result.is_err());
+        assert!(
+            output.contains("step 1: variable '${version}' is only captured by later step 2"),
+            "{output}"
+        );
+        assert!(
+            output.contains("step 1: unknown variable '${typo}'"),
+            "{output}"
+        );
+        assert!(
+            output.contains("on_error 1: unknown variable '${tpyo}'"),
+            "{output}"
+        );
+        assert!(!output.contains("zirv.failed_step"), "{output}");
+        assert!(
+            output.contains(
+                "on_error 1: '${zirv.error}' is not quoted; write '${zirv.error:q}' as it holds text of the failure"
+            ),
+            "{output}"
+        );
+        assert!(
+            output.contains("step 2: invalid when condition: expected a value at column 18"),
+            "{output}"
+        );
+        assert!(
+            output.contains(
+                "group 'Release': invalid when condition: unknown name 'linux'; use os, env.NAME, ${name} or a quoted string at column 7"
+            ),
+            "{output}"
+        );
+        assert!(
+            output.contains("group 'Unused' is not the group of any step"),
+            "{output}"
+        );
+        assert!(!output.contains("group 'Release' is not"), "{output}");
+        assert!(
+            output.contains(
+                "step 3: export target 'version' is already a param, secret, capture or variable"
+            ),
+            "{output}"
+        );
+        assert!(!output.contains("build_artifact"), "{output}");
+        assert!(
+            output.contains(
+                "step 5: `export` on 'zirv pack' has no effect in a group, whose commands run in a new terminal window"
+            ),
+            "{output}"
+        );
+        assert!(
+            output.contains("step 5: unknown variable '${packed}'"),
+            "{output}"
+        );
+        assert!(!output.contains("step 6"), "{output}");
+    }
+
+    #[test]
+    fn test_config_file_is_checked() {
+        let dir = tempdir().unwrap();
+        write(dir.path().join(CONFIG_FILE), "shell: bash\n").unwrap();
+        let (output, result) = validate(dir.path());
+        assert_eq!(result, Ok(()), "{output}");
+
+        write(dir.path().join(CONFIG_FILE), "colour: false\n").unwrap();
+        let (output, result) = validate(dir.path());
+        assert!(result.is_err());
+        assert!(output.contains("unknown field `colour`"), "{output}");
+    }
+
+    #[test]
+    fn test_overlays_are_merged_before_checking() {
+        let dir = tempdir().unwrap();
+        write(
+            dir.path().join("deploy.yaml"),
+            "name: Deploy\ncommands:\n  - command: echo hi\n",
+        )
+        .unwrap();
+        write(
+            dir.path().join("deploy.prod.yaml"),
+            "commands:\n  - command: echo prod\n",
+        )
+        .unwrap();
+        let (output, result) = validate(dir.path());
+        assert_eq!(result, Ok(()), "{output}");
+
+        write(dir.path().join("deploy.qa.yaml"), "commands: 3\n").unwrap();
+        let (output, result) = validate(dir.path());
+        assert_eq!(
+            result,
+            Err("Found 1 problem(s) in 1 of 3 file(s)".to_string())
+        );
+        assert!(output.contains("deploy.qa.yaml:\n  - "), "{output}");
+        assert!(output.contains("is not a valid script"), "{output}");
+    }
+
+    #[test]
+    fn test_scripts_file_entries_are_checked() {
+        let dir = tempdir().unwrap();
+        let zirv_dir = dir.path().join(".zirv");
+        write(
+            dir.path().join(".zirv.yaml"),
+            "scripts:\n  build:\n    name: Build\n    commands:\n      - command: echo ${typo}\n  lint:\n    name: Lint\n    commands: []\n",
+        )
+        .unwrap();
+
+        let (output, result) = validate(&zirv_dir);
+        assert_eq!(
+            result,
+            Err("Found 1 problem(s) in 1 of 2 file(s)".to_string())
+        );
+        assert!(
+            output.contains(".zirv.yaml#build:\n  - step 1: unknown variable '${typo}'"),
+            "{output}"
+        );
+
+        write(dir.path().join(".zirv.yaml"), "scripts: [\n").unwrap();
+        let (output, result) = validate(&zirv_dir);
+        assert!(result.is_err());
+        assert!(output.contains(".zirv.yaml:\n  - "), "{output}");
+    }
+
+    #[test]
+    fn test_shortcut_problems() {
+        let dir = tempdir().unwrap();
+        write(dir.path().join("build.yaml"), "name: Build\ncommands: []\n").unwrap();
+        write(
+            dir.path().join(".shortcuts.yaml"),
+            "shortcuts:\n  b: build\n  t: test\n",
+        )
+        .unwrap();
+        let (output, result) = validate(dir.path());
+        assert_eq!(
+            result,
+            Err("Found 1 problem(s) in 1 of 2 file(s)".to_string())
+        );
+        assert!(output.contains("Shortcut 't' points to 'test' which does not exist"));
+
+        write(
+            dir.path().join(".shortcuts.yaml"),
+            "shortcuts:\n  b: bb\n  bb: build\n  x: y\n  y: x\n",
+        )
+        .unwrap();
+        let (output, _) = validate(dir.path());
+        assert!(!output.contains("'b'"), "{output}");
+        assert!(output.contains("Shortcut cycle: x -> y -> x"), "{output}");
+        assert!(output.contains("Shortcut cycle: y -> x -> y"), "{output}");
+
+        write(
+            dir.path().join(".shortcuts.yaml"),
+            "shortcuts:\n  b: build\n  b: build.yaml\n",
+        )
+        .unwrap();
+        let (output, _) = validate(dir.path());
+        assert!(
+            output.contains("duplicate entry with key \"b\""),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn test_unknown_fields_are_problems() {
+        let dir = tempdir().unwrap();
+        write(
+            dir.path().join("build.yaml"),
+            "name: Build\ncommands:\n  - command: ls\n    captur: files\n  \
+             - command: cargo test\n    options:\n      porceed_on_failure: true\n",
+        )
+        .unwrap();
+        let expected = "build.yaml:\n  \
+            - step 1: unknown field 'captur'\n  \
+            - step 2: unknown field 'porceed_on_failure' in options\n";
+
+        let (output, result) = validate(dir.path());
+        assert_eq!(
+            result,
+            Err("Found 2 problem(s) in 1 of 1 file(s)".to_string())
+        );
+        assert!(output.ends_with(expected), "{output}");
+
+        // The schema does not report them a second time.
+        let mut buffer = Vec::new();
+        let result = validate_scripts(&mut buffer, &[dir.path().to_path_buf()], true, &Vars::new())
+            .map_err(|e| e.to_string());
+        let output = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            result,
+            Err("Found 2 problem(s) in 1 of 1 file(s)".to_string())
+        );
+        assert!(output.ends_with(expected), "{output}");
+    }
+
+    #[test]
+    fn test_capture_names() {
+        let dir = tempdir().unwrap();
+        write(
+            dir.path().join("build.yaml"),
+            r#"
+name: Build
+params:
+  - env
+commands:
+  - command: echo prod
+    capture: env
+"#,
+        )
+        .unwrap();
+
+        let (output, result) = validate(dir.path());
+        assert_eq!(result, Ok(()), "{output}");
+        assert!(
+            output.contains(
+                "  - warning: step 1: capture 'env' overwrites the param of the same name"
+            ),
+            "{output}"
+        );
+
+        let mut buffer = Vec::new();
+        let result = validate_scripts(&mut buffer, &[dir.path().to_path_buf()], true, &Vars::new());
+        let output = String::from_utf8(buffer).unwrap();
+        assert!(result.is_err());
+        assert!(
+            output.contains("  - step 1: capture 'env' overwrites the param of the same name"),
+            "{output}"
+        );
+
+        write(
+            dir.path().join("build.yaml"),
+            r#"
+name: Build
+commands:
+  - command: pwd
+    capture: cwd
+  - command: zirv other
+    export:
+      root: zirv.project_root
+on_error:
+  - command: echo failed
+    capture: zirv.error
+"#,
+        )
+        .unwrap();
+        let (output, result) = validate(dir.path());
+        assert!(result.is_err());
+        for problem in [
+            "step 1: capture 'cwd' uses a reserved name",
+            "step 2: export target 'zirv.project_root' uses a reserved name",
+            "on_error 1: capture 'zirv.error' uses a reserved name",
+        ] {
+            assert!(output.contains(problem), "{output}");
+        }
+    }
+
+    #[test]
+    fn test_scripts_hidden_by_builtins() {
+        let dir = tempdir().unwrap();
+        create_dir_all(dir.path().join("db")).unwrap();
+        let script = "name: Stats\ncommands:\n  - command: echo stats\n";
+        write(dir.path().join("stats.yaml"), script).unwrap();
+        write(dir.path().join("db").join("stats.yaml"), script).unwrap();
+
+        let (output, result) = validate(dir.path());
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            output,
+            format!(
+                "{}:\n  - warning: 'stats' is a built-in command, so this script cannot be run by name; rename it\n\
+                 All 2 file(s) are valid.\n",
+                dir.path().join("stats.yaml").display()
+            )
+        );
+
+        let mut buffer = Vec::new();
+        let result = validate_scripts(&mut buffer, &[dir.path().to_path_buf()], true, &Vars::new());
+        assert!(result.is_err());
+    }
+}
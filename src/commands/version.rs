@@ -1,4 +1,22 @@
-use std::io::Write;
+use std::{
+    fs,
+    io::Write,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::script_runner::compat::parse_version;
+
+/// The file under `~/.zirv` remembering the last version check.
+pub const VERSION_CHECK_FILE: &str = ".version-check";
+
+/// How long a looked-up version is trusted before asking again.
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long to wait for the registry before giving up.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub fn get_version<W: Write>(writer: &mut W) -> Result<(), Box<dyn std::error::Error>> {
     writeln!(writer, "Version: {}", env!("CARGO_PKG_VERSION"))?;
@@ -6,10 +24,153 @@ pub fn get_version<W: Write>(writer: &mut W) -> Result<(), Box<dyn std::error::E
     Ok(())
 }
 
+/// Where the latest published version is looked up.
+pub trait ReleaseSource {
+    fn latest_version(&self) -> Result<String, Box<dyn std::error::Error>>;
+}
+
+/// The crates.io registry.
+pub struct CratesIo;
+
+#[derive(Deserialize)]
+struct CrateResponse {
+    #[serde(rename = "crate")]
+    krate: CrateInfo,
+}
+
+#[derive(Deserialize)]
+struct CrateInfo {
+    max_stable_version: Option<String>,
+    max_version: String,
+}
+
+impl ReleaseSource for CratesIo {
+    fn latest_version(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(CHECK_TIMEOUT))
+            .build()
+            .into();
+        let body = agent
+            .get(concat!(
+                "https://crates.io/api/v1/crates/",
+                env!("CARGO_PKG_NAME")
+            ))
+            // crates.io rejects requests without a user agent.
+            .header(
+                "User-Agent",
+                concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+            )
+            .call()?
+            .body_mut()
+            .read_to_string()?;
+        let response: CrateResponse = serde_json::from_str(&body)?;
+        Ok(response
+            .krate
+            .max_stable_version
+            .unwrap_or(response.krate.max_version))
+    }
+}
+
+/// The last looked-up version, stored in `~/.zirv/.version-check`.
+#[derive(Debug, Deserialize, Serialize)]
+struct VersionCache {
+    /// Seconds since the Unix epoch.
+    checked_at: u64,
+    latest: String,
+}
+
+/// The latest version, from `cache` if it was checked within the last day,
+/// otherwise from `source`. A fresh lookup is written back to `cache`.
+fn latest_version(
+    source: &impl ReleaseSource,
+    cache: Option<&Path>,
+    now: SystemTime,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let now = now.duration_since(UNIX_EPOCH)?.as_secs();
+    let cached = cache
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<VersionCache>(&content).ok())
+        .filter(|cached| now.saturating_sub(cached.checked_at) < CHECK_INTERVAL.as_secs());
+    if let Some(cached) = cached {
+        return Ok(cached.latest);
+    }
+
+    let latest = source
+        .latest_version()
+        .map_err(|e| format!("Could not check for a newer version: {e}"))?;
+    if let Some(path) = cache {
+        let entry = VersionCache {
+            checked_at: now,
+            latest: latest.clone(),
+        };
+        // A cache that cannot be written only costs another lookup next time.
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let _ = fs::write(path, serde_json::to_string(&entry)?);
+    }
+    Ok(latest)
+}
+
+/// Prints whether a newer version than `current` has been published.
+pub fn check_version<W: Write>(
+    writer: &mut W,
+    current: &str,
+    source: &impl ReleaseSource,
+    cache: Option<&Path>,
+    now: SystemTime,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let latest = latest_version(source, cache, now)?;
+    let newer = match (parse_version(&latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => return Err(format!("Could not compare versions '{latest}' and '{current}'").into()),
+    };
+    if newer {
+        writeln!(writer, "{latest} available (you have {current})")?;
+    } else {
+        writeln!(writer, "Up to date ({current})")?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::cell::Cell;
     use std::io::Cursor;
+    use tempfile::tempdir;
+
+    struct FakeSource {
+        result: Result<&'static str, &'static str>,
+        calls: Cell<usize>,
+    }
+
+    impl FakeSource {
+        fn new(result: Result<&'static str, &'static str>) -> Self {
+            Self {
+                result,
+                calls: Cell::new(0),
+            }
+        }
+    }
+
+    impl ReleaseSource for FakeSource {
+        fn latest_version(&self) -> Result<String, Box<dyn std::error::Error>> {
+            self.calls.set(self.calls.get() + 1);
+            self.result.map(String::from).map_err(|e| e.into())
+        }
+    }
+
+    fn check(
+        current: &str,
+        source: &FakeSource,
+        cache: Option<&Path>,
+        now: SystemTime,
+    ) -> Result<String, String> {
+        let mut buffer = Vec::new();
+        check_version(&mut buffer, current, source, cache, now).map_err(|e| e.to_string())?;
+        Ok(String::from_utf8(buffer).unwrap())
+    }
 
     #[test]
     fn test_get_version_output() {
@@ -23,4 +184,60 @@ mod tests {
             "Output should contain the version"
         );
     }
+
+    #[test]
+    fn test_compares_versions() {
+        let now = SystemTime::now();
+        let source = FakeSource::new(Ok("0.4.1"));
+        assert_eq!(
+            check("0.3.3", &source, None, now),
+            Ok("0.4.1 available (you have 0.3.3)\n".to_string())
+        );
+        assert_eq!(
+            check("0.10.0", &source, None, now),
+            Ok("Up to date (0.10.0)\n".to_string())
+        );
+        assert_eq!(
+            check("0.4.1", &source, None, now),
+            Ok("Up to date (0.4.1)\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_offline_error() {
+        let source = FakeSource::new(Err("failed to lookup address"));
+        assert_eq!(
+            check("0.3.3", &source, None, SystemTime::now()),
+            Err("Could not check for a newer version: failed to lookup address".to_string())
+        );
+    }
+
+    #[test]
+    fn test_cache_is_used_for_a_day() {
+        let dir = tempdir().unwrap();
+        let cache = dir.path().join(".zirv").join(".version-check");
+        let start = SystemTime::now();
+        let source = FakeSource::new(Ok("0.4.1"));
+
+        check("0.3.3", &source, Some(&cache), start).unwrap();
+        let later = start + Duration::from_secs(23 * 60 * 60);
+        assert_eq!(
+            check(
+                "0.3.3",
+                &FakeSource::new(Err("offline")),
+                Some(&cache),
+                later
+            ),
+            Ok("0.4.1 available (you have 0.3.3)\n".to_string())
+        );
+        assert_eq!(source.calls.get(), 1);
+
+        let next_day = start + Duration::from_secs(25 * 60 * 60);
+        let source = FakeSource::new(Ok("0.5.0"));
+        assert_eq!(
+            check("0.3.3", &source, Some(&cache), next_day),
+            Ok("0.5.0 available (you have 0.3.3)\n".to_string())
+        );
+        assert_eq!(source.calls.get(), 1);
+    }
 }
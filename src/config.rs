@@ -0,0 +1,222 @@
+//! Persistent preferences from the `config.yaml` of the project's `.zirv`
+//! and of `~/.zirv`. Command line flags and environment variables take
+//! precedence over both, and the project's file over the global one.
+//!
+//! Every environment variable that overrides a setting has a key here. The
+//! one exception is `ZIRV_SAVE_CONTEXT`, which a step with `export` sets for
+//! the zirv run it starts and which means nothing as a lasting preference.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    script_runner::run_options::{UiMode, Verbosity},
+    utils::CONFIG_FILE,
+};
+
+/// The settings a `config.yaml` may contain. Every one is optional.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The shell steps run in, instead of `sh` (`powershell` on Windows).
+    pub shell: Option<String>,
+    /// Like `--plain`/`--tui` and `ZIRV_UI`.
+    pub ui: Option<UiMode>,
+    /// Like `--log-file` and `ZIRV_LOG_FILE`.
+    pub log_file: Option<PathBuf>,
+    /// Like `--report` and `ZIRV_REPORT`.
+    pub report: Option<PathBuf>,
+    /// `false` turns colors off, like `NO_COLOR`.
+    pub color: Option<bool>,
+    /// Like `-q`, `-v` and `-vv`.
+    pub verbosity: Option<Verbosity>,
+    /// Like `--timestamps`.
+    pub timestamps: Option<bool>,
+    /// Default for every command's `heartbeat_ms` option.
+    pub heartbeat_ms: Option<u64>,
+    /// Default for every command's `grace_period_ms` option.
+    pub grace_period_ms: Option<u64>,
+    /// Like `ZIRV_PATH`: extra script directories, searched in order.
+    /// Relative ones are taken from the directory holding the file's
+    /// `.zirv`.
+    pub path: Option<Vec<PathBuf>>,
+}
+
+impl Config {
+    /// Reads the configuration file at `path`.
+    pub fn load(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(Config::default());
+        }
+        Ok(serde_yaml::from_str(&content).map_err(|e| format!("{}: {e}", path.display()))?)
+    }
+}
+
+/// The project and global configuration files that were found.
+#[derive(Debug, Default, Clone)]
+pub struct Configs {
+    pub project: Option<(PathBuf, Config)>,
+    pub global: Option<(PathBuf, Config)>,
+}
+
+impl Configs {
+    /// Reads the `config.yaml` of the project's `.zirv` and of the global
+    /// directory, where they exist.
+    pub fn load(
+        project_dir: Option<&Path>,
+        global_dir: Option<&Path>,
+    ) -> Result<Configs, Box<dyn std::error::Error>> {
+        let (configs, problems) = Configs::load_readable(project_dir, global_dir);
+        match problems.into_iter().next() {
+            Some(problem) => Err(problem.into()),
+            None => Ok(configs),
+        }
+    }
+
+    /// Reads the configuration files like [`Configs::load`], leaving out
+    /// those that cannot be read, whose problems are returned instead.
+    pub fn load_readable(
+        project_dir: Option<&Path>,
+        global_dir: Option<&Path>,
+    ) -> (Configs, Vec<String>) {
+        let mut problems = Vec::new();
+        let mut read = |dir: Option<&Path>| match dir.map(|dir| dir.join(CONFIG_FILE)) {
+            Some(path) if path.is_file() => match Config::load(&path) {
+                Ok(config) => Some((path, config)),
+                Err(e) => {
+                    problems.push(e.to_string());
+                    None
+                }
+            },
+            _ => None,
+        };
+        let project = read(project_dir);
+        let global = match global_dir {
+            Some(dir) if project_dir == Some(dir) => None,
+            dir => read(dir),
+        };
+        (Configs { project, global }, problems)
+    }
+
+    /// The value `get` reads from the project's configuration, else from
+    /// the global one, with the file it came from.
+    pub fn lookup<T>(&self, get: impl Fn(&Config) -> Option<T>) -> Option<(T, Origin)> {
+        if let Some((path, config)) = &self.project
+            && let Some(value) = get(config)
+        {
+            return Some((value, Origin::Project(path.clone())));
+        }
+        if let Some((path, config)) = &self.global
+            && let Some(value) = get(config)
+        {
+            return Some((value, Origin::Global(path.clone())));
+        }
+        None
+    }
+}
+
+/// Where the effective value of a setting comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Origin {
+    CommandLine,
+    Env(&'static str),
+    Project(PathBuf),
+    Global(PathBuf),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::CommandLine => write!(f, "command line"),
+            Origin::Env(var) => write!(f, "{var}"),
+            Origin::Project(path) | Origin::Global(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Reads the environment variable `var`, treating an empty value as unset.
+pub fn env_value(var: &'static str) -> Option<(String, Origin)> {
+    std::env::var(var)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .map(|value| (value, Origin::Env(var)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(dir: &Path, content: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(CONFIG_FILE), content).unwrap();
+    }
+
+    #[test]
+    fn test_project_config_overrides_global() {
+        let temp = tempfile::tempdir().unwrap();
+        let project = temp.path().join("project");
+        let global = temp.path().join("global");
+        write_config(&project, "ui: plain\n");
+        write_config(&global, "ui: tui\nshell: bash\n");
+
+        let configs = Configs::load(Some(&project), Some(&global)).unwrap();
+        assert_eq!(
+            configs.lookup(|c| c.ui),
+            Some((UiMode::Plain, Origin::Project(project.join(CONFIG_FILE))))
+        );
+        assert_eq!(
+            configs.lookup(|c| c.shell.clone()),
+            Some(("bash".to_string(), Origin::Global(global.join(CONFIG_FILE))))
+        );
+        assert_eq!(configs.lookup(|c| c.report.clone()), None);
+
+        let configs = Configs::load(None, Some(&global)).unwrap();
+        assert_eq!(
+            configs.lookup(|c| c.ui).map(|(ui, _)| ui),
+            Some(UiMode::Tui)
+        );
+    }
+
+    #[test]
+    fn test_unknown_settings_are_rejected() {
+        let temp = tempfile::tempdir().unwrap();
+        write_config(temp.path(), "colour: false\n");
+
+        let error = Configs::load(Some(temp.path()), None)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with(&format!(
+                "{}: unknown field `colour`",
+                temp.path().join(CONFIG_FILE).display()
+            )),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_readable_configs_are_kept() {
+        let temp = tempfile::tempdir().unwrap();
+        let project = temp.path().join("project");
+        let global = temp.path().join("global");
+        write_config(&project, "colour: false\n");
+        write_config(&global, "shell: bash\n");
+
+        let (configs, problems) = Configs::load_readable(Some(&project), Some(&global));
+        assert_eq!(
+            configs.lookup(|c| c.shell.clone()).map(|(shell, _)| shell),
+            Some("bash".to_string())
+        );
+        assert!(configs.project.is_none());
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].contains("unknown field `colour`"),
+            "{problems:?}"
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! The errors zirv reports, by kind, so that each can end the process with
+//! its own exit code.
+
+use thiserror::Error;
+
+use crate::{script_runner::RunError, script_runner::StepError, utils::ScriptParseError};
+
+/// An error reported by zirv. The messages are those shown to the user;
+/// the underlying error, if any, is available as the source.
+#[derive(Debug, Error)]
+pub enum ZirvError {
+    /// No script, shortcut or alias matches `name`.
+    #[error("{message}")]
+    ScriptNotFound { name: String, message: String },
+    /// A script or another file zirv reads could not be parsed.
+    #[error(transparent)]
+    Parse(Box<ScriptParseError>),
+    /// A YAML file other than a script, such as `.shortcuts.yaml`, could
+    /// not be parsed.
+    #[error(transparent)]
+    Yaml(#[from] serde_yaml::Error),
+    /// Running a script failed: a param or secret is missing, or a step
+    /// failed.
+    #[error(transparent)]
+    Run(#[from] RunError),
+    /// Reading or writing a file failed.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// Anything else, such as a wrong combination of flags.
+    #[error(transparent)]
+    Other(Box<dyn std::error::Error>),
+}
+
+impl ZirvError {
+    /// The error for a `name` nothing was found for, described by `message`.
+    pub fn not_found(name: &str, message: String) -> ZirvError {
+        ZirvError::ScriptNotFound {
+            name: name.to_string(),
+            message,
+        }
+    }
+
+    /// The exit code zirv ends with on this error: that of the command that
+    /// failed, 126 if it could not be started, 127 if the script does not
+    /// exist, 65 if it cannot be parsed or is invalid, 64 for missing params, 78 for
+    /// missing secrets and 1 otherwise.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ZirvError::ScriptNotFound { .. } => 127,
+            ZirvError::Parse(_) | ZirvError::Yaml(_) | ZirvError::Run(RunError::Invalid { .. }) => {
+                65
+            }
+            ZirvError::Run(RunError::Inputs(errors)) if errors.params_wrong() => 64,
+            ZirvError::Run(RunError::Inputs(_)) => 78,
+            ZirvError::Run(RunError::Step {
+                error: StepError::Spawn { .. },
+                ..
+            }) => 126,
+            ZirvError::Run(RunError::TimedOut { .. }) => 124,
+            ZirvError::Run(RunError::Stopped { .. }) => 130,
+            ZirvError::Run(RunError::Step {
+                exit_code: Some(code),
+                ..
+            }) => u8::try_from(*code)
+                .ok()
+                .filter(|&code| code != 0)
+                .unwrap_or(1),
+            _ => 1,
+        }
+    }
+}
+
+impl From<ScriptParseError> for ZirvError {
+    fn from(error: ScriptParseError) -> Self {
+        ZirvError::Parse(Box::new(error))
+    }
+}
+
+impl From<String> for ZirvError {
+    fn from(message: String) -> Self {
+        ZirvError::Other(message.into())
+    }
+}
+
+impl From<&str> for ZirvError {
+    fn from(message: &str) -> Self {
+        ZirvError::Other(message.into())
+    }
+}
+
+/// Recovers the kind of an error that was passed along boxed.
+impl From<Box<dyn std::error::Error>> for ZirvError {
+    fn from(error: Box<dyn std::error::Error>) -> Self {
+        let error = match error.downcast::<ZirvError>() {
+            Ok(error) => return *error,
+            Err(error) => error,
+        };
+        let error = match error.downcast::<ScriptParseError>() {
+            Ok(error) => return ZirvError::Parse(error),
+            Err(error) => error,
+        };
+        let error = match error.downcast::<serde_yaml::Error>() {
+            Ok(error) => return ZirvError::Yaml(*error),
+            Err(error) => error,
+        };
+        let error = match error.downcast::<RunError>() {
+            Ok(error) => return ZirvError::Run(*error),
+            Err(error) => error,
+        };
+        match error.downcast::<std::io::Error>() {
+            Ok(error) => ZirvError::Io(*error),
+            Err(error) => ZirvError::Other(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script_runner::{InputErrors, secret::Secret};
+    use crate::utils::parse_script_content;
+
+    fn step_error(exit_code: Option<i32>, error: StepError) -> ZirvError {
+        ZirvError::Run(RunError::Step {
+            script: "build".to_string(),
+            exit_code,
+            error,
+        })
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(
+            ZirvError::not_found("x", "gone".to_string()).exit_code(),
+            127
+        );
+        let parse = parse_script_content("name: [", "yaml").unwrap_err();
+        assert_eq!(ZirvError::from(parse).exit_code(), 65);
+        let invalid = RunError::Invalid {
+            script: "build".to_string(),
+            problems: vec!["step 1: capture 'cwd' uses a reserved name".to_string()],
+        };
+        assert_eq!(ZirvError::from(invalid).exit_code(), 65);
+        let secret = || Secret {
+            name: "token".to_string(),
+            env_var: "TOKEN".to_string(),
+        };
+        let params = InputErrors {
+            declared: vec!["version".to_string()],
+            given: 0,
+            missing_secrets: vec![secret()],
+        };
+        assert_eq!(ZirvError::from(RunError::from(params)).exit_code(), 64);
+        let secrets = InputErrors {
+            missing_secrets: vec![secret()],
+            ..Default::default()
+        };
+        assert_eq!(ZirvError::from(RunError::from(secrets)).exit_code(), 78);
+        let failed = || StepError::Failed("`make` failed".to_string());
+        assert_eq!(step_error(Some(3), failed()).exit_code(), 3);
+        assert_eq!(step_error(Some(256), failed()).exit_code(), 1);
+        assert_eq!(step_error(None, failed()).exit_code(), 1);
+        // The step's message is part of the error's own, not repeated as its cause.
+        let error = step_error(None, failed());
+        assert_eq!(
+            error.to_string(),
+            "Error executing command in script 'build': `make` failed"
+        );
+        assert!(std::error::Error::source(&error).is_none());
+        let spawn = StepError::Spawn {
+            message: "Command 'make' failed: no shell".to_string(),
+            source: std::io::Error::from(std::io::ErrorKind::NotFound),
+        };
+        assert_eq!(step_error(None, spawn).exit_code(), 126);
+        let timed_out = RunError::TimedOut {
+            script: "ci".to_string(),
+            after: std::time::Duration::from_millis(1500),
+            step: 2,
+        };
+        assert_eq!(
+            timed_out.to_string(),
+            "Script 'ci' timed out after 1.5s at step 2"
+        );
+        assert_eq!(ZirvError::from(timed_out).exit_code(), 124);
+        let stopped = RunError::Stopped {
+            script: "ci".to_string(),
+        };
+        assert_eq!(ZirvError::from(stopped).exit_code(), 130);
+        assert_eq!(ZirvError::from("--local and --global").exit_code(), 1);
+    }
+
+    #[test]
+    fn test_boxed_errors_keep_their_kind() {
+        let boxed: Box<dyn std::error::Error> =
+            ZirvError::not_found("deploy", "No script or shortcut found for 'deploy'".into())
+                .into();
+        assert!(matches!(
+            ZirvError::from(boxed),
+            ZirvError::ScriptNotFound { name, .. } if name == "deploy"
+        ));
+
+        let boxed: Box<dyn std::error::Error> =
+            parse_script_content("name: [", "yaml").unwrap_err().into();
+        let error = ZirvError::from(boxed);
+        assert!(matches!(error, ZirvError::Parse(_)));
+        assert!(std::error::Error::source(&error).is_some());
+
+        let boxed: Box<dyn std::error::Error> = "plain message".into();
+        let error = ZirvError::from(boxed);
+        assert!(matches!(error, ZirvError::Other(_)));
+        assert_eq!(error.to_string(), "plain message");
+    }
+}
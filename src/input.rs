@@ -2,66 +2,1227 @@ use std::path::{Path, PathBuf};
 
 use clap::Parser;
 
-use crate::utils::{SCRIPT_DIR_NAME, SUPPORTED_EXTENSIONS, Shortcuts, home_dir};
+use crate::catalog::{
+    AliasIndex, ResolvedScript, file_script_names, resolve_shortcut, script_names,
+};
+use crate::commands::{create::ScriptFormat, export::ExportFormat};
+use serde_yaml::Value;
+
+use crate::commands::config::Setting;
+use crate::config::{Configs, Origin, env_value};
+use crate::error::ZirvError;
+use crate::script_runner::SAVE_CONTEXT_ENV;
+use crate::script_runner::process::DEFAULT_GRACE_PERIOD;
+use crate::script_runner::session::shell_program;
+use crate::script_runner::{
+    run_options::{OutputFormat, RunOptions, UiMode, Verbosity},
+    script::Script,
+    selection::StepSelection,
+    style::ColorMode,
+};
+use crate::utils::{
+    Overlay, RESERVED_FILES, SCRIPT_DIR_NAME, SUPPORTED_EXTENSIONS, ScriptLocation,
+    ScriptParseError, ScriptSource, Shortcuts, VARS_FILE, Vars, ZIRV_PATH_VAR, canonicalize,
+    file_to_script_with_overlay, find_project_dir, load_vars, merge_vars, script_unknown_fields,
+    suggestions, zirv_home, zirv_path_dirs,
+};
 
 #[derive(Debug, Parser)]
 pub struct Input {
     /// A descriptive name for the script.
-    pub command: String,
+    #[arg(required_unless_present = "file")]
+    pub command: Option<String>,
+    /// Run the script at this path instead of looking one up by name.
+    #[arg(short, long)]
+    pub file: Option<PathBuf>,
     /// Optional parameters (positional arguments) that will be mapped to the script's expected params.
     #[arg(num_args = 0..)]
     pub params: Vec<String>,
+    /// Do not print the summary table after the script finishes.
+    #[arg(long)]
+    pub no_summary: bool,
+    /// Write a JSON report of the run to this path [env: ZIRV_REPORT].
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+    /// Write a JUnit XML report of the run to this path.
+    #[arg(long)]
+    pub junit: Option<PathBuf>,
+    /// POST the JSON report of the run to this URL when it finishes,
+    /// instead of the script's `notify_webhook`.
+    #[arg(long, value_name = "URL")]
+    pub webhook: Option<String>,
+    /// Start at this step (a step name or 1-based index). With `import`, the
+    /// package.json, Makefile or justfile to import.
+    #[arg(long)]
+    pub from: Option<String>,
+    /// Stop after this step (a step name or 1-based index).
+    #[arg(long)]
+    pub to: Option<String>,
+    /// Run only this step (a step name or 1-based index). May be repeated.
+    #[arg(long)]
+    pub step: Vec<String>,
+    /// Run only steps carrying this tag. May be repeated.
+    #[arg(long)]
+    pub only: Vec<String>,
+    /// Skip steps carrying this tag. May be repeated.
+    #[arg(long)]
+    pub skip_tag: Vec<String>,
+    /// Print the run as `text` for people, or as `json`: one JSON event per line and nothing else.
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub output: Option<OutputFormat>,
+    /// Prefix every line printed during the run with the time since it started and the step's index.
+    #[arg(long)]
+    pub timestamps: bool,
+    /// Append everything printed during the run to this file, with timestamps [env: ZIRV_LOG_FILE].
+    #[arg(long)]
+    pub log_file: Option<PathBuf>,
+    /// After a successful run, write its final variables, without secrets, to this YAML file [env: ZIRV_SAVE_CONTEXT].
+    #[arg(long, value_name = "FILE")]
+    pub save_context: Option<PathBuf>,
+    /// Start with the variables saved by `--save-context`; params and secrets still win.
+    #[arg(long, value_name = "FILE")]
+    pub load_context: Option<PathBuf>,
+    /// Run the script this many times and print timing statistics.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub repeat: Option<u32>,
+    /// With --repeat, keep going after a failed run.
+    #[arg(long)]
+    pub keep_going: bool,
+    /// Run commands with a `cache` even when their inputs are unchanged.
+    #[arg(long)]
+    pub no_cache: bool,
+    /// Re-run the script whenever files under this path change. May be repeated.
+    #[arg(long)]
+    pub watch: Vec<PathBuf>,
+    /// Ignore changes to paths matching this pattern in watch mode. May be repeated.
+    #[arg(long)]
+    pub watch_ignore: Vec<String>,
+    /// Stream command output as-is, without step prefixes.
+    #[arg(long, alias = "no-tui", conflicts_with = "tui")]
+    pub plain: bool,
+    /// Decorate command output even when stdout is not a terminal.
+    #[arg(long)]
+    pub tui: bool,
+    /// When to color output: `auto` (when stdout is a terminal), `always` or
+    /// `never`.
+    #[arg(long, value_enum, value_name = "WHEN")]
+    pub color: Option<ColorMode>,
+    /// With `version`, look up whether a newer release has been published.
+    #[arg(long)]
+    pub check: bool,
+    /// Merge this environment's overlay over the script, e.g. `deploy.prod.yaml`
+    /// over `deploy.yaml` for `--env prod`.
+    #[arg(long, value_name = "NAME")]
+    pub env: Option<String>,
+    /// With `run-url`, the SHA-256 hash the downloaded script must have.
+    #[arg(long, value_name = "HASH")]
+    pub sha256: Option<String>,
+    /// Print machine-readable JSON where supported (`list`, `explain`, `stats`).
+    #[arg(long)]
+    pub json: bool,
+    /// Fail on unknown fields in a script instead of warning about them; with
+    /// `validate`, also check scripts against the JSON schema.
+    #[arg(long)]
+    pub strict: bool,
+    /// Only look for a .zirv directory in the current directory, not its parents.
+    #[arg(long)]
+    pub no_parent_search: bool,
+    /// Print `help` output all at once instead of a screen at a time.
+    #[arg(long)]
+    pub no_pager: bool,
+    /// Also print each step's working directory, shell, environment overrides
+    /// and substituted placeholders, and on failure the errors that caused
+    /// it; `-vv` also prints the processes steps start.
+    #[arg(long, short = 'v', action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+    /// Print nothing but errors and the final status of the run.
+    #[arg(long, short = 'q')]
+    pub quiet: bool,
+    /// With `remove`, `run-url`, `install-hook` or a `dangerous` script, go ahead without asking for confirmation; with `init`, answer every question with yes.
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+    /// With `remove` or `rename`, only act on the project's .zirv directory; with `init`, initialize it without asking.
+    #[arg(long)]
+    pub local: bool,
+    /// With `remove`, `rename`, `shortcut`, `create` or `init`, act on the global ~/.zirv directory.
+    #[arg(long)]
+    pub global: bool,
+    /// With `clean`, remove the cache of steps with a `cache` and the last version check.
+    #[arg(long)]
+    pub cache: bool,
+    /// With `clean`, remove the run history.
+    #[arg(long)]
+    pub history: bool,
+    /// With `clean`, remove everything zirv generated.
+    #[arg(long)]
+    pub all: bool,
+    /// With `clean`, only print what would be removed.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// With `promote` or `demote`, delete the original after copying it.
+    #[arg(long = "move")]
+    pub move_source: bool,
+    /// With `create`, add this shortcut for the new script.
+    #[arg(long)]
+    pub shortcut: Option<String>,
+    /// With `create`, the file format of the new script (yaml, json or toml);
+    /// with `export`, the shell to export to (sh or ps1).
+    #[arg(long)]
+    pub format: Option<String>,
+    /// With `create`, `import` or `init --template`, replace existing files of the same name.
+    #[arg(long)]
+    pub force: bool,
+    /// With `create`, pick the steps from the last N commands in your shell history.
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "20"
+    )]
+    pub from_history: Option<usize>,
+    /// With `last`, choose which of the last N runs to repeat.
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "10"
+    )]
+    pub pick: Option<usize>,
+    /// With `init`, add .zirv/ to the project's .gitignore without asking.
+    #[arg(long)]
+    pub gitignore: bool,
+    /// With `init`, copy the scripts of this template directory or git repository into .zirv.
+    #[arg(long, value_name = "GIT_URL_OR_DIR")]
+    pub template: Option<String>,
+    /// The project's and the global `config.yaml`, read at startup.
+    #[arg(skip)]
+    pub config: Configs,
 }
 
-fn find_script_in_dir(
-    dir: &Path,
-    name: &str,
-) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+/// Finds `name` among the scripts of `dir`, then in its file of scripts,
+/// then among its shortcuts.
+fn find_script_in_dir(dir: &Path, name: &str) -> Result<Option<ResolvedScript>, ZirvError> {
     for ext in SUPPORTED_EXTENSIONS {
         let path = dir.join(format!("{name}.{ext}"));
-        if path.exists() {
-            return Ok(Some(path.canonicalize()?));
+        if path.exists() && !RESERVED_FILES.iter().any(|file| path == dir.join(file)) {
+            return Ok(Some((
+                ScriptLocation::File(canonicalize(&path)?),
+                Vec::new(),
+            )));
         }
     }
 
+    if let Some((_, location)) = file_script_names(dir)?.into_iter().find(|(n, _)| n == name) {
+        return Ok(Some((location.canonicalize()?, Vec::new())));
+    }
+
     let shortcuts_path = dir.join(".shortcuts.yaml");
     if shortcuts_path.exists() {
         let content = std::fs::read_to_string(&shortcuts_path)?;
         let shortcuts: Shortcuts = serde_yaml::from_str(&content)?;
-        if let Some(mapped_file) = shortcuts.shortcuts.get(name) {
-            let path = dir.join(mapped_file);
-            if path.exists() {
-                return Ok(Some(path.canonicalize()?));
+        return Ok(resolve_shortcut(dir, &shortcuts, name)?);
+    }
+
+    Ok(None)
+}
+
+/// The name the script at `location` is run by: its path in the one of
+/// `roots` holding it without the extension, such as `db/seed`, or its file
+/// name outside of them. Scripts in a file of scripts go by their key.
+fn run_name(roots: &[PathBuf], location: &ScriptLocation) -> String {
+    let file = match location {
+        ScriptLocation::Entry { name, .. } => return name.clone(),
+        ScriptLocation::File(file) => file,
+    };
+    for root in roots {
+        if let Ok(root) = canonicalize(root)
+            && let Ok(relative) = file.strip_prefix(&root)
+        {
+            let parts: Vec<String> = relative
+                .with_extension("")
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            return parts.join("/");
+        }
+    }
+    file.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Finds the script for `command` in the first of `roots` that has it.
+///
+/// Both `/` and `\` separate namespaces. If `command` is a namespace
+/// directory rather than a script, the first of `params` is removed and
+/// looked up inside it. Failing all that, `command` may be an alias
+/// declared inside a script.
+fn find_script(
+    roots: &[PathBuf],
+    command: &str,
+    params: &mut Vec<String>,
+) -> Result<ResolvedScript, ZirvError> {
+    let command = command.replace('\\', "/");
+    for root in roots {
+        if let Some(found) = find_script_in_dir(root, &command)? {
+            return Ok(found);
+        }
+    }
+
+    if !params.is_empty() && roots.iter().any(|root| root.join(&command).is_dir()) {
+        let name = params.remove(0);
+        return find_script(roots, &format!("{command}/{name}"), params);
+    }
+
+    let aliases = AliasIndex::for_roots(roots)?;
+    if let Some(path) = aliases.get(&command) {
+        return Ok((ScriptLocation::File(canonicalize(path)?), Vec::new()));
+    }
+
+    if let Some((namespace, _)) = command.rsplit_once('/')
+        && !roots.iter().any(|root| root.join(namespace).is_dir())
+    {
+        return Err(ZirvError::not_found(
+            &command,
+            format!("No namespace '{namespace}' found for '{command}'"),
+        ));
+    }
+
+    let mut names: Vec<String> = aliases.aliases.keys().cloned().collect();
+    for root in roots {
+        names.extend(file_script_names(root)?.into_iter().map(|(name, _)| name));
+    }
+    for root in roots.iter().filter(|root| root.is_dir()) {
+        names.extend(script_names(root)?.into_iter().map(|(name, _)| name));
+        if let Ok(content) = std::fs::read_to_string(root.join(".shortcuts.yaml"))
+            && let Ok(shortcuts) = serde_yaml::from_str::<Shortcuts>(&content)
+        {
+            names.extend(shortcuts.shortcuts.into_keys());
+        }
+    }
+    let mut message = format!("No script or shortcut found for '{command}'");
+    let suggested = suggestions(&command, names.iter().map(String::as_str));
+    if !suggested.is_empty() {
+        message.push_str(&format!(". Did you mean '{}'?", suggested.join("', '")));
+    }
+    Err(ZirvError::not_found(&command, message))
+}
+
+/// Parses a `--format` value, defaulting when it is not given.
+fn parse_format<T: clap::ValueEnum + Default>(
+    format: Option<&str>,
+    expected: &str,
+) -> Result<T, String> {
+    match format {
+        None => Ok(T::default()),
+        Some(format) => T::from_str(format, true)
+            .map_err(|_| format!("Unknown format '{format}'; expected {expected}")),
+    }
+}
+
+impl Input {
+    pub fn step_selection(&self) -> StepSelection {
+        StepSelection {
+            from: self.from.clone(),
+            to: self.to.clone(),
+            steps: self.step.clone(),
+        }
+    }
+
+    /// Loads the script at `location`, with its `--env` overlay merged over
+    /// it, along with a warning for each field of the script zirv does not
+    /// know and each alias lookup found declared twice. With `--strict`,
+    /// unknown fields fail the load instead.
+    pub fn load_script(
+        &self,
+        location: &ScriptLocation,
+    ) -> Result<(Script, Option<Overlay>, Vec<String>), ZirvError> {
+        let (script, overlay) = match &self.env {
+            Some(env) => {
+                let (script, overlay) = file_to_script_with_overlay(location, env)?;
+                (script, Some(overlay))
             }
-            for ext in SUPPORTED_EXTENSIONS {
-                let path = dir.join(format!("{mapped_file}.{ext}"));
-                if path.exists() {
-                    return Ok(Some(path.canonicalize()?));
+            None => (location.load()?, None),
+        };
+        let unknown = script_unknown_fields(location);
+        if self.strict && !unknown.is_empty() {
+            let file = location.file();
+            let ext = file.extension().and_then(|s| s.to_str()).unwrap_or("");
+            let mut error = ScriptParseError::without_location(ext, unknown.join("; "));
+            error.path = Some(file.to_path_buf());
+            return Err(error.into());
+        }
+        let mut warnings: Vec<String> = unknown
+            .into_iter()
+            .map(|field| format!("{location}: {field}"))
+            .collect();
+        if let Some(aliases) = AliasIndex::built(&self.script_roots()?) {
+            warnings.extend(aliases.warnings());
+        }
+        Ok((script, overlay, warnings))
+    }
+
+    /// The options for running a script from the command line flags.
+    pub fn run_options(&self, project_root: Option<PathBuf>) -> Result<RunOptions, ZirvError> {
+        Ok(RunOptions {
+            no_summary: self.no_summary,
+            report: self.report_setting().map(|(path, _)| path),
+            junit: self.junit.clone(),
+            webhook: self.webhook.clone(),
+            only_tags: self.only.clone(),
+            skip_tags: self.skip_tag.clone(),
+            log_file: self.log_file_setting().map(|(path, _)| path),
+            keep_going: self.keep_going,
+            no_cache: self.no_cache,
+            ui: self.ui_mode()?,
+            project_root,
+            vars: self.vars()?,
+            save_context: self
+                .save_context
+                .clone()
+                .or_else(|| env_value(SAVE_CONTEXT_ENV).map(|(path, _)| PathBuf::from(path))),
+            loaded_context: match &self.load_context {
+                Some(path) => load_vars(path)?,
+                None => Vars::new(),
+            },
+            shell: self
+                .config
+                .lookup(|c| c.shell.clone())
+                .map(|(shell, _)| shell),
+            color: self.color_mode(),
+            verbosity: self.verbosity(),
+            output: self.output.unwrap_or_default(),
+            timestamps: self
+                .timestamps_setting()
+                .is_some_and(|(timestamps, _)| timestamps),
+            heartbeat_ms: self
+                .config
+                .lookup(|c| c.heartbeat_ms)
+                .map(|(heartbeat, _)| heartbeat),
+            grace_period_ms: self
+                .config
+                .lookup(|c| c.grace_period_ms)
+                .map(|(grace, _)| grace),
+            ..Default::default()
+        })
+    }
+
+    /// Every setting `config.yaml` can hold, with its effective value.
+    pub fn settings(&self) -> Result<Vec<Setting>, String> {
+        let path = |setting: Option<(PathBuf, Origin)>| match setting {
+            Some((path, origin)) => (Value::from(path.to_string_lossy()), Some(origin)),
+            None => (Value::Null, None),
+        };
+        let shell = self.config.lookup(|c| c.shell.clone());
+        let ui = self.ui_setting()?;
+        let color = self.color_setting();
+        let verbosity = self.config.lookup(|c| c.verbosity);
+        let timestamps = self.timestamps_setting();
+        let report = path(self.report_setting());
+        let log_file = path(self.log_file_setting());
+        let heartbeat = self.config.lookup(|c| c.heartbeat_ms);
+        let grace = self.config.lookup(|c| c.grace_period_ms);
+        let search_path = self.search_path_setting().map_err(|e| e.to_string())?;
+        Ok(vec![
+            Setting {
+                name: "shell",
+                value: Value::from(shell_program(shell.as_ref().map(|(s, _)| s.as_str()))),
+                origin: shell.map(|(_, origin)| origin),
+            },
+            Setting {
+                name: "ui",
+                value: serde_yaml::to_value(ui.as_ref().map(|(ui, _)| *ui).unwrap_or_default())
+                    .map_err(|e| e.to_string())?,
+                origin: ui.map(|(_, origin)| origin),
+            },
+            Setting {
+                name: "log_file",
+                value: log_file.0,
+                origin: log_file.1,
+            },
+            Setting {
+                name: "report",
+                value: report.0,
+                origin: report.1,
+            },
+            Setting {
+                name: "color",
+                value: Value::from(color.as_ref().is_none_or(|(color, _)| *color)),
+                origin: color.map(|(_, origin)| origin),
+            },
+            Setting {
+                name: "verbosity",
+                value: serde_yaml::to_value(
+                    verbosity
+                        .as_ref()
+                        .map(|(verbosity, _)| *verbosity)
+                        .unwrap_or_default(),
+                )
+                .map_err(|e| e.to_string())?,
+                origin: verbosity.map(|(_, origin)| origin),
+            },
+            Setting {
+                name: "timestamps",
+                value: Value::from(timestamps.as_ref().is_some_and(|(stamp, _)| *stamp)),
+                origin: timestamps.map(|(_, origin)| origin),
+            },
+            Setting {
+                name: "heartbeat_ms",
+                value: heartbeat
+                    .as_ref()
+                    .map_or(Value::Null, |(heartbeat, _)| Value::from(*heartbeat)),
+                origin: heartbeat.map(|(_, origin)| origin),
+            },
+            Setting {
+                name: "grace_period_ms",
+                value: Value::from(
+                    grace
+                        .as_ref()
+                        .map_or(DEFAULT_GRACE_PERIOD.as_millis() as u64, |(grace, _)| *grace),
+                ),
+                origin: grace.map(|(_, origin)| origin),
+            },
+            Setting {
+                name: "path",
+                value: search_path.as_ref().map_or(Value::Null, |(dirs, _)| {
+                    dirs.iter()
+                        .map(|dir| Value::from(dir.to_string_lossy()))
+                        .collect()
+                }),
+                origin: search_path.map(|(_, origin)| origin),
+            },
+        ])
+    }
+
+    /// Reads the configuration files of the project and of `~/.zirv`,
+    /// which must be valid for a script to run.
+    pub fn load_config(&mut self) -> Result<(), ZirvError> {
+        self.config = Configs::load(self.project_dir()?.as_deref(), zirv_home().as_deref())?;
+        Ok(())
+    }
+
+    /// Reads the configuration files that are valid, for commands that
+    /// show or check them, and returns the problems of the others.
+    pub fn load_readable_config(&mut self) -> Result<Vec<String>, ZirvError> {
+        let (config, problems) =
+            Configs::load_readable(self.project_dir()?.as_deref(), zirv_home().as_deref());
+        self.config = config;
+        Ok(problems)
+    }
+
+    /// `--report`, then `ZIRV_REPORT`, then the configuration.
+    pub fn report_setting(&self) -> Option<(PathBuf, Origin)> {
+        match &self.report {
+            Some(path) => Some((path.clone(), Origin::CommandLine)),
+            None => env_value("ZIRV_REPORT")
+                .map(|(path, origin)| (PathBuf::from(path), origin))
+                .or_else(|| self.config.lookup(|c| c.report.clone())),
+        }
+    }
+
+    /// `--log-file`, then `ZIRV_LOG_FILE`, then the configuration.
+    pub fn log_file_setting(&self) -> Option<(PathBuf, Origin)> {
+        match &self.log_file {
+            Some(path) => Some((path.clone(), Origin::CommandLine)),
+            None => env_value("ZIRV_LOG_FILE")
+                .map(|(path, origin)| (PathBuf::from(path), origin))
+                .or_else(|| self.config.lookup(|c| c.log_file.clone())),
+        }
+    }
+
+    /// Whether colors are wanted: as `--color always|never` says, else
+    /// never with `NO_COLOR`, else as configured.
+    pub fn color_setting(&self) -> Option<(bool, Origin)> {
+        match self.color {
+            Some(ColorMode::Always) => Some((true, Origin::CommandLine)),
+            Some(ColorMode::Never) => Some((false, Origin::CommandLine)),
+            _ => env_value("NO_COLOR")
+                .map(|(_, origin)| (false, origin))
+                .or_else(|| self.config.lookup(|c| c.color)),
+        }
+    }
+
+    /// `ZIRV_PATH`, then the `path` of the configuration. Scripts are looked
+    /// up by commands that do not read the configuration otherwise, so the
+    /// valid files are read here if need be, and a broken one is skipped.
+    pub fn search_path_setting(&self) -> Result<Option<(Vec<PathBuf>, Origin)>, ZirvError> {
+        let dirs = zirv_path_dirs();
+        if !dirs.is_empty() {
+            return Ok(Some((dirs, Origin::Env(ZIRV_PATH_VAR))));
+        }
+        let readable;
+        let configs = if self.config.project.is_none() && self.config.global.is_none() {
+            readable =
+                Configs::load_readable(self.project_dir()?.as_deref(), zirv_home().as_deref()).0;
+            &readable
+        } else {
+            &self.config
+        };
+        Ok(configs.lookup(|c| c.path.clone()).map(|(dirs, origin)| {
+            let base = match &origin {
+                Origin::Project(file) | Origin::Global(file) => {
+                    file.parent().and_then(Path::parent).map(Path::to_path_buf)
                 }
+                _ => None,
+            };
+            let dirs = dirs
+                .into_iter()
+                .map(|dir| match &base {
+                    Some(base) => base.join(dir),
+                    None => dir,
+                })
+                .collect();
+            (dirs, origin)
+        }))
+    }
+
+    /// `--timestamps`, else the configuration.
+    pub fn timestamps_setting(&self) -> Option<(bool, Origin)> {
+        if self.timestamps {
+            Some((true, Origin::CommandLine))
+        } else {
+            self.config.lookup(|c| c.timestamps)
+        }
+    }
+
+    /// `-q`, `-v` or `-vv`, else the configured verbosity.
+    pub fn verbosity(&self) -> Verbosity {
+        match (self.quiet, self.verbose) {
+            (true, _) => Verbosity::Quiet,
+            (_, 1) => Verbosity::Verbose,
+            (_, 2..) => Verbosity::Debug,
+            (_, 0) => self
+                .config
+                .lookup(|c| c.verbosity)
+                .map(|(verbosity, _)| verbosity)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// When to color output: `--color`, else never if colors are turned off
+    /// by `NO_COLOR` or the configuration, else automatically.
+    pub fn color_mode(&self) -> ColorMode {
+        match (self.color, self.color_setting()) {
+            (Some(mode), _) => mode,
+            (None, Some((false, _))) => ColorMode::Never,
+            (None, _) => ColorMode::Auto,
+        }
+    }
+
+    /// The variables of the project's `vars.yaml` and the global one, the
+    /// project's winning. Every variable set in both is warned about.
+    pub fn vars(&self) -> Result<Vars, ZirvError> {
+        let mut layers = Vec::new();
+        for dir in self.project_dir()?.into_iter().chain(zirv_home()) {
+            let path = dir.join(VARS_FILE);
+            if path.is_file() && !layers.iter().any(|(p, _)| *p == path) {
+                let vars = load_vars(&path)?;
+                layers.push((path, vars));
             }
         }
+        let (vars, warnings) = merge_vars(&layers);
+        for warning in warnings {
+            eprintln!("Warning: {warning}");
+        }
+        Ok(vars)
     }
 
-    Ok(None)
+    /// The output mode requested by `--plain`/`--tui`, `ZIRV_UI` or the
+    /// configuration.
+    pub fn ui_mode(&self) -> Result<UiMode, String> {
+        Ok(self.ui_setting()?.map(|(ui, _)| ui).unwrap_or_default())
+    }
+
+    /// `--plain`/`--tui`, then `ZIRV_UI`, then the configuration.
+    pub fn ui_setting(&self) -> Result<Option<(UiMode, Origin)>, String> {
+        let cli = match (self.plain, self.tui) {
+            (true, _) => UiMode::Plain,
+            (_, true) => UiMode::Tui,
+            _ => UiMode::Auto,
+        };
+        if cli != UiMode::Auto {
+            return Ok(Some((cli, Origin::CommandLine)));
+        }
+        if let Some((value, origin)) = env_value("ZIRV_UI") {
+            return Ok(Some((UiMode::resolve(cli, Some(&value))?, origin)));
+        }
+        Ok(self.config.lookup(|c| c.ui))
+    }
+
+    /// The source `--local` or `--global` restricts script management to.
+    pub fn scope(&self) -> Result<Option<ScriptSource>, String> {
+        match (self.local, self.global) {
+            (true, true) => Err("--local and --global cannot be used together".to_string()),
+            (true, _) => Ok(Some(ScriptSource::Local)),
+            (_, true) => Ok(Some(ScriptSource::Global)),
+            _ => Ok(None),
+        }
+    }
+
+    /// The directory new files go in: `~/.zirv` with `--global`, the
+    /// project's `.zirv` otherwise.
+    pub fn target_dir(&self) -> Result<PathBuf, ZirvError> {
+        if self.global {
+            return Ok(zirv_home().ok_or("Could not determine home directory")?);
+        }
+        Ok(self
+            .project_dir()?
+            .unwrap_or_else(|| PathBuf::from(SCRIPT_DIR_NAME)))
+    }
+
+    /// `--format` as the file format for `create`.
+    pub fn script_format(&self) -> Result<ScriptFormat, String> {
+        parse_format(self.format.as_deref(), "yaml, json or toml")
+    }
+
+    /// `--format` as the shell for `export`.
+    pub fn export_format(&self) -> Result<ExportFormat, String> {
+        parse_format(self.format.as_deref(), "sh or ps1")
+    }
+
+    /// Whether `create` was given anything to run without prompting.
+    pub fn create_flags_given(&self) -> bool {
+        self.shortcut.is_some() || self.format.is_some() || self.global || self.force
+    }
+
+    /// The project's `.zirv` directory: the nearest one in the current
+    /// directory or its parents, or only `./.zirv` with `--no-parent-search`.
+    pub fn project_dir(&self) -> Result<Option<PathBuf>, ZirvError> {
+        let cwd = std::env::current_dir()?;
+        if self.no_parent_search {
+            let dir = cwd.join(SCRIPT_DIR_NAME);
+            return Ok(dir.is_dir().then_some(dir));
+        }
+        Ok(find_project_dir(&cwd, zirv_home().as_deref()))
+    }
+
+    /// The directory containing the project's `.zirv`, if `script` was found
+    /// there or in its `.zirv.yaml`. Relative paths in the script are
+    /// resolved against it.
+    pub fn project_root(&self, script: &ScriptLocation) -> Result<Option<PathBuf>, ZirvError> {
+        let Some(root) = self
+            .project_dir()?
+            .and_then(|dir| dir.parent().map(Path::to_path_buf))
+        else {
+            return Ok(None);
+        };
+        let root = canonicalize(&root)?;
+        let in_project = match script {
+            ScriptLocation::Entry { file, .. } => file.parent() == Some(root.as_path()),
+            ScriptLocation::File(file) => file.starts_with(root.join(SCRIPT_DIR_NAME)),
+        };
+        Ok(in_project.then_some(root))
+    }
+
+    /// The directories searched for scripts, in order of precedence. The
+    /// global directory is left out when there is no home directory.
+    pub fn script_sources(&self) -> Result<Vec<(ScriptSource, PathBuf)>, ZirvError> {
+        let local = self
+            .project_dir()?
+            .unwrap_or_else(|| PathBuf::from(SCRIPT_DIR_NAME));
+        let mut sources = vec![(ScriptSource::Local, local)];
+        let extra = self.search_path_setting()?.map(|(dirs, _)| dirs);
+        sources.extend(
+            extra
+                .into_iter()
+                .flatten()
+                .map(|dir| (ScriptSource::Extra, dir)),
+        );
+        sources.extend(zirv_home().map(|dir| (ScriptSource::Global, dir)));
+        Ok(sources)
+    }
+
+    /// The built-in command to run, if any. Never set together with `--file`.
+    pub fn builtin(&self) -> Option<&str> {
+        match self.file {
+            Some(_) => None,
+            None => self.command.as_deref(),
+        }
+    }
+
+    /// Resolves the command to a script file.
+    ///
+    /// With `--file`, the given path is used as-is, relative to the current
+    /// directory, and the command (if any) becomes the first parameter.
+    ///
+    /// `zirv db migrate`, where `db` is a namespace directory, is treated
+    /// like `zirv db/migrate`: the first parameter is consumed as the script
+    /// name.
+    ///
+    /// Along with the path, returns the params bundled into the shortcut the
+    /// script was found through; see [`merge_params`](crate::utils::merge_params).
+    pub fn get_file_path(&mut self) -> Result<ResolvedScript, ZirvError> {
+        if let Some(file) = &self.file {
+            if !file.is_file() {
+                return Err(format!("Script file '{}' does not exist", file.display()).into());
+            }
+            if let Some(command) = self.command.take() {
+                self.params.insert(0, command);
+            }
+            return Ok((ScriptLocation::File(canonicalize(file)?), Vec::new()));
+        }
+
+        let command = self.command.clone().unwrap_or_default();
+        let cmd_path = PathBuf::from(&command);
+        if cmd_path.is_file() {
+            return Ok((ScriptLocation::File(canonicalize(&cmd_path)?), Vec::new()));
+        }
+
+        let mut params = std::mem::take(&mut self.params);
+        let found = self.resolve_named_script(&command, &mut params);
+        self.params = params;
+        found
+    }
+
+    /// Looks `name` up like a command, consuming leading `params` as
+    /// namespace members (see [`Input::get_file_path`]).
+    pub fn find_named_script(
+        &self,
+        name: &str,
+        params: &mut Vec<String>,
+    ) -> Result<ScriptLocation, ZirvError> {
+        Ok(self.resolve_named_script(name, params)?.0)
+    }
+
+    /// Like [`Input::find_named_script`], also returning the params bundled
+    /// into the shortcut the script was found through.
+    pub fn resolve_named_script(
+        &self,
+        name: &str,
+        params: &mut Vec<String>,
+    ) -> Result<ResolvedScript, ZirvError> {
+        find_script(&self.script_roots()?, name, params)
+    }
+
+    /// The name the script at `location` is run by; see [`run_name`].
+    pub fn run_name(&self, location: &ScriptLocation) -> Result<String, ZirvError> {
+        Ok(run_name(&self.script_roots()?, location))
+    }
+
+    /// The directories scripts are looked up in, in lookup order.
+    fn script_roots(&self) -> Result<Vec<PathBuf>, ZirvError> {
+        Ok(self
+            .script_sources()?
+            .into_iter()
+            .map(|(_, dir)| dir)
+            .collect())
+    }
 }
 
-impl Input {
-    pub fn get_file_path(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let cmd_path = PathBuf::from(&self.command);
-        if cmd_path.exists() {
-            return Ok(cmd_path.canonicalize()?);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn roots_with(scripts: &[&str]) -> (tempfile::TempDir, Vec<PathBuf>) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join(SCRIPT_DIR_NAME);
+        for script in scripts {
+            let path = root.join(format!("{script}.yaml"));
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "name: x\ncommands: []\n").unwrap();
         }
+        (dir, vec![root])
+    }
+
+    #[test]
+    fn test_settings_precedence() {
+        let project = PathBuf::from("/p/.zirv/config.yaml");
+        let global = PathBuf::from("/home/.zirv/config.yaml");
+        let configs = Configs {
+            project: Some((
+                project.clone(),
+                Config {
+                    report: Some(PathBuf::from("project.json")),
+                    path: Some(vec![PathBuf::from("/project/scripts")]),
+                    ..Config::default()
+                },
+            )),
+            global: Some((
+                global.clone(),
+                Config {
+                    report: Some(PathBuf::from("global.json")),
+                    ui: Some(UiMode::Tui),
+                    path: Some(vec![PathBuf::from("/global/scripts")]),
+                    ..Config::default()
+                },
+            )),
+        };
 
-        let local_dir = PathBuf::from(SCRIPT_DIR_NAME);
-        if let Some(path) = find_script_in_dir(&local_dir, &self.command)? {
-            return Ok(path);
+        let mut input = Input::parse_from(["zirv", "deploy"]);
+        input.config = configs.clone();
+        assert_eq!(
+            input.report_setting(),
+            Some((
+                PathBuf::from("project.json"),
+                Origin::Project(project.clone())
+            ))
+        );
+        assert_eq!(
+            input.ui_setting(),
+            Ok(Some((UiMode::Tui, Origin::Global(global.clone()))))
+        );
+        // `ZIRV_PATH` winning over both is covered in tests/settings.rs.
+        if std::env::var_os(ZIRV_PATH_VAR).is_none() {
+            assert_eq!(
+                input.search_path_setting().unwrap(),
+                Some((
+                    vec![PathBuf::from("/project/scripts")],
+                    Origin::Project(project.clone())
+                ))
+            );
+            input.config.project = None;
+            assert_eq!(
+                input.search_path_setting().unwrap(),
+                Some((
+                    vec![PathBuf::from("/global/scripts")],
+                    Origin::Global(global)
+                ))
+            );
         }
 
-        let global_dir = home_dir()?.join(SCRIPT_DIR_NAME);
-        if let Some(path) = find_script_in_dir(&global_dir, &self.command)? {
-            return Ok(path);
+        let mut input = Input::parse_from(["zirv", "--report", "cli.json", "--plain", "deploy"]);
+        input.config = configs;
+        assert_eq!(
+            input.report_setting(),
+            Some((PathBuf::from("cli.json"), Origin::CommandLine))
+        );
+        assert_eq!(
+            input.ui_setting(),
+            Ok(Some((UiMode::Plain, Origin::CommandLine)))
+        );
+    }
+
+    #[test]
+    fn test_search_path_from_config() {
+        let project = PathBuf::from("/p/.zirv/config.yaml");
+        let mut input = Input::parse_from(["zirv", "deploy"]);
+        input.config = Configs {
+            project: Some((
+                project.clone(),
+                Config {
+                    path: Some(vec![
+                        PathBuf::from("tools/scripts"),
+                        PathBuf::from("/opt/zirv-scripts"),
+                    ]),
+                    ..Config::default()
+                },
+            )),
+            global: None,
+        };
+        if std::env::var_os(ZIRV_PATH_VAR).is_none() {
+            assert_eq!(
+                input.search_path_setting().unwrap(),
+                Some((
+                    vec![
+                        PathBuf::from("/p/tools/scripts"),
+                        PathBuf::from("/opt/zirv-scripts")
+                    ],
+                    Origin::Project(project)
+                ))
+            );
         }
+    }
+
+    #[test]
+    fn test_find_namespaced_script() {
+        let (_dir, roots) = roots_with(&["db/migrate", "db/seed/users"]);
+
+        let path = find_script(&roots, "db/migrate", &mut vec![]).unwrap().0;
+        assert!(path.file().ends_with("db/migrate.yaml"));
+
+        let path = find_script(&roots, "db\\seed\\users", &mut vec![])
+            .unwrap()
+            .0;
+        assert!(path.file().ends_with("db/seed/users.yaml"));
+
+        let mut params = vec!["migrate".to_string(), "--force".to_string()];
+        let path = find_script(&roots, "db", &mut params).unwrap().0;
+        assert!(path.file().ends_with("db/migrate.yaml"));
+        assert_eq!(params, vec!["--force"]);
+
+        let mut params = vec!["seed".to_string(), "users".to_string()];
+        let path = find_script(&roots, "db", &mut params).unwrap().0;
+        assert!(path.file().ends_with("db/seed/users.yaml"));
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_shortcut_to_namespaced_script() {
+        let (_dir, roots) = roots_with(&["db/migrate"]);
+        std::fs::write(
+            roots[0].join(".shortcuts.yaml"),
+            "shortcuts:\n  m: db/migrate\n",
+        )
+        .unwrap();
+
+        let path = find_script(&roots, "m", &mut vec![]).unwrap().0;
+        assert!(path.file().ends_with("db/migrate.yaml"));
+    }
+
+    #[test]
+    fn test_run_name_is_the_lookup_name() {
+        let (dir, roots) = roots_with(&["db/migrate"]);
+        std::fs::write(
+            roots[0].join(".shortcuts.yaml"),
+            "shortcuts:\n  m: db/migrate\n",
+        )
+        .unwrap();
+
+        let path = find_script(&roots, "m", &mut vec![]).unwrap().0;
+        assert_eq!(run_name(&roots, &path), "db/migrate");
+
+        let outside = dir.path().join("wipe.yaml");
+        std::fs::write(&outside, "name: Wipe\ncommands: []\n").unwrap();
+        let outside = ScriptLocation::File(canonicalize(&outside).unwrap());
+        assert_eq!(run_name(&roots, &outside), "wipe");
+
+        let entry = ScriptLocation::Entry {
+            file: dir.path().join("zirv.yaml"),
+            name: "lint".to_string(),
+        };
+        assert_eq!(run_name(&roots, &entry), "lint");
+    }
+
+    #[test]
+    fn test_shortcut_with_bundled_params() {
+        let (_dir, roots) = roots_with(&["deploy"]);
+        std::fs::write(
+            roots[0].join(".shortcuts.yaml"),
+            "shortcuts:\n  d: deploy\n  dp:\n    script: deploy.yaml\n    params: [prod]\n",
+        )
+        .unwrap();
+
+        let (path, bundled) = find_script(&roots, "dp", &mut vec![]).unwrap();
+        assert!(path.file().ends_with("deploy.yaml"));
+        assert_eq!(
+            bundled,
+            [crate::utils::ShortcutParam::Positional("prod".to_string())]
+        );
+
+        let (path, bundled) = find_script(&roots, "d", &mut vec![]).unwrap();
+        assert!(path.file().ends_with("deploy.yaml"));
+        assert!(bundled.is_empty());
+    }
+
+    #[test]
+    fn test_shortcut_chains() {
+        let (_dir, roots) = roots_with(&["deploy"]);
+        std::fs::write(
+            roots[0].join(".shortcuts.yaml"),
+            "shortcuts:\n  d: deploy\n  dd: d\n  ddd: dd\n  a: b\n  b: a\n  gone: deploy-old.yaml\n",
+        )
+        .unwrap();
+
+        let (path, _) = find_script(&roots, "ddd", &mut vec![]).unwrap();
+        assert!(path.file().ends_with("deploy.yaml"));
+
+        let err = find_script(&roots, "a", &mut vec![]).unwrap_err();
+        assert_eq!(err.to_string(), "Shortcut cycle: a -> b -> a");
+
+        let err = find_script(&roots, "gone", &mut vec![]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Shortcut 'gone' points to 'deploy-old.yaml' which does not exist"
+        );
+    }
+
+    #[test]
+    fn test_lookup_by_alias() {
+        let (_dir, roots) = roots_with(&["db/migrate"]);
+        std::fs::write(
+            roots[0].join("build.yaml"),
+            "name: Build\naliases: [b, bld]\ncommands: []\n",
+        )
+        .unwrap();
+
+        let (path, _) = find_script(&roots, "bld", &mut vec![]).unwrap();
+        assert!(path.file().ends_with("build.yaml"));
+
+        let err = find_script(&roots, "bl", &mut vec![]).unwrap_err();
+        assert!(
+            err.to_string().contains("Did you mean 'b', 'bld'?"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_missing_namespace() {
+        let (_dir, roots) = roots_with(&["db/migrate"]);
+
+        let err = find_script(&roots, "cache/clear", &mut vec![]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No namespace 'cache' found for 'cache/clear'"
+        );
+
+        let err = find_script(&roots, "db/migrat", &mut vec![]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No script or shortcut found for 'db/migrat'. Did you mean 'db/migrate'?"
+        );
+
+        let err = find_script(&roots, "db/rollback", &mut vec![]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No script or shortcut found for 'db/rollback'"
+        );
+        assert!(
+            matches!(&err, ZirvError::ScriptNotFound { name, .. } if name == "db/rollback"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn test_unparsable_script_is_a_parse_error() {
+        let (_dir, roots) = roots_with(&[]);
+        std::fs::create_dir_all(&roots[0]).unwrap();
+        std::fs::write(roots[0].join("broken.yaml"), "name: [\n").unwrap();
+
+        let input = Input::parse_from(["zirv", "broken"]);
+        let path = find_script(&roots, "broken", &mut vec![]).unwrap().0;
+        let err = input.load_script(&path).unwrap_err();
+        assert!(matches!(err, ZirvError::Parse(_)), "{err:?}");
+        assert_eq!(err.exit_code(), 65);
+    }
+
+    #[test]
+    fn test_find_script_from_grandchild_directory() {
+        let (dir, _) = roots_with(&["build"]);
+        let grandchild = dir.path().join("src").join("module");
+        std::fs::create_dir_all(&grandchild).unwrap();
+        let global = dir.path().join("home").join(SCRIPT_DIR_NAME);
+
+        let project = find_project_dir(&grandchild, Some(&global)).unwrap();
+        assert_eq!(project, dir.path().join(SCRIPT_DIR_NAME));
+        let path = find_script(&[project], "build", &mut vec![]).unwrap().0;
+        assert!(path.file().ends_with("build.yaml"));
+    }
+
+    #[test]
+    fn test_parent_search_skips_global_dir() {
+        let (dir, roots) = roots_with(&["build"]);
+        let project = dir.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+
+        assert_eq!(find_project_dir(&project, Some(&roots[0])), None);
+    }
+
+    #[test]
+    fn test_lookup_order_across_roots() {
+        let dirs: Vec<_> = (0..3).map(|_| roots_with(&["deploy"])).collect();
+        let roots: Vec<PathBuf> = dirs.iter().map(|(_, roots)| roots[0].clone()).collect();
+
+        for expected in &roots {
+            let path = find_script(&roots, "deploy", &mut vec![]).unwrap().0;
+            assert_eq!(
+                path,
+                ScriptLocation::File(canonicalize(&expected.join("deploy.yaml")).unwrap())
+            );
+            std::fs::remove_file(expected.join("deploy.yaml")).unwrap();
+        }
+        assert!(find_script(&roots, "deploy", &mut vec![]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_file_outside_zirv_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let script_path = dir.path().join("ops").join("deploy.yaml");
+        std::fs::create_dir_all(script_path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &script_path,
+            format!(
+                "name: Deploy\nparams: [target]\ncommands:\n  - command: touch '{}/${{target}}'\n",
+                dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let mut input =
+            Input::parse_from(["zirv", "--file", script_path.to_str().unwrap(), "staging"]);
+        assert_eq!(input.builtin(), None);
+        let (path, _) = input.get_file_path().unwrap();
+        assert_eq!(
+            path,
+            ScriptLocation::File(canonicalize(&script_path).unwrap())
+        );
+        assert_eq!(input.params, vec!["staging"]);
+
+        let script = path.load().unwrap();
+        let options = crate::script_runner::run_options::RunOptions {
+            no_summary: true,
+            ..Default::default()
+        };
+        crate::script_runner::execute(&script, &input.params, &options)
+            .await
+            .unwrap();
+        assert!(dir.path().join("staging").exists());
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let mut input = Input::parse_from(["zirv", "-f", "does/not/exist.yaml"]);
+        let err = input.get_file_path().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Script file 'does/not/exist.yaml' does not exist"
+        );
+    }
+
+    #[test]
+    fn test_lookup_in_scripts_file() {
+        let (dir, roots) = roots_with(&["build"]);
+        let file = dir.path().join(".zirv.yaml");
+        std::fs::write(
+            &file,
+            "scripts:\n  build:\n    name: Inline Build\n    commands: []\n  lint:\n    name: Lint\n    commands: []\n",
+        )
+        .unwrap();
+        std::fs::write(
+            roots[0].join(".shortcuts.yaml"),
+            "shortcuts:\n  l: .zirv.yaml#lint\n  x: .zirv.yaml#missing\n",
+        )
+        .unwrap();
+
+        let (path, _) = find_script(&roots, "lint", &mut vec![]).unwrap();
+        assert_eq!(
+            path,
+            ScriptLocation::Entry {
+                file: canonicalize(&file).unwrap(),
+                name: "lint".to_string(),
+            }
+        );
+        assert_eq!(path.load().unwrap().name, "Lint");
+
+        let (path, _) = find_script(&roots, "build", &mut vec![]).unwrap();
+        assert!(path.file().ends_with("build.yaml"));
+
+        let (path, _) = find_script(&roots, "l", &mut vec![]).unwrap();
+        assert_eq!(path.load().unwrap().name, "Lint");
+
+        let err = find_script(&roots, "x", &mut vec![]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Shortcut 'x' points to '.zirv.yaml#missing' which does not exist"
+        );
+
+        let err = find_script(&roots, "lnt", &mut vec![]).unwrap_err();
+        assert!(err.to_string().contains("Did you mean 'lint'?"), "{err}");
+    }
+
+    #[test]
+    fn test_scripts_file_marks_the_project() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".zirv.yaml"), "scripts: {}\n").unwrap();
+        let child = dir.path().join("src");
+        std::fs::create_dir_all(&child).unwrap();
 
-        Err(format!("No script or shortcut found for '{}'", self.command).into())
+        assert_eq!(
+            find_project_dir(&child, None),
+            Some(dir.path().join(SCRIPT_DIR_NAME))
+        );
     }
 }
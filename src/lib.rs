@@ -0,0 +1,38 @@
+//! zirv runs developer-defined scripts of shell commands, described in
+//! YAML, JSON or TOML files.
+//!
+//! Besides the `zirv` binary, the crate can be embedded to run scripts from
+//! another tool: load a [`Script`](script_runner::Script) with
+//! [`utils::file_to_script`] or [`utils::parse_script_content`], then run it
+//! with [`script_runner::execute`]. Secrets can be supplied through
+//! [`RunOptions::secrets`](script_runner::RunOptions::secrets) and progress
+//! followed through [`RunOptions::events`](script_runner::RunOptions::events).
+//!
+//! ```
+//! use zirv::script_runner::{RunOptions, UiEvent, execute};
+//! use zirv::utils::parse_script_content;
+//!
+//! let script = parse_script_content("name: Hi\ncommands:\n  - command: echo hi\n", "yaml")?;
+//! let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+//! let mut options = RunOptions::default();
+//! options.no_summary = true;
+//! options.events = Some(events);
+//! tokio::runtime::Runtime::new()?.block_on(execute(&script, &[], &options))?;
+//! assert!(matches!(received.try_recv()?, UiEvent::StepStarted { index: 1, .. }));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [`script_runner`], [`utils`] and [`error`] are the library's API, along
+//! with [`run_cli`], which is the whole of the `zirv` binary.
+
+mod catalog;
+mod cli;
+mod commands;
+mod config;
+pub mod error;
+mod input;
+mod pager;
+pub mod script_runner;
+pub mod utils;
+
+pub use cli::run_cli;
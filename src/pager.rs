@@ -0,0 +1,91 @@
+//! Shows long output a screen at a time, like `more`.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+const PROMPT: &str = "-- more -- (Enter for the next page, q to quit)";
+
+/// Height used when `LINES` is unset or invalid.
+const DEFAULT_HEIGHT: usize = 24;
+
+/// Prints `text` to stdout, pausing after every screenful when both stdout
+/// and stdin are terminals and `enabled` is set.
+pub fn show(text: &str, enabled: bool) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    if !enabled || !io::stdout().is_terminal() || !io::stdin().is_terminal() {
+        return stdout.write_all(text.as_bytes());
+    }
+    page(
+        text,
+        terminal_height(),
+        &mut stdout,
+        &mut io::stdin().lock(),
+    )
+}
+
+/// The terminal height from `LINES`, falling back to a common default.
+fn terminal_height() -> usize {
+    std::env::var("LINES")
+        .ok()
+        .and_then(|lines| lines.parse().ok())
+        .filter(|&lines| lines > 1)
+        .unwrap_or(DEFAULT_HEIGHT)
+}
+
+/// Writes `text` in pages of `height - 1` lines, leaving room for the
+/// prompt. Answering `q`, or closing `input`, stops the output.
+fn page<W: Write, R: BufRead>(
+    text: &str,
+    height: usize,
+    writer: &mut W,
+    input: &mut R,
+) -> io::Result<()> {
+    let page_size = height.saturating_sub(1).max(1);
+    let lines: Vec<&str> = text.lines().collect();
+    let mut pages = lines.chunks(page_size).peekable();
+
+    while let Some(page) = pages.next() {
+        for line in page {
+            writeln!(writer, "{line}")?;
+        }
+        if pages.peek().is_none() {
+            break;
+        }
+
+        write!(writer, "{PROMPT}")?;
+        writer.flush()?;
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 || answer.trim().eq_ignore_ascii_case("q") {
+            writeln!(writer)?;
+            break;
+        }
+        // Erase the prompt, which the answer's newline moved above the cursor.
+        write!(writer, "\x1b[1A\x1b[2K")?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paged(text: &str, height: usize, answers: &str) -> String {
+        let mut buffer = Vec::new();
+        page(text, height, &mut buffer, &mut answers.as_bytes()).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_short_text_is_not_paged() {
+        assert_eq!(paged("a\nb\n", 5, ""), "a\nb\n");
+    }
+
+    #[test]
+    fn test_pages_until_quit() {
+        let text = "1\n2\n3\n4\n5\n6\n7\n";
+        assert_eq!(
+            paged(text, 3, "\n\n"),
+            format!("1\n2\n{PROMPT}\x1b[1A\x1b[2K3\n4\n{PROMPT}\x1b[1A\x1b[2K5\n6\n{PROMPT}\n")
+        );
+        assert_eq!(paged(text, 3, "q\n"), format!("1\n2\n{PROMPT}\n"));
+    }
+}
@@ -0,0 +1,299 @@
+use std::io::Write;
+use std::time::Duration;
+
+use hashbrown::HashMap;
+
+use super::{
+    command_types::CommandTypes,
+    report::{RunReport, format_duration},
+    run_options::{OutputFormat, RunOptions, Verbosity},
+    run_with_report,
+    script::Script,
+};
+
+/// Runs `script` `repeat` times and prints timing statistics afterwards.
+///
+/// Interactive steps are rejected up front. A failing run aborts the remaining
+/// repetitions unless `options.keep_going` is set.
+pub async fn execute_repeated(
+    script: &Script,
+    params: &[String],
+    options: &RunOptions,
+    repeat: usize,
+) -> Result<(), String> {
+    if has_interactive_steps(script) {
+        return Err(format!(
+            "Script '{}' contains interactive steps, which cannot be used with --repeat",
+            script.name
+        ));
+    }
+
+    let run_options = RunOptions {
+        no_summary: true,
+        ..options.clone()
+    };
+
+    let mut reports = Vec::with_capacity(repeat);
+    let mut failures = 0;
+    for i in 1..=repeat {
+        if options.verbosity >= Verbosity::Normal && options.output == OutputFormat::Text {
+            println!("\nRun {i}/{repeat} of '{}'", script.name);
+        }
+        let (report, result) = run_with_report(script, params, &run_options).await;
+        reports.push(report);
+        if let Err(e) = result {
+            eprintln!("{e}");
+            failures += 1;
+            if !options.keep_going || run_options.stopper.is_stopping() {
+                break;
+            }
+        }
+    }
+
+    // Keep stdout to JSON events.
+    let stats = BenchStats::new(&script.name, &reports);
+    let _ = match options.output {
+        OutputFormat::Text => stats.write(&mut std::io::stdout()),
+        OutputFormat::Json => stats.write(&mut std::io::stderr()),
+    };
+
+    if failures > 0 {
+        return Err(format!(
+            "{failures} of {} runs of '{}' failed",
+            reports.len(),
+            script.name
+        ));
+    }
+    Ok(())
+}
+
+fn has_interactive_steps(script: &Script) -> bool {
+    script
+        .commands
+        .iter()
+        .flat_map(CommandTypes::commands)
+        .filter_map(|cmd| cmd.options.as_ref())
+        .any(|options| {
+            options.interactive()
+                || options
+                    .fallback
+                    .iter()
+                    .flatten()
+                    .filter_map(|f| f.options.as_ref())
+                    .any(|o| o.interactive())
+        })
+}
+
+/// Timing statistics over several runs of the same script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchStats {
+    pub script: String,
+    pub runs: usize,
+    pub min: Duration,
+    pub median: Duration,
+    pub max: Duration,
+    /// Average duration per step index, with the step's label, in step order.
+    pub step_averages: Vec<(usize, String, Duration)>,
+}
+
+impl BenchStats {
+    pub fn new(script: &str, reports: &[RunReport]) -> Self {
+        let mut totals: Vec<Duration> = reports.iter().map(|r| r.duration).collect();
+        totals.sort();
+
+        let median = match totals.len() {
+            0 => Duration::ZERO,
+            n if n % 2 == 1 => totals[n / 2],
+            n => (totals[n / 2 - 1] + totals[n / 2]) / 2,
+        };
+
+        let mut steps: HashMap<usize, (String, Duration, u32)> = HashMap::new();
+        for step in reports.iter().flat_map(|r| &r.steps) {
+            let entry = steps
+                .entry(step.index)
+                .or_insert_with(|| (step.command.clone(), Duration::ZERO, 0));
+            entry.1 += step.duration;
+            entry.2 += 1;
+        }
+        let mut step_averages: Vec<(usize, String, Duration)> = steps
+            .into_iter()
+            .map(|(index, (label, total, count))| (index, label, total / count))
+            .collect();
+        step_averages.sort_by_key(|(index, _, _)| *index);
+
+        Self {
+            script: script.to_string(),
+            runs: reports.len(),
+            min: totals.first().copied().unwrap_or_default(),
+            median,
+            max: totals.last().copied().unwrap_or_default(),
+            step_averages,
+        }
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "\nBenchmark for '{}' ({} runs):",
+            self.script, self.runs
+        )?;
+        writeln!(
+            writer,
+            "  Total: min {}  median {}  max {}",
+            format_duration(self.min),
+            format_duration(self.median),
+            format_duration(self.max)
+        )?;
+        writeln!(writer, "  {:<4}{:<40}  {:>10}", "#", "Step", "Average")?;
+        for (index, label, average) in &self.step_averages {
+            writeln!(
+                writer,
+                "  {:<4}{:<40}  {:>10}",
+                index,
+                label.lines().next().unwrap_or_default(),
+                format_duration(*average)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script_runner::{command::Command, options::Options, report::StepReport};
+
+    fn report(total_ms: u64, steps: &[u64]) -> RunReport {
+        RunReport {
+            duration: Duration::from_millis(total_ms),
+            steps: steps
+                .iter()
+                .enumerate()
+                .map(|(i, ms)| StepReport {
+                    duration: Duration::from_millis(*ms),
+                    ..StepReport::new(i + 1, format!("step {}", i + 1))
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_bench_stats() {
+        let stats = BenchStats::new(
+            "Bench",
+            &[
+                report(300, &[100, 200]),
+                report(100, &[50, 50]),
+                report(200, &[150]),
+            ],
+        );
+
+        assert_eq!(stats.runs, 3);
+        assert_eq!(stats.min, Duration::from_millis(100));
+        assert_eq!(stats.median, Duration::from_millis(200));
+        assert_eq!(stats.max, Duration::from_millis(300));
+        assert_eq!(
+            stats.step_averages,
+            vec![
+                (1, "step 1".to_string(), Duration::from_millis(100)),
+                (2, "step 2".to_string(), Duration::from_millis(125)),
+            ]
+        );
+
+        let even = BenchStats::new("Bench", &[report(100, &[]), report(200, &[])]);
+        assert_eq!(even.median, Duration::from_millis(150));
+    }
+
+    #[test]
+    fn test_bench_stats_output() {
+        let stats = BenchStats::new("Bench", &[report(1000, &[1000])]);
+        let mut buffer = Vec::new();
+        stats.write(&mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+
+        assert!(output.contains("Benchmark for 'Bench' (1 runs):"));
+        assert!(output.contains("Total: min 1.00s  median 1.00s  max 1.00s"));
+        assert!(output.contains("step 1"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_repeated_runs_n_times() {
+        let dir = tempfile::tempdir().unwrap();
+        let counter = dir.path().join("counter");
+        let script = Script {
+            name: "Bench".to_string(),
+            commands: vec![CommandTypes::Command(Command {
+                command: format!("sleep 0.01; echo run >> '{}'", counter.display()),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+
+        execute_repeated(&script, &[], &RunOptions::default(), 3)
+            .await
+            .unwrap();
+
+        let runs = std::fs::read_to_string(&counter).unwrap();
+        assert_eq!(runs.lines().count(), 3);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_repeated_stops_on_failure_unless_keep_going() {
+        let dir = tempfile::tempdir().unwrap();
+        let counter = dir.path().join("counter");
+        let script = Script {
+            name: "Bench".to_string(),
+            commands: vec![CommandTypes::Command(Command {
+                command: format!("echo run >> '{}'; exit 1", counter.display()),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+
+        let error = execute_repeated(&script, &[], &RunOptions::default(), 3)
+            .await
+            .unwrap_err();
+        assert_eq!(error, "1 of 1 runs of 'Bench' failed");
+        assert_eq!(
+            std::fs::read_to_string(&counter).unwrap().lines().count(),
+            1
+        );
+
+        let options = RunOptions {
+            keep_going: true,
+            ..Default::default()
+        };
+        let error = execute_repeated(&script, &[], &options, 3)
+            .await
+            .unwrap_err();
+        assert_eq!(error, "3 of 3 runs of 'Bench' failed");
+        assert_eq!(
+            std::fs::read_to_string(&counter).unwrap().lines().count(),
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_repeated_rejects_interactive_steps() {
+        let script = Script {
+            name: "Interactive".to_string(),
+            commands: vec![CommandTypes::Command(Command {
+                command: "vim".to_string(),
+                options: Some(Options {
+                    interactive: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+
+        let error = execute_repeated(&script, &[], &RunOptions::default(), 2)
+            .await
+            .unwrap_err();
+        assert!(error.contains("interactive steps"));
+    }
+}
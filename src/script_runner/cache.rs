@@ -0,0 +1,254 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::watch::wildcard_match;
+
+/// The directory under `.zirv` that holds the cache entries.
+pub const CACHE_DIR_NAME: &str = ".cache";
+
+/// What a step's result depends on. While none of it changes, the step is
+/// skipped.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct Cache {
+    /// Files, directories or patterns relative to the step's working
+    /// directory. `*` matches any part of a name and `**` any number of
+    /// directories; a directory stands for every file under it.
+    pub inputs: Vec<String>,
+    /// More text the entry depends on, e.g. `${target}`, with placeholders
+    /// substituted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_extra: Option<String>,
+}
+
+/// What is stored for a step after it succeeded.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct CacheEntry {
+    /// The hash of the step's command and inputs when it last succeeded.
+    pub key: String,
+    /// The value the step captured, if it captures one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capture: Option<String>,
+}
+
+/// The file holding the entry of the steps running `command`, before
+/// substitution, with `cache`.
+pub fn entry_path(cache_dir: &Path, command: &str, cache: &Cache) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(command);
+    for input in &cache.inputs {
+        hasher.update([0]);
+        hasher.update(input);
+    }
+    cache_dir.join(format!("{}.json", hex(&hasher.finalize())))
+}
+
+/// Reads the entry at `path`, if there is a valid one.
+pub fn read_entry(path: &Path) -> Option<CacheEntry> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Stores `entry` at `path`, creating the cache directory if needed.
+pub fn write_entry(path: &Path, entry: &CacheEntry) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string(entry)?)
+}
+
+/// The hash of `command`, `key_extra`, the values of the script's
+/// `params`, the working directory `base` and the names and contents of the
+/// files `inputs` match under it.
+pub fn input_key(
+    command: &str,
+    key_extra: Option<&str>,
+    params: &[&str],
+    inputs: &[String],
+    base: &Path,
+) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(command);
+    hasher.update([0]);
+    hasher.update(key_extra.unwrap_or_default());
+    for param in params {
+        hasher.update([0]);
+        hasher.update(param);
+    }
+    hasher.update([0]);
+    hasher.update(base.to_string_lossy().as_bytes());
+    for file in input_files(inputs, base) {
+        hasher.update([0]);
+        hasher.update(
+            file.strip_prefix(base)
+                .unwrap_or(&file)
+                .to_string_lossy()
+                .as_bytes(),
+        );
+        hasher.update([0]);
+        hasher.update(fs::read(&file)?);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Every file `inputs` match under `base`, sorted and without duplicates.
+pub fn input_files(inputs: &[String], base: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let path = base.join(input);
+        let mut start = PathBuf::new();
+        let mut parts = Vec::new();
+        for component in path.components() {
+            let name = component.as_os_str().to_string_lossy();
+            if parts.is_empty() && !name.contains('*') {
+                start.push(component);
+            } else {
+                parts.push(name.into_owned());
+            }
+        }
+        collect(&start, &parts, &mut files);
+    }
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Adds the files under `dir` matching the remaining pattern `parts`.
+fn collect(dir: &Path, parts: &[String], files: &mut Vec<PathBuf>) {
+    let Some((part, rest)) = parts.split_first() else {
+        if dir.is_file() {
+            files.push(dir.to_path_buf());
+        } else if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                collect(&entry.path(), &[], files);
+            }
+        }
+        return;
+    };
+    if !part.contains('*') {
+        collect(&dir.join(part), rest, files);
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    if part == "**" {
+        collect(dir, rest, files);
+    }
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if part == "**" {
+            if path.is_dir() {
+                collect(&path, parts, files);
+            }
+        } else if wildcard_match(part, &entry.file_name().to_string_lossy()) {
+            collect(&path, rest, files);
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for file in [
+            "package.json",
+            "src/main.rs",
+            "src/lib.rs",
+            "src/bin/tool.rs",
+            "src/notes.txt",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, file).unwrap();
+        }
+        let files = |inputs: &[&str]| -> Vec<String> {
+            let inputs: Vec<String> = inputs.iter().map(|i| i.to_string()).collect();
+            input_files(&inputs, root)
+                .iter()
+                .map(|f| {
+                    f.strip_prefix(root)
+                        .unwrap()
+                        .to_string_lossy()
+                        .replace('\\', "/")
+                })
+                .collect()
+        };
+
+        assert_eq!(files(&["package.json", "missing.lock"]), ["package.json"]);
+        assert_eq!(files(&["src/*.rs"]), ["src/lib.rs", "src/main.rs"]);
+        assert_eq!(
+            files(&["src/**/*.rs", "src/main.rs"]),
+            ["src/bin/tool.rs", "src/lib.rs", "src/main.rs"]
+        );
+        assert_eq!(
+            files(&["src"]),
+            [
+                "src/bin/tool.rs",
+                "src/lib.rs",
+                "src/main.rs",
+                "src/notes.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_input_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = dir.path().join("package-lock.json");
+        fs::write(&lock, "{}").unwrap();
+        let inputs = ["package-lock.json".to_string()];
+        let key = |command, extra| input_key(command, extra, &[], &inputs, dir.path()).unwrap();
+
+        let first = key("npm ci", None);
+        assert_eq!(first, key("npm ci", None));
+        assert_ne!(first, key("npm install", None));
+        assert_ne!(first, key("npm ci", Some("linux")));
+        let with_param = input_key("npm ci", None, &["prod"], &inputs, dir.path()).unwrap();
+        assert_ne!(first, with_param);
+        let elsewhere = tempfile::tempdir().unwrap();
+        fs::write(elsewhere.path().join("package-lock.json"), "{}").unwrap();
+        let moved = input_key("npm ci", None, &[], &inputs, elsewhere.path()).unwrap();
+        assert_ne!(first, moved);
+        fs::write(&lock, "{\"lockfileVersion\": 3}").unwrap();
+        assert_ne!(first, key("npm ci", None));
+    }
+
+    #[test]
+    fn test_entry_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().join(CACHE_DIR_NAME);
+        let cache = |inputs: &[&str]| Cache {
+            inputs: inputs.iter().map(|i| i.to_string()).collect(),
+            key_extra: None,
+        };
+        let path = entry_path(&cache_dir, "npm ci", &cache(&["package-lock.json"]));
+        assert_ne!(
+            path,
+            entry_path(&cache_dir, "npm ci", &cache(&["package.json"]))
+        );
+        assert_ne!(
+            path,
+            entry_path(&cache_dir, "npm i", &cache(&["package-lock.json"]))
+        );
+        assert_eq!(read_entry(&path), None);
+        let entry = CacheEntry {
+            key: "abc".to_string(),
+            capture: Some("v1.2.3".to_string()),
+        };
+        write_entry(&path, &entry).unwrap();
+        assert_eq!(read_entry(&path), Some(entry));
+    }
+}
@@ -1,165 +1,1020 @@
 use hashbrown::HashMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use tokio::process::Command as TokioCommand;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::task::JoinHandle;
 use tokio::time::{Duration, sleep};
 
+use super::cache::{self, Cache, CacheEntry};
+use super::condition::Condition;
+use super::error::StepError;
+use super::operating_system::OperatingSystem;
 use super::options::Options;
+use super::output::{Stream, drain, heartbeat, tee};
+use super::priority::Priority;
+use super::process::{DRAIN_LIMIT, ProcessTree};
+use super::pty::Pty;
+use super::quoting::Quoting;
+use super::report::{SkipReason, StepReport, StepStatus};
+use super::run_options::Verbosity;
+use super::session::{Session, exported_env, shell_program};
+use super::style::Style;
+use super::wsl::WSL_PROGRAM;
+use super::{PROJECT_ROOT_VAR, SAVE_CONTEXT_ENV};
+use crate::utils::{Vars, canonicalize, load_vars};
+
+/// Default for `Options::max_capture_bytes`.
+const DEFAULT_MAX_CAPTURE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Appended to a captured value that exceeded `max_capture_bytes`.
+const TRUNCATED_MARKER: &str = "... truncated";
 
 /// Represents a single command in the YAML script.
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default, JsonSchema)]
 pub struct Command {
+    /// An optional name for the step, used to select it with `--from`, `--to`
+    /// and `--step` and shown in the run summary.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     /// The shell command to execute.
     pub command: String,
     /// Optional argument defines varable names to capture from the command output.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub capture: Option<String>,
+    /// Mask the captured value like a secret in everything shown after it
+    /// is captured.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sensitive: bool,
     /// An optional description of what the command does.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     /// Optional options that control the behavior of the command.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<Options>,
+    /// Optional tags used to include or exclude the step with `--only` and `--skip-tag`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// The phase the step belongs to, e.g. `Build`. Consecutive steps of a
+    /// group are printed under one header; it does not change how they run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Runs the command only while this condition holds, e.g.
+    /// `os == "linux" && ${branch} != "main"`; see [`Condition`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    /// Skips the command while its inputs are the same as when it last
+    /// succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<Cache>,
+    /// For a command running another script with `zirv <name>`, the
+    /// variables of that run to bring back, from their name there to their
+    /// name here, e.g. `{ version: build_version }`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub export: Option<BTreeMap<String, String>>,
 }
 
 impl Command {
     pub async fn execute(
         &self,
         context: &mut HashMap<String, String>,
-    ) -> Result<Option<String>, String> {
+        session: &Session,
+        step: &mut StepReport,
+    ) -> Result<StepStatus, StepError> {
         if let Some(options) = &self.options
             && let Some(os) = &options.operating_system
             && !os.is_current()
         {
-            return Ok(Some("Command skipped due to OS filter".to_string()));
+            return Ok(skip(
+                session,
+                step,
+                SkipReason::OperatingSystem {
+                    required: os.name().to_string(),
+                    current: OperatingSystem::current().name().to_string(),
+                },
+            ));
         }
 
-        let command = self.substituted_command(context);
+        if let Some(when) = &self.when {
+            let holds = Condition::parse(when)
+                .and_then(|condition| condition.evaluate(context))
+                .map_err(|e| StepError::Failed(format!("Invalid when condition: {e}")))?;
+            if !holds {
+                return Ok(skip(
+                    session,
+                    step,
+                    SkipReason::Condition { when: when.clone() },
+                ));
+            }
+        }
+
+        let quoting = self.quoting(session.shell.as_deref());
+        self.check_quoted(context, quoting)?;
+        let command = self.substituted_command(context, quoting);
 
         if let Some(rest) = command.trim_start().strip_prefix("cd ") {
+            // The directory may be quoted, e.g. for spaces.
             let dir = rest.trim();
+            let dir = ['"', '\'']
+                .iter()
+                .find_map(|quote| dir.strip_prefix(*quote)?.strip_suffix(*quote))
+                .unwrap_or(dir);
 
-            let mut path = std::path::PathBuf::new();
-            if let Some(cwd) = context.get("cwd") {
-                path.push(cwd);
-            } else if let Ok(cwd) = std::env::current_dir() {
-                path.push(cwd);
-            }
+            let base = match context.get("cwd") {
+                Some(cwd) => PathBuf::from(cwd),
+                None => std::env::current_dir().unwrap_or_default(),
+            };
+            let path = base.join(dir);
 
-            if std::path::Path::new(dir).is_absolute() {
-                path = std::path::PathBuf::from(dir);
-            } else {
-                path.push(dir);
+            match canonicalize(&path) {
+                Ok(p) => {
+                    context.insert("cwd".to_string(), p.to_string_lossy().to_string());
+                }
+                Err(e) => {
+                    let shown =
+                        |path: &Path| session.output.redact(&path.to_string_lossy()).into_owned();
+                    return Err(format!(
+                        "Failed to change directory to {}: {} ({e}, resolved against {})",
+                        session.output.redact(dir),
+                        shown(&path),
+                        shown(&base)
+                    )
+                    .into());
+                }
             }
 
-            if let Ok(p) = path.canonicalize() {
-                context.insert("cwd".to_string(), p.to_string_lossy().to_string());
-            } else {
-                return Err(format!("Failed to change directory to {dir}"));
-            }
+            return Ok(StepStatus::Success);
+        }
 
-            return Ok(None);
+        let cache_entry = self
+            .cache
+            .as_ref()
+            .zip(session.cache_dir.as_deref())
+            .map(|(cache, dir)| cache::entry_path(dir, &self.command, cache));
+        if let Some(path) = &cache_entry {
+            session.output.detail(
+                Some(step.index),
+                Verbosity::Debug,
+                &format!("Cache entry: {}", path.display()),
+            );
+        }
+        if let Some(path) = &cache_entry
+            && !session.no_cache
+            && let Some(entry) = self.cached(path, &command, context, session)
+        {
+            if let Some(var) = &self.capture
+                && let Some(value) = entry.capture
+            {
+                context.insert(var.clone(), value);
+            }
+            return Ok(skip(session, step, SkipReason::Cached));
         }
 
-        let invoke = self.invoke(&command, context).await;
+        let invoke = self.invoke(&command, context, session, step).await;
 
         if let Err(e) = invoke {
+            // Error messages end up in reports, so secrets are masked
+            let shown = session.output.redact(&command);
+            let mut error = format!("Command '{}' failed: {}", shown, e);
+
             if let Some(options) = &self.options {
                 if let Some(commands) = &options.fallback {
                     for cmd in commands {
-                        if let Err(fallback_error) = cmd.invoke().await {
+                        let quoting = cmd.quoting(session.shell.as_deref());
+                        cmd.check_quoted(context, quoting)?;
+                        let fallback = cmd.substituted_command(context, quoting);
+                        // A fallback without a `path_prepend` of its own
+                        // gets the step's.
+                        let mut fallback_options = cmd.options.clone().unwrap_or_default();
+                        fallback_options.path_prepend = fallback_options
+                            .path_prepend
+                            .or_else(|| options.path_prepend.clone());
+                        let env = path_env(Some(&fallback_options), context)?
+                            .into_iter()
+                            .collect();
+                        if let Err(fallback_error) =
+                            cmd.invoke(&fallback, session, Some(step.index), env).await
+                        {
                             return Err(format!(
                                 "Command '{}' failed and fallback '{}' also failed: {}",
-                                command, cmd.command, fallback_error
-                            ));
+                                shown,
+                                session.output.redact(&fallback),
+                                fallback_error
+                            )
+                            .into());
+                        }
+                    }
+
+                    if options.retry_after_fallback() {
+                        match self.invoke(&command, context, session, step).await {
+                            Ok(()) => {
+                                self.store_cache(
+                                    cache_entry.as_deref(),
+                                    &command,
+                                    context,
+                                    session,
+                                    step,
+                                );
+                                self.delay().await;
+                                return Ok(StepStatus::Success);
+                            }
+                            Err(retry_error) => {
+                                error = format!(
+                                    "Command '{}' failed: {}; retry after fallback also failed: {}",
+                                    shown, e, retry_error
+                                );
+                            }
                         }
                     }
                 }
 
-                if options.proceed_on_failure {
-                    return Ok(Some(
-                        "Command failed but proceeding due to options".to_string(),
-                    ));
+                if options.proceed_on_failure() {
+                    session.output.print_styled(
+                        Some(step.index),
+                        Style::Yellow,
+                        "Command failed but proceeding due to options",
+                    );
+                    return Ok(StepStatus::Failed);
                 }
             }
-            return Err(format!("Command '{}' failed: {}", command, e));
+            return Err(e.with_message(error));
+        }
+
+        self.store_cache(cache_entry.as_deref(), &command, context, session, step);
+        self.delay().await;
+
+        Ok(StepStatus::Success)
+    }
+
+    /// The hash of what the command's `cache` declares it depends on.
+    fn cache_key(
+        &self,
+        command: &str,
+        context: &HashMap<String, String>,
+        session: &Session,
+    ) -> std::io::Result<String> {
+        let cache = self.cache.clone().unwrap_or_default();
+        let base = match context.get("cwd") {
+            Some(cwd) => PathBuf::from(cwd),
+            None => std::env::current_dir()?,
+        };
+        let inputs: Vec<String> = cache
+            .inputs
+            .iter()
+            .map(|input| substitute(input, context, None))
+            .collect();
+        let extra = cache
+            .key_extra
+            .map(|extra| substitute(&extra, context, None));
+        let params: Vec<&str> = session
+            .param_names
+            .iter()
+            .map(|name| context.get(name).map_or("", String::as_str))
+            .collect();
+        cache::input_key(command, extra.as_deref(), &params, &inputs, &base)
+    }
+
+    /// The entry at `path` if it is up to date and holds the value the
+    /// command captures. Sensitive captures, and those holding a secret,
+    /// are never stored, so such commands always run.
+    fn cached(
+        &self,
+        path: &Path,
+        command: &str,
+        context: &HashMap<String, String>,
+        session: &Session,
+    ) -> Option<CacheEntry> {
+        let entry = cache::read_entry(path)?;
+        let up_to_date = entry.key == self.cache_key(command, context, session).ok()?
+            && (self.capture.is_none() || entry.capture.is_some());
+        up_to_date.then_some(entry)
+    }
+
+    /// Records at `path` that the command succeeded with its inputs as they
+    /// are now.
+    fn store_cache(
+        &self,
+        path: Option<&Path>,
+        command: &str,
+        context: &HashMap<String, String>,
+        session: &Session,
+        step: &StepReport,
+    ) {
+        let Some(path) = path else {
+            return;
+        };
+        // The cache is a plain file, so a value output would mask stays out.
+        let capture = match (&self.capture, self.sensitive) {
+            (Some(var), false) => context
+                .get(var)
+                .filter(|value| matches!(session.output.redact(value), Cow::Borrowed(_)))
+                .cloned(),
+            _ => None,
+        };
+        let stored = self
+            .cache_key(command, context, session)
+            .and_then(|key| cache::write_entry(path, &CacheEntry { key, capture }));
+        if let Err(e) = stored {
+            session.output.eprint_styled(
+                Some(step.index),
+                Style::Yellow,
+                &format!("Warning: failed to update the cache: {e}"),
+            );
         }
+    }
 
+    async fn delay(&self) {
         if let Some(options) = &self.options
             && let Some(d) = options.delay_ms
         {
             sleep(Duration::from_millis(d)).await;
         }
-
-        Ok(None)
     }
 
     async fn invoke(
         &self,
         command: &str,
         context: &mut HashMap<String, String>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut shell = if cfg!(windows) {
-            let mut c = TokioCommand::new("powershell");
-            c.arg("-Command").arg(command);
-            c
-        } else {
-            let mut c = TokioCommand::new("sh");
-            c.arg("-c").arg(command);
-            c
+        session: &Session,
+        step: &mut StepReport,
+    ) -> Result<(), StepError> {
+        let options = self.options.as_ref();
+        let mut env = Vec::new();
+        if options
+            .and_then(|o| o.export_context)
+            .unwrap_or(session.export_context)
+        {
+            let prefix = options
+                .and_then(|o| o.export_prefix.as_deref())
+                .or(session.export_prefix.as_deref())
+                .unwrap_or_default();
+            let include_secrets = options
+                .and_then(|o| o.export_secrets)
+                .unwrap_or(session.export_secrets);
+            env = exported_env(context, session, prefix, include_secrets);
+        }
+
+        // Only the run started by this step saves its context, not any run
+        // started by a step of a script that exports itself.
+        // The context goes to a directory only zirv's user can enter, which
+        // is removed with everything in it whatever happens to the step.
+        let saved_dir = match &self.export {
+            Some(_) => Some(
+                private_dir_builder()
+                    .prefix("zirv-context-")
+                    .tempdir()
+                    .map_err(|e| {
+                        StepError::Failed(format!(
+                            "Cannot create a directory for the called script's context: {e}"
+                        ))
+                    })?,
+            ),
+            None => None,
         };
+        let saved_context = saved_dir
+            .as_ref()
+            .map(|dir| dir.path().join("context.yaml"));
+        match &saved_context {
+            Some(path) => env.push((
+                SAVE_CONTEXT_ENV.to_string(),
+                path.to_string_lossy().into_owned(),
+            )),
+            None if std::env::var_os(SAVE_CONTEXT_ENV).is_some() => {
+                env.push((SAVE_CONTEXT_ENV.to_string(), String::new()));
+            }
+            None => {}
+        }
 
-        if let Some(cwd) = context.get("cwd") {
-            shell.current_dir(cwd);
+        if options.is_some_and(|o| o.force_color()) {
+            env.push(("FORCE_COLOR".to_string(), "1".to_string()));
+            env.push(("CLICOLOR_FORCE".to_string(), "1".to_string()));
         }
 
-        println!("Executing command: {command}");
-        if let Some(description) = &self.description {
-            println!("Description: {description}");
+        if options.is_some_and(|o| o.wsl() && o.container.is_none()) && !cfg!(windows) {
+            session.output.eprint_styled(
+                Some(step.index),
+                Style::Yellow,
+                "Warning: `wsl` only applies on Windows; running the command in the shell",
+            );
         }
 
-        if let Some(options) = &self.options
-            && options.interactive
+        let cwd = context.get("cwd").map(PathBuf::from);
+        env.extend(path_env(options, context)?);
+
+        let output = &session.output;
+        output.print_styled(
+            Some(step.index),
+            Style::Bold,
+            &format!("Executing command: {command}"),
+        );
+        if let Some(description) = &self.description {
+            output.print(Some(step.index), &format!("Description: {description}"));
+        }
+        if let Some(priority) = options.and_then(|o| o.priority)
+            && priority != Priority::Normal
         {
+            output.print(Some(step.index), &format!("Priority: {priority}"));
+        }
+        if output.shows(Verbosity::Verbose) {
+            let dir = match &cwd {
+                Some(cwd) => cwd.clone(),
+                None => std::env::current_dir().map_err(|e| {
+                    StepError::Failed(format!("Cannot read the working directory: {e}"))
+                })?,
+            };
+            let mut details = vec![
+                format!("Working directory: {}", dir.display()),
+                runner(options, session),
+            ];
+            details.extend(
+                env.iter()
+                    .map(|(name, value)| format!("Environment: {name}={value}")),
+            );
+            let mut names: Vec<String> = Vec::new();
+            for name in self.placeholders() {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            details.extend(names.iter().filter_map(|name| {
+                let value = context.get(name)?;
+                Some(format!("Substituted ${{{name}}}: {value}"))
+            }));
+            for detail in details {
+                output.detail(Some(step.index), Verbosity::Verbose, &detail);
+            }
+        }
+        let mut shell = session.step_command(command, options, cwd.as_deref(), env);
+
+        let mut interactive = self.options.as_ref().is_some_and(|o| o.interactive());
+        // An interactive step whose output is captured prompts through a pty.
+        let pty = if interactive && self.capture.is_some() {
+            Pty::open()
+                .inspect_err(|e| {
+                    output.eprint_styled(
+                        Some(step.index),
+                        Style::Yellow,
+                        &format!(
+                            "Warning: cannot give the step a terminal ({e}); running it without one to capture its output"
+                        ),
+                    );
+                    interactive = false;
+                })
+                .ok()
+        } else {
+            None
+        };
+        let heartbeat_every = options
+            .and_then(|o| o.heartbeat_ms)
+            .or(session.heartbeat_ms)
+            .filter(|&ms| ms > 0 && !interactive)
+            .map(Duration::from_millis);
+        if let Some(pty) = &pty {
+            pty.attach(&mut shell).map_err(StepError::spawn)?;
+            shell.stdout(Stdio::piped());
+        } else if interactive {
             shell
                 .stdin(Stdio::inherit())
-                .stdout(Stdio::inherit())
+                .stdout(output.terminal_stream().inherited())
                 .stderr(Stdio::inherit());
+        } else {
+            shell.stderr(Stdio::piped());
+            if self.capture.is_some() {
+                shell.stdin(Stdio::null()).stdout(Stdio::piped());
+            } else if output.forwards() || heartbeat_every.is_some() {
+                shell.stdout(Stdio::piped());
+            }
         }
 
-        if let Some(var) = &self.capture {
-            let out = shell.output().await?;
-            if !out.status.success() {
-                return Err(format!("`{command}` failed").into());
+        // Dropping a cancelled run (e.g. in watch mode) must not leave the
+        // step's processes behind. A step with a pty is in a session of its
+        // own, and on Windows an interactive one shares zirv's console so
+        // that Ctrl-C reaches it.
+        let own_group = pty.is_none() && (cfg!(unix) || !interactive);
+        let mut tree = ProcessTree::spawn(&mut shell, own_group, &session.stopper)
+            .map_err(StepError::spawn)?;
+        let relay = pty
+            .map(|pty| pty.relay(&mut shell, output.terminal_stream()))
+            .transpose()
+            .map_err(StepError::spawn)?;
+        let child = tree.child();
+        let pid = child.id().unwrap_or_default();
+        output.detail(
+            Some(step.index),
+            Verbosity::Debug,
+            &format!("Started process {pid}"),
+        );
+        let heartbeat_task = heartbeat_every.map(|every| {
+            let label = step.command.lines().next().unwrap_or_default().to_string();
+            AbortOnDrop(tokio::spawn(heartbeat(
+                output.clone(),
+                step.index,
+                label,
+                std::time::Instant::now(),
+                every,
+            )))
+        });
+
+        let label = self.name.clone().unwrap_or_else(|| step.index.to_string());
+        let stderr_tail = child.stderr.take().map(|e| {
+            tokio::spawn(tee(
+                e,
+                output.clone(),
+                Some(step.index),
+                label.clone(),
+                Stream::Stderr,
+            ))
+        });
+
+        let mut out = child.stdout.take();
+        let stdout_tee = out.take_if(|_| self.capture.is_none()).map(|out| {
+            tokio::spawn(tee(
+                out,
+                output.clone(),
+                Some(step.index),
+                label,
+                Stream::Stdout,
+            ))
+        });
+        // Read a capture while waiting, so that a failed step's leftover
+        // processes, which may hold the pipe open, are killed first.
+        let read_stdout = async {
+            let mut stdout = Vec::new();
+            let mut truncated = false;
+            if let Some(out) = out {
+                let limit = self
+                    .options
+                    .as_ref()
+                    .and_then(|o| o.max_capture_bytes)
+                    .unwrap_or(DEFAULT_MAX_CAPTURE_BYTES);
+                let mirror = relay.is_some().then(|| output.terminal_stream());
+                truncated = read_capped(out, limit, &mut stdout, mirror).await?;
             }
+            Ok::<_, std::io::Error>((stdout, truncated))
+        };
+        let wait = async {
+            let status = tree.wait(session.grace_period(options)).await?;
+            if !status.success() {
+                tree.kill();
+            }
+            Ok::<_, std::io::Error>(status)
+        };
+        let (read, status) = tokio::join!(read_stdout, wait);
+        // What the step left running in the background may hold its output
+        // open; a step is done when its own process is.
+        let deadline = tokio::time::Instant::now() + DRAIN_LIMIT;
+        if let Some(reader) = stdout_tee {
+            drain(reader, deadline).await;
+        }
+        let stderr_tail = match stderr_tail {
+            Some(reader) => drain(reader, deadline).await,
+            None => None,
+        };
+        // Stops relaying the terminal on unix; there is no relay elsewhere.
+        #[cfg_attr(not(unix), allow(clippy::drop_non_drop))]
+        drop(relay);
+        let (stdout, truncated) = read.map_err(|e| {
+            StepError::Failed(format!("Cannot read the output of the command: {e}"))
+        })?;
+        let status = status.map_err(StepError::spawn)?;
+        drop(heartbeat_task);
+        output.detail(
+            Some(step.index),
+            Verbosity::Debug,
+            &format!("Process {pid} ended with {status}"),
+        );
+        step.exit_code = status.code();
+        step.stderr_tail = stderr_tail.filter(|tail| !tail.is_empty());
+
+        if !status.success() {
+            return Err(StepError::Failed(format!(
+                "`{}` failed",
+                output.redact(command)
+            )));
+        }
 
-            let val = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        if let Some(var) = &self.capture {
+            let mut val = String::from_utf8_lossy(&stdout).trim().to_string();
+            if truncated {
+                output.eprint_styled(
+                    Some(step.index),
+                    Style::Yellow,
+                    &format!(
+                        "Warning: output captured into '{var}' exceeded {} bytes and was truncated",
+                        stdout.len()
+                    ),
+                );
+                val.push('\n');
+                val.push_str(TRUNCATED_MARKER);
+            }
 
+            if self.sensitive {
+                output.mask(&val);
+            }
             context.insert(var.clone(), val);
+            step.capture = Some(var.clone());
+        }
 
-            Ok(())
-        } else {
-            let status = shell.status().await?;
+        if let (Some(export), Some(path)) = (&self.export, &saved_context) {
+            let saved = load_vars(path).map_err(|e| {
+                StepError::Failed(format!(
+                    "`export` needs a step running a script with `zirv <name>`, but no context was saved: {e}"
+                ))
+            })?;
+            import_exports(export, &saved, context)?;
+        }
 
-            if !status.success() {
-                return Err(format!("`{command}` failed").into());
+        Ok(())
+    }
+
+    /// Returns the names of all `${name}` placeholders in the command; see
+    /// [`pieces`] for what counts as one.
+    pub fn placeholders(&self) -> Vec<String> {
+        pieces(&self.command)
+            .into_iter()
+            .filter_map(|piece| match piece {
+                Piece::Placeholder(name) | Piece::Quoted(name) => Some(name.to_string()),
+                Piece::Text(_) => None,
+            })
+            .collect()
+    }
+
+    /// How `${name:q}` placeholders are quoted for the shell the command
+    /// runs in, `shell` being the configured one: containers and WSL run
+    /// commands with sh and bash.
+    pub(crate) fn quoting(&self, shell: Option<&str>) -> Quoting {
+        quoting_for(self.options.as_ref(), shell)
+    }
+
+    /// The command with its placeholders substituted. Values are not
+    /// quoted for `cd`, which zirv runs itself.
+    pub(crate) fn substituted_command(
+        &self,
+        params: &HashMap<String, String>,
+        quoting: Quoting,
+    ) -> String {
+        let quoting = (!self.command.trim_start().starts_with("cd ")).then_some(quoting);
+        substitute(&self.command, params, quoting)
+    }
+
+    /// Fails if the value of a `${name:q}` placeholder cannot be quoted
+    /// for the shell; see [`Quoting::check`].
+    pub(crate) fn check_quoted(
+        &self,
+        params: &HashMap<String, String>,
+        quoting: Quoting,
+    ) -> Result<(), StepError> {
+        check_quoted(&self.command, params, quoting)
+    }
+}
+
+/// How `${name:q}` placeholders are quoted for a command with `options`;
+/// see [`Command::quoting`].
+pub(crate) fn quoting_for(options: Option<&Options>, shell: Option<&str>) -> Quoting {
+    if options.is_some_and(|o| o.container.is_some())
+        || (cfg!(windows) && options.is_some_and(|o| o.wsl()))
+    {
+        return Quoting::Posix;
+    }
+    Quoting::for_shell(shell_program(shell))
+}
+
+/// Fails if the value of a `${name:q}` placeholder of `text` cannot be
+/// quoted by `quoting`.
+pub(crate) fn check_quoted(
+    text: &str,
+    params: &HashMap<String, String>,
+    quoting: Quoting,
+) -> Result<(), StepError> {
+    for piece in pieces(text) {
+        if let Piece::Quoted(name) = piece
+            && let Some(value) = params.get(name)
+        {
+            quoting
+                .check(value)
+                .map_err(|e| StepError::Failed(format!("Cannot quote '${{{name}:q}}': {e}")))?;
+        }
+    }
+    Ok(())
+}
+
+/// Records on `step` that it was skipped for `reason`, and says so.
+fn skip(session: &Session, step: &mut StepReport, reason: SkipReason) -> StepStatus {
+    session.output.print_styled(
+        Some(step.index),
+        Style::Dim,
+        &format!("Command skipped: {reason}"),
+    );
+    step.skip(reason);
+    StepStatus::Skipped
+}
+
+/// Adds the variables `export` names from `saved`, the context of a called
+/// script, to `context` under their new names. Neither may be missing from
+/// `saved` nor already set in `context`.
+fn import_exports(
+    export: &BTreeMap<String, String>,
+    saved: &Vars,
+    context: &mut HashMap<String, String>,
+) -> Result<(), StepError> {
+    for (from, to) in export {
+        let Some(value) = saved.get(from) else {
+            return Err(StepError::Failed(format!(
+                "Cannot export '{from}': the called script did not save it (secrets and sensitive captures are never saved)"
+            )));
+        };
+        if context.contains_key(to) {
+            return Err(StepError::Failed(format!(
+                "Cannot export '{from}' as '{to}': '{to}' is already set"
+            )));
+        }
+        context.insert(to.clone(), value.clone());
+    }
+    Ok(())
+}
+
+/// What runs a command with `options`, e.g. `Shell: bash` or
+/// `Container: rust:1`.
+fn runner(options: Option<&Options>, session: &Session) -> String {
+    match options.and_then(|o| o.container.as_deref()) {
+        Some(image) => format!("Container: {image}"),
+        None if cfg!(windows) && options.is_some_and(|o| o.wsl()) => {
+            format!("Shell: {WSL_PROGRAM}")
+        }
+        None => format!("Shell: {}", shell_program(session.shell.as_deref())),
+    }
+}
+
+/// A part of a command: text taken as is, a `${name}` placeholder, or a
+/// `${name:q}` placeholder, whose value is quoted for the shell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Piece<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+    Quoted(&'a str),
+}
+
+/// Splits `text` into literal text and placeholders:
+///
+/// - `${name}` is a placeholder if `name` starts with a letter or `_` and
+///   holds only letters, digits, `_`, `.` and `-`. Anything else, such as
+///   `${HOME:-~}` or PowerShell's `${env:PATH}`, is left to the shell.
+/// - `${name:q}` is a placeholder whose value is quoted; see [`Quoting`].
+/// - `$$` is a literal `$`, so `$${name}` is the text `${name}`.
+/// - `${{` is left as is, e.g. for `${{ github.sha }}`.
+///
+/// Every other `$` is taken as is, e.g. in `awk '{print $1}'`.
+pub(crate) fn pieces(text: &str) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while let Some(offset) = text[i..].find('$') {
+        let dollar = i + offset;
+        let rest = &text[dollar + 1..];
+        if rest.starts_with('$') {
+            pieces.push(Piece::Text(&text[start..=dollar]));
+            i = dollar + 2;
+            start = i;
+        } else if rest.starts_with("{{") {
+            i = dollar + 3;
+        } else if let Some(inner) = rest
+            .strip_prefix('{')
+            .and_then(|rest| rest.split_once('}'))
+            .map(|(inner, _)| inner)
+            .filter(|inner| is_placeholder_name(inner.strip_suffix(":q").unwrap_or(inner)))
+        {
+            if start < dollar {
+                pieces.push(Piece::Text(&text[start..dollar]));
             }
+            pieces.push(match inner.strip_suffix(":q") {
+                Some(name) => Piece::Quoted(name),
+                None => Piece::Placeholder(inner),
+            });
+            i = dollar + inner.len() + 3;
+            start = i;
+        } else {
+            i = dollar + 1;
+        }
+    }
+    if start < text.len() {
+        pieces.push(Piece::Text(&text[start..]));
+    }
+    pieces
+}
+
+/// Whether `${name}` is a placeholder rather than something for the shell.
+fn is_placeholder_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
 
-            Ok(())
+/// `text` written so that [`pieces`] reads it back as literal text, e.g.
+/// `$${HOME}` for `${HOME}`.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        escaped.push(c);
+        if c == '$' && matches!(chars.peek(), Some('$' | '{')) {
+            escaped.push('$');
         }
     }
+    escaped
+}
+
+/// `text` with every `${name}` placeholder of `params` replaced by its
+/// value and every `$$` by `$`. Placeholders without a value are kept.
+/// The value of a `${name:q}` placeholder is quoted by `quoting`, or taken
+/// as is for text that is not run by a shell, such as a path.
+pub(crate) fn substitute(
+    text: &str,
+    params: &HashMap<String, String>,
+    quoting: Option<Quoting>,
+) -> String {
+    pieces(text)
+        .into_iter()
+        .map(|piece| match piece {
+            Piece::Text(text) => text.to_string(),
+            Piece::Placeholder(name) => params
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| format!("${{{name}}}")),
+            Piece::Quoted(name) => match (params.get(name), quoting) {
+                (Some(value), Some(quoting)) => quoting.quote(value),
+                (Some(value), None) => value.clone(),
+                (None, _) => format!("${{{name}:q}}"),
+            },
+        })
+        .collect()
+}
 
-    fn substituted_command(&self, params: &HashMap<String, String>) -> String {
-        let mut command = self.command.clone();
-        for (key, value) in params {
-            let placeholder = format!("${{{key}}}");
-            command = command.replace(&placeholder, value);
+/// The `${name}` placeholders of `text` standing alone between spaces and
+/// outside quotes, such as in `git commit -m ${message}`, whose values are
+/// split by the shell at spaces and may run as shell syntax.
+pub(crate) fn bare_placeholders(text: &str) -> Vec<&str> {
+    let pieces = pieces(text);
+    let mut quote = None;
+    let mut escaped = false;
+    let mut bare = Vec::new();
+    for (i, piece) in pieces.iter().enumerate() {
+        match piece {
+            Piece::Text(text) => {
+                for c in text.chars() {
+                    match (quote, c) {
+                        _ if escaped => escaped = false,
+                        (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+                        (Some('\''), _) => {}
+                        (_, '\\') => escaped = true,
+                        (None, '\'' | '"') => quote = Some(c),
+                        _ => {}
+                    }
+                }
+            }
+            Piece::Placeholder(name) if quote.is_none() => {
+                let before = match i.checked_sub(1).map(|i| pieces[i]) {
+                    Some(Piece::Text(text)) => text.ends_with(char::is_whitespace),
+                    Some(_) => false,
+                    None => true,
+                };
+                let after = match pieces.get(i + 1) {
+                    Some(Piece::Text(text)) => text.starts_with(char::is_whitespace),
+                    Some(_) => false,
+                    None => true,
+                };
+                if before && after {
+                    bare.push(*name);
+                }
+            }
+            _ => escaped = false,
         }
-        command
     }
+    bare
+}
+
+/// The `path_prepend` directories of `options`, substituted with `context`.
+/// Relative ones are resolved against the project root, or outside of a
+/// project against the directory zirv runs in, wherever the step runs.
+pub(crate) fn path_prefix(
+    options: &Options,
+    context: &HashMap<String, String>,
+) -> std::io::Result<Vec<PathBuf>> {
+    let root = match context.get(PROJECT_ROOT_VAR) {
+        Some(root) => PathBuf::from(root),
+        None => std::env::current_dir()?,
+    };
+    Ok(options
+        .path_prepend
+        .iter()
+        .flatten()
+        .map(|dir| root.join(substitute(dir, context, None)))
+        .collect())
+}
+
+/// The `PATH` for a process run with `options`, with their `path_prepend`
+/// directories in front. `None` without such directories, or for a process
+/// in a container or WSL, which has a `PATH` of its own.
+fn path_env(
+    options: Option<&Options>,
+    context: &HashMap<String, String>,
+) -> Result<Option<(String, String)>, StepError> {
+    let Some(options) = options.filter(|o| {
+        o.path_prepend.as_ref().is_some_and(|p| !p.is_empty())
+            && o.container.is_none()
+            && !(cfg!(windows) && o.wsl())
+    }) else {
+        return Ok(None);
+    };
+    let prefix = path_prefix(options, context)
+        .map_err(|e| StepError::Failed(format!("Cannot read the working directory: {e}")))?;
+    let path = prepended_path(&prefix, std::env::var_os("PATH"))?;
+    Ok(Some((
+        "PATH".to_string(),
+        path.to_string_lossy().into_owned(),
+    )))
+}
+
+/// `PATH` with `prefix` in front of the directories of `path`.
+fn prepended_path(prefix: &[PathBuf], path: Option<OsString>) -> Result<OsString, StepError> {
+    let rest: Vec<PathBuf> = path
+        .as_deref()
+        .map(|path| std::env::split_paths(path).collect())
+        .unwrap_or_default();
+    std::env::join_paths(prefix.iter().chain(&rest))
+        .map_err(|e| StepError::Failed(format!("Invalid path_prepend entry: {e}")))
+}
+
+/// Aborts a background task, such as a step's heartbeat, when the step
+/// ends or is cancelled.
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Reads `reader` to the end, keeping at most `limit` bytes in `buf`, and
+/// with `mirror` also writing everything read to that stream of zirv as it
+/// arrives.
+///
+/// The remainder is drained and discarded so the child never blocks on a full
+/// pipe. Returns whether anything was discarded.
+async fn read_capped<R: AsyncRead + Unpin>(
+    mut reader: R,
+    limit: usize,
+    buf: &mut Vec<u8>,
+    mirror: Option<Stream>,
+) -> std::io::Result<bool> {
+    let mut chunk = [0u8; 8192];
+    let mut truncated = false;
+
+    loop {
+        let n = reader.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(truncated);
+        }
+        if let Some(stream) = mirror {
+            stream.write_through(&chunk[..n]);
+        }
+        let room = limit.saturating_sub(buf.len());
+        if n > room {
+            truncated = true;
+        }
+        buf.extend_from_slice(&chunk[..n.min(room)]);
+    }
+}
+
+/// A builder for temporary directories that only the current user can enter.
+fn private_dir_builder<'a, 'b>() -> tempfile::Builder<'a, 'b> {
+    #[allow(unused_mut)]
+    let mut builder = tempfile::Builder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(std::fs::Permissions::from_mode(0o700));
+    }
+    builder
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(unix)]
+    use crate::script_runner::fallback_command::FallbackCommand;
+    use crate::script_runner::output::Output;
     use hashbrown::HashMap;
+    use std::sync::Arc;
+
+    fn step() -> StepReport {
+        StepReport::new(1, String::new())
+    }
 
     #[tokio::test]
     async fn test_substituted_command() {
@@ -168,14 +1023,1004 @@ mod tests {
             capture: None,
             description: None,
             options: None,
+            ..Default::default()
         };
 
         let mut params = HashMap::new();
         params.insert("name".to_string(), "Alice".to_string());
         params.insert("age".to_string(), "30".to_string());
 
-        let result = command.substituted_command(&params);
+        let result = command.substituted_command(&params, Quoting::Posix);
 
         assert_eq!(result, "echo Alice is 30 years old");
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_retry_after_fallback_succeeds() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("marker");
+
+        let command = Command {
+            command: format!("test -f '{}'", marker.display()),
+            capture: None,
+            description: None,
+            options: Some(Options {
+                fallback: Some(vec![FallbackCommand {
+                    command: format!("touch '{}'", marker.display()),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        let result = command
+            .execute(&mut context, &Session::default(), &mut step())
+            .await;
+
+        assert_eq!(result.unwrap(), StepStatus::Success);
+        assert!(marker.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_fallback_placeholders_are_substituted() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("it's here");
+
+        let command = Command {
+            command: "test -f ${marker:q}".to_string(),
+            options: Some(Options {
+                fallback: Some(vec![FallbackCommand {
+                    command: "touch ${marker:q}".to_string(),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        context.insert("marker".to_string(), marker.display().to_string());
+        let result = command
+            .execute(&mut context, &Session::default(), &mut step())
+            .await;
+
+        assert_eq!(result.unwrap(), StepStatus::Success);
+        assert!(marker.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_retry_after_fallback_reports_second_failure() {
+        let command = Command {
+            command: "exit 1".to_string(),
+            capture: None,
+            description: None,
+            options: Some(Options {
+                fallback: Some(vec![FallbackCommand {
+                    command: "true".to_string(),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        let error = command
+            .execute(&mut context, &Session::default(), &mut step())
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("retry after fallback also failed"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_no_retry_when_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("marker");
+
+        let command = Command {
+            command: format!("test -f '{}'", marker.display()),
+            capture: None,
+            description: None,
+            options: Some(Options {
+                fallback: Some(vec![FallbackCommand {
+                    command: format!("touch '{}'", marker.display()),
+                    ..Default::default()
+                }]),
+                retry_after_fallback: Some(false),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        let error = command
+            .execute(&mut context, &Session::default(), &mut step())
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert!(marker.exists());
+        assert!(!error.contains("retry"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_retry_after_fallback_applies_capture() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("marker");
+
+        let command = Command {
+            command: format!("cat '{}'", marker.display()),
+            capture: Some("value".to_string()),
+            description: None,
+            options: Some(Options {
+                fallback: Some(vec![FallbackCommand {
+                    command: format!("echo restored > '{}'", marker.display()),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        command
+            .execute(&mut context, &Session::default(), &mut step())
+            .await
+            .unwrap();
+
+        assert_eq!(context.get("value"), Some(&"restored".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_records_exit_code() {
+        let command = Command {
+            command: "exit 3".to_string(),
+            capture: None,
+            description: None,
+            options: Some(Options {
+                proceed_on_failure: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        let mut step = step();
+        let status = command
+            .execute(&mut context, &Session::default(), &mut step)
+            .await;
+
+        assert_eq!(status.unwrap(), StepStatus::Failed);
+        assert_eq!(step.exit_code, Some(3));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_records_stderr_tail() {
+        let command = Command {
+            command: "echo first >&2; echo second >&2; exit 1".to_string(),
+            capture: Some("out".to_string()),
+            description: None,
+            options: None,
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        let mut step = step();
+        assert!(
+            command
+                .execute(&mut context, &Session::default(), &mut step)
+                .await
+                .is_err()
+        );
+
+        assert_eq!(step.stderr_tail.as_deref(), Some("first\nsecond"));
+        assert!(!context.contains_key("out"));
+    }
+
+    #[tokio::test]
+    async fn test_read_capped() {
+        let mut buf = Vec::new();
+        let truncated = read_capped(&b"hello world"[..], 5, &mut buf, None)
+            .await
+            .unwrap();
+        assert!(truncated);
+        assert_eq!(buf, b"hello");
+
+        let mut buf = Vec::new();
+        let truncated = read_capped(&b"hello"[..], 5, &mut buf, None).await.unwrap();
+        assert!(!truncated);
+        assert_eq!(buf, b"hello");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_capture_is_truncated() {
+        let command = Command {
+            command: "head -c 3000000 /dev/zero | tr '\\0' a".to_string(),
+            capture: Some("big".to_string()),
+            description: None,
+            options: Some(Options {
+                max_capture_bytes: Some(1024),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        let mut step = step();
+        let status = command
+            .execute(&mut context, &Session::default(), &mut step)
+            .await;
+
+        assert_eq!(status.unwrap(), StepStatus::Success);
+        assert_eq!(step.exit_code, Some(0));
+        let value = context.get("big").unwrap();
+        assert!(value.len() <= 1024 + TRUNCATED_MARKER.len() + 1);
+        assert!(value.starts_with("aaaa"));
+        assert!(value.ends_with(TRUNCATED_MARKER));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_interactive_capture_runs_in_a_terminal() {
+        let command = Command {
+            command: "for fd in 0 1 2; do [ -t $fd ] && printf '%s=tty ' $fd || printf '%s=pipe ' $fd; done; tty >/dev/null && echo ctty"
+                .to_string(),
+            capture: Some("terminal".to_string()),
+            options: Some(Options {
+                interactive: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        let mut step = step();
+        let status = command
+            .execute(&mut context, &Session::default(), &mut step)
+            .await;
+
+        assert_eq!(status.unwrap(), StepStatus::Success);
+        assert_eq!(context["terminal"], "0=tty 1=pipe 2=tty ctty");
+        assert_eq!(step.capture.as_deref(), Some("terminal"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_export_brings_back_saved_variables() {
+        let command = |line: &str| Command {
+            command: line.to_string(),
+            export: Some(BTreeMap::from([("sha".to_string(), "commit".to_string())])),
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        let status = command("printf 'sha: abc123\\nother: x\\n' > \"$ZIRV_SAVE_CONTEXT\"")
+            .execute(&mut context, &Session::default(), &mut step())
+            .await;
+        assert_eq!(status.unwrap(), StepStatus::Success);
+        assert_eq!(context.get("commit").map(String::as_str), Some("abc123"));
+        assert!(!context.contains_key("other"));
+
+        let error = command("true")
+            .execute(&mut HashMap::new(), &Session::default(), &mut step())
+            .await
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("`export` needs a step running a script with `zirv <name>`"),
+            "{error}"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cache_leaves_out_secret_captures() {
+        let dir = tempfile::tempdir().unwrap();
+        let command = |line: &str| Command {
+            command: line.to_string(),
+            capture: Some("out".to_string()),
+            cache: Some(Cache::default()),
+            ..Default::default()
+        };
+        let session = Session {
+            cache_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        session.output.mask("hunter2");
+        let run = |command: Command| {
+            let session = &session;
+            async move {
+                let mut context = HashMap::new();
+                let status = command
+                    .execute(&mut context, session, &mut step())
+                    .await
+                    .unwrap();
+                (status, context.remove("out"))
+            }
+        };
+
+        let plain = command("echo v1.2.3");
+        assert_eq!(run(plain.clone()).await.0, StepStatus::Success);
+        assert_eq!(
+            run(plain).await,
+            (StepStatus::Skipped, Some("v1.2.3".to_string()))
+        );
+
+        let secret = command("echo token=hunter2");
+        assert_eq!(run(secret.clone()).await.0, StepStatus::Success);
+        assert_eq!(run(secret).await.0, StepStatus::Success);
+        for entry in std::fs::read_dir(dir.path()).unwrap() {
+            let stored = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert!(!stored.contains("hunter2"), "{stored}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_export_context_file_is_private() {
+        let command = Command {
+            command: "printf 'sha: abc\\n' > \"$ZIRV_SAVE_CONTEXT\"; \
+                      ls -ld \"$(dirname \"$ZIRV_SAVE_CONTEXT\")\"; echo \"$ZIRV_SAVE_CONTEXT\""
+                .to_string(),
+            capture: Some("out".to_string()),
+            export: Some(BTreeMap::from([("sha".to_string(), "commit".to_string())])),
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        command
+            .execute(&mut context, &Session::default(), &mut step())
+            .await
+            .unwrap();
+        let (listing, path) = context["out"].split_once('\n').unwrap();
+        assert!(listing.starts_with("drwx------"), "{listing}");
+        assert!(!Path::new(path).parent().unwrap().exists(), "{path}");
+        assert_eq!(context["commit"], "abc");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_export_context() {
+        let command = Command {
+            command: "echo \"$ZIRV_BRANCH:$ZIRV_TOKEN\"".to_string(),
+            capture: Some("out".to_string()),
+            options: Some(Options {
+                export_context: Some(true),
+                export_prefix: Some("ZIRV_".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let session = Session {
+            secret_names: vec!["token".to_string()],
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        context.insert("branch".to_string(), "main".to_string());
+        context.insert("token".to_string(), "hunter2".to_string());
+        command
+            .execute(&mut context, &session, &mut step())
+            .await
+            .unwrap();
+        assert_eq!(context.get("out"), Some(&"main:".to_string()));
+
+        let mut command = command.clone();
+        command.options.as_mut().unwrap().export_secrets = Some(true);
+        command
+            .execute(&mut context, &session, &mut step())
+            .await
+            .unwrap();
+        assert_eq!(context.get("out"), Some(&"main:hunter2".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_export_context_script_default() {
+        let command = Command {
+            command: "echo \"$BRANCH\"".to_string(),
+            capture: Some("out".to_string()),
+            ..Default::default()
+        };
+        let session = Session {
+            export_context: true,
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        context.insert("branch".to_string(), "main".to_string());
+        command
+            .execute(&mut context, &session, &mut step())
+            .await
+            .unwrap();
+        assert_eq!(context.get("out"), Some(&"main".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_isolate_env() {
+        // SAFETY: no other test reads or writes these variables.
+        unsafe {
+            std::env::set_var("ZIRV_ISOLATE_TEST_AMBIENT", "stray");
+            std::env::set_var("ZIRV_ISOLATE_TEST_KEPT", "kept");
+        }
+        let command = |options: Options| {
+            Command {
+            command: "echo \"$ZIRV_ISOLATE_TEST_AMBIENT:$ZIRV_ISOLATE_TEST_KEPT:$BRANCH:$(command -v sh)\""
+                .to_string(),
+            capture: Some("out".to_string()),
+            options: Some(Options {
+                export_context: Some(true),
+                ..options
+            }),
+            ..Default::default()
+        }
+        };
+        let run = async |command: Command, session: &Session| {
+            let mut context = HashMap::new();
+            context.insert("branch".to_string(), "main".to_string());
+            command
+                .execute(&mut context, session, &mut step())
+                .await
+                .unwrap();
+            context.remove("out").unwrap()
+        };
+
+        let out = run(command(Options::default()), &Session::default()).await;
+        assert!(out.starts_with("stray:kept:main:/"), "{out}");
+
+        let isolated = Session {
+            isolate_env: true,
+            ..Default::default()
+        };
+        let out = run(command(Options::default()), &isolated).await;
+        assert!(out.starts_with("::main:/"), "{out}");
+
+        let keep = Options {
+            env_keep: Some(vec!["ZIRV_ISOLATE_TEST_KEPT".to_string()]),
+            ..Default::default()
+        };
+        let out = run(command(keep), &isolated).await;
+        assert!(out.starts_with(":kept:main:/"), "{out}");
+
+        let opt_in = Options {
+            isolate_env: Some(true),
+            ..Default::default()
+        };
+        let out = run(command(opt_in), &Session::default()).await;
+        assert!(out.starts_with("::main:/"), "{out}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_path_prepend() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let project = tempfile::tempdir().unwrap();
+        let bin = project.path().join("tools").join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        let tool = bin.join("zirv-test-tool");
+        std::fs::write(&tool, "#!/bin/sh\necho \"tool $1\"\n").unwrap();
+        std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let command = Command {
+            command: "zirv-test-tool ran".to_string(),
+            capture: Some("out".to_string()),
+            options: Some(Options {
+                path_prepend: Some(vec!["${tools}/bin".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        // Entries are relative to the project root, not to where the step runs.
+        let sub = project.path().join("sub");
+        std::fs::create_dir(&sub).unwrap();
+        let mut context = HashMap::new();
+        context.insert("tools".to_string(), "tools".to_string());
+        context.insert(
+            PROJECT_ROOT_VAR.to_string(),
+            project.path().to_string_lossy().to_string(),
+        );
+        context.insert("cwd".to_string(), sub.to_string_lossy().to_string());
+        command
+            .execute(&mut context, &Session::default(), &mut step())
+            .await
+            .unwrap();
+        assert_eq!(context.get("out"), Some(&"tool ran".to_string()));
+
+        // So are they for the step's fallbacks.
+        let marker = project.path().join("fallback.txt");
+        let command = Command {
+            command: "exit 1".to_string(),
+            options: Some(Options {
+                path_prepend: Some(vec!["${tools}/bin".to_string()]),
+                fallback: Some(vec![FallbackCommand {
+                    command: format!("zirv-test-tool fallback > '{}'", marker.display()),
+                    ..Default::default()
+                }]),
+                retry_after_fallback: Some(false),
+                proceed_on_failure: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        command
+            .execute(&mut context, &Session::default(), &mut step())
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&marker).unwrap(), "tool fallback\n");
+
+        let path = prepended_path(
+            std::slice::from_ref(&bin),
+            Some(OsString::from("/usr/bin:/bin")),
+        )
+        .unwrap();
+        assert_eq!(
+            path,
+            OsString::from(format!("{}:/usr/bin:/bin", bin.display()))
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_heartbeat_while_silent() {
+        let session = Session {
+            output: Arc::new(Output::captured(false)),
+            heartbeat_ms: Some(60),
+            ..Default::default()
+        };
+        let command = Command {
+            command: "sleep 0.4".to_string(),
+            ..Default::default()
+        };
+        let mut silent = StepReport::new(4, "sleep 0.4".to_string());
+        command
+            .execute(&mut HashMap::new(), &session, &mut silent)
+            .await
+            .unwrap();
+        let text = session.output.captured_text();
+        let beats = text
+            .lines()
+            .filter(|line| line.starts_with("Still running step 4 (sleep 0.4) — "))
+            .count();
+        assert!(beats >= 3, "{text}");
+
+        // Output keeps the heartbeat quiet, and `0` turns it off
+        for (command, heartbeat_ms) in [
+            ("for i in 1 2 3 4 5 6; do echo $i; sleep 0.05; done", 250),
+            ("sleep 0.2", 0),
+        ] {
+            let session = Session {
+                output: Arc::new(Output::captured(false)),
+                heartbeat_ms: Some(10_000),
+                ..Default::default()
+            };
+            let command = Command {
+                command: command.to_string(),
+                options: Some(Options {
+                    heartbeat_ms: Some(heartbeat_ms),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            command
+                .execute(&mut HashMap::new(), &session, &mut step())
+                .await
+                .unwrap();
+            let text = session.output.captured_text();
+            assert!(!text.contains("Still running"), "{text}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_step_kills_its_background_processes() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("sleeper.pid");
+        let command = Command {
+            command: format!("sleep 30 & echo $! > {}; exit 3", pid_file.display()),
+            capture: Some("out".to_string()),
+            ..Default::default()
+        };
+        let mut context = HashMap::new();
+        let start = std::time::Instant::now();
+        let result = command
+            .execute(&mut context, &Session::default(), &mut step())
+            .await;
+        assert!(result.is_err());
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let running = || {
+            let output = std::process::Command::new("ps")
+                .args(["-o", "stat=", "-p", pid.trim()])
+                .output()
+                .unwrap();
+            let stat = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            !stat.is_empty() && !stat.starts_with('Z')
+        };
+        for _ in 0..50 {
+            if !running() {
+                return;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        panic!("the backgrounded sleeper {} outlived its step", pid.trim());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_step_does_not_wait_for_its_background_processes() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("sleeper.pid");
+        let command = Command {
+            command: format!(
+                "sleep 4 & echo $! > {}; echo started >&2",
+                pid_file.display()
+            ),
+            ..Default::default()
+        };
+        let mut context = HashMap::new();
+        let start = std::time::Instant::now();
+        let status = command
+            .execute(&mut context, &Session::default(), &mut step())
+            .await;
+        assert_eq!(status.unwrap(), StepStatus::Success);
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // It may leave them running on purpose.
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let killed = std::process::Command::new("kill")
+            .arg(pid.trim())
+            .status()
+            .unwrap();
+        assert!(killed.success());
+    }
+
+    #[tokio::test]
+    async fn test_cd_error_names_path_and_base() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = crate::utils::canonicalize(dir.path()).unwrap();
+        let mut context = HashMap::from([("cwd".to_string(), base.display().to_string())]);
+
+        let error = Command {
+            command: "cd missing".to_string(),
+            ..Default::default()
+        }
+        .execute(&mut context, &Session::default(), &mut step())
+        .await
+        .unwrap_err()
+        .to_string();
+        assert!(
+            error.starts_with(&format!(
+                "Failed to change directory to missing: {} (",
+                base.join("missing").display()
+            )),
+            "{error}"
+        );
+        assert!(
+            error.ends_with(&format!(", resolved against {})", base.display())),
+            "{error}"
+        );
+    }
+
+    /// Runs `line` from a new directory holding `dir` and checks that it
+    /// changes into `dir`, returning the new `cwd`.
+    async fn cd_into(dir: &str, line: &str) -> String {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path();
+        std::fs::create_dir_all(base.join(dir)).unwrap();
+        let mut context = HashMap::from([("cwd".to_string(), base.display().to_string())]);
+        let status = Command {
+            command: line.to_string(),
+            ..Default::default()
+        }
+        .execute(&mut context, &Session::default(), &mut step())
+        .await
+        .unwrap();
+        assert_eq!(status, StepStatus::Success);
+        let cwd = context["cwd"].clone();
+        assert_eq!(
+            std::fs::canonicalize(&cwd).unwrap(),
+            std::fs::canonicalize(base.join(dir)).unwrap()
+        );
+        cwd
+    }
+
+    #[tokio::test]
+    async fn test_cd_into_dir_with_spaces() {
+        for line in ["cd my dir", "cd \"my dir\"", "cd 'my dir'"] {
+            let cwd = cd_into("my dir", line).await;
+            assert!(cwd.ends_with("my dir"), "{cwd}");
+        }
+    }
+
+    #[cfg(windows)]
+    #[tokio::test]
+    async fn test_cd_on_windows_stores_plain_paths() {
+        for (dir, line) in [
+            ("sub", "cd sub"),
+            ("sub\\nested", "cd sub\\nested"),
+            ("with spaces", "cd \"with spaces\""),
+        ] {
+            let cwd = cd_into(dir, line).await;
+            assert!(!cwd.starts_with(r"\\?\"), "{cwd}");
+            assert!(cwd.ends_with(dir), "{cwd}");
+        }
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let command = Command {
+            command: "echo ${name} is ${age} years old ${unterminated".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(command.placeholders(), vec!["name", "age"]);
+
+        let command = Command {
+            command: "echo $${a} ${b} ${{c}} ${d:-x} ${e.f} $$${g-h} ${1} ${env:PATH} ${x${y}}"
+                .to_string(),
+            ..Default::default()
+        };
+        assert_eq!(command.placeholders(), vec!["b", "e.f", "g-h", "y"]);
+
+        let command = Command {
+            command: "git commit -m ${message:q} ${x:-y} ${env:q} $${z:q} ${w:qq}".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(command.placeholders(), vec!["message", "env"]);
+    }
+
+    #[test]
+    fn test_quoted_placeholders() {
+        let context = HashMap::from([(
+            "message".to_string(),
+            "fix: handle \"quotes\" & it's $5".to_string(),
+        )]);
+        let text = "git commit -m ${message:q} ${missing:q} $${message:q}";
+        for (quoting, expected) in [
+            (
+                Some(Quoting::Posix),
+                r#"git commit -m 'fix: handle "quotes" & it'\''s $5' ${missing:q} ${message:q}"#,
+            ),
+            (
+                Some(Quoting::PowerShell),
+                r#"git commit -m 'fix: handle "quotes" & it''s $5' ${missing:q} ${message:q}"#,
+            ),
+            (
+                Some(Quoting::Cmd),
+                r#"git commit -m ^"fix:^ handle^ \^"quotes\^"^ ^&^ it's^ $5^" ${missing:q} ${message:q}"#,
+            ),
+            (
+                None,
+                r#"git commit -m fix: handle "quotes" & it's $5 ${missing:q} ${message:q}"#,
+            ),
+        ] {
+            assert_eq!(substitute(text, &context, quoting), expected, "{quoting:?}");
+        }
+
+        // zirv changes the directory itself, so there is nothing to quote.
+        let cd = Command {
+            command: "cd ${message:q}".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            cd.substituted_command(&context, Quoting::Posix),
+            "cd fix: handle \"quotes\" & it's $5"
+        );
+
+        let multiline = HashMap::from([("message".to_string(), "a\nb".to_string())]);
+        let command = Command {
+            command: "git commit -m ${message:q}".to_string(),
+            ..Default::default()
+        };
+        assert!(command.check_quoted(&multiline, Quoting::Posix).is_ok());
+        let error = command.check_quoted(&multiline, Quoting::Cmd).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Cannot quote '${message:q}': cmd cannot pass on a value with a line break"
+        );
+    }
+
+    #[test]
+    fn test_quoting_follows_where_the_command_runs() {
+        let command = |options: Options| Command {
+            command: "echo ${message:q}".to_string(),
+            options: Some(options),
+            ..Default::default()
+        };
+        let local = command(Options::default());
+        assert_eq!(local.quoting(Some("pwsh")), Quoting::PowerShell);
+        assert_eq!(local.quoting(Some("cmd")), Quoting::Cmd);
+        assert_eq!(local.quoting(Some("zsh")), Quoting::Posix);
+        let contained = command(Options {
+            container: Some("alpine".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(contained.quoting(Some("pwsh")), Quoting::Posix);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_quoted_values_reach_the_command_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        for value in [
+            "fix: handle \"quotes\" & spaces",
+            "costs $5; echo $HOME `id` $(id)",
+            "it's a\nmultiline message",
+        ] {
+            let mut context = HashMap::from([
+                ("cwd".to_string(), dir.path().display().to_string()),
+                ("message".to_string(), value.to_string()),
+            ]);
+            let status = Command {
+                command: "printf '%s|' ${message:q} > out.txt".to_string(),
+                ..Default::default()
+            }
+            .execute(&mut context, &Session::default(), &mut step())
+            .await
+            .unwrap();
+            assert_eq!(status, StepStatus::Success);
+            let out = std::fs::read_to_string(dir.path().join("out.txt")).unwrap();
+            assert_eq!(out, format!("{value}|"));
+        }
+    }
+
+    #[test]
+    fn test_bare_placeholders() {
+        assert_eq!(
+            bare_placeholders("git commit -m ${message} ${a}b \"${c}\" '${d}' ${e:q} ${f}"),
+            vec!["message", "f"]
+        );
+        assert_eq!(bare_placeholders("${tool}\t--flag"), vec!["tool"]);
+        assert_eq!(bare_placeholders("${a}${b}"), Vec::<&str>::new());
+        assert_eq!(
+            bare_placeholders("echo --out=${dir} $${x}"),
+            Vec::<&str>::new()
+        );
+        assert_eq!(
+            bare_placeholders("echo \"deploy ${target} ${region}\" ${env}"),
+            vec!["env"]
+        );
+        assert_eq!(
+            bare_placeholders("echo 'it\"s ${a}' \\\" ${b} \"say \\\" ${c}\""),
+            vec!["b"]
+        );
+    }
+
+    #[test]
+    fn test_substitute_escaping() {
+        let context: HashMap<String, String> = [
+            ("name", "Ada"),
+            ("zirv.project_root", "/work"),
+            ("build-id", "7"),
+            ("a", "${name}"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        for (text, expected) in [
+            // awk
+            ("awk '{print $1}' data.txt", "awk '{print $1}' data.txt"),
+            (
+                "awk -F, '{ print $2 }' ${name}.csv",
+                "awk -F, '{ print $2 }' Ada.csv",
+            ),
+            (
+                "awk '{s+=$NF} END {print s}'",
+                "awk '{s+=$NF} END {print s}'",
+            ),
+            // PowerShell
+            ("echo $env:FOO ${env:PATH}", "echo $env:FOO ${env:PATH}"),
+            ("Write-Host \"$($env:HOME)\"", "Write-Host \"$($env:HOME)\""),
+            ("$name = '${name}'; $name", "$name = 'Ada'; $name"),
+            // Escapes
+            ("echo $$", "echo $"),
+            ("echo $$$$", "echo $$"),
+            ("echo $${name}", "echo ${name}"),
+            ("echo $$${name}", "echo $Ada"),
+            ("echo ${{ github.sha }}", "echo ${{ github.sha }}"),
+            ("echo ${{name}}", "echo ${{name}}"),
+            // Braces
+            ("echo ${a${name}}", "echo ${aAda}"),
+            ("echo ${name}}", "echo Ada}"),
+            ("echo {${name}}", "echo {Ada}"),
+            // Shell expansions and what is not a placeholder
+            (
+                "echo ${HOME:-/tmp} ${1} ${#name}",
+                "echo ${HOME:-/tmp} ${1} ${#name}",
+            ),
+            ("echo ${missing} ${name", "echo ${missing} ${name"),
+            ("echo cost$ ${}", "echo cost$ ${}"),
+            // Names with dots and dashes
+            ("cd ${zirv.project_root}/${build-id}", "cd /work/7"),
+            // Values are not substituted again
+            ("echo ${a}", "echo ${name}"),
+        ] {
+            assert_eq!(substitute(text, &context, None), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_escape_is_read_back_literally() {
+        let context: HashMap<String, String> =
+            HashMap::from([("name".to_string(), "Ada".to_string())]);
+        for text in [
+            "echo ${name}",
+            "echo $$ $${name}",
+            "echo $",
+            "awk '{print $1}'",
+            "echo ${{name}} ${HOME}",
+        ] {
+            assert_eq!(substitute(&escape(text), &context, None), text, "{text}");
+        }
+        assert_eq!(escape("echo \"$HOME\""), "echo \"$HOME\"");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_force_color_sets_env() {
+        let command = Command {
+            command: "echo \"${FORCE_COLOR:-unset}:${CLICOLOR_FORCE:-unset}\"".to_string(),
+            capture: Some("out".to_string()),
+            options: Some(Options {
+                force_color: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        command
+            .execute(&mut context, &Session::default(), &mut step())
+            .await
+            .unwrap();
+        assert_eq!(context.get("out"), Some(&"1:1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_wsl_step() {
+        let command = Command {
+            command: "uname -s".to_string(),
+            capture: Some("kernel".to_string()),
+            options: Some(Options {
+                wsl: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let session = Session {
+            output: Arc::new(Output::captured(false)),
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        command
+            .execute(&mut context, &session, &mut step())
+            .await
+            .unwrap();
+        let warning = "Warning: `wsl` only applies on Windows";
+        if cfg!(windows) {
+            assert_eq!(context.get("kernel"), Some(&"Linux".to_string()));
+            assert!(!session.output.captured_text().contains(warning));
+        } else {
+            assert!(context.contains_key("kernel"));
+            assert!(session.output.captured_text().contains(warning));
+        }
+    }
 }
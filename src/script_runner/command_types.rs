@@ -1,10 +1,26 @@
 use std::process::Command as StdCommand;
 
 use super::command::Command;
+use super::error::StepError;
+use super::quoting::Quoting;
+use super::report::{StepReport, StepStatus};
+use super::session::Session;
 use hashbrown::HashMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// How `${name:q}` placeholders are quoted in a group, whose commands run
+/// in a new terminal window: by cmd on Windows, by sh or bash elsewhere.
+pub(crate) const TERMINAL_QUOTING: Quoting = if cfg!(windows) {
+    Quoting::Cmd
+} else {
+    Quoting::Posix
+};
+
+// A script holds a handful of steps, so their size does not matter; boxing
+// `Command` would only make every match on a step more awkward.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 #[serde(untagged)]
 pub enum CommandTypes {
     Command(Command),
@@ -12,30 +28,139 @@ pub enum CommandTypes {
 }
 
 impl CommandTypes {
+    /// The commands of the step: the one command, or every member of a
+    /// group.
+    pub fn commands(&self) -> &[Command] {
+        match self {
+            CommandTypes::Command(cmd) => std::slice::from_ref(cmd),
+            CommandTypes::Commands(cmds) => cmds.as_slice(),
+        }
+    }
+
+    /// Like [`CommandTypes::commands`], but mutable.
+    pub fn commands_mut(&mut self) -> &mut [Command] {
+        match self {
+            CommandTypes::Command(cmd) => std::slice::from_mut(cmd),
+            CommandTypes::Commands(cmds) => cmds.as_mut_slice(),
+        }
+    }
+
+    /// Returns the names of all `${name}` placeholders used by the step.
+    pub fn placeholders(&self) -> Vec<String> {
+        match self {
+            CommandTypes::Command(cmd) => cmd.placeholders(),
+            CommandTypes::Commands(cmds) => cmds.iter().flat_map(|c| c.placeholders()).collect(),
+        }
+    }
+
+    /// Returns the variable the step captures its output into, if any.
+    pub fn capture(&self) -> Option<&str> {
+        match self {
+            CommandTypes::Command(cmd) => cmd.capture.as_deref(),
+            CommandTypes::Commands(_) => None,
+        }
+    }
+
+    /// Returns the variables the step sets: its capture and the names its
+    /// `export` brings back from a called script. A group sets none, as its
+    /// commands run in a new terminal window; see [`CommandTypes::unsupported`].
+    pub fn variables(&self) -> Vec<&str> {
+        match self {
+            CommandTypes::Command(cmd) => cmd
+                .capture
+                .iter()
+                .chain(cmd.export.iter().flat_map(|export| export.values()))
+                .map(String::as_str)
+                .collect(),
+            CommandTypes::Commands(_) => Vec::new(),
+        }
+    }
+
+    /// Describes the `capture` or `export` of a group member, which has
+    /// nothing to read back from the terminal window the group runs in.
+    pub fn unsupported(&self) -> Option<String> {
+        let CommandTypes::Commands(cmds) = self else {
+            return None;
+        };
+        cmds.iter().find_map(|cmd| {
+            let field = if cmd.capture.is_some() {
+                "capture"
+            } else if cmd.export.is_some() {
+                "export"
+            } else {
+                return None;
+            };
+            Some(format!(
+                "`{field}` on '{}' has no effect in a group, whose commands run in a new terminal window",
+                cmd.command
+            ))
+        })
+    }
+
+    /// Returns the tags of the step. A group carries the tags of all its members.
+    pub fn tags(&self) -> Vec<String> {
+        match self {
+            CommandTypes::Command(cmd) => cmd.tags.clone().unwrap_or_default(),
+            CommandTypes::Commands(cmds) => cmds
+                .iter()
+                .flat_map(|c| c.tags.iter().flatten().cloned())
+                .collect(),
+        }
+    }
+
+    /// The group the step is printed under, if any.
+    pub fn group(&self) -> Option<&str> {
+        match self {
+            CommandTypes::Command(cmd) => cmd.group.as_deref(),
+            CommandTypes::Commands(cmds) => cmds.iter().find_map(|c| c.group.as_deref()),
+        }
+    }
+
+    /// A short label for the step, used in summaries and reports.
+    pub fn label(&self) -> String {
+        match self {
+            CommandTypes::Command(cmd) => cmd.name.clone().unwrap_or_else(|| cmd.command.clone()),
+            CommandTypes::Commands(cmds) => cmds
+                .iter()
+                .map(|c| c.command.as_str())
+                .collect::<Vec<_>>()
+                .join(" & "),
+        }
+    }
+
+    /// The step's commands with their placeholders substituted, as run
+    /// with `shell`, the configured shell.
+    pub fn command_line(&self, context: &HashMap<String, String>, shell: Option<&str>) -> String {
+        match self {
+            CommandTypes::Command(cmd) => cmd.substituted_command(context, cmd.quoting(shell)),
+            CommandTypes::Commands(cmds) => cmds
+                .iter()
+                .map(|c| c.substituted_command(context, TERMINAL_QUOTING))
+                .collect::<Vec<_>>()
+                .join(" && "),
+        }
+    }
+
     pub async fn execute(
         &self,
         context: &mut HashMap<String, String>,
-    ) -> Result<Option<String>, String> {
+        session: &Session,
+        step: &mut StepReport,
+    ) -> Result<StepStatus, StepError> {
         match self {
-            CommandTypes::Command(cmd) => cmd.execute(context).await,
+            CommandTypes::Command(cmd) => cmd.execute(context, session, step).await,
             CommandTypes::Commands(cmds) => {
                 if cmds.is_empty() {
-                    return Ok(None);
+                    return Ok(StepStatus::Success);
                 }
-
-                let mut substituted = cmds.clone();
-                for cmd in &mut substituted {
-                    for (key, value) in context.iter() {
-                        let placeholder = format!("${{{key}}}");
-                        cmd.command = cmd.command.replace(&placeholder, value);
-                    }
+                if let Some(message) = self.unsupported() {
+                    return Err(StepError::Failed(message));
                 }
 
-                let joined = substituted
-                    .into_iter()
-                    .map(|c| c.command)
-                    .collect::<Vec<_>>()
-                    .join(" && ");
+                for cmd in cmds {
+                    cmd.check_quoted(context, TERMINAL_QUOTING)?;
+                }
+                let joined = self.command_line(context, session.shell.as_deref());
 
                 let cwd = context.get("cwd").cloned().unwrap_or_else(|| {
                     std::env::current_dir()
@@ -51,23 +176,23 @@ impl CommandTypes {
                     spawn_terminal_macos(&full_cmd)
                 } else {
                     spawn_terminal_linux(&cwd, &joined)
-                }?;
+                }
+                .map_err(StepError::spawn)?;
 
-                Ok(None)
+                Ok(StepStatus::Success)
             }
         }
     }
 }
 
-fn spawn_terminal_windows(command: &str, working_dir: &str) -> Result<(), String> {
+fn spawn_terminal_windows(command: &str, working_dir: &str) -> std::io::Result<()> {
     StdCommand::new("cmd")
         .args(["/C", "start", "", "/D", working_dir, "cmd", "/K", command])
         .spawn()
         .map(|_| ())
-        .map_err(|e| e.to_string())
 }
 
-fn spawn_terminal_macos(command: &str) -> Result<(), String> {
+fn spawn_terminal_macos(command: &str) -> std::io::Result<()> {
     let applescript_cmd = format!(
         r#"tell application "Terminal"
 activate
@@ -81,10 +206,9 @@ end tell"#,
         .arg(applescript_cmd)
         .spawn()
         .map(|_| ())
-        .map_err(|e| e.to_string())
 }
 
-fn spawn_terminal_linux(cwd: &str, joined: &str) -> Result<(), String> {
+fn spawn_terminal_linux(cwd: &str, joined: &str) -> std::io::Result<()> {
     let fallback_cmd = format!(
         "cd '{}' ; {} ; exec bash",
         escape_single_quotes(cwd),
@@ -118,7 +242,6 @@ fn spawn_terminal_linux(cwd: &str, joined: &str) -> Result<(), String> {
         .args(["-hold", "-e", "bash", "-lc", &fallback_cmd])
         .spawn()
         .map(|_| ())
-        .map_err(|e| e.to_string())
 }
 
 fn escape_for_applescript(s: &str) -> String {
@@ -0,0 +1,236 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+/// The newest script `schema` this version of zirv understands.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// What a script requires of the zirv running it, read before the rest of
+/// the script so that a script written for a newer zirv is rejected with a
+/// clear error instead of being misread.
+#[derive(Debug, Deserialize, Default)]
+pub struct Compatibility {
+    pub zirv_version: Option<String>,
+    pub schema: Option<u32>,
+}
+
+impl Compatibility {
+    /// Checks the requirements against zirv's `version`.
+    pub fn check(&self, version: &str) -> Result<(), String> {
+        if let Some(requirement) = &self.zirv_version {
+            let requirement: VersionRequirement = requirement.parse()?;
+            let current = parse_version(version)
+                .ok_or_else(|| format!("zirv's own version '{version}' is malformed"))?;
+            if !requirement.matches(&current) {
+                return Err(format!(
+                    "this script requires zirv {requirement}, you have {version}"
+                ));
+            }
+        }
+        match self.schema {
+            Some(schema) if schema > SCHEMA_VERSION => Err(format!(
+                "this script uses schema {schema}, but zirv {version} only understands \
+                 schema {SCHEMA_VERSION} and older; upgrade zirv to run it"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A comparison operator of a [`VersionRequirement`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+/// A requirement on zirv's version, such as `>=0.4` or `>=1.2, <2`: every
+/// comparison must hold. A version without an operator is a minimum. A
+/// version with fewer than three parts stands for all versions starting
+/// with them, so `>0.4` means `>=0.5`, and `=0.4` matches `0.4.3`.
+#[derive(Debug, PartialEq)]
+pub struct VersionRequirement(Vec<(Op, Vec<u64>)>);
+
+impl VersionRequirement {
+    /// Whether `version`, as `major.minor.patch`, meets every comparison.
+    pub fn matches(&self, version: &[u64; 3]) -> bool {
+        self.0.iter().all(|(op, parts)| {
+            let ordering = version[..parts.len()].cmp(parts.as_slice());
+            match op {
+                Op::Eq => ordering.is_eq(),
+                Op::Gt => ordering.is_gt(),
+                Op::Ge => ordering.is_ge(),
+                Op::Lt => ordering.is_lt(),
+                Op::Le => ordering.is_le(),
+            }
+        })
+    }
+}
+
+impl std::str::FromStr for VersionRequirement {
+    type Err = String;
+
+    fn from_str(requirement: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| format!("invalid zirv_version '{requirement}': {reason}");
+        let comparisons = requirement
+            .split(',')
+            .map(|comparison| {
+                let comparison = comparison.trim();
+                let (op, version) = [
+                    (">=", Op::Ge),
+                    ("<=", Op::Le),
+                    (">", Op::Gt),
+                    ("<", Op::Lt),
+                    ("=", Op::Eq),
+                ]
+                .into_iter()
+                .find_map(|(prefix, op)| comparison.strip_prefix(prefix).map(|rest| (op, rest)))
+                .unwrap_or((Op::Ge, comparison));
+                let version = version.trim();
+                if version.is_empty() {
+                    return Err(invalid("expected a version such as >=0.4"));
+                }
+                let parts = version
+                    .split('.')
+                    .map(|part| part.parse::<u64>().ok())
+                    .collect::<Option<Vec<u64>>>()
+                    .filter(|parts| parts.len() <= 3)
+                    .ok_or_else(|| {
+                        invalid(&format!(
+                            "'{version}' is not a version of up to three numbers, such as 0.4"
+                        ))
+                    })?;
+                Ok((op, parts))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(VersionRequirement(comparisons))
+    }
+}
+
+impl fmt::Display for VersionRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (op, parts)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            let op = match op {
+                Op::Eq => "=",
+                Op::Gt => ">",
+                Op::Ge => ">=",
+                Op::Lt => "<",
+                Op::Le => "<=",
+            };
+            let version: Vec<String> = parts.iter().map(u64::to_string).collect();
+            write!(f, "{op} {}", version.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the numeric `major.minor.patch` part of a version, ignoring a
+/// leading `v` and any pre-release or build suffix.
+pub fn parse_version(version: &str) -> Option<[u64; 3]> {
+    let core = version
+        .trim()
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    let version = [parts.next()??, parts.next()??, parts.next()??];
+    parts.next().is_none().then_some(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requiring(zirv_version: &str) -> Compatibility {
+        Compatibility {
+            zirv_version: Some(zirv_version.to_string()),
+            schema: None,
+        }
+    }
+
+    #[test]
+    fn test_satisfied_requirements() {
+        for requirement in [
+            ">=0.3",
+            ">= 0.3.3",
+            "0.3",
+            "=0.3",
+            ">0.2",
+            "<0.4",
+            "<=0.3.3",
+            ">=0.3, <1",
+        ] {
+            assert_eq!(
+                requiring(requirement).check("0.3.3"),
+                Ok(()),
+                "{requirement}"
+            );
+        }
+        assert_eq!(Compatibility::default().check("0.3.3"), Ok(()));
+    }
+
+    #[test]
+    fn test_unsatisfied_requirements() {
+        assert_eq!(
+            requiring(">=0.4").check("0.3.3"),
+            Err("this script requires zirv >= 0.4, you have 0.3.3".to_string())
+        );
+        assert_eq!(
+            requiring(">0.3").check("0.3.3"),
+            Err("this script requires zirv > 0.3, you have 0.3.3".to_string())
+        );
+        assert_eq!(
+            requiring(">=0.2,<0.3").check("0.3.3"),
+            Err("this script requires zirv >= 0.2, < 0.3, you have 0.3.3".to_string())
+        );
+        assert!(requiring("=0.3.2").check("0.3.3").is_err());
+    }
+
+    #[test]
+    fn test_malformed_requirements() {
+        for (requirement, reason) in [
+            ("", "expected a version such as >=0.4"),
+            (">=", "expected a version such as >=0.4"),
+            (">=0.4,", "expected a version such as >=0.4"),
+            ("~0.4", "'~0.4' is not a version"),
+            (">=0.x", "'0.x' is not a version"),
+            (">=1.2.3.4", "'1.2.3.4' is not a version"),
+            ("latest", "'latest' is not a version"),
+        ] {
+            let error = requiring(requirement).check("0.3.3").unwrap_err();
+            assert!(
+                error.starts_with(&format!("invalid zirv_version '{requirement}': ")),
+                "{error}"
+            );
+            assert!(error.contains(reason), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("2.1.0-beta.1"), Some([2, 1, 0]));
+        assert_eq!(parse_version("v1.2.3"), Some([1, 2, 3]));
+        assert_eq!(parse_version("1.2"), None);
+        assert_eq!(parse_version("1.2.3.4"), None);
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let schema = |schema| Compatibility {
+            zirv_version: None,
+            schema: Some(schema),
+        };
+        assert_eq!(schema(SCHEMA_VERSION).check("0.3.3"), Ok(()));
+        let error = schema(SCHEMA_VERSION + 1).check("0.3.3").unwrap_err();
+        assert!(
+            error.starts_with(&format!("this script uses schema {}", SCHEMA_VERSION + 1)),
+            "{error}"
+        );
+    }
+}
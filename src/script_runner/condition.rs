@@ -0,0 +1,461 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use hashbrown::HashMap;
+
+/// A parsed `when` expression, such as
+/// `os == "linux" && env.CI == "true" && ${branch} != "main"`.
+///
+/// Operands are `"strings"` or `'strings'`, numbers, `true`, `false`, `os`
+/// (e.g. `linux`), `env.NAME` (empty when unset) and `${name}` from the
+/// context. They compare with `==`, `!=`, `<`, `<=`, `>` and `>=`, as
+/// numbers when both sides are plain decimal numbers such as `-3` or `1.5`
+/// and as text otherwise, so `nan`, `inf` and `1e3` are text. Conditions
+/// combine with `!`, `&&` and `||`, in that order of precedence, and
+/// parentheses. `&&` and `||` only evaluate their right side when it
+/// matters. An operand on its own is true unless it is empty, `false` or
+/// `0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+/// Why a `when` expression could not be parsed or evaluated, and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionError {
+    pub source: String,
+    /// Byte offset of the offending part of `source`.
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConditionError {
+    /// The message, followed by the expression with a caret under the
+    /// offending part.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let column = self.source[..self.offset].chars().count();
+        write!(
+            f,
+            "{} at column {}\n  {}\n  {}^",
+            self.message,
+            column + 1,
+            self.source,
+            " ".repeat(column)
+        )
+    }
+}
+
+impl std::error::Error for ConditionError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Operand(Operand),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Operand, Comparison, Operand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Literal(String),
+    Os,
+    Env(String),
+    Var { name: String, offset: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Str(String),
+    Word(String),
+    Var(String),
+    Op(&'static str),
+}
+
+const OPERATORS: [&str; 11] = ["==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "(", ")"];
+
+impl Condition {
+    /// Parses `source`, failing with the position of the first mistake.
+    pub fn parse(source: &str) -> Result<Condition, ConditionError> {
+        let mut parser = Parser {
+            source,
+            tokens: tokenize(source)?,
+            next: 0,
+        };
+        let expr = parser.or()?;
+        if let Some((token, offset)) = parser.tokens.get(parser.next) {
+            return Err(parser.error(*offset, &format!("unexpected {}", describe(token))));
+        }
+        Ok(Condition {
+            source: source.to_string(),
+            expr,
+        })
+    }
+
+    /// Whether the condition holds for `context` on this machine.
+    pub fn evaluate(&self, context: &HashMap<String, String>) -> Result<bool, ConditionError> {
+        self.evaluate_with(context, std::env::consts::OS, &|name| {
+            std::env::var(name).ok()
+        })
+    }
+
+    fn evaluate_with(
+        &self,
+        context: &HashMap<String, String>,
+        os: &str,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<bool, ConditionError> {
+        let lookup = Lookup { context, os, env };
+        self.holds(&self.expr, &lookup)
+    }
+
+    fn holds(&self, expr: &Expr, lookup: &Lookup) -> Result<bool, ConditionError> {
+        Ok(match expr {
+            Expr::Operand(operand) => {
+                let value = self.value(operand, lookup)?;
+                !matches!(value.as_str(), "" | "false" | "0")
+            }
+            Expr::Not(inner) => !self.holds(inner, lookup)?,
+            Expr::And(left, right) => self.holds(left, lookup)? && self.holds(right, lookup)?,
+            Expr::Or(left, right) => self.holds(left, lookup)? || self.holds(right, lookup)?,
+            Expr::Compare(left, comparison, right) => {
+                let (left, right) = (self.value(left, lookup)?, self.value(right, lookup)?);
+                let ordering = match (decimal(&left), decimal(&right)) {
+                    // Decimal numbers are never NaN, so they always compare.
+                    (Some(left), Some(right)) => {
+                        left.partial_cmp(&right).unwrap_or(Ordering::Equal)
+                    }
+                    _ => left.cmp(&right),
+                };
+                match comparison {
+                    Comparison::Eq => ordering.is_eq(),
+                    Comparison::Ne => ordering.is_ne(),
+                    Comparison::Lt => ordering.is_lt(),
+                    Comparison::Le => ordering.is_le(),
+                    Comparison::Gt => ordering.is_gt(),
+                    Comparison::Ge => ordering.is_ge(),
+                }
+            }
+        })
+    }
+
+    fn value(&self, operand: &Operand, lookup: &Lookup) -> Result<String, ConditionError> {
+        match operand {
+            Operand::Literal(value) => Ok(value.clone()),
+            Operand::Os => Ok(lookup.os.to_string()),
+            Operand::Env(name) => Ok((lookup.env)(name).unwrap_or_default()),
+            Operand::Var { name, offset } => {
+                lookup
+                    .context
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| ConditionError {
+                        source: self.source.clone(),
+                        offset: *offset,
+                        message: format!("undefined variable '${{{name}}}'"),
+                    })
+            }
+        }
+    }
+}
+
+/// `text` as a number, if it is a plain decimal number: digits, optionally
+/// with a fraction and a leading `-`.
+fn decimal(text: &str) -> Option<f64> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let (whole, fraction) = match digits.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (digits, None),
+    };
+    let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !is_digits(whole) || !fraction.is_none_or(is_digits) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+struct Lookup<'a> {
+    context: &'a HashMap<String, String>,
+    os: &'a str,
+    env: &'a dyn Fn(&str) -> Option<String>,
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ConditionError> {
+    let error = |offset, message: &str| ConditionError {
+        source: source.to_string(),
+        offset,
+        message: message.to_string(),
+    };
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(offset, c)) = chars.peek() {
+        let rest = &source[offset..];
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, escaped)) => text.push(escaped),
+                        None => return Err(error(offset, "unterminated string")),
+                    },
+                    Some((_, end)) if end == c => break,
+                    Some((_, other)) => text.push(other),
+                    None => return Err(error(offset, "unterminated string")),
+                }
+            }
+            tokens.push((Token::Str(text), offset));
+        } else if rest.starts_with("${") {
+            let Some(end) = rest.find('}') else {
+                return Err(error(offset, "unterminated '${'"));
+            };
+            let name = rest[2..end].trim();
+            if name.is_empty() {
+                return Err(error(offset, "empty variable name"));
+            }
+            tokens.push((Token::Var(name.to_string()), offset));
+            while chars.next_if(|&(i, _)| i <= offset + end).is_some() {}
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push((Token::Op(op), offset));
+            for _ in 0..op.len() {
+                chars.next();
+            }
+        } else if c.is_alphanumeric() || matches!(c, '_' | '.' | '-') {
+            let mut word = String::new();
+            while let Some((_, c)) =
+                chars.next_if(|&(_, c)| c.is_alphanumeric() || matches!(c, '_' | '.' | '-'))
+            {
+                word.push(c);
+            }
+            tokens.push((Token::Word(word), offset));
+        } else {
+            return Err(error(offset, &format!("unexpected '{c}'")));
+        }
+    }
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Str(text) => format!("string \"{text}\""),
+        Token::Word(word) => format!("'{word}'"),
+        Token::Var(name) => format!("'${{{name}}}'"),
+        Token::Op(op) => format!("'{op}'"),
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, offset: usize, message: &str) -> ConditionError {
+        ConditionError {
+            source: self.source.to_string(),
+            offset,
+            message: message.to_string(),
+        }
+    }
+
+    /// The offset of the next token, or the end of the source.
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.next)
+            .map_or(self.source.len(), |(_, offset)| *offset)
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        let matched = matches!(self.tokens.get(self.next), Some((Token::Op(o), _)) if *o == op);
+        if matched {
+            self.next += 1;
+        }
+        matched
+    }
+
+    fn or(&mut self) -> Result<Expr, ConditionError> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ConditionError> {
+        let mut expr = self.not()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, ConditionError> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.eat("(") {
+            let expr = self.or()?;
+            if !self.eat(")") {
+                return Err(self.error(self.offset(), "expected ')'"));
+            }
+            return Ok(expr);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ConditionError> {
+        let left = self.operand()?;
+        let comparison = match self.tokens.get(self.next) {
+            Some((Token::Op("=="), _)) => Comparison::Eq,
+            Some((Token::Op("!="), _)) => Comparison::Ne,
+            Some((Token::Op("<"), _)) => Comparison::Lt,
+            Some((Token::Op("<="), _)) => Comparison::Le,
+            Some((Token::Op(">"), _)) => Comparison::Gt,
+            Some((Token::Op(">="), _)) => Comparison::Ge,
+            _ => return Ok(Expr::Operand(left)),
+        };
+        self.next += 1;
+        Ok(Expr::Compare(left, comparison, self.operand()?))
+    }
+
+    fn operand(&mut self) -> Result<Operand, ConditionError> {
+        let offset = self.offset();
+        let Some((token, _)) = self.tokens.get(self.next).cloned() else {
+            return Err(self.error(offset, "expected a value"));
+        };
+        let operand = match token {
+            Token::Str(text) => Operand::Literal(text),
+            Token::Var(name) => Operand::Var { name, offset },
+            Token::Word(word) if word == "os" => Operand::Os,
+            Token::Word(word) if word.starts_with("env.") && word.len() > 4 => {
+                Operand::Env(word[4..].to_string())
+            }
+            Token::Word(word) if word == "true" || word == "false" || decimal(&word).is_some() => {
+                Operand::Literal(word)
+            }
+            Token::Word(word) => {
+                return Err(self.error(
+                    offset,
+                    &format!(
+                        "unknown name '{word}'; use os, env.NAME, ${{name}} or a quoted string"
+                    ),
+                ));
+            }
+            Token::Op(_) => {
+                return Err(self.error(
+                    offset,
+                    &format!("expected a value, found {}", describe(&token)),
+                ));
+            }
+        };
+        self.next += 1;
+        Ok(operand)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str) -> Result<bool, ConditionError> {
+        let context = HashMap::from([
+            ("branch".to_string(), "feature/x".to_string()),
+            ("count".to_string(), "10".to_string()),
+            ("empty".to_string(), String::new()),
+        ]);
+        let env = |name: &str| (name == "CI").then(|| "true".to_string());
+        Condition::parse(source)?.evaluate_with(&context, "linux", &env)
+    }
+
+    #[test]
+    fn test_combined_conditions() {
+        assert_eq!(
+            eval(r#"os == "linux" && env.CI == "true" && ${branch} != "main""#),
+            Ok(true)
+        );
+        assert_eq!(eval(r#"os == "windows" || env.HOME == """#), Ok(true));
+        assert_eq!(eval("env.CI && !${empty}"), Ok(true));
+        assert_eq!(eval("'it\\'s' == \"it's\""), Ok(true));
+    }
+
+    #[test]
+    fn test_precedence() {
+        // `&&` binds tighter than `||`, and `!` tighter than both.
+        assert_eq!(eval("true || false && false"), Ok(true));
+        assert_eq!(eval("(true || false) && false"), Ok(false));
+        assert_eq!(eval("!false && false"), Ok(false));
+        assert_eq!(eval("!(false && false)"), Ok(true));
+        assert_eq!(eval("!!true"), Ok(true));
+    }
+
+    #[test]
+    fn test_string_and_number_comparison() {
+        assert_eq!(eval("${count} > 9"), Ok(true));
+        assert_eq!(eval("${count} >= 10.0"), Ok(true));
+        assert_eq!(eval("${count} == 10.0"), Ok(true));
+        assert_eq!(eval("\"10\" > \"9\""), Ok(true));
+        assert_eq!(eval("${branch} > 9"), Ok(true));
+        assert_eq!(eval("\"abc\" < \"abd\""), Ok(true));
+        assert_eq!(eval("\"b\" <= \"a\""), Ok(false));
+        assert_eq!(eval("-1.5 < 0"), Ok(true));
+    }
+
+    #[test]
+    fn test_only_decimal_numbers_compare_as_numbers() {
+        assert_eq!(eval("\"nan\" == \"nan\""), Ok(true));
+        assert_eq!(eval("\"inf\" > 9"), Ok(true));
+        assert_eq!(eval("\"1e3\" > 999"), Ok(false));
+        assert_eq!(eval("\" 10\" == 10"), Ok(false));
+        let error = Condition::parse("${count} < inf").unwrap_err();
+        assert!(error.message.starts_with("unknown name 'inf'"), "{error}");
+    }
+
+    #[test]
+    fn test_undefined_variables() {
+        let error = eval("os == \"linux\" && ${sha} == \"abc\"").unwrap_err();
+        assert_eq!(error.message, "undefined variable '${sha}'");
+        assert_eq!(error.offset, 17);
+        assert_eq!(
+            error.to_string(),
+            "undefined variable '${sha}' at column 18\n  os == \"linux\" && ${sha} == \"abc\"\n                   ^"
+        );
+        // The right side is never evaluated when the left side decides.
+        assert_eq!(eval("false && ${sha} == \"abc\""), Ok(false));
+        assert_eq!(eval("true || ${sha}"), Ok(true));
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let error = |source| Condition::parse(source).unwrap_err();
+        let cases = [
+            ("os == \"linux\" &&", 16, "expected a value"),
+            ("os = \"linux\"", 3, "unexpected '='"),
+            ("branch == \"main\"", 0, "unknown name 'branch'"),
+            ("(os == \"linux\"", 14, "expected ')'"),
+            ("os == \"linux", 6, "unterminated string"),
+            ("${branch", 0, "unterminated '${'"),
+            ("os == \"linux\" true", 14, "unexpected 'true'"),
+            ("== 1", 0, "expected a value, found '=='"),
+            ("()", 1, "expected a value"),
+        ];
+        for (source, offset, message) in cases {
+            let error = error(source);
+            assert_eq!(error.offset, offset, "{source}: {error}");
+            assert!(error.message.starts_with(message), "{source}: {error}");
+        }
+    }
+}
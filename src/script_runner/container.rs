@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+
+use super::{command_types::CommandTypes, options::Options, script::Script};
+
+/// The programs that can run `container` steps, in order of preference.
+pub const CONTAINER_RUNTIMES: &[&str] = &["docker", "podman"];
+
+/// Where the step's working directory is mounted inside the container.
+pub const CONTAINER_WORKDIR: &str = "/work";
+
+/// The first of [`CONTAINER_RUNTIMES`] found on `PATH`.
+pub fn find_container_runtime() -> Option<String> {
+    let path = std::env::var_os("PATH")?;
+    let dirs: Vec<PathBuf> = std::env::split_paths(&path).collect();
+    CONTAINER_RUNTIMES
+        .iter()
+        .find(|runtime| {
+            dirs.iter().any(|dir| {
+                dir.join(runtime).is_file()
+                    || (cfg!(windows) && dir.join(format!("{runtime}.exe")).is_file())
+            })
+        })
+        .map(|runtime| runtime.to_string())
+}
+
+/// Whether any step of `script`, or any of its fallbacks, runs in a
+/// container.
+pub fn uses_containers(script: &Script) -> bool {
+    let in_container = |options: &Option<Options>| {
+        options
+            .as_ref()
+            .is_some_and(|options| options.container.is_some())
+    };
+    script
+        .commands
+        .iter()
+        .flat_map(CommandTypes::commands)
+        .any(|command| {
+            in_container(&command.options)
+                || command
+                    .options
+                    .iter()
+                    .flat_map(|options| options.fallback.iter().flatten())
+                    .any(|fallback| in_container(&fallback.options))
+        })
+}
+
+/// The arguments to the container runtime that run `command` with `sh -c`
+/// in `image`, with `cwd` mounted as the working directory. The variables
+/// named in `env` are passed on by name, so their values stay out of the
+/// command line; `extra` goes before the image.
+pub fn container_args(
+    image: &str,
+    extra: &[String],
+    cwd: &Path,
+    env: &[String],
+    interactive: bool,
+    command: &str,
+) -> Vec<String> {
+    let mut args = vec!["run".to_string(), "--rm".to_string()];
+    if interactive {
+        args.push("-it".to_string());
+    }
+    args.push("-v".to_string());
+    args.push(format!("{}:{CONTAINER_WORKDIR}", cwd.display()));
+    args.push("-w".to_string());
+    args.push(CONTAINER_WORKDIR.to_string());
+    for name in env {
+        args.push("-e".to_string());
+        args.push(name.clone());
+    }
+    args.extend(extra.iter().cloned());
+    args.push(image.to_string());
+    args.push("sh".to_string());
+    args.push("-c".to_string());
+    args.push(command.to_string());
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script_runner::{
+        RunError, RunOptions, command::Command, execute, fallback_command::FallbackCommand,
+    };
+    use crate::utils::parse_script_content;
+
+    #[test]
+    fn test_container_args() {
+        let args = container_args(
+            "rust:1.80",
+            &["--network".to_string(), "none".to_string()],
+            Path::new("/src/app"),
+            &["ZIRV_VERSION".to_string(), "FORCE_COLOR".to_string()],
+            false,
+            "cargo build && echo 'done'",
+        );
+        assert_eq!(
+            args,
+            [
+                "run",
+                "--rm",
+                "-v",
+                "/src/app:/work",
+                "-w",
+                "/work",
+                "-e",
+                "ZIRV_VERSION",
+                "-e",
+                "FORCE_COLOR",
+                "--network",
+                "none",
+                "rust:1.80",
+                "sh",
+                "-c",
+                "cargo build && echo 'done'",
+            ]
+        );
+
+        let args = container_args("alpine", &[], Path::new("/"), &[], true, "sh");
+        assert_eq!(args[..3], ["run", "--rm", "-it"]);
+    }
+
+    #[test]
+    fn test_uses_containers() {
+        let command = |options: Option<Options>| Command {
+            command: "make".to_string(),
+            options,
+            ..Default::default()
+        };
+        let in_image = || Options {
+            container: Some("alpine".to_string()),
+            ..Default::default()
+        };
+        let script = |commands| Script {
+            name: "Build".to_string(),
+            commands,
+            ..Default::default()
+        };
+
+        assert!(!uses_containers(&script(vec![CommandTypes::Command(
+            command(None)
+        )])));
+        assert!(uses_containers(&script(vec![CommandTypes::Commands(
+            vec![command(None), command(Some(in_image()))]
+        )])));
+        let fallback = Options {
+            fallback: Some(vec![FallbackCommand {
+                command: "make clean".to_string(),
+                description: None,
+                options: Some(in_image()),
+            }]),
+            ..Default::default()
+        };
+        assert!(uses_containers(&script(vec![CommandTypes::Command(
+            command(Some(fallback))
+        )])));
+    }
+
+    /// Runs real containers, so only with `ZIRV_TEST_CONTAINERS` set and
+    /// docker or podman installed. The last step only exits with 3 if the
+    /// captures and the exported variable made it through.
+    #[tokio::test]
+    async fn test_steps_run_in_a_container() {
+        if std::env::var_os("ZIRV_TEST_CONTAINERS").is_none() {
+            return;
+        }
+        let script = parse_script_content(
+            r#"
+name: Container
+defaults:
+  container: alpine:3.20
+  export_context: true
+commands:
+  - command: echo ${greeting}
+    capture: echoed
+  - command: uname -s
+    capture: kernel
+  - command: test "${echoed}" = hello && test "${kernel}" = Linux && test "$GREETING" = hello && exit 3
+"#,
+            "yaml",
+        )
+        .unwrap();
+        let options = RunOptions {
+            no_summary: true,
+            vars: crate::utils::Vars::from([("greeting".to_string(), "hello".to_string())]),
+            ..Default::default()
+        };
+        let error = execute(&script, &[], &options).await.unwrap_err();
+        assert!(
+            matches!(
+                error,
+                RunError::Step {
+                    exit_code: Some(3),
+                    ..
+                }
+            ),
+            "{error}"
+        );
+    }
+}
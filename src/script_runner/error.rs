@@ -0,0 +1,161 @@
+use std::{fmt, time::Duration};
+
+use thiserror::Error;
+
+use super::secret::Secret;
+
+/// Why a run of a script failed.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum RunError {
+    /// The script was given the wrong number of params, or some of its
+    /// secrets are not set. Every problem is listed at once.
+    #[error(transparent)]
+    Inputs(#[from] InputErrors),
+    /// A step failed and the script stopped. `exit_code` is that of the
+    /// step's last process, if it ran and exited. The message includes
+    /// `error`'s, so it is not also given as the source.
+    #[error("Error executing command in script '{script}': {error}")]
+    Step {
+        script: String,
+        exit_code: Option<i32>,
+        error: StepError,
+    },
+    /// The run took longer than the script's `timeout_ms` and was stopped
+    /// during step `step`.
+    #[error("Script '{script}' timed out after {}s at step {step}", .after.as_secs_f64())]
+    TimedOut {
+        script: String,
+        after: Duration,
+        step: usize,
+    },
+    /// The script asks for something zirv does not allow, such as a capture
+    /// into a reserved name, so none of its steps ran.
+    #[error("Script '{script}' is invalid:\n  {}", .problems.join("\n  "))]
+    Invalid {
+        script: String,
+        problems: Vec<String>,
+    },
+    /// The run was stopped from outside, by Ctrl-C or a change in watch
+    /// mode.
+    #[error("Script '{script}' was stopped")]
+    Stopped { script: String },
+}
+
+/// Why a step failed.
+#[derive(Debug, Error)]
+pub enum StepError {
+    /// The step's process could not be started, or waited for.
+    #[error("{message}")]
+    Spawn {
+        message: String,
+        #[source]
+        source: std::io::Error,
+    },
+    /// The step ran and failed.
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl StepError {
+    /// The step's process could not be started, or waited for, because of
+    /// `source`. Other I/O errors are plain failures.
+    pub fn spawn(source: std::io::Error) -> StepError {
+        StepError::Spawn {
+            message: source.to_string(),
+            source,
+        }
+    }
+
+    /// The same failure, described by `message` instead.
+    pub fn with_message(self, message: String) -> StepError {
+        match self {
+            StepError::Spawn { source, .. } => StepError::Spawn { message, source },
+            StepError::Failed(_) => StepError::Failed(message),
+        }
+    }
+}
+
+impl From<String> for StepError {
+    fn from(message: String) -> Self {
+        StepError::Failed(message)
+    }
+}
+
+/// Everything wrong with the params and secrets a script was given.
+#[derive(Debug, Default, Clone, PartialEq, Error)]
+pub struct InputErrors {
+    /// The params the script declares, in order.
+    pub declared: Vec<String>,
+    /// How many param values were given.
+    pub given: usize,
+    /// The secrets neither supplied nor set in their environment variable.
+    pub missing_secrets: Vec<Secret>,
+}
+
+impl InputErrors {
+    /// The declared params no value was given for.
+    pub fn missing_params(&self) -> &[String] {
+        self.declared.get(self.given..).unwrap_or_default()
+    }
+
+    /// Whether the number of params given is wrong, as opposed to only
+    /// secrets missing.
+    pub fn params_wrong(&self) -> bool {
+        self.given != self.declared.len()
+    }
+
+    /// Whether there is nothing wrong.
+    pub fn is_empty(&self) -> bool {
+        !self.params_wrong() && self.missing_secrets.is_empty()
+    }
+}
+
+/// One line naming every problem, followed by a line on how to fix each.
+impl fmt::Display for InputErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut problems = Vec::new();
+        if self.given < self.declared.len() {
+            problems.push(format!(
+                "missing params: {}",
+                self.missing_params().join(", ")
+            ));
+        } else if self.params_wrong() {
+            problems.push(format!(
+                "too many params: expected {}, got {}",
+                self.declared.len(),
+                self.given
+            ));
+        }
+        if !self.missing_secrets.is_empty() {
+            let secrets: Vec<String> = self
+                .missing_secrets
+                .iter()
+                .map(|secret| format!("{} (env {})", secret.name, secret.env_var))
+                .collect();
+            problems.push(format!("missing secrets: {}", secrets.join(", ")));
+        }
+        write!(f, "{}", problems.join("; "))?;
+
+        if self.params_wrong() {
+            let usage: Vec<String> = self.declared.iter().map(|p| format!("<{p}>")).collect();
+            write!(
+                f,
+                "\n  pass the params in order: zirv <script> {}",
+                usage.join(" ")
+            )?;
+        }
+        for secret in &self.missing_secrets {
+            if cfg!(windows) {
+                write!(
+                    f,
+                    "\n  set the secret: $env:{} = \"<value>\"",
+                    secret.env_var
+                )?;
+            } else {
+                write!(f, "\n  set the secret: export {}=<value>", secret.env_var)?;
+            }
+        }
+        Ok(())
+    }
+}
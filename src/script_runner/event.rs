@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::{
+    output::Stream,
+    report::{SkipReason, StepStatus},
+};
+
+/// Something that happened during a run, sent to
+/// [`RunOptions::events`](super::run_options::RunOptions::events) as it
+/// happens.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum UiEvent {
+    /// Step `index` (counting from 1) of `group`, if it has one, is about
+    /// to run.
+    StepStarted {
+        index: usize,
+        label: String,
+        group: Option<String>,
+    },
+    /// A line zirv printed itself, such as `Executing command: make`.
+    Message {
+        step: Option<usize>,
+        stream: Stream,
+        text: String,
+    },
+    /// A line written by a command.
+    Output {
+        step: Option<usize>,
+        stream: Stream,
+        text: String,
+    },
+    /// Step `index` was skipped for `reason`; its `StepFinished` follows.
+    CommandSkipped { index: usize, reason: SkipReason },
+    /// Step `index` ended, or was skipped without a `StepStarted`.
+    StepFinished {
+        index: usize,
+        status: StepStatus,
+        duration: Duration,
+        /// Exit code of the last process the step spawned, if any.
+        exit_code: Option<i32>,
+    },
+}
+
+/// One line of `--output json`: a [`UiEvent`], or the start or end of the
+/// run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JsonEvent {
+    RunStart {
+        script: String,
+    },
+    CommandStart {
+        index: usize,
+        command: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
+    },
+    /// A line written by a command.
+    Log {
+        step: Option<usize>,
+        stream: Stream,
+        line: String,
+    },
+    /// A line zirv printed itself.
+    Message {
+        step: Option<usize>,
+        stream: Stream,
+        line: String,
+    },
+    /// Why step `index` was skipped: `reason` as data, and `message` as
+    /// zirv prints it.
+    CommandSkipped {
+        index: usize,
+        reason: SkipReason,
+        message: String,
+    },
+    CommandEnd {
+        index: usize,
+        status: StepStatus,
+        exit_code: Option<i32>,
+        duration_ms: u64,
+    },
+    RunEnd {
+        success: bool,
+        timed_out: bool,
+        error: Option<String>,
+        duration_ms: u64,
+    },
+}
+
+impl JsonEvent {
+    /// The event with `redact` applied to each of its texts.
+    pub fn redacted(self, redact: impl Fn(&str) -> String) -> Self {
+        match self {
+            JsonEvent::RunStart { script } => JsonEvent::RunStart {
+                script: redact(&script),
+            },
+            JsonEvent::CommandStart {
+                index,
+                command,
+                group,
+            } => JsonEvent::CommandStart {
+                index,
+                command: redact(&command),
+                group: group.as_deref().map(&redact),
+            },
+            JsonEvent::Log { step, stream, line } => JsonEvent::Log {
+                step,
+                stream,
+                line: redact(&line),
+            },
+            JsonEvent::Message { step, stream, line } => JsonEvent::Message {
+                step,
+                stream,
+                line: redact(&line),
+            },
+            JsonEvent::CommandSkipped {
+                index,
+                reason,
+                message,
+            } => JsonEvent::CommandSkipped {
+                index,
+                reason,
+                message: redact(&message),
+            },
+            JsonEvent::RunEnd {
+                success,
+                timed_out,
+                error,
+                duration_ms,
+            } => JsonEvent::RunEnd {
+                success,
+                timed_out,
+                error: error.as_deref().map(redact),
+                duration_ms,
+            },
+            event @ JsonEvent::CommandEnd { .. } => event,
+        }
+    }
+}
+
+impl From<UiEvent> for JsonEvent {
+    fn from(event: UiEvent) -> Self {
+        match event {
+            UiEvent::StepStarted {
+                index,
+                label,
+                group,
+            } => JsonEvent::CommandStart {
+                index,
+                command: label,
+                group,
+            },
+            UiEvent::Message { step, stream, text } => JsonEvent::Message {
+                step,
+                stream,
+                line: text,
+            },
+            UiEvent::Output { step, stream, text } => JsonEvent::Log {
+                step,
+                stream,
+                line: text,
+            },
+            UiEvent::CommandSkipped { index, reason } => JsonEvent::CommandSkipped {
+                index,
+                message: reason.to_string(),
+                reason,
+            },
+            UiEvent::StepFinished {
+                index,
+                status,
+                duration,
+                exit_code,
+            } => JsonEvent::CommandEnd {
+                index,
+                status,
+                exit_code,
+                duration_ms: duration.as_millis() as u64,
+            },
+        }
+    }
+}
@@ -1,48 +1,123 @@
 use std::process::Stdio;
 
+use hashbrown::HashMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tokio::process::Command as TokioCommand;
 
+use crate::script_runner::command::{check_quoted, quoting_for, substitute};
+use crate::script_runner::error::StepError;
 use crate::script_runner::options::Options;
+use crate::script_runner::output::{Stream, drain, tee};
+use crate::script_runner::process::{DRAIN_LIMIT, ProcessTree};
+use crate::script_runner::quoting::Quoting;
+use crate::script_runner::session::Session;
+use crate::script_runner::style::Style;
 
-#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default, JsonSchema)]
 pub struct FallbackCommand {
     pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<Options>,
 }
 
 impl FallbackCommand {
-    pub async fn invoke(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // Pick shell based on the OS
-        let mut shell = if cfg!(windows) {
-            let mut c = TokioCommand::new("powershell");
-            c.arg("-Command").arg(&self.command);
-            c
-        } else {
-            let mut c = TokioCommand::new("sh");
-            c.arg("-c").arg(&self.command);
-            c
-        };
-
-        println!("Executing command: {}", &self.command);
+    /// How `${name:q}` placeholders are quoted for the shell the fallback
+    /// runs in; see [`Command::quoting`](super::command::Command::quoting).
+    pub(crate) fn quoting(&self, shell: Option<&str>) -> Quoting {
+        quoting_for(self.options.as_ref(), shell)
+    }
+
+    /// The command with its placeholders substituted, like a step's.
+    pub(crate) fn substituted_command(
+        &self,
+        params: &HashMap<String, String>,
+        quoting: Quoting,
+    ) -> String {
+        substitute(&self.command, params, Some(quoting))
+    }
+
+    /// Fails if the value of a `${name:q}` placeholder cannot be quoted
+    /// for the shell; see [`Quoting::check`].
+    pub(crate) fn check_quoted(
+        &self,
+        params: &HashMap<String, String>,
+        quoting: Quoting,
+    ) -> Result<(), StepError> {
+        check_quoted(&self.command, params, quoting)
+    }
+
+    /// Runs `command`, the fallback's with its placeholders substituted,
+    /// with `env` added to its environment.
+    pub async fn invoke(
+        &self,
+        command: &str,
+        session: &Session,
+        step: Option<usize>,
+        env: Vec<(String, String)>,
+    ) -> Result<(), StepError> {
+        let mut shell = session.step_command(command, self.options.as_ref(), None, env);
+
+        let output = &session.output;
+        output.print_styled(step, Style::Bold, &format!("Executing command: {command}"));
         if let Some(description) = &self.description {
-            println!("Description: {description}");
+            output.print(step, &format!("Description: {description}"));
         }
 
         if let Some(options) = &self.options
-            && options.interactive
+            && options.interactive()
         {
             shell
                 .stdin(Stdio::inherit())
-                .stdout(Stdio::inherit())
+                .stdout(output.terminal_stream().inherited())
                 .stderr(Stdio::inherit());
+        } else if output.forwards() {
+            shell.stdout(Stdio::piped()).stderr(Stdio::piped());
         }
 
-        let status = shell.status().await?;
+        // Dropping a cancelled run (e.g. in watch mode) must not leave the
+        // fallback's processes behind.
+        let interactive = self.options.as_ref().is_some_and(|o| o.interactive());
+        let mut tree = ProcessTree::spawn(&mut shell, cfg!(unix) || !interactive, &session.stopper)
+            .map_err(StepError::spawn)?;
+        let child = tree.child();
+        let label = step.map_or_else(|| "fallback".to_string(), |i| i.to_string());
+        let readers: Vec<_> = [
+            child.stdout.take().map(|out| {
+                tokio::spawn(tee(
+                    out,
+                    output.clone(),
+                    step,
+                    label.clone(),
+                    Stream::Stdout,
+                ))
+            }),
+            child
+                .stderr
+                .take()
+                .map(|e| tokio::spawn(tee(e, output.clone(), step, label, Stream::Stderr))),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        // Kill what a failed fallback left behind before draining its
+        // output, which those processes may hold open; what a successful
+        // one left running only gets a moment to close it.
+        let status = tree
+            .wait(session.grace_period(self.options.as_ref()))
+            .await
+            .map_err(StepError::spawn)?;
+        if !status.success() {
+            tree.kill();
+        }
+        let deadline = tokio::time::Instant::now() + DRAIN_LIMIT;
+        for reader in readers {
+            drain(reader, deadline).await;
+        }
 
         if !status.success() {
-            return Err(format!("`{}` failed", &self.command).into());
+            return Err(format!("`{}` failed", output.redact(command)).into());
         }
 
         Ok(())
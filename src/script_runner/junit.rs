@@ -0,0 +1,167 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use super::report::{RunReport, StepStatus};
+
+impl RunReport {
+    /// Renders the report as a JUnit `<testsuite>` document, one test case per step.
+    pub fn to_junit_xml(&self) -> String {
+        let failures = self.count(StepStatus::Failed);
+        let skipped = self.count(StepStatus::Skipped);
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+            escape(&self.script),
+            self.steps.len(),
+            failures,
+            skipped,
+            self.duration.as_secs_f64()
+        );
+
+        for step in &self.steps {
+            let _ = write!(
+                xml,
+                "  <testcase name=\"{}: {}\" classname=\"{}\" time=\"{:.3}\"",
+                step.index,
+                escape(&step.command),
+                escape(&self.script),
+                step.duration.as_secs_f64()
+            );
+            match step.status {
+                StepStatus::Success => xml.push_str("/>\n"),
+                StepStatus::Skipped => match &step.skip_reason {
+                    Some(reason) => {
+                        let _ = write!(
+                            xml,
+                            ">\n    <skipped message=\"{}\"/>\n  </testcase>\n",
+                            escape(&reason.to_string())
+                        );
+                    }
+                    None => xml.push_str(">\n    <skipped/>\n  </testcase>\n"),
+                },
+                StepStatus::Failed => {
+                    let message = match step.exit_code {
+                        Some(code) => format!("exit code {code}"),
+                        None => "failed".to_string(),
+                    };
+                    let _ = write!(
+                        xml,
+                        ">\n    <failure message=\"{}\">{}</failure>\n  </testcase>\n",
+                        escape(&message),
+                        escape(step.stderr_tail.as_deref().unwrap_or_default())
+                    );
+                }
+            }
+        }
+
+        if let Some(error) = &self.error {
+            let _ = writeln!(xml, "  <system-err>{}</system-err>", escape(error));
+        }
+
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+
+    /// Writes the JUnit XML rendering of the report to `path`.
+    pub fn write_junit(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_junit_xml())?;
+        Ok(())
+    }
+
+    fn count(&self, status: StepStatus) -> usize {
+        self.steps.iter().filter(|s| s.status == status).count()
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c if c.is_control() && c != '\n' && c != '\t' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::script_runner::report::{SkipReason, StepReport};
+
+    fn step(index: usize, command: &str, status: StepStatus) -> StepReport {
+        StepReport {
+            status,
+            duration: Duration::from_millis(250),
+            ..StepReport::new(index, command.to_string())
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("a < b && \"c\" > 'd'"),
+            "a &lt; b &amp;&amp; &quot;c&quot; &gt; &apos;d&apos;"
+        );
+    }
+
+    #[test]
+    fn test_to_junit_xml_structure() {
+        let mut failed = step(3, "cargo test", StepStatus::Failed);
+        failed.exit_code = Some(101);
+        failed.stderr_tail = Some("test foo ... FAILED".to_string());
+
+        let report = RunReport {
+            script: "CI".to_string(),
+            success: false,
+            error: Some("Command 'cargo test' failed".to_string()),
+            duration: Duration::from_secs(1),
+            steps: vec![
+                step(1, "cargo fmt --check", StepStatus::Success),
+                step(2, "brew update", StepStatus::Skipped),
+                failed,
+            ],
+            timed_out: false,
+            termination: None,
+            on_error: Vec::new(),
+        };
+
+        let xml = report.to_junit_xml();
+
+        assert_eq!(xml.matches("<testsuite ").count(), 1);
+        assert_eq!(xml.matches("</testsuite>").count(), 1);
+        assert_eq!(xml.matches("<testcase ").count(), 3);
+        assert_eq!(xml.matches("<skipped/>").count(), 1);
+        assert_eq!(xml.matches("<failure ").count(), 1);
+        assert!(xml.contains("tests=\"3\" failures=\"1\" skipped=\"1\" time=\"1.000\""));
+        assert!(xml.contains("<failure message=\"exit code 101\">test foo ... FAILED</failure>"));
+        assert!(xml.contains("name=\"1: cargo fmt --check\" classname=\"CI\" time=\"0.250\""));
+    }
+
+    #[test]
+    fn test_skip_reason_is_the_skipped_message() {
+        let mut skipped = step(1, "deploy", StepStatus::Skipped);
+        skipped.skip(SkipReason::Condition {
+            when: "$env == \"prod\"".to_string(),
+        });
+        let report = RunReport {
+            steps: vec![skipped],
+            ..RunReport::new("Release")
+        };
+
+        assert!(
+            report
+                .to_junit_xml()
+                .contains("<skipped message=\"`$env == &quot;prod&quot;` is false\"/>")
+        );
+    }
+}
@@ -1,71 +1,582 @@
+use std::borrow::Cow;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub use bench::execute_repeated;
+pub use command::Command;
+pub use error::{InputErrors, RunError, StepError};
+pub use event::{JsonEvent, UiEvent};
 use hashbrown::HashMap;
-use script::Script;
+pub use options::Options;
+use output::MIN_MASK_CHARS;
+pub use output::Stream;
+use process::{DRAIN_LIMIT, Stopper};
+use report::StepReport;
+pub use report::{RunReport, StepStatus};
+pub use run_options::RunOptions;
+use run_options::Verbosity;
+pub use script::Script;
+use session::Session;
+use style::Style;
+pub use watch::{WatchOptions, execute_watching};
+
+use crate::utils::Vars;
+
+/// Context variable holding the directory containing the project's `.zirv`.
+pub const PROJECT_ROOT_VAR: &str = "zirv.project_root";
+
+/// Variables zirv itself may provide to `${...}` placeholders.
+pub const BUILTIN_VARIABLES: &[&str] = &[PROJECT_ROOT_VAR];
+
+/// Context variable holding the label of the step that failed the run.
+pub const FAILED_STEP_VAR: &str = "zirv.failed_step";
 
-mod command;
-mod command_types;
-mod fallback_command;
-mod operating_system;
-mod options;
+/// Context variable holding the command line of the step that failed the
+/// run, with its placeholders substituted.
+pub const FAILED_COMMAND_VAR: &str = "zirv.failed_command";
+
+/// Context variable holding the error that failed the run.
+pub const ERROR_VAR: &str = "zirv.error";
+
+/// Variables zirv provides to a script's `on_error` commands only.
+pub const FAILURE_VARIABLES: &[&str] = &[FAILED_STEP_VAR, FAILED_COMMAND_VAR, ERROR_VAR];
+
+/// Environment variable telling a `zirv` run started by a step with
+/// `export` where to save its context, like `--save-context`.
+pub const SAVE_CONTEXT_ENV: &str = "ZIRV_SAVE_CONTEXT";
+
+pub(crate) mod bench;
+pub mod cache;
+pub mod command;
+pub mod command_types;
+pub mod compat;
+pub mod condition;
+pub mod container;
+mod error;
+mod event;
+pub mod fallback_command;
+mod junit;
+pub mod operating_system;
+pub mod options;
+mod output;
+pub mod plan;
+pub mod priority;
+pub mod process;
+mod pty;
+pub mod quoting;
+pub mod report;
+pub mod run_options;
+pub(crate) mod schema;
 pub mod script;
-mod secret;
+pub mod secret;
+pub mod selection;
+pub mod session;
+pub mod style;
+mod tags;
+mod watch;
+pub mod webhook;
+pub mod wsl;
 
-pub async fn execute(script: &Script, params: &[String]) -> Result<(), String> {
-    // Build the context from script parameters and secrets
-    let mut context = build_context(script, params)?;
+/// Runs `script` with `params`, one per name in its `params`, and returns
+/// the report of the run. Its output goes to the terminal as configured by
+/// `options`, and to [`RunOptions::events`] if set.
+///
+/// ```
+/// use zirv::{script_runner::{RunOptions, StepStatus, execute}, utils::parse_script_content};
+///
+/// let script = parse_script_content(
+///     "name: Greet\nparams: [who]\ncommands:\n  - command: echo hello ${who}\n",
+///     "yaml",
+/// )?;
+/// let mut options = RunOptions::default();
+/// options.no_summary = true;
+/// let runtime = tokio::runtime::Runtime::new()?;
+/// let report = runtime.block_on(execute(&script, &["world".to_string()], &options))?;
+/// assert_eq!(report.steps[0].status, StepStatus::Success);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub async fn execute(
+    script: &Script,
+    params: &[String],
+    options: &RunOptions,
+) -> Result<RunReport, RunError> {
+    let (report, result) = run_with_report(script, params, options).await;
+    result.map(|()| report)
+}
+
+/// Runs the script and returns the collected report along with the result,
+/// so that the report of a failed run is not lost.
+pub async fn run_with_report(
+    script: &Script,
+    params: &[String],
+    options: &RunOptions,
+) -> (RunReport, Result<(), RunError>) {
+    let session = Session::new(script, options);
+    let mut report = RunReport::new(&script.name);
+    let start = Instant::now();
+    session.output.print_json(JsonEvent::RunStart {
+        script: script.name.clone(),
+    });
+
+    let mut result = run(script, params, options, &session, &mut report).await;
+    session.output.enter_group(None);
+    if session.stopper.is_stopping() && !report.timed_out {
+        // Stopped from outside: the report still says how the steps ended.
+        report.termination = session.stopper.termination();
+        result = Err(RunError::Stopped {
+            script: script.name.clone(),
+        });
+    }
+
+    report.duration = start.elapsed();
+    report.success = result.is_ok();
+    let output = &session.output;
+    report.error = result
+        .as_ref()
+        .err()
+        .map(|e| output.redact(&e.to_string()).into_owned());
+
+    if let Err(e) = &result {
+        output.log(None, &e.to_string());
+    }
+
+    if !options.no_summary && !output.is_json() {
+        let mut summary = Vec::new();
+        let _ = report.write_summary(&mut summary);
+        let mut colored = Vec::new();
+        let _ = report.write_colored_summary(&mut colored, output.colors());
+        output.print_colored(
+            None,
+            String::from_utf8_lossy(&summary).trim_end(),
+            String::from_utf8_lossy(&colored).trim_end(),
+        );
+        if !output.shows(Verbosity::Normal) {
+            output.status(&report.status_line(false), &report.status_line(true));
+        }
+    }
+
+    if let Some(path) = &options.report
+        && let Err(e) = report.write_json(path)
+    {
+        output.eprint_styled(
+            None,
+            Style::Red,
+            &format!("Failed to write run report to {}: {e}", path.display()),
+        );
+    }
+
+    if let Some(path) = &options.junit
+        && let Err(e) = report.write_junit(path)
+    {
+        output.eprint_styled(
+            None,
+            Style::Red,
+            &format!("Failed to write JUnit report to {}: {e}", path.display()),
+        );
+    }
+
+    if let Some(url) = options.webhook.as_ref().or(script.notify_webhook.as_ref()) {
+        let payload = webhook::WebhookPayload::new(
+            &report,
+            |text| output.redact(text).into_owned(),
+            webhook::host_name(),
+        );
+        let url = url.clone();
+        let sent = tokio::task::spawn_blocking(move || webhook::notify(&url, &payload))
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()));
+        if let Err(e) = sent {
+            output.eprint_styled(None, Style::Red, &format!("Failed to notify webhook: {e}"));
+        }
+    }
+
+    output.print_json(JsonEvent::RunEnd {
+        success: report.success,
+        timed_out: report.timed_out,
+        error: report.error.clone(),
+        duration_ms: report.duration.as_millis() as u64,
+    });
+    (report, result)
+}
+
+async fn run(
+    script: &Script,
+    params: &[String],
+    options: &RunOptions,
+    session: &Session,
+    report: &mut RunReport,
+) -> Result<(), RunError> {
+    let reserved = reserved_captures(script);
+    if !reserved.is_empty() {
+        return Err(RunError::Invalid {
+            script: script.name.clone(),
+            problems: reserved,
+        });
+    }
+    let warnings = options
+        .warnings
+        .iter()
+        .cloned()
+        .chain(shadowed_vars(script, &options.vars, "vars.yaml"))
+        .chain(shadowed_vars(
+            script,
+            &options.loaded_context,
+            "the loaded context",
+        ))
+        .chain(shadowing_captures(script));
+    for warning in warnings {
+        session
+            .output
+            .eprint_styled(None, Style::Yellow, &format!("Warning: {warning}"));
+    }
+    let mut context = initial_context(script, params, options)?;
+    for name in &session.secret_names {
+        if let Some(value) = context.get(name)
+            && !session.output.mask(value)
+            && !value.trim().is_empty()
+        {
+            session.output.eprint_styled(
+                None,
+                Style::Yellow,
+                &format!(
+                    "Warning: secret '{name}' is shorter than {MIN_MASK_CHARS} characters and will not be masked"
+                ),
+            );
+        }
+    }
 
-    // Execution loop
-    script.run(&mut context).await?;
+    if session.container_runtime.is_none() && container::uses_containers(script) {
+        return Err(RunError::Step {
+            script: script.name.clone(),
+            exit_code: None,
+            error: StepError::Spawn {
+                message: format!(
+                    "Script '{}' runs steps in containers, but neither {} was found on PATH",
+                    script.name,
+                    container::CONTAINER_RUNTIMES.join(" nor ")
+                ),
+                source: std::io::ErrorKind::NotFound.into(),
+            },
+        });
+    }
 
-    // Placeholder for the main execution logic
-    // This function will orchestrate the execution of commands, handling files, etc.
+    run_steps(script, &mut context, session, report).await?;
+    if let Some(path) = &options.save_context
+        && let Err(e) = save_context(path, &context, session)
+    {
+        session.output.eprint_styled(
+            None,
+            Style::Red,
+            &format!("Failed to save context to {}: {e}", path.display()),
+        );
+    }
     Ok(())
 }
 
-fn build_context(
+/// Runs the steps of `script`, stopping them once its timeout passes.
+async fn run_steps(
     script: &Script,
-    cli_params: &[String],
-) -> Result<HashMap<String, String>, String> {
-    // Build initial context from params + secrets
-    let context: HashMap<String, String> = {
-        // params
-        let params = if let Some(names) = &script.params {
-            if names.len() != cli_params.len() {
-                return Err(format!(
-                    "Expected {} parameters, got {}",
-                    names.len(),
-                    cli_params.len()
-                ));
-            }
+    context: &mut HashMap<String, String>,
+    session: &Session,
+    report: &mut RunReport,
+) -> Result<(), RunError> {
+    let Some(limit) = script.timeout_ms.map(Duration::from_millis) else {
+        return script.run(context, session, report).await;
+    };
+    let start = Instant::now();
+    let running;
+    {
+        let steps = script.run(context, session, report);
+        tokio::pin!(steps);
+        tokio::select! {
+            result = &mut steps => return result,
+            _ = tokio::time::sleep(limit) => {}
+        }
+        running = session.running_step();
+        // Let the running step exit within its grace period, but do not
+        // wait for what it started outside its process tree to close its
+        // output; dropping the steps kills whatever is still running.
+        session.stopper.stop();
+        let grace = running
+            .checked_sub(1)
+            .and_then(|index| script.commands.get(index))
+            .and_then(|step| {
+                step.commands()
+                    .iter()
+                    .map(|cmd| session.grace_period(cmd.options.as_ref()))
+                    .max()
+            })
+            .unwrap_or_else(|| session.grace_period(None));
+        let _ = tokio::time::timeout(grace + DRAIN_LIMIT, steps).await;
+    }
+    let error = timed_out(script, session, report, running, limit, start.elapsed());
+    // The handlers get the grace period too, under a stopper of their own
+    // as the run's is stopping; what they leave running is then killed.
+    if let RunError::TimedOut { step, .. } = &error
+        && let Some(failed) = script.commands.get(step - 1)
+    {
+        let handlers = Session {
+            stopper: Stopper::default(),
+            ..session.clone()
+        };
+        let message = error.to_string();
+        let handled = script.handle_error(*step, failed, &message, context, &handlers, report);
+        let _ = tokio::time::timeout(session.grace_period(None), handled).await;
+    }
+    Err(error)
+}
 
-            names
+/// Records `running`, the step that was running when `script` hit its
+/// timeout, as failed unless it recorded how it ended while stopping, and
+/// returns the error ending the run.
+fn timed_out(
+    script: &Script,
+    session: &Session,
+    report: &mut RunReport,
+    running: usize,
+    limit: Duration,
+    elapsed: Duration,
+) -> RunError {
+    report.timed_out = true;
+    report.termination = session.stopper.termination();
+    // A run that had not started its first step yet stopped at it.
+    let index = running.max(1);
+    if !report.steps.iter().any(|step| step.index == index)
+        && let Some(step) = script.commands.get(index - 1)
+    {
+        let finished: Duration = report.steps.iter().map(|step| step.duration).sum();
+        let mut step_report = StepReport::new(index, step.label());
+        step_report.group = step.group().map(String::from);
+        step_report.status = StepStatus::Failed;
+        step_report.duration = elapsed.saturating_sub(finished);
+        session.output.send(UiEvent::StepFinished {
+            index,
+            status: step_report.status,
+            duration: step_report.duration,
+            exit_code: None,
+        });
+        report.steps.push(step_report);
+    }
+    RunError::TimedOut {
+        script: script.name.clone(),
+        after: limit,
+        step: index,
+    }
+}
+
+/// What each param or secret of `script` named like one of `vars` hides,
+/// e.g. `param 'env' overrides the variable of the same name from vars.yaml`
+/// when `origin` is `vars.yaml`.
+pub(crate) fn shadowed_vars(script: &Script, vars: &Vars, origin: &str) -> Vec<String> {
+    let params = script.params.iter().flatten().map(|name| ("param", name));
+    let secrets = script
+        .secrets
+        .iter()
+        .flatten()
+        .map(|secret| ("secret", &secret.name));
+    params
+        .chain(secrets)
+        .filter(|(_, name)| vars.contains_key(*name))
+        .map(|(kind, name)| {
+            format!("{kind} '{name}' overrides the variable of the same name from {origin}")
+        })
+        .collect()
+}
+
+/// Whether `name` is set by zirv itself and may not be captured into:
+/// `cwd` or anything under `zirv.`.
+pub fn is_reserved_variable(name: &str) -> bool {
+    name == "cwd" || name.starts_with("zirv.")
+}
+
+/// Every command of `script`, with where it is, e.g. `step 2` or
+/// `on_error 1`.
+fn located_commands(script: &Script) -> impl Iterator<Item = (String, &Command)> {
+    let steps = script.commands.iter().enumerate().flat_map(|(i, step)| {
+        step.commands()
+            .iter()
+            .map(move |command| (format!("step {}", i + 1), command))
+    });
+    let handlers = script
+        .on_error
+        .iter()
+        .enumerate()
+        .map(|(i, command)| (format!("on_error {}", i + 1), command));
+    steps.chain(handlers)
+}
+
+/// A problem for every capture or `export` of `script` into a reserved
+/// name, e.g. `step 2: capture 'cwd' uses a reserved name ...`.
+pub(crate) fn reserved_captures(script: &Script) -> Vec<String> {
+    located_commands(script)
+        .flat_map(|(location, command)| {
+            let captures = command.capture.iter().map(|name| ("capture", name));
+            let exports = command
+                .export
                 .iter()
-                .cloned()
-                .zip(cli_params.iter().cloned())
-                .collect()
-        } else {
-            HashMap::new()
-        };
-
-        // secrets
-        let mut map = params;
-        if let Some(secret_defs) = &script.secrets {
-            for sd in secret_defs {
-                let val = std::env::var(&sd.env_var).map_err(|_| {
-                    format!("Secret '{}' not found in env '{}'", sd.name, sd.env_var)
-                })?;
-                map.insert(sd.name.clone(), val);
+                .flat_map(|export| export.values())
+                .map(|name| ("export target", name));
+            captures
+                .chain(exports)
+                .filter(|(_, name)| is_reserved_variable(name))
+                .map(move |(kind, name)| {
+                    format!(
+                        "{location}: {kind} '{name}' uses a reserved name; cwd and zirv.* are set by zirv"
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// What each capture of `script` named like a param or secret overwrites,
+/// e.g. `step 2: capture 'env' overwrites the param of the same name`.
+pub(crate) fn shadowing_captures(script: &Script) -> Vec<String> {
+    located_commands(script)
+        .filter_map(|(location, command)| {
+            let name = command.capture.as_ref()?;
+            let kind = if script.params.iter().flatten().any(|p| p == name) {
+                "param"
+            } else if script.secrets.iter().flatten().any(|s| s.name == *name) {
+                "secret"
+            } else {
+                return None;
+            };
+            Some(format!(
+                "{location}: capture '{name}' overwrites the {kind} of the same name"
+            ))
+        })
+        .collect()
+}
+
+/// Every placeholder of a param of `script` standing alone in a command
+/// run by a shell, e.g. `step 2: '${message}' is not quoted ...`, and every
+/// unquoted failure variable in `on_error`. A param can be any text, which
+/// the shell splits at spaces and may run as shell syntax; an error message
+/// often holds quotes, so even a quoted `'${zirv.error}'` is not safe.
+pub(crate) fn unquoted_placeholders(script: &Script) -> Vec<String> {
+    let params = script.params.as_deref().unwrap_or_default();
+    let failures = script.on_error.iter().enumerate().flat_map(|(i, handler)| {
+        command::pieces(&handler.command)
+            .into_iter()
+            .filter_map(move |piece| match piece {
+                command::Piece::Placeholder(name) if FAILURE_VARIABLES.contains(&name) => {
+                    Some(format!(
+                        "on_error {}: '${{{name}}}' is not quoted; write '${{{name}:q}}' as it holds text of the failure",
+                        i + 1
+                    ))
+                }
+                _ => None,
+            })
+    });
+    located_commands(script)
+        .filter(|(_, command)| !command.command.trim_start().starts_with("cd "))
+        .flat_map(|(location, command)| {
+            command::bare_placeholders(&command.command)
+                .into_iter()
+                .filter(|name| params.iter().any(|param| param == name))
+                .map(move |name| {
+                    format!(
+                        "{location}: '${{{name}}}' is not quoted; write '${{{name}:q}}' if its value may hold spaces or shell syntax"
+                    )
+                })
+        })
+        .chain(failures)
+        .collect()
+}
+
+/// The context a run starts with: variables, params, secrets and the
+/// variables zirv provides itself, with `cwd` set to the project root if
+/// there is one.
+fn initial_context(
+    script: &Script,
+    params: &[String],
+    options: &RunOptions,
+) -> Result<HashMap<String, String>, RunError> {
+    // A loaded context wins over vars.yaml
+    let mut vars = options.vars.clone();
+    vars.extend(options.loaded_context.clone());
+    // Build the context from script parameters and secrets
+    let mut context = build_context(script, params, &vars, &options.secrets)?;
+    if let Some(root) = &options.project_root {
+        let root = root.to_string_lossy().to_string();
+        context.insert(PROJECT_ROOT_VAR.to_string(), root.clone());
+        context.insert("cwd".to_string(), root);
+    }
+    Ok(context)
+}
+
+/// Writes the variables of `context` worth reusing in another run to `path`
+/// as YAML: everything but secrets, values containing a masked value, and
+/// the variables zirv sets itself.
+fn save_context(
+    path: &Path,
+    context: &HashMap<String, String>,
+    session: &Session,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let saved: Vars = context
+        .iter()
+        .filter(|(name, value)| {
+            name.as_str() != "cwd"
+                && !name.starts_with("zirv.")
+                && !session.is_secret(name)
+                && !matches!(session.output.redact(value), Cow::Owned(_))
+        })
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    std::fs::write(path, serde_yaml::to_string(&saved)?)?;
+    Ok(())
+}
+
+fn build_context(
+    script: &Script,
+    cli_params: &[String],
+    vars: &Vars,
+    secrets: &Vars,
+) -> Result<HashMap<String, String>, RunError> {
+    // Build initial context from params + secrets, collecting every
+    // missing one before failing
+    // A script without `params` ignores any it is given
+    let mut errors = InputErrors {
+        declared: script.params.clone().unwrap_or_default(),
+        given: script.params.as_ref().map_or(0, |_| cli_params.len()),
+        ..Default::default()
+    };
+    let mut context: HashMap<String, String> = errors
+        .declared
+        .iter()
+        .cloned()
+        .zip(cli_params.iter().cloned())
+        .collect();
+    for sd in script.secrets.iter().flatten() {
+        let value = match secrets.get(&sd.name) {
+            Some(value) => Some(value.clone()),
+            None => std::env::var(&sd.env_var).ok(),
+        };
+        match value {
+            Some(value) => {
+                context.insert(sd.name.clone(), value);
             }
+            None => errors.missing_secrets.push(sd.clone()),
         }
-        map
-    };
+    }
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
 
+    // vars.yaml values fill in whatever params and secrets leave unset
+    for (name, value) in vars {
+        context.entry(name.clone()).or_insert_with(|| value.clone());
+    }
     Ok(context)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::script_runner::{command::Command, command_types::CommandTypes};
+    use crate::script_runner::{
+        command::Command, command_types::CommandTypes, process::Termination, report::StepStatus,
+    };
 
     #[tokio::test]
     async fn test_build_context() {
@@ -82,15 +593,22 @@ mod tests {
                 capture: None,
                 description: Some("Prints Hello World".to_string()),
                 options: None,
+                ..Default::default()
             })],
+            ..Default::default()
         };
 
         unsafe {
             std::env::set_var("COMMIT_PASSWORD", "secret123");
         }
 
-        let context = build_context(&script, &["value1".to_string(), "value2".to_string()])
-            .expect("Failed to build context");
+        let context = build_context(
+            &script,
+            &["value1".to_string(), "value2".to_string()],
+            &Vars::new(),
+            &Vars::new(),
+        )
+        .expect("Failed to build context");
 
         assert_eq!(context.get("param1"), Some(&"value1".to_string()));
         assert_eq!(context.get("param2"), Some(&"value2".to_string()));
@@ -99,4 +617,973 @@ mod tests {
             Some(&"secret123".to_string())
         );
     }
+
+    #[test]
+    fn test_build_context_reports_everything_missing() {
+        let secret = |name: &str| secret::Secret {
+            name: name.to_string(),
+            env_var: format!("ZIRV_TEST_UNSET_{}", name.to_uppercase()),
+        };
+        let script = Script {
+            name: "Deploy".to_string(),
+            params: Some(vec![
+                "region".to_string(),
+                "env".to_string(),
+                "version".to_string(),
+            ]),
+            secrets: Some(vec![
+                secret("deploy_token"),
+                secret("supplied"),
+                secret("registry_pass"),
+            ]),
+            ..Default::default()
+        };
+        let supplied = Vars::from([("supplied".to_string(), "s3cr3t".to_string())]);
+
+        let error = build_context(&script, &["eu".to_string()], &Vars::new(), &supplied)
+            .unwrap_err()
+            .to_string();
+        let export = |var: &str| {
+            if cfg!(windows) {
+                format!("$env:{var} = \"<value>\"")
+            } else {
+                format!("export {var}=<value>")
+            }
+        };
+        assert_eq!(
+            error,
+            format!(
+                "missing params: env, version; missing secrets: deploy_token (env ZIRV_TEST_UNSET_DEPLOY_TOKEN), registry_pass (env ZIRV_TEST_UNSET_REGISTRY_PASS)\n  \
+                 pass the params in order: zirv <script> <region> <env> <version>\n  \
+                 set the secret: {}\n  \
+                 set the secret: {}",
+                export("ZIRV_TEST_UNSET_DEPLOY_TOKEN"),
+                export("ZIRV_TEST_UNSET_REGISTRY_PASS")
+            )
+        );
+
+        let params: Vec<String> = ["eu", "prod", "v2", "extra"].map(String::from).to_vec();
+        let error = build_context(&script, &params, &Vars::new(), &supplied)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("too many params: expected 3, got 4; missing secrets: deploy_token"),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_container_steps_need_a_runtime() {
+        let script = Script {
+            name: "Pinned".to_string(),
+            commands: vec![CommandTypes::Command(Command {
+                command: "cargo build".to_string(),
+                options: Some(options::Options {
+                    container: Some("rust:1.80".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+        let options = RunOptions::default();
+        let session = Session {
+            container_runtime: None,
+            ..Session::new(&script, &options)
+        };
+        let error = run(
+            &script,
+            &[],
+            &options,
+            &session,
+            &mut RunReport::new("Pinned"),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Error executing command in script 'Pinned': Script 'Pinned' runs steps in containers, but neither docker nor podman was found on PATH"
+        );
+        assert!(matches!(
+            error,
+            RunError::Step {
+                error: StepError::Spawn { .. },
+                ..
+            }
+        ));
+    }
+
+    fn command(command: &str) -> CommandTypes {
+        CommandTypes::Command(Command {
+            command: command.to_string(),
+            capture: None,
+            description: None,
+            options: None,
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_execute_writes_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+
+        let script = Script {
+            name: "Report Script".to_string(),
+            description: None,
+            params: None,
+            secrets: None,
+            commands: vec![
+                CommandTypes::Command(Command {
+                    command: "echo captured".to_string(),
+                    capture: Some("value".to_string()),
+                    description: None,
+                    options: None,
+                    ..Default::default()
+                }),
+                command("echo done"),
+            ],
+            ..Default::default()
+        };
+        let options = RunOptions {
+            no_summary: true,
+            report: Some(path.clone()),
+            ..Default::default()
+        };
+
+        execute(&script, &[], &options).await.unwrap();
+
+        let report: RunReport =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(report.script, "Report Script");
+        assert!(report.success);
+        assert_eq!(report.steps.len(), 2);
+        assert_eq!(report.steps[0].capture.as_deref(), Some("value"));
+        assert_eq!(report.steps[0].exit_code, Some(0));
+        assert_eq!(report.steps[1].command, "echo done");
+    }
+
+    #[tokio::test]
+    async fn test_execute_writes_report_on_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+
+        let script = Script {
+            name: "Failing Script".to_string(),
+            description: None,
+            params: None,
+            secrets: None,
+            commands: vec![command("exit 1"), command("echo unreachable")],
+            ..Default::default()
+        };
+        let options = RunOptions {
+            no_summary: true,
+            report: Some(path.clone()),
+            ..Default::default()
+        };
+
+        assert!(execute(&script, &[], &options).await.is_err());
+
+        let report: RunReport =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(!report.success);
+        assert!(report.error.is_some());
+        assert_eq!(report.steps.len(), 1);
+        assert_eq!(report.steps[0].status, StepStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_execute_times_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+
+        let script = Script {
+            name: "Slow Script".to_string(),
+            commands: vec![
+                command("echo first"),
+                command("sleep 5"),
+                command("echo unreachable"),
+            ],
+            timeout_ms: Some(300),
+            ..Default::default()
+        };
+        let options = RunOptions {
+            no_summary: true,
+            report: Some(path.clone()),
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+        let error = execute(&script, &[], &options).await.unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(3));
+        assert!(
+            matches!(error, RunError::TimedOut { step: 2, .. }),
+            "{error}"
+        );
+        assert_eq!(
+            error.to_string(),
+            "Script 'Slow Script' timed out after 0.3s at step 2"
+        );
+
+        let report: RunReport =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(report.timed_out);
+        assert_eq!(report.steps.len(), 2);
+        assert_eq!(report.steps[0].status, StepStatus::Success);
+        assert_eq!(report.steps[1].command, "sleep 5");
+        assert_eq!(report.steps[1].status, StepStatus::Failed);
+        assert_eq!(report.termination, Some(Termination::Graceful));
+
+        let mut summary = Vec::new();
+        report.write_summary(&mut summary).unwrap();
+        assert!(String::from_utf8(summary).unwrap().contains("(timed out)"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cached_step_runs_only_when_inputs_change() {
+        let project = tempfile::tempdir().unwrap();
+        let input = project.path().join("package.json");
+        std::fs::write(&input, "{}").unwrap();
+        let script = crate::utils::parse_script_content(
+            r#"
+name: Install
+commands:
+  - command: echo run >> runs.txt && echo v1 && test ! -f fail
+    capture: version
+    cache:
+      inputs: [package.json, "src/**/*.js"]
+  - command: echo "${version}" > version.txt
+"#,
+            "yaml",
+        )
+        .unwrap();
+        let run = |no_cache: bool| {
+            let options = RunOptions {
+                no_summary: true,
+                project_root: Some(project.path().to_path_buf()),
+                no_cache,
+                ..Default::default()
+            };
+            let script = script.clone();
+            async move { run_with_report(&script, &[], &options).await }
+        };
+        let runs = || {
+            std::fs::read_to_string(project.path().join("runs.txt"))
+                .unwrap()
+                .lines()
+                .count()
+        };
+
+        let (report, _) = run(false).await;
+        assert_eq!(report.steps[0].status, StepStatus::Success);
+        let (report, result) = run(false).await;
+        assert!(result.is_ok());
+        assert_eq!(report.steps[0].status, StepStatus::Skipped);
+        assert_eq!(runs(), 1);
+        let version = std::fs::read_to_string(project.path().join("version.txt")).unwrap();
+        assert_eq!(version.trim(), "v1");
+
+        run(true).await.1.unwrap();
+        assert_eq!(runs(), 2);
+
+        std::fs::create_dir_all(project.path().join("src/lib")).unwrap();
+        std::fs::write(project.path().join("src/lib/index.js"), "").unwrap();
+        run(false).await.1.unwrap();
+        assert_eq!(runs(), 3);
+        run(false).await.1.unwrap();
+        assert_eq!(runs(), 3);
+
+        // A failure leaves the entry of the last success alone.
+        std::fs::write(&input, "{\"name\": \"shop\"}").unwrap();
+        std::fs::write(project.path().join("fail"), "").unwrap();
+        let (_, result) = run(false).await;
+        assert!(result.is_err());
+        assert_eq!(runs(), 4);
+        let (_, result) = run(false).await;
+        assert!(result.is_err());
+        assert_eq!(runs(), 5);
+
+        let entries = std::fs::read_dir(project.path().join(".zirv").join(".cache")).unwrap();
+        assert_eq!(entries.count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_names_the_step_it_hit() {
+        // The first step's process is done, but its delay is still running.
+        let script = Script {
+            name: "Paced".to_string(),
+            commands: vec![
+                CommandTypes::Command(Command {
+                    command: "echo first".to_string(),
+                    options: Some(Options {
+                        delay_ms: Some(1000),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                command("echo second"),
+            ],
+            timeout_ms: Some(200),
+            ..Default::default()
+        };
+        let options = RunOptions {
+            no_summary: true,
+            ..Default::default()
+        };
+
+        let (report, result) = run_with_report(&script, &[], &options).await;
+        assert!(
+            matches!(result, Err(RunError::TimedOut { step: 1, .. })),
+            "{result:?}"
+        );
+        assert_eq!(report.steps.len(), 1);
+        assert_eq!(report.steps[0].status, StepStatus::Success);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timeout_terminates_before_killing() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("marker");
+        let run = |grace_period_ms: u64, trap: &str| {
+            let script = Script {
+                name: "Migrate".to_string(),
+                commands: vec![command(&format!(
+                    "trap '{trap}' TERM; echo started > {marker}; sleep 5 & wait; sleep 5",
+                    marker = marker.display()
+                ))],
+                timeout_ms: Some(300),
+                defaults: Some(Options {
+                    grace_period_ms: Some(grace_period_ms),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }
+            .with_defaults();
+            async move {
+                let options = RunOptions {
+                    no_summary: true,
+                    ..Default::default()
+                };
+                run_with_report(&script, &[], &options).await
+            }
+        };
+
+        let trap = format!("echo cleaned up > {}; exit 1", marker.display());
+        let (report, result) = run(5000, &trap).await;
+        assert!(matches!(result, Err(RunError::TimedOut { step: 1, .. })));
+        assert_eq!(report.termination, Some(Termination::Graceful));
+        assert_eq!(report.steps.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(&marker).unwrap().trim(),
+            "cleaned up"
+        );
+
+        let start = Instant::now();
+        let (report, result) = run(200, "").await;
+        assert!(matches!(result, Err(RunError::TimedOut { step: 1, .. })));
+        assert_eq!(report.termination, Some(Termination::Forced));
+        assert_eq!(std::fs::read_to_string(&marker).unwrap().trim(), "started");
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+
+    /// What a timed out step started outside its process group, and which
+    /// holds its output open, does not keep the run waiting.
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_timeout_does_not_wait_for_escaped_processes() {
+        let script = Script {
+            name: "Escape".to_string(),
+            commands: vec![command("setsid sleep 4 & sleep 4")],
+            timeout_ms: Some(200),
+            ..Default::default()
+        };
+        let options = RunOptions {
+            no_summary: true,
+            grace_period_ms: Some(200),
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+        let (report, result) = run_with_report(&script, &[], &options).await;
+        assert!(matches!(result, Err(RunError::TimedOut { step: 1, .. })));
+        assert_eq!(report.steps.len(), 1);
+        assert_eq!(report.steps[0].status, StepStatus::Failed);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_on_error_runs_after_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let handler = |command: &str| Command {
+            command: command.to_string(),
+            ..Default::default()
+        };
+        let script = Script {
+            name: "Slow".to_string(),
+            commands: vec![command("sleep 5")],
+            on_error: vec![
+                handler("echo ${zirv.error:q} > error.txt"),
+                handler("sleep 5"),
+            ],
+            timeout_ms: Some(200),
+            ..Default::default()
+        };
+        let options = RunOptions {
+            no_summary: true,
+            project_root: Some(dir.path().to_path_buf()),
+            grace_period_ms: Some(300),
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+        let (report, result) = run_with_report(&script, &[], &options).await;
+        assert!(matches!(result, Err(RunError::TimedOut { step: 1, .. })));
+        let message = std::fs::read_to_string(dir.path().join("error.txt")).unwrap();
+        assert_eq!(message, "Script 'Slow' timed out after 0.2s at step 1\n");
+        // The second handler is stopped with the grace period.
+        assert_eq!(report.on_error.len(), 1);
+        assert_eq!(report.on_error[0].status, StepStatus::Success);
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn test_captures_into_reserved_names_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("ran.txt");
+        let script = Script {
+            name: "Hijack".to_string(),
+            params: Some(vec!["env".to_string()]),
+            commands: vec![
+                command(&format!("touch {}", marker.display())),
+                CommandTypes::Command(Command {
+                    command: "echo /tmp".to_string(),
+                    capture: Some("cwd".to_string()),
+                    ..Default::default()
+                }),
+                CommandTypes::Command(Command {
+                    command: "echo prod".to_string(),
+                    capture: Some("env".to_string()),
+                    ..Default::default()
+                }),
+            ],
+            ..Default::default()
+        };
+
+        let error = execute(&script, &["dev".to_string()], &RunOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, RunError::Invalid { .. }), "{error:?}");
+        assert!(
+            error
+                .to_string()
+                .contains("step 2: capture 'cwd' uses a reserved name"),
+            "{error}"
+        );
+        assert!(!marker.exists());
+        assert_eq!(
+            shadowing_captures(&script),
+            ["step 3: capture 'env' overwrites the param of the same name"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_context_is_saved_and_loaded_between_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let context_path = dir.path().join("context.yaml");
+        let out = dir.path().join("out.txt");
+
+        let prepare = crate::utils::parse_script_content(
+            r#"
+name: Prepare
+params:
+  - env
+secrets:
+  - name: token
+    env_var: ZIRV_TEST_UNSET_CONTEXT_TOKEN
+commands:
+  - command: printf 'build-%s' 42
+    capture: build_id
+  - command: printf 'Bearer %s' ${token}
+    capture: header
+  - command: printf session-7
+    capture: session
+    sensitive: true
+"#,
+            "yaml",
+        )
+        .unwrap();
+        let options = RunOptions {
+            secrets: Vars::from([("token".to_string(), "s3cr3t-value".to_string())]),
+            project_root: Some(dir.path().to_path_buf()),
+            save_context: Some(context_path.clone()),
+            no_summary: true,
+            ..Default::default()
+        };
+        execute(&prepare, &["staging".to_string()], &options)
+            .await
+            .unwrap();
+
+        let saved = crate::utils::load_vars(&context_path).unwrap();
+        assert_eq!(
+            saved,
+            Vars::from([
+                ("build_id".to_string(), "build-42".to_string()),
+                ("env".to_string(), "staging".to_string()),
+            ])
+        );
+
+        let deploy = crate::utils::parse_script_content(
+            &format!(
+                r#"
+name: Deploy
+params:
+  - env
+commands:
+  - command: echo "${{build_id}} to ${{env}}" > {}
+"#,
+                out.display()
+            ),
+            "yaml",
+        )
+        .unwrap();
+        let options = RunOptions {
+            loaded_context: saved.clone(),
+            no_summary: true,
+            ..Default::default()
+        };
+        execute(&deploy, &["prod".to_string()], &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap().trim(),
+            "build-42 to prod"
+        );
+        assert_eq!(
+            shadowed_vars(&deploy, &saved, "the loaded context"),
+            ["param 'env' overrides the variable of the same name from the loaded context"]
+        );
+
+        // A failed run leaves the saved context alone
+        let failing = Script {
+            name: "Failing".to_string(),
+            commands: vec![command("exit 1")],
+            ..Default::default()
+        };
+        std::fs::remove_file(&context_path).unwrap();
+        let options = RunOptions {
+            save_context: Some(context_path.clone()),
+            no_summary: true,
+            ..Default::default()
+        };
+        execute(&failing, &[], &options).await.unwrap_err();
+        assert!(!context_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_secrets_and_sensitive_captures_are_masked_everywhere() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("zirv.log");
+        let report_path = dir.path().join("report.json");
+
+        let script = crate::utils::parse_script_content(
+            r#"
+name: Leaky
+secrets:
+  - name: token
+    env_var: ZIRV_TEST_MASKED_TOKEN
+commands:
+  - command: printf 'Bearer %s' ${token}
+    capture: header
+  - command: echo "copy=${header} and again ${header}"
+  - command: printf 'gen-%s' session-42
+    capture: session_id
+    sensitive: true
+  - command: echo "id ${session_id}" >&2 && echo ${token} && exit 2
+"#,
+            "yaml",
+        )
+        .unwrap();
+        let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let options = RunOptions {
+            secrets: Vars::from([("token".to_string(), "s3cr3t-value".to_string())]),
+            log_file: Some(log_path.clone()),
+            report: Some(report_path.clone()),
+            events: Some(events),
+            ..Default::default()
+        };
+
+        let error = execute(&script, &[], &options).await.unwrap_err();
+
+        let mut sinks = vec![
+            error.to_string(),
+            std::fs::read_to_string(&log_path).unwrap(),
+            std::fs::read_to_string(&report_path).unwrap(),
+        ];
+        while let Ok(event) = received.try_recv() {
+            sinks.push(format!("{event:?}"));
+        }
+        for sink in &sinks {
+            assert!(!sink.contains("s3cr3t-value"), "{sink}");
+            assert!(!sink.contains("gen-session-42"), "{sink}");
+        }
+        let log = &sinks[1];
+        assert!(log.contains("copy=Bearer ******** and again Bearer ********"));
+        assert!(
+            log.contains(r#"Executing command: echo "id ********" >&2 && echo ******** && exit 2"#)
+        );
+        let report: RunReport = serde_json::from_str(&sinks[2]).unwrap();
+        assert_eq!(report.steps[3].stderr_tail.as_deref(), Some("id ********"));
+    }
+
+    #[tokio::test]
+    async fn test_secrets_too_short_to_mask_are_warned_about() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("zirv.log");
+        let script = crate::utils::parse_script_content(
+            "name: Short\nsecrets:\n  - name: pin\n    env_var: ZIRV_TEST_PIN\n  - name: token\n    env_var: ZIRV_TEST_SHORT_TOKEN\ncommands:\n  - command: \"true\"\n",
+            "yaml",
+        )
+        .unwrap();
+        let options = RunOptions {
+            secrets: Vars::from([
+                ("pin".to_string(), "123".to_string()),
+                ("token".to_string(), "s3cr3t-value".to_string()),
+            ]),
+            log_file: Some(log_path.clone()),
+            ..Default::default()
+        };
+
+        execute(&script, &[], &options).await.unwrap();
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        assert!(
+            log.contains(
+                "Warning: secret 'pin' is shorter than 4 characters and will not be masked"
+            ),
+            "{log}"
+        );
+        assert!(!log.contains("'token'"), "{log}");
+    }
+
+    #[tokio::test]
+    async fn test_verbosity_levels() {
+        let script = crate::utils::parse_script_content(
+            "name: Levels\ncommands:\n  - command: echo \"hello ${who}\"\n  - command: echo oops >&2\n",
+            "yaml",
+        )
+        .unwrap();
+        let messages = |verbosity| {
+            let script = script.clone();
+            async move {
+                let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+                let options = RunOptions {
+                    vars: Vars::from([("who".to_string(), "world".to_string())]),
+                    events: Some(events),
+                    verbosity,
+                    ..Default::default()
+                };
+                execute(&script, &[], &options).await.unwrap();
+                let mut messages = std::collections::BTreeSet::new();
+                while let Ok(event) = received.try_recv() {
+                    if let UiEvent::Message { text, .. } = event
+                        && !text.contains("Summary for")
+                    {
+                        messages.insert(text);
+                    }
+                }
+                messages
+            }
+        };
+
+        let quiet = messages(Verbosity::Quiet).await;
+        let normal = messages(Verbosity::Normal).await;
+        let verbose = messages(Verbosity::Verbose).await;
+        let debug = messages(Verbosity::Debug).await;
+
+        assert_eq!(quiet.len(), 1);
+        assert!(quiet.first().unwrap().starts_with("'Levels' succeeded in "));
+        assert_eq!(
+            normal,
+            [
+                "Executing command: echo \"hello world\"",
+                "Executing command: echo oops >&2"
+            ]
+            .map(String::from)
+            .into()
+        );
+        assert!(verbose.is_superset(&normal));
+        let extra: Vec<&String> = verbose.difference(&normal).collect();
+        assert!(
+            extra.contains(&&"Substituted ${who}: world".to_string()),
+            "{extra:?}"
+        );
+        assert!(
+            extra
+                .iter()
+                .any(|line| line.starts_with("Working directory: "))
+        );
+        assert!(extra.iter().any(|line| line.starts_with("Shell: ")));
+        assert!(debug.is_superset(&verbose));
+        let extra: Vec<&String> = debug.difference(&verbose).collect();
+        assert_eq!(extra.len(), 4, "{extra:?}");
+        assert!(
+            extra
+                .iter()
+                .all(|line| line.starts_with("Started process ") || line.starts_with("Process "))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_writes_log_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zirv.log");
+
+        let script = Script {
+            name: "Logged Script".to_string(),
+            commands: vec![command("echo first-step"), command("echo second-step")],
+            ..Default::default()
+        };
+        let options = RunOptions {
+            log_file: Some(path.clone()),
+            ..Default::default()
+        };
+
+        execute(&script, &[], &options).await.unwrap();
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        let first = log
+            .lines()
+            .find(|l| l.ends_with("[1] Executing command: echo first-step"))
+            .expect("first command is logged");
+        let second = log
+            .lines()
+            .find(|l| l.ends_with("[2] Executing command: echo second-step"))
+            .expect("second command is logged");
+        for line in [first, second] {
+            let timestamp = line.split(' ').next().unwrap();
+            assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+        }
+        assert!(log.lines().any(|l| l.ends_with("[1] first-step")));
+        assert!(log.contains("Summary for 'Logged Script':"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_starts_in_project_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = Script {
+            name: "Rooted".to_string(),
+            commands: vec![
+                command("touch relative.txt"),
+                command("echo ${zirv.project_root} > root.txt"),
+            ],
+            ..Default::default()
+        };
+        let options = RunOptions {
+            no_summary: true,
+            project_root: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        execute(&script, &[], &options).await.unwrap();
+
+        assert!(dir.path().join("relative.txt").exists());
+        let root = std::fs::read_to_string(dir.path().join("root.txt")).unwrap();
+        assert_eq!(root.trim(), dir.path().to_string_lossy());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_on_error_runs_once_when_a_step_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let handler = |command: &str| Command {
+            command: command.to_string(),
+            ..Default::default()
+        };
+        let script = |commands| Script {
+            name: "Handled".to_string(),
+            commands,
+            on_error: vec![
+                handler("echo \"${zirv.failed_step}|${zirv.failed_command}\" >> failure.txt"),
+                handler("cat > error.txt <<'EOF'\n${zirv.error}\nEOF"),
+                handler("exit 1"),
+                handler("touch unreachable.txt"),
+            ],
+            ..Default::default()
+        };
+        let options = RunOptions {
+            no_summary: true,
+            project_root: Some(dir.path().to_path_buf()),
+            vars: Vars::from([("code".to_string(), "3".to_string())]),
+            ..Default::default()
+        };
+
+        execute(&script(vec![command("echo fine")]), &[], &options)
+            .await
+            .unwrap();
+        assert!(!dir.path().join("failure.txt").exists());
+
+        let migrate = CommandTypes::Command(Command {
+            name: Some("migrate".to_string()),
+            command: "exit ${code}".to_string(),
+            ..Default::default()
+        });
+        let (report, result) = run_with_report(
+            &script(vec![
+                command("echo fine"),
+                migrate,
+                command("touch late.txt"),
+            ]),
+            &[],
+            &options,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(RunError::Step {
+                exit_code: Some(3),
+                ..
+            })
+        ));
+        let handlers: Vec<(usize, StepStatus)> = report
+            .on_error
+            .iter()
+            .map(|handler| (handler.index, handler.status))
+            .collect();
+        assert_eq!(
+            handlers,
+            [
+                (2, StepStatus::Success),
+                (2, StepStatus::Success),
+                (2, StepStatus::Failed)
+            ]
+        );
+        let failure = std::fs::read_to_string(dir.path().join("failure.txt")).unwrap();
+        assert_eq!(failure, "migrate|exit 3\n");
+        let message = std::fs::read_to_string(dir.path().join("error.txt")).unwrap();
+        assert_eq!(message, "Command 'exit 3' failed: `exit 3` failed\n");
+        assert!(!dir.path().join("unreachable.txt").exists());
+        assert!(!dir.path().join("late.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_when_conditions_skip_steps_and_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let script: Script = serde_yaml::from_str(
+            r#"
+name: Conditional
+params: [branch]
+groups:
+  Deploy:
+    when: ${branch} == "main" && ${version} >= 2
+commands:
+  - command: echo 2.1
+    capture: version
+  - command: touch linted
+    when: os == "plan9" || ${branch} != "main"
+  - command: touch built
+    group: Build
+  - command: touch deployed
+    group: Deploy
+  - command: touch announced
+    group: Deploy
+  - command: touch done
+"#,
+        )
+        .unwrap();
+        let options = RunOptions {
+            no_summary: true,
+            project_root: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let statuses = |report: &RunReport| -> Vec<StepStatus> {
+            report.steps.iter().map(|step| step.status).collect()
+        };
+
+        let report = execute(&script, &["main".to_string()], &options)
+            .await
+            .unwrap();
+        use StepStatus::{Skipped, Success};
+        assert_eq!(
+            statuses(&report),
+            [Success, Skipped, Success, Success, Success, Success]
+        );
+        assert!(dir.path().join("deployed").exists());
+        assert!(!dir.path().join("linted").exists());
+
+        std::fs::remove_file(dir.path().join("deployed")).unwrap();
+        let report = execute(&script, &["feature".to_string()], &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            statuses(&report),
+            [Success, Success, Success, Skipped, Skipped, Success]
+        );
+        assert!(dir.path().join("linted").exists());
+        assert!(!dir.path().join("deployed").exists());
+
+        let broken = Script {
+            groups: std::collections::BTreeMap::from([(
+                "Deploy".to_string(),
+                script::Group {
+                    when: Some("${branch} = \"main\"".to_string()),
+                },
+            )]),
+            ..script
+        };
+        let error = execute(&broken, &["main".to_string()], &options)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("Invalid when condition of group 'Deploy': unexpected '=' at column 11"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_vars_precedence() {
+        let script: Script = serde_yaml::from_str(
+            "name: Vars\nparams: [env]\nsecrets:\n  - name: token\n    env_var: ZIRV_VARS_TEST_TOKEN\ncommands: []\n",
+        )
+        .unwrap();
+        unsafe {
+            std::env::set_var("ZIRV_VARS_TEST_TOKEN", "from-secret");
+        }
+        let (vars, _) = crate::utils::merge_vars(&[
+            (
+                "local/vars.yaml".into(),
+                Vars::from([
+                    ("env".to_string(), "from-local".to_string()),
+                    ("registry".to_string(), "from-local".to_string()),
+                ]),
+            ),
+            (
+                "global/vars.yaml".into(),
+                Vars::from([
+                    ("registry".to_string(), "from-global".to_string()),
+                    ("slug".to_string(), "from-global".to_string()),
+                    ("token".to_string(), "from-global".to_string()),
+                ]),
+            ),
+        ]);
+
+        let context =
+            build_context(&script, &["from-param".to_string()], &vars, &Vars::new()).unwrap();
+        assert_eq!(context["env"], "from-param");
+        assert_eq!(context["token"], "from-secret");
+        assert_eq!(context["registry"], "from-local");
+        assert_eq!(context["slug"], "from-global");
+
+        assert_eq!(
+            shadowed_vars(&script, &vars, "vars.yaml"),
+            [
+                "param 'env' overrides the variable of the same name from vars.yaml",
+                "secret 'token' overrides the variable of the same name from vars.yaml"
+            ]
+        );
+    }
 }
@@ -1,6 +1,7 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
 #[serde(rename = "os")]
 pub enum OperatingSystem {
     #[serde(rename = "linux")]
@@ -25,6 +26,15 @@ impl OperatingSystem {
     pub fn is_current(&self) -> bool {
         *self == Self::current()
     }
+
+    /// The name scripts use for the operating system, e.g. `macos`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            OperatingSystem::Linux => "linux",
+            OperatingSystem::Windows => "windows",
+            OperatingSystem::MacOS => "macos",
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1,24 +1,256 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::script_runner::fallback_command::FallbackCommand;
 
-use super::operating_system::OperatingSystem;
+use super::{operating_system::OperatingSystem, priority::Priority};
 
 /// A set of options that control how a command is executed.
-#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct Options {
     /// If true, the script continues even if this command fails.
-    #[serde(default)]
-    pub proceed_on_failure: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proceed_on_failure: Option<bool>,
     /// Optional delay in milliseconds after executing this command.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub delay_ms: Option<u64>,
     /// If true, the command is executed in interactive mode.
-    #[serde(default)]
-    pub interactive: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interactive: Option<bool>,
     /// If provided, the command is only executed on the specified operating system
     /// (e.g. "linux", "windows", "macos").
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub operating_system: Option<OperatingSystem>,
     /// Optional commands to be executed if the command fails.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub fallback: Option<Vec<FallbackCommand>>,
+    /// If true (the default), the original command is retried once after all
+    /// fallback commands have succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_fallback: Option<bool>,
+    /// Maximum number of bytes of stdout stored by `capture`. Anything beyond
+    /// is discarded and the value is marked as truncated. Defaults to 10 MB.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_capture_bytes: Option<usize>,
+    /// If true, every context variable is passed to the command as an
+    /// upper-cased environment variable. Overrides the script-level default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub export_context: Option<bool>,
+    /// If true, secrets are exported along with the rest of the context.
+    /// Overrides the script-level default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub export_secrets: Option<bool>,
+    /// Prefix for exported variable names (e.g. "ZIRV_") to avoid collisions.
+    /// Overrides the script-level default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub export_prefix: Option<String>,
+    /// If true, `FORCE_COLOR` and `CLICOLOR_FORCE` are set for the command so
+    /// tools keep emitting color even though their output is piped through zirv.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force_color: Option<bool>,
+    /// If set, the command runs with `sh -c` in a container of this image,
+    /// through docker (or podman when docker is not installed), with the
+    /// working directory mounted at `/work`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// Extra arguments to `docker run` for `container`, e.g. `["--network", "none"]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_args: Option<Vec<String>>,
+    /// If true, on Windows the command runs in WSL with `bash -lc`, in the
+    /// WSL path of the working directory. Ignored, with a warning, elsewhere.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wsl: Option<bool>,
+    /// The priority the command runs at: `low`, `normal` or `high`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// If true, the command starts from an empty environment instead of
+    /// zirv's, keeping only `PATH` and the home directory (plus `SystemRoot`
+    /// on Windows), `env_keep` and exported context. Overrides the
+    /// script-level default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isolate_env: Option<bool>,
+    /// More variables an `isolate_env` command keeps from zirv's environment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_keep: Option<Vec<String>>,
+    /// Directories put in front of `PATH` for the command, e.g.
+    /// `["bin", "node_modules/.bin"]`. Relative ones are resolved against the
+    /// working directory, which starts at the project root; `${...}`
+    /// placeholders are substituted. Not applied in a container or WSL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_prepend: Option<Vec<String>>,
+    /// If set, while the command prints nothing, a line saying it is still
+    /// running is printed every this many milliseconds. `0` turns off the
+    /// default from `config.yaml`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heartbeat_ms: Option<u64>,
+    /// How long the command gets to exit after being sent SIGTERM, or
+    /// CTRL_BREAK on Windows, when the run is stopped, before it is killed.
+    /// Defaults to `grace_period_ms` from `config.yaml`, else 5 seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grace_period_ms: Option<u64>,
+}
+
+impl Options {
+    /// Whether the script continues when the command fails. Off by default.
+    pub fn proceed_on_failure(&self) -> bool {
+        self.proceed_on_failure.unwrap_or(false)
+    }
+
+    /// Whether the command runs in interactive mode. Off by default.
+    pub fn interactive(&self) -> bool {
+        self.interactive.unwrap_or(false)
+    }
+
+    /// Whether the command is retried after its fallbacks succeed. On by default.
+    pub fn retry_after_fallback(&self) -> bool {
+        self.retry_after_fallback.unwrap_or(true)
+    }
+
+    /// Whether the command is told to keep emitting color. Off by default.
+    pub fn force_color(&self) -> bool {
+        self.force_color.unwrap_or(false)
+    }
+
+    /// Whether the command runs in WSL on Windows. Off by default.
+    pub fn wsl(&self) -> bool {
+        self.wsl.unwrap_or(false)
+    }
+
+    /// These options with `defaults` filling in what they leave unset, so a
+    /// field keeps its own value when it has one, even a flag turned off.
+    /// Fallback commands get `defaults` too, except its `fallback`.
+    pub fn merged_over(self, defaults: &Options) -> Options {
+        let inherited = Options {
+            fallback: None,
+            ..defaults.clone()
+        };
+        let fallback = self.fallback.or_else(|| defaults.fallback.clone());
+        Options {
+            proceed_on_failure: self.proceed_on_failure.or(defaults.proceed_on_failure),
+            delay_ms: self.delay_ms.or(defaults.delay_ms),
+            interactive: self.interactive.or(defaults.interactive),
+            operating_system: self
+                .operating_system
+                .or_else(|| defaults.operating_system.clone()),
+            fallback: fallback.map(|commands| {
+                commands
+                    .into_iter()
+                    .map(|mut command| {
+                        command.options =
+                            Some(command.options.unwrap_or_default().merged_over(&inherited));
+                        command
+                    })
+                    .collect()
+            }),
+            retry_after_fallback: self.retry_after_fallback.or(defaults.retry_after_fallback),
+            max_capture_bytes: self.max_capture_bytes.or(defaults.max_capture_bytes),
+            export_context: self.export_context.or(defaults.export_context),
+            export_secrets: self.export_secrets.or(defaults.export_secrets),
+            export_prefix: self
+                .export_prefix
+                .or_else(|| defaults.export_prefix.clone()),
+            force_color: self.force_color.or(defaults.force_color),
+            container: self.container.or_else(|| defaults.container.clone()),
+            container_args: self
+                .container_args
+                .or_else(|| defaults.container_args.clone()),
+            wsl: self.wsl.or(defaults.wsl),
+            priority: self.priority.or(defaults.priority),
+            isolate_env: self.isolate_env.or(defaults.isolate_env),
+            env_keep: self.env_keep.or_else(|| defaults.env_keep.clone()),
+            path_prepend: self.path_prepend.or_else(|| defaults.path_prepend.clone()),
+            heartbeat_ms: self.heartbeat_ms.or(defaults.heartbeat_ms),
+            grace_period_ms: self.grace_period_ms.or(defaults.grace_period_ms),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fallback(command: &str, options: Option<Options>) -> FallbackCommand {
+        FallbackCommand {
+            command: command.to_string(),
+            description: None,
+            options,
+        }
+    }
+
+    #[test]
+    fn test_merged_over_booleans() {
+        let defaults = Options {
+            proceed_on_failure: Some(true),
+            retry_after_fallback: Some(false),
+            force_color: Some(true),
+            ..Options::default()
+        };
+        let options = Options {
+            interactive: Some(true),
+            force_color: Some(false),
+            retry_after_fallback: Some(true),
+            ..Options::default()
+        }
+        .merged_over(&defaults);
+        assert!(options.proceed_on_failure());
+        assert!(options.interactive());
+        assert!(options.retry_after_fallback());
+        assert!(!options.force_color());
+        assert!(!options.wsl());
+
+        let unset = Options::default().merged_over(&Options::default());
+        assert!(unset.retry_after_fallback());
+        assert!(!unset.proceed_on_failure());
+    }
+
+    #[test]
+    fn test_merged_over_optional_fields() {
+        let defaults = Options {
+            delay_ms: Some(100),
+            export_context: Some(true),
+            export_prefix: Some("APP_".to_string()),
+            ..Options::default()
+        };
+        let options = Options {
+            delay_ms: Some(5),
+            export_context: Some(false),
+            ..Options::default()
+        }
+        .merged_over(&defaults);
+        assert_eq!(options.delay_ms, Some(5));
+        assert_eq!(options.export_context, Some(false));
+        assert_eq!(options.export_prefix.as_deref(), Some("APP_"));
+        assert_eq!(options.max_capture_bytes, None);
+    }
+
+    #[test]
+    fn test_merged_over_fallbacks() {
+        let defaults = Options {
+            delay_ms: Some(100),
+            fallback: Some(vec![fallback("make clean", None)]),
+            ..Options::default()
+        };
+
+        let options = Options::default().merged_over(&defaults);
+        let fallbacks = options.fallback.unwrap();
+        assert_eq!(fallbacks[0].command, "make clean");
+        let inner = fallbacks[0].options.as_ref().unwrap();
+        assert_eq!(inner.delay_ms, Some(100));
+        assert!(inner.fallback.is_none());
+
+        let own = Options {
+            fallback: Some(vec![fallback(
+                "npm ci",
+                Some(Options {
+                    delay_ms: Some(1),
+                    ..Options::default()
+                }),
+            )]),
+            ..Options::default()
+        };
+        let fallbacks = own.merged_over(&defaults).fallback.unwrap();
+        assert_eq!(fallbacks.len(), 1);
+        assert_eq!(fallbacks[0].command, "npm ci");
+        assert_eq!(fallbacks[0].options.as_ref().unwrap().delay_ms, Some(1));
+    }
 }
@@ -0,0 +1,1020 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{Local, SecondsFormat};
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::{
+    event::{JsonEvent, UiEvent},
+    run_options::{OutputFormat, UiMode, Verbosity},
+    style::{ColorMode, Style},
+};
+
+/// Number of trailing lines returned by [`tee`].
+const TAIL_LINES: usize = 20;
+
+/// What secret values are replaced with wherever they would be shown.
+pub const MASK: &str = "********";
+
+/// Masked values shorter than this many characters are left alone, since
+/// masking every `1` or `on` would garble the output without hiding much.
+pub const MIN_MASK_CHARS: usize = 4;
+
+/// Which standard stream of a child process is being forwarded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    /// zirv's own stream, for a child process to write to directly.
+    pub fn inherited(self) -> Stdio {
+        match self {
+            Stream::Stdout => Stdio::inherit(),
+            Stream::Stderr => Stdio::from(std::io::stderr()),
+        }
+    }
+
+    /// Writes `bytes` to zirv's own stream as they are, right away.
+    pub fn write_through(self, bytes: &[u8]) {
+        let _ = match self {
+            Stream::Stdout => {
+                let mut stdout = std::io::stdout();
+                stdout.write_all(bytes).and_then(|()| stdout.flush())
+            }
+            Stream::Stderr => {
+                let mut stderr = std::io::stderr();
+                stderr.write_all(bytes).and_then(|()| stderr.flush())
+            }
+        };
+    }
+}
+
+/// Where everything zirv prints during a run ends up: the terminal and,
+/// optionally, a log file where each line is prefixed with an RFC3339
+/// timestamp and the index of the step that produced it.
+///
+/// When decorated, lines streamed from child processes are prefixed with
+/// the label of the step that produced them. Colors only ever reach the
+/// terminal, never the log file.
+///
+/// With [`OutputFormat::Json`], stdout gets nothing but one [`JsonEvent`]
+/// per line.
+///
+/// With timestamps, every line on the terminal and in the log file is
+/// prefixed by [`line_prefix`] with the time since the run started.
+///
+/// Each line zirv prints itself has a [`Verbosity`] it is shown at. The log
+/// file gets every line shown at [`Verbosity::Normal`] even when quieter.
+///
+/// Steps with a group get a header when their group starts. On GitHub
+/// Actions, the header and the end of the group are workflow commands, so
+/// the group's output folds.
+#[derive(Debug, Default)]
+pub struct Output {
+    log: Option<Mutex<LogFile>>,
+    decorate: bool,
+    /// Whether lines printed to stdout are colored.
+    color: bool,
+    /// Whether lines printed to stderr are colored.
+    stderr_color: bool,
+    verbosity: Verbosity,
+    /// Replaces the terminal in tests.
+    sink: Option<Mutex<Vec<u8>>>,
+    /// Receives every line as a [`UiEvent`], along with step events.
+    events: Option<UnboundedSender<UiEvent>>,
+    /// Values replaced with [`MASK`] in everything printed, logged or sent,
+    /// longest first.
+    masks: Mutex<Vec<String>>,
+    /// When a child process last wrote a line.
+    last_line: Mutex<Option<Instant>>,
+    /// When the run started, if every line is stamped with the time since.
+    started: Option<Instant>,
+    /// Prints [`JsonEvent`]s instead of lines.
+    json: bool,
+    /// The group of the steps being printed.
+    group: Mutex<Option<String>>,
+    /// Whether groups are printed as GitHub Actions workflow commands.
+    github: bool,
+}
+
+#[derive(Debug)]
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    warned: bool,
+}
+
+impl Output {
+    /// Creates an output that additionally appends to `log_path`, if given.
+    ///
+    /// Streamed lines are decorated in [`UiMode::Tui`], and in
+    /// [`UiMode::Auto`] when stdout is a terminal; whether they and zirv's
+    /// own messages are colored is up to `color`, and decided for stdout
+    /// and stderr apart.
+    ///
+    /// A log file that cannot be opened produces a warning, not an error.
+    pub fn new(log_path: Option<&Path>, ui: UiMode, color: ColorMode) -> Self {
+        let log = log_path.and_then(|path| {
+            match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => Some(Mutex::new(LogFile {
+                    path: path.to_path_buf(),
+                    file,
+                    warned: false,
+                })),
+                Err(e) => {
+                    eprintln!("Warning: could not open log file {}: {e}", path.display());
+                    None
+                }
+            }
+        });
+        let decorate = match ui {
+            UiMode::Auto => std::io::stdout().is_terminal(),
+            UiMode::Plain => false,
+            UiMode::Tui => true,
+        };
+        Self {
+            log,
+            decorate,
+            color: color.enabled(std::io::stdout().is_terminal()),
+            stderr_color: color.enabled(std::io::stderr().is_terminal()),
+            verbosity: Verbosity::Normal,
+            sink: None,
+            events: None,
+            masks: Mutex::default(),
+            last_line: Mutex::default(),
+            started: None,
+            json: false,
+            group: Mutex::default(),
+            github: std::env::var_os("GITHUB_ACTIONS").is_some_and(|v| v == "true"),
+        }
+    }
+
+    /// Also sends everything to `events`, if given.
+    pub fn with_events(self, events: Option<UnboundedSender<UiEvent>>) -> Self {
+        Self { events, ..self }
+    }
+
+    /// Stamps every line with the time since now, if `timestamps`.
+    pub fn with_timestamps(self, timestamps: bool) -> Self {
+        Self {
+            started: timestamps.then(Instant::now),
+            ..self
+        }
+    }
+
+    /// Prints the run as `format`.
+    pub fn with_format(self, format: OutputFormat) -> Self {
+        Self {
+            json: format == OutputFormat::Json,
+            ..self
+        }
+    }
+
+    /// Shows only the lines of `verbosity` and below.
+    pub fn with_verbosity(self, verbosity: Verbosity) -> Self {
+        Self { verbosity, ..self }
+    }
+
+    /// Sends `event` to the receiver of [`Output::with_events`], if any,
+    /// and prints it when printing JSON. A receiver that went away is
+    /// ignored.
+    pub fn send(&self, event: UiEvent) {
+        if self.json {
+            self.print_json(event.clone().into());
+        }
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+
+    /// Prints `event` as a line of JSON with every masked value redacted,
+    /// when printing JSON.
+    pub fn print_json(&self, event: JsonEvent) {
+        if !self.json {
+            return;
+        }
+        let event = event.redacted(|text| self.redact(text).into_owned());
+        if let Ok(line) = serde_json::to_string(&event) {
+            self.write(Stream::Stdout, &format!("{line}\n"));
+        }
+    }
+
+    /// Whether the run is printed as JSON events.
+    pub fn is_json(&self) -> bool {
+        self.json
+    }
+
+    /// Where a step that uses the terminal writes what it shows: stdout, or
+    /// stderr with JSON events, so that stdout only carries events.
+    pub fn terminal_stream(&self) -> Stream {
+        if self.json {
+            Stream::Stderr
+        } else {
+            Stream::Stdout
+        }
+    }
+
+    /// Creates an output that writes to an in-memory buffer instead of the
+    /// terminal, see [`Output::captured_text`].
+    #[cfg(test)]
+    pub fn captured(decorate: bool) -> Self {
+        Self {
+            decorate,
+            sink: Some(Mutex::new(Vec::new())),
+            ..Default::default()
+        }
+    }
+
+    /// Everything written to the terminal by a [`Output::captured`] output.
+    #[cfg(test)]
+    pub fn captured_text(&self) -> String {
+        let sink = self.sink.as_ref().expect("output is not captured");
+        String::from_utf8_lossy(&sink.lock().unwrap()).to_string()
+    }
+
+    /// Masks `value` in everything printed from now on. Each line of a
+    /// multi-line value is masked on its own as well. Values, and lines,
+    /// of fewer than [`MIN_MASK_CHARS`] characters are not masked; returns
+    /// whether `value` itself is.
+    pub fn mask(&self, value: &str) -> bool {
+        let Ok(mut masks) = self.masks.lock() else {
+            return false;
+        };
+        for value in std::iter::once(value).chain(value.lines()) {
+            let value = value.trim();
+            if value.chars().count() >= MIN_MASK_CHARS && !masks.iter().any(|mask| mask == value) {
+                masks.push(value.to_string());
+            }
+        }
+        masks.sort_by_key(|mask| std::cmp::Reverse(mask.len()));
+        value.trim().chars().count() >= MIN_MASK_CHARS
+    }
+
+    /// `text` with every masked value replaced by [`MASK`].
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let Ok(masks) = self.masks.lock() else {
+            return Cow::Borrowed(text);
+        };
+        let mut text = Cow::Borrowed(text);
+        for mask in masks.iter() {
+            if text.contains(mask.as_str()) {
+                text = Cow::Owned(text.replace(mask.as_str(), MASK));
+            }
+        }
+        text
+    }
+
+    /// When a child process last wrote a line, if one has.
+    pub fn last_line_at(&self) -> Option<Instant> {
+        self.last_line.lock().ok().and_then(|last| *last)
+    }
+
+    fn is_masking(&self) -> bool {
+        self.masks.lock().is_ok_and(|masks| !masks.is_empty())
+    }
+
+    /// Whether lines are also written to a log file.
+    pub fn is_logging(&self) -> bool {
+        self.log.is_some()
+    }
+
+    /// Whether child output has to be read and forwarded by us rather than
+    /// inherited by the child.
+    pub fn forwards(&self) -> bool {
+        self.is_logging()
+            || self.decorate
+            || self.sink.is_some()
+            || self.events.is_some()
+            || self.is_masking()
+            || self.verbosity == Verbosity::Quiet
+            || self.started.is_some()
+            || self.json
+    }
+
+    /// Whether lines of `level` are shown.
+    pub fn shows(&self, level: Verbosity) -> bool {
+        self.verbosity >= level
+    }
+
+    /// Whether what is printed to stdout is colored.
+    pub fn colors(&self) -> bool {
+        self.color
+    }
+
+    /// Whether what is printed to `stream` is colored.
+    fn colors_on(&self, stream: Stream) -> bool {
+        match stream {
+            Stream::Stdout => self.color,
+            Stream::Stderr => self.stderr_color,
+        }
+    }
+
+    /// Prints a line to stdout and the log file, unless quiet.
+    pub fn print(&self, step: Option<usize>, line: &str) {
+        let line = self.redact(line);
+        self.emit(Stream::Stdout, Verbosity::Normal, step, &line, &line);
+    }
+
+    /// Prints a line to stderr and the log file.
+    pub fn eprint(&self, step: Option<usize>, line: &str) {
+        let line = self.redact(line);
+        self.emit(Stream::Stderr, Verbosity::Quiet, step, &line, &line);
+    }
+
+    /// Prints a line to stdout, in `style` when colored, and to the log
+    /// file, unless quiet.
+    pub fn print_styled(&self, step: Option<usize>, style: Style, line: &str) {
+        let line = self.redact(line);
+        let colored = style.paint(&line);
+        self.emit(Stream::Stdout, Verbosity::Normal, step, &line, &colored);
+    }
+
+    /// Prints a line to stderr, in `style` when colored, and to the log file.
+    pub fn eprint_styled(&self, step: Option<usize>, style: Style, line: &str) {
+        let line = self.redact(line);
+        let colored = style.paint(&line);
+        self.emit(Stream::Stderr, Verbosity::Quiet, step, &line, &colored);
+    }
+
+    /// Prints `colored` to stdout when colored and `line` otherwise, unless
+    /// quiet; the log file always gets `line`.
+    pub fn print_colored(&self, step: Option<usize>, line: &str, colored: &str) {
+        let (line, colored) = (self.redact(line), self.redact(colored));
+        self.emit(Stream::Stdout, Verbosity::Normal, step, &line, &colored);
+    }
+
+    /// Prints the final status of the run, which even quiet output shows.
+    pub fn status(&self, line: &str, colored: &str) {
+        let (line, colored) = (self.redact(line), self.redact(colored));
+        self.emit(Stream::Stdout, Verbosity::Quiet, None, &line, &colored);
+    }
+
+    /// Moves on to the steps of `group`: unless it is the current group
+    /// already, ends the current group and prints the header of `group`,
+    /// e.g. `=== Build ===`.
+    pub fn enter_group(&self, group: Option<&str>) {
+        let previous = match self.group.lock() {
+            Ok(mut current) if current.as_deref() != group => {
+                std::mem::replace(&mut *current, group.map(String::from))
+            }
+            _ => return,
+        };
+        let header = group.map(|group| format!("=== {} ===", self.redact(group)));
+        if !self.github || self.json {
+            if let Some(header) = header {
+                self.print_styled(None, Style::Bold, &header);
+            }
+            return;
+        }
+
+        // Workflow commands only work at the start of a line.
+        let shown = self.shows(Verbosity::Normal);
+        if shown && previous.is_some() {
+            self.write(Stream::Stdout, "::endgroup::\n");
+        }
+        if let (Some(group), Some(header)) = (group, header) {
+            self.log_line(&self.prefix(None), &header);
+            if shown {
+                self.write(
+                    Stream::Stdout,
+                    &format!("::group::{}\n", self.redact(group)),
+                );
+                self.message(None, Stream::Stdout, &header);
+            }
+        }
+    }
+
+    /// Prints a dim line to stdout if lines of `level` are shown.
+    pub fn detail(&self, step: Option<usize>, level: Verbosity, line: &str) {
+        let line = self.redact(line);
+        let colored = Style::Dim.paint(&line);
+        self.emit(Stream::Stdout, level, step, &line, &colored);
+    }
+
+    fn emit(
+        &self,
+        stream: Stream,
+        level: Verbosity,
+        step: Option<usize>,
+        line: &str,
+        colored: &str,
+    ) {
+        let prefix = self.prefix(step);
+        if self.verbosity.max(Verbosity::Normal) >= level {
+            self.log_line(&prefix, line);
+        }
+        if !self.shows(level) {
+            return;
+        }
+        if !self.json {
+            let shown = if self.colors_on(stream) {
+                colored
+            } else {
+                line
+            };
+            self.write(stream, &self.stamp(&prefix, &format!("{shown}\n")));
+        }
+        self.message(step, stream, line);
+    }
+
+    /// The [`line_prefix`] of a line of `step` printed now.
+    fn prefix(&self, step: Option<usize>) -> String {
+        line_prefix(self.started.map(|started| started.elapsed()), step)
+    }
+
+    /// `text` with `prefix` before each of its lines if lines are stamped.
+    fn stamp<'a>(&self, prefix: &str, text: &'a str) -> Cow<'a, str> {
+        match self.started {
+            Some(_) => Cow::Owned(
+                text.split_inclusive('\n')
+                    .map(|line| format!("{prefix}{line}"))
+                    .collect(),
+            ),
+            None => Cow::Borrowed(text),
+        }
+    }
+
+    fn message(&self, step: Option<usize>, stream: Stream, text: &str) {
+        self.send(UiEvent::Message {
+            step,
+            stream,
+            text: text.to_string(),
+        });
+    }
+
+    /// Prints a line streamed from a child process, prefixed with `label`
+    /// when decorated. Quiet output leaves out stdout.
+    fn stream(&self, stream: Stream, prefix: &str, label: &str, text: &str) {
+        if self.json || (stream == Stream::Stdout && !self.shows(Verbosity::Normal)) {
+            return;
+        }
+        if !self.decorate {
+            self.write(stream, &self.stamp(prefix, text));
+            return;
+        }
+
+        let text = text.trim_end_matches(['\r', '\n']);
+        let line = match (self.colors_on(stream), stream) {
+            (false, _) => format!("[{label}] {text}\n"),
+            (true, Stream::Stdout) => {
+                format!("{} {text}\n", Style::Dim.paint(&format!("[{label}]")))
+            }
+            (true, Stream::Stderr) => format!(
+                "{} {}\n",
+                Style::Dim.paint(&format!("[{label}]")),
+                Style::Red.paint(text)
+            ),
+        };
+        self.write(stream, &self.stamp(prefix, &line));
+    }
+
+    fn write(&self, stream: Stream, text: &str) {
+        if let Some(sink) = &self.sink {
+            if let Ok(mut sink) = sink.lock() {
+                sink.extend_from_slice(text.as_bytes());
+            }
+            return;
+        }
+        match stream {
+            Stream::Stdout => print!("{text}"),
+            Stream::Stderr => eprint!("{text}"),
+        }
+    }
+
+    /// Writes a line to the log file only.
+    pub fn log(&self, step: Option<usize>, line: &str) {
+        self.log_line(&self.prefix(step), &self.redact(line));
+    }
+
+    fn log_line(&self, prefix: &str, line: &str) {
+        let Some(log) = &self.log else {
+            return;
+        };
+        let Ok(mut log) = log.lock() else {
+            return;
+        };
+
+        let timestamp = Local::now().to_rfc3339_opts(SecondsFormat::Millis, false);
+        let mut result = Ok(());
+        for text in line.lines() {
+            result = result.and_then(|_| writeln!(log.file, "{timestamp} {prefix}{text}"));
+        }
+
+        if let Err(e) = result
+            && !log.warned
+        {
+            log.warned = true;
+            eprintln!(
+                "Warning: failed to write to log file {}: {e}",
+                log.path.display()
+            );
+        }
+    }
+}
+
+/// Forwards a child's output stream line by line to the matching stream of
+/// our own process and to the log file, returning the last `TAIL_LINES` lines.
+///
+/// `label` identifies the step in decorated output.
+pub async fn tee<R: AsyncRead + Unpin>(
+    reader: R,
+    output: Arc<Output>,
+    step: Option<usize>,
+    label: String,
+    stream: Stream,
+) -> String {
+    let mut reader = BufReader::new(reader);
+    let mut tail = VecDeque::with_capacity(TAIL_LINES);
+    let mut line = Vec::new();
+
+    while let Ok(n) = reader.read_until(b'\n', &mut line).await {
+        if n == 0 {
+            break;
+        }
+        if let Ok(mut last) = output.last_line.lock() {
+            *last = Some(Instant::now());
+        }
+        let text = String::from_utf8_lossy(&line);
+        let text = output.redact(&text);
+        let prefix = output.prefix(step);
+        output.stream(stream, &prefix, &label, &text);
+        let text = text.trim_end().to_string();
+        output.log_line(&prefix, &text);
+        output.send(UiEvent::Output {
+            step,
+            stream,
+            text: text.clone(),
+        });
+        if tail.len() == TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(text);
+        line.clear();
+    }
+
+    Vec::from(tail).join("\n")
+}
+
+/// Waits for `reader`, a [`tee`] of a child's output, until `deadline`.
+/// Processes the child left running in the background may keep its output
+/// open for as long as they run; the reader then goes on forwarding their
+/// output, so that they do not fail writing it, and `None` is returned.
+pub async fn drain<T>(
+    reader: tokio::task::JoinHandle<T>,
+    deadline: tokio::time::Instant,
+) -> Option<T> {
+    tokio::time::timeout_at(deadline, reader).await.ok()?.ok()
+}
+
+/// Prints `Still running step <step> (<label>) — <elapsed> elapsed` each
+/// time a child process started at `start` has been silent for `every`.
+/// Runs until dropped or aborted.
+pub async fn heartbeat(
+    output: Arc<Output>,
+    step: usize,
+    label: String,
+    start: Instant,
+    every: Duration,
+) {
+    let mut next = start + every;
+    loop {
+        tokio::time::sleep_until(next.into()).await;
+        let now = Instant::now();
+        if let Some(last) = output.last_line_at().filter(|&last| last > start)
+            && last + every > now
+        {
+            next = last + every;
+            continue;
+        }
+        output.print_styled(
+            Some(step),
+            Style::Dim,
+            &format!(
+                "Still running step {step} ({label}) — {} elapsed",
+                format_elapsed(now - start)
+            ),
+        );
+        next = now + every;
+    }
+}
+
+/// What every line of `step` starts with in the log file, and with
+/// timestamps on the terminal: the time since the run started, if
+/// `elapsed` is given, and the step, or `-` outside of one. For example
+/// `[00:02:15.3] [2] `.
+pub fn line_prefix(elapsed: Option<Duration>, step: Option<usize>) -> String {
+    let step = step.map_or_else(|| "-".to_string(), |i| i.to_string());
+    match elapsed {
+        Some(elapsed) => {
+            let (secs, tenths) = (elapsed.as_secs(), elapsed.subsec_millis() / 100);
+            let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+            format!("[{h:02}:{m:02}:{s:02}.{tenths}] [{step}] ")
+        }
+        None => format!("[{step}] "),
+    }
+}
+
+/// Formats a duration like `45s`, `2m30s` or `1h05m00s`.
+fn format_elapsed(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m{s:02}s"),
+        (h, m, s) => format!("{h}h{m:02}m{s:02}s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_lines_are_timestamped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zirv.log");
+
+        let output = Output::new(Some(&path), UiMode::Plain, ColorMode::Never);
+        assert!(output.is_logging());
+        output.print(Some(1), "first");
+        output.eprint(None, "second\nthird");
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with(" [1] first"));
+        assert!(lines[1].ends_with(" [-] second"));
+        assert!(lines[2].ends_with(" [-] third"));
+        for line in lines {
+            let timestamp = line.split(' ').next().unwrap();
+            assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_colors_reach_the_terminal_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zirv.log");
+        let output = Output::new(Some(&path), UiMode::Plain, ColorMode::Always);
+        assert!(output.colors());
+        output.print_styled(Some(1), Style::Bold, "Executing command: make");
+        output.eprint_styled(None, Style::Red, "Warning: oops");
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains(" [1] Executing command: make\n"));
+        assert!(!content.contains('\x1b'), "{content}");
+        assert!(!Output::new(None, UiMode::Tui, ColorMode::Never).colors());
+
+        let output = Output {
+            color: true,
+            ..Output::captured(false)
+        };
+        output.print_styled(Some(1), Style::Dim, "Command skipped due to OS filter");
+        output.print_colored(None, "Total: 1.00s", "Total: \x1b[1m1.00s\x1b[0m");
+        // Stderr is colored on its own terms, e.g. when only stdout is a
+        // terminal.
+        output.eprint_styled(None, Style::Red, "Warning: oops");
+        assert_eq!(
+            output.captured_text(),
+            format!(
+                "{}\nTotal: \x1b[1m1.00s\x1b[0m\nWarning: oops\n",
+                Style::Dim.paint("Command skipped due to OS filter")
+            )
+        );
+
+        let output = Output::captured(false);
+        output.print_styled(Some(1), Style::Dim, "Command skipped due to OS filter");
+        output.print_colored(None, "Total: 1.00s", "Total: \x1b[1m1.00s\x1b[0m");
+        assert_eq!(
+            output.captured_text(),
+            "Command skipped due to OS filter\nTotal: 1.00s\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lines_are_shown_by_verbosity() {
+        let shown = |verbosity| async move {
+            let output = Arc::new(Output::captured(false).with_verbosity(verbosity));
+            output.print(Some(1), "Executing command: make");
+            output.detail(Some(1), Verbosity::Verbose, "Shell: sh");
+            output.detail(Some(1), Verbosity::Debug, "Started process 42");
+            output.eprint(Some(1), "Warning: slow");
+            tee(
+                &b"built\n"[..],
+                output.clone(),
+                Some(1),
+                "1".into(),
+                Stream::Stdout,
+            )
+            .await;
+            tee(
+                &b"oops\n"[..],
+                output.clone(),
+                Some(1),
+                "1".into(),
+                Stream::Stderr,
+            )
+            .await;
+            output.status("'Build' succeeded in 1.00s", "");
+            output.captured_text()
+        };
+        assert_eq!(
+            shown(Verbosity::Quiet).await,
+            "Warning: slow\noops\n'Build' succeeded in 1.00s\n"
+        );
+        assert_eq!(
+            shown(Verbosity::Normal).await,
+            "Executing command: make\nWarning: slow\nbuilt\noops\n'Build' succeeded in 1.00s\n"
+        );
+        assert_eq!(
+            shown(Verbosity::Verbose).await,
+            "Executing command: make\nShell: sh\nWarning: slow\nbuilt\noops\n'Build' succeeded in 1.00s\n"
+        );
+        assert!(
+            shown(Verbosity::Debug)
+                .await
+                .contains("Shell: sh\nStarted process 42\n")
+        );
+    }
+
+    #[test]
+    fn test_quiet_output_still_logs_normal_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zirv.log");
+        let output = Output::new(Some(&path), UiMode::Plain, ColorMode::Never)
+            .with_verbosity(Verbosity::Quiet);
+        output.print(Some(1), "Executing command: make");
+        output.detail(Some(1), Verbosity::Verbose, "Shell: sh");
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains(" [1] Executing command: make"));
+        assert!(!content.contains("Shell: sh"));
+    }
+
+    #[test]
+    fn test_unopenable_log_file_is_not_fatal() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = Output::new(
+            Some(&dir.path().join("missing").join("zirv.log")),
+            UiMode::Plain,
+            ColorMode::Never,
+        );
+        assert!(!output.is_logging());
+        output.print(None, "still printed");
+    }
+
+    #[tokio::test]
+    async fn test_tee_returns_tail_and_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zirv.log");
+        let output = Arc::new(Output::new(Some(&path), UiMode::Plain, ColorMode::Never));
+
+        let input: String = (1..=25).map(|i| format!("line {i}\n")).collect();
+        let tail = tee(
+            input.as_bytes(),
+            output,
+            Some(2),
+            "2".into(),
+            Stream::Stdout,
+        )
+        .await;
+
+        assert_eq!(tail.lines().count(), TAIL_LINES);
+        assert!(tail.starts_with("line 6\n"));
+        assert!(tail.ends_with("line 25"));
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 25);
+        assert!(content.lines().all(|l| l.contains(" [2] line ")));
+    }
+
+    #[tokio::test]
+    async fn test_tee_prefixes_decorated_lines() {
+        let output = Arc::new(Output::captured(true));
+        tee(
+            &b"a\nb"[..],
+            output.clone(),
+            Some(1),
+            "build".into(),
+            Stream::Stdout,
+        )
+        .await;
+        assert_eq!(output.captured_text(), "[build] a\n[build] b\n");
+
+        let output = Arc::new(Output {
+            stderr_color: true,
+            ..Output::captured(true)
+        });
+        tee(
+            &b"oops\n"[..],
+            output.clone(),
+            Some(3),
+            "3".into(),
+            Stream::Stderr,
+        )
+        .await;
+        assert_eq!(
+            output.captured_text(),
+            format!("{} {}\n", Style::Dim.paint("[3]"), Style::Red.paint("oops"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_masked_values_are_redacted() {
+        let output = Arc::new(Output::captured(false));
+        assert!(!output.mask(""));
+        assert!(!output.mask("on"));
+        assert!(output.mask("hunter2"));
+        assert!(output.mask("multi\nline-value\n"));
+        assert_eq!(output.redact("no secrets here"), "no secrets here");
+        assert_eq!(output.redact("turned on"), "turned on");
+
+        output.print(Some(1), "Executing command: login -p hunter2");
+        tee(
+            &b"a hunter2b hunter2\nline-value\n"[..],
+            output.clone(),
+            Some(1),
+            "1".into(),
+            Stream::Stdout,
+        )
+        .await;
+        assert_eq!(
+            output.captured_text(),
+            format!("Executing command: login -p {MASK}\na {MASK}b {MASK}\n{MASK}\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_json_output_replaces_lines_with_events() {
+        let output = Arc::new(Output::captured(true).with_format(OutputFormat::Json));
+        output.mask("hunter2");
+        output.send(UiEvent::StepStarted {
+            index: 1,
+            label: "login -p hunter2".to_string(),
+            group: Some("Auth".to_string()),
+        });
+        output.print(Some(1), "Executing command: login -p hunter2");
+        tee(
+            &b"welcome\n"[..],
+            output.clone(),
+            Some(1),
+            "1".into(),
+            Stream::Stdout,
+        )
+        .await;
+
+        assert_eq!(
+            output.captured_text(),
+            [
+                r#"{"type":"command_start","index":1,"command":"login -p ********","group":"Auth"}"#,
+                r#"{"type":"message","step":1,"stream":"stdout","line":"Executing command: login -p ********"}"#,
+                r#"{"type":"log","step":1,"stream":"stdout","line":"welcome"}"#,
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    fn print_groups(output: &Output) {
+        output.enter_group(Some("Build"));
+        output.print(Some(1), "Executing command: make");
+        output.enter_group(Some("Build"));
+        output.print(Some(2), "Executing command: make test");
+        output.enter_group(None);
+        output.print(Some(3), "Executing command: date");
+        output.enter_group(Some("Deploy"));
+        output.enter_group(None);
+    }
+
+    #[test]
+    fn test_group_headers() {
+        let output = Output::captured(false);
+        print_groups(&output);
+        assert_eq!(
+            output.captured_text(),
+            "=== Build ===\nExecuting command: make\nExecuting command: make test\nExecuting command: date\n=== Deploy ===\n"
+        );
+    }
+
+    #[test]
+    fn test_github_actions_groups() {
+        let github = |github| Output {
+            github,
+            ..Output::captured(false)
+        };
+
+        let output = github(true);
+        print_groups(&output);
+        assert_eq!(
+            output.captured_text(),
+            "::group::Build\nExecuting command: make\nExecuting command: make test\n::endgroup::\nExecuting command: date\n::group::Deploy\n::endgroup::\n"
+        );
+
+        let output = github(false);
+        output.enter_group(Some("Build"));
+        assert_eq!(output.captured_text(), "=== Build ===\n");
+    }
+
+    #[test]
+    fn test_line_prefix() {
+        assert_eq!(line_prefix(None, Some(2)), "[2] ");
+        assert_eq!(line_prefix(None, None), "[-] ");
+        assert_eq!(
+            line_prefix(Some(Duration::from_millis(135_349)), Some(2)),
+            "[00:02:15.3] [2] "
+        );
+        assert_eq!(
+            line_prefix(Some(Duration::from_millis(36_061_999)), None),
+            "[10:01:01.9] [-] "
+        );
+    }
+
+    /// The elapsed time a stamped line starts with, in tenths of a second.
+    fn stamp_of(line: &str) -> u64 {
+        let stamp = line.strip_prefix('[').unwrap().split(']').next().unwrap();
+        let (time, tenths) = stamp.split_once('.').unwrap();
+        let parts: Vec<u64> = time.split(':').map(|p| p.parse().unwrap()).collect();
+        assert_eq!((parts.len(), tenths.len()), (3, 1), "{line}");
+        ((parts[0] * 60 + parts[1]) * 60 + parts[2]) * 10 + tenths.parse::<u64>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_timestamped_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zirv.log");
+        let terminal = Arc::new(Output::captured(false).with_timestamps(true));
+        let logged = Arc::new(
+            Output::new(Some(&path), UiMode::Plain, ColorMode::Never)
+                .with_timestamps(true)
+                .with_verbosity(Verbosity::Quiet),
+        );
+        for output in [&terminal, &logged] {
+            output.print(Some(1), "Executing command: make");
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            tee(
+                &b"built\nlinked\n"[..],
+                output.clone(),
+                Some(1),
+                "1".into(),
+                Stream::Stdout,
+            )
+            .await;
+            output.eprint(None, "Warning: slow\nvery slow");
+        }
+
+        let shown = terminal.captured_text();
+        let log = std::fs::read_to_string(&path).unwrap();
+        let log: Vec<&str> = log
+            .lines()
+            .map(|line| line.split_once(' ').unwrap().1)
+            .collect();
+        for lines in [shown.lines().collect::<Vec<_>>(), log] {
+            let rest: Vec<&str> = lines
+                .iter()
+                .map(|line| line.split_once("] ").unwrap().1)
+                .collect();
+            assert_eq!(
+                rest,
+                [
+                    "[1] Executing command: make",
+                    "[1] built",
+                    "[1] linked",
+                    "[-] Warning: slow",
+                    "[-] very slow"
+                ]
+            );
+            let stamps: Vec<u64> = lines.iter().map(|line| stamp_of(line)).collect();
+            assert!(stamps.is_sorted(), "{lines:?}");
+            assert!(stamps[1] > stamps[0], "{lines:?}");
+        }
+    }
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_millis(45_900)), "45s");
+        assert_eq!(format_elapsed(Duration::from_secs(150)), "2m30s");
+        assert_eq!(format_elapsed(Duration::from_secs(3900)), "1h05m00s");
+    }
+
+    #[tokio::test]
+    async fn test_tee_passes_plain_lines_through() {
+        let output = Arc::new(Output::captured(false));
+        tee(
+            &b"a\nb"[..],
+            output.clone(),
+            None,
+            "1".into(),
+            Stream::Stdout,
+        )
+        .await;
+        assert_eq!(output.captured_text(), "a\nb");
+    }
+}
@@ -0,0 +1,762 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::utils::{Overlay, canonicalize};
+
+use hashbrown::HashMap;
+use serde::Serialize;
+
+use super::{
+    command::{Command, path_prefix},
+    command_types::{CommandTypes, TERMINAL_QUOTING},
+    condition::Condition,
+    initial_context,
+    operating_system::OperatingSystem,
+    output::MASK,
+    report::SkipReason,
+    run_options::RunOptions,
+    script::Script,
+    secret::Secret,
+    session::{SHELL_FLAG, shell_program},
+    tags::TagFilter,
+};
+
+/// What running a script would do, worked out without running anything.
+#[derive(Debug, Serialize)]
+pub struct Plan {
+    pub script: String,
+    /// Param values in declaration order.
+    pub params: Vec<PlannedParam>,
+    /// The variables from `vars.yaml`, by name.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub vars: Vec<PlannedVar>,
+    /// The secrets the script reads; their values are never shown.
+    pub secrets: Vec<Secret>,
+    /// The environment overlay merged over the script, with the fields it
+    /// set outside of steps.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlay: Option<Overlay>,
+    pub steps: Vec<PlannedStep>,
+}
+
+/// A param with the value it gets.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PlannedParam {
+    pub name: String,
+    pub value: String,
+}
+
+/// A variable from `vars.yaml` with its value.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PlannedVar {
+    pub name: String,
+    pub value: String,
+    /// `param` or `secret` when one of the same name hides the variable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overridden_by: Option<String>,
+}
+
+/// A single step of a [`Plan`].
+#[derive(Debug, Serialize)]
+pub struct PlannedStep {
+    /// 1-based, like in the run summary.
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The command after substitution, with secrets masked. Variables
+    /// captured by earlier steps are only known at run time and stay as
+    /// `${...}`.
+    pub command: String,
+    /// How the command is started.
+    pub shell: String,
+    /// The directory the command starts in.
+    pub cwd: String,
+    /// The directories put in front of `PATH` for the command.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub path_prepend: Vec<String>,
+    /// The operating system the step is limited to, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operating_system: Option<String>,
+    /// The group the step belongs to, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// The `when` conditions of the step's group and of the step itself.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<PlannedCondition>,
+    /// Why the step would be skipped on this machine, if it would.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capture: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<String>,
+    pub proceed_on_failure: bool,
+    pub interactive: bool,
+    /// The fields of the step set by the environment overlay.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub from_overlay: Vec<String>,
+}
+
+/// A `when` condition with what it evaluates to before the run.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PlannedCondition {
+    /// The group the condition belongs to, or none for the step's own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub when: String,
+    /// Whether the condition holds, unless that cannot be told yet.
+    pub verdict: Option<bool>,
+    /// Why there is no verdict: the condition is invalid, or uses a
+    /// variable only the run captures.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl PlannedCondition {
+    fn new(group: Option<&str>, when: &str, context: &HashMap<String, String>) -> Self {
+        let (verdict, reason) = match Condition::parse(when) {
+            Err(e) => (
+                None,
+                Some(format!("invalid: {}", first_line(&e.to_string()))),
+            ),
+            Ok(condition) => match condition.evaluate(context) {
+                Ok(verdict) => (Some(verdict), None),
+                Err(e) => (
+                    None,
+                    Some(format!(
+                        "decided at run time: {}",
+                        first_line(&e.to_string())
+                    )),
+                ),
+            },
+        };
+        PlannedCondition {
+            group: group.map(String::from),
+            when: when.to_string(),
+            verdict,
+            reason,
+        }
+    }
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default()
+}
+
+fn shell(options: &RunOptions) -> String {
+    format!("{} {SHELL_FLAG}", shell_program(options.shell.as_deref()))
+}
+
+/// Where `cd <dir>` leads from `cwd`. Unlike a run, a directory that does
+/// not exist yet is not an error.
+fn change_dir(cwd: &Path, dir: &str) -> PathBuf {
+    let path = cwd.join(dir);
+    canonicalize(&path).unwrap_or(path)
+}
+
+/// Builds the plan for running `script` with `params`, checking params and
+/// secrets exactly like a run would.
+pub fn plan(script: &Script, params: &[String], options: &RunOptions) -> Result<Plan, String> {
+    let mut context = initial_context(script, params, options).map_err(|e| e.to_string())?;
+    let secrets = script.secrets.clone().unwrap_or_default();
+    for secret in &secrets {
+        context.insert(secret.name.clone(), MASK.to_string());
+    }
+
+    let mut cwd = match context.get("cwd") {
+        Some(cwd) => PathBuf::from(cwd),
+        None => std::env::current_dir().map_err(|e| e.to_string())?,
+    };
+    let tag_filter = TagFilter {
+        only: options.only_tags.clone(),
+        skip: options.skip_tags.clone(),
+    };
+
+    let shell = shell(options);
+    let mut steps = Vec::new();
+    for (i, step) in script.commands.iter().enumerate() {
+        let tag_skip = tag_filter.skip_reason(&step.tags());
+        let planned = match step {
+            CommandTypes::Command(command) => {
+                let planned = plan_command(
+                    i + 1,
+                    command,
+                    &context,
+                    &cwd,
+                    &shell,
+                    options.shell.as_deref(),
+                    tag_skip,
+                );
+                let planned = with_conditions(planned, step, script, &context);
+                if planned.skipped.is_none()
+                    && let Some(dir) = planned.command.trim_start().strip_prefix("cd ")
+                {
+                    cwd = change_dir(&cwd, dir.trim());
+                }
+                planned
+            }
+            CommandTypes::Commands(commands) => {
+                let planned = PlannedStep {
+                    index: i + 1,
+                    name: None,
+                    description: None,
+                    command: commands
+                        .iter()
+                        .map(|c| c.substituted_command(&context, TERMINAL_QUOTING))
+                        .collect::<Vec<_>>()
+                        .join(" && "),
+                    shell: "a new terminal window".to_string(),
+                    cwd: cwd.display().to_string(),
+                    path_prepend: Vec::new(),
+                    operating_system: None,
+                    group: None,
+                    conditions: Vec::new(),
+                    skipped: tag_skip,
+                    capture: None,
+                    fallbacks: Vec::new(),
+                    proceed_on_failure: false,
+                    interactive: true,
+                    from_overlay: Vec::new(),
+                };
+                with_conditions(planned, step, script, &context)
+            }
+        };
+        steps.push(planned);
+    }
+
+    Ok(Plan {
+        script: script.name.clone(),
+        params: script
+            .params
+            .iter()
+            .flatten()
+            .zip(params)
+            .map(|(name, value)| PlannedParam {
+                name: name.clone(),
+                value: value.clone(),
+            })
+            .collect(),
+        vars: options
+            .vars
+            .iter()
+            .map(|(name, value)| PlannedVar {
+                name: name.clone(),
+                value: value.clone(),
+                overridden_by: if script.params.iter().flatten().any(|p| p == name) {
+                    Some("param".to_string())
+                } else if secrets.iter().any(|s| s.name == *name) {
+                    Some("secret".to_string())
+                } else {
+                    None
+                },
+            })
+            .collect(),
+        secrets,
+        overlay: None,
+        steps,
+    })
+}
+
+/// `planned` with the `when` conditions of `step` and its group, skipped
+/// if one of them is false.
+fn with_conditions(
+    mut planned: PlannedStep,
+    step: &CommandTypes,
+    script: &Script,
+    context: &HashMap<String, String>,
+) -> PlannedStep {
+    planned.group = step.group().map(String::from);
+    if let Some(group) = step.group()
+        && let Some(when) = script.groups.get(group).and_then(|g| g.when.as_deref())
+    {
+        planned
+            .conditions
+            .push(PlannedCondition::new(Some(group), when, context));
+    }
+    if let CommandTypes::Command(command) = step
+        && let Some(when) = &command.when
+    {
+        planned
+            .conditions
+            .push(PlannedCondition::new(None, when, context));
+    }
+    if planned.skipped.is_none()
+        && let Some(condition) = planned.conditions.iter().find(|c| c.verdict == Some(false))
+    {
+        planned.skipped = Some(match &condition.group {
+            Some(group) => format!("the when condition of group '{group}' is false"),
+            None => format!("`{}` is false", condition.when),
+        });
+    }
+    planned
+}
+
+fn plan_command(
+    index: usize,
+    command: &Command,
+    context: &HashMap<String, String>,
+    cwd: &Path,
+    shell: &str,
+    configured_shell: Option<&str>,
+    tag_skip: Option<String>,
+) -> PlannedStep {
+    let options = command.options.clone().unwrap_or_default();
+    let operating_system = options
+        .operating_system
+        .as_ref()
+        .map(|os| os.name().to_string());
+    let os_skip = options
+        .operating_system
+        .as_ref()
+        .filter(|os| !os.is_current())
+        .map(|os| {
+            SkipReason::OperatingSystem {
+                required: os.name().to_string(),
+                current: OperatingSystem::current().name().to_string(),
+            }
+            .to_string()
+        });
+    let resolved = command.substituted_command(context, command.quoting(configured_shell));
+    let is_cd = resolved.trim_start().starts_with("cd ");
+
+    PlannedStep {
+        index,
+        name: command.name.clone(),
+        description: command.description.clone(),
+        command: resolved,
+        shell: match (is_cd, &options.container) {
+            (true, _) => "none, zirv changes the working directory".to_string(),
+            (false, Some(image)) => format!("sh -c in a container of {image}"),
+            (false, None) if options.wsl() && cfg!(windows) => "bash -lc in WSL".to_string(),
+            (false, None) => shell.to_string(),
+        },
+        cwd: cwd.display().to_string(),
+        path_prepend: if options.container.is_none() && !(options.wsl() && cfg!(windows)) {
+            path_prefix(&options, context)
+                .unwrap_or_default()
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect()
+        } else {
+            Vec::new()
+        },
+        operating_system,
+        group: None,
+        conditions: Vec::new(),
+        skipped: os_skip.or(tag_skip),
+        capture: command.capture.clone(),
+        fallbacks: options
+            .fallback
+            .iter()
+            .flatten()
+            .map(|f| f.substituted_command(context, f.quoting(configured_shell)))
+            .collect(),
+        proceed_on_failure: options.proceed_on_failure(),
+        interactive: options.interactive(),
+        from_overlay: Vec::new(),
+    }
+}
+
+impl Plan {
+    /// Records which values `overlay` contributed, by step.
+    pub fn annotate(&mut self, overlay: &Overlay) {
+        let mut script_fields = Vec::new();
+        for field in &overlay.fields {
+            let Some((index, rest)) = field
+                .strip_prefix("commands[")
+                .and_then(|rest| rest.split_once(']'))
+            else {
+                script_fields.push(field.clone());
+                continue;
+            };
+            if let Some(step) = index
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| self.steps.get_mut(i))
+            {
+                step.from_overlay.push(match rest.strip_prefix('.') {
+                    Some(rest) => rest.to_string(),
+                    None => "the whole step".to_string(),
+                });
+            }
+        }
+        self.overlay = Some(Overlay {
+            path: overlay.path.clone(),
+            fields: script_fields,
+        });
+    }
+
+    /// Writes the plan in a layout meant for reading.
+    pub fn write_text<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(writer, "Plan for '{}':", self.script)?;
+        if !self.params.is_empty() {
+            writeln!(writer, "Params:")?;
+            for param in &self.params {
+                writeln!(writer, "  {} = {}", param.name, param.value)?;
+            }
+        }
+        if !self.vars.is_empty() {
+            writeln!(writer, "Variables:")?;
+            for var in &self.vars {
+                write!(writer, "  {} = {}", var.name, var.value)?;
+                match &var.overridden_by {
+                    Some(kind) => writeln!(writer, " (overridden by the {kind})")?,
+                    None => writeln!(writer)?,
+                }
+            }
+        }
+        if !self.secrets.is_empty() {
+            writeln!(writer, "Secrets (masked):")?;
+            for secret in &self.secrets {
+                writeln!(writer, "  {} from ${}", secret.name, secret.env_var)?;
+            }
+        }
+
+        if let Some(overlay) = &self.overlay {
+            write!(writer, "Overlay: {}", overlay.path.display())?;
+            if overlay.fields.is_empty() {
+                writeln!(writer)?;
+            } else {
+                writeln!(writer, " (sets {})", overlay.fields.join(", "))?;
+            }
+        }
+
+        for step in &self.steps {
+            writeln!(writer)?;
+            match &step.name {
+                Some(name) => writeln!(writer, "Step {}: {name}", step.index)?,
+                None => writeln!(writer, "Step {}:", step.index)?,
+            }
+            if let Some(description) = &step.description {
+                writeln!(writer, "  Description: {description}")?;
+            }
+            writeln!(writer, "  Command: {}", step.command)?;
+            writeln!(writer, "  Shell: {}", step.shell)?;
+            writeln!(writer, "  Working directory: {}", step.cwd)?;
+            if !step.path_prepend.is_empty() {
+                let separator = if cfg!(windows) { ";" } else { ":" };
+                writeln!(
+                    writer,
+                    "  PATH prefix: {}",
+                    step.path_prepend.join(separator)
+                )?;
+            }
+            if let Some(os) = &step.operating_system {
+                writeln!(writer, "  Operating system: {os}")?;
+            }
+            if let Some(group) = &step.group {
+                writeln!(writer, "  Group: {group}")?;
+            }
+            for condition in &step.conditions {
+                match &condition.group {
+                    Some(group) => write!(writer, "  When (group {group}): {}", condition.when)?,
+                    None => write!(writer, "  When: {}", condition.when)?,
+                }
+                match (condition.verdict, &condition.reason) {
+                    (Some(verdict), _) => writeln!(writer, " ({verdict})")?,
+                    (None, Some(reason)) => writeln!(writer, " ({reason})")?,
+                    (None, None) => writeln!(writer)?,
+                }
+            }
+            if let Some(reason) = &step.skipped {
+                writeln!(writer, "  Skipped: {reason}")?;
+            }
+            if let Some(capture) = &step.capture {
+                writeln!(writer, "  Captures output into: ${{{capture}}}")?;
+            }
+            for fallback in &step.fallbacks {
+                writeln!(writer, "  Fallback: {fallback}")?;
+            }
+            if step.interactive {
+                writeln!(writer, "  Interactive")?;
+            }
+            if step.proceed_on_failure {
+                writeln!(writer, "  On failure: continue")?;
+            }
+            if !step.from_overlay.is_empty() {
+                writeln!(writer, "  From overlay: {}", step.from_overlay.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Script {
+        let other_os = if cfg!(target_os = "linux") {
+            "windows"
+        } else {
+            "linux"
+        };
+        serde_yaml::from_str(&format!(
+            r#"
+name: Deploy
+params: [env]
+secrets:
+  - name: plan_token
+    env_var: ZIRV_PLAN_TEST_TOKEN
+commands:
+  - name: build
+    command: cargo build --features ${{env}}
+    capture: artifact
+    options:
+      proceed_on_failure: true
+      fallback:
+        - command: cargo clean -p ${{env}}
+  - command: cd target
+  - command: ./upload ${{artifact}} --token ${{plan_token}}
+    description: Upload
+    options:
+      path_prepend: [bin, "${{env}}-tools"]
+  - command: echo elsewhere
+    options:
+      operating_system: {other_os}
+"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_plan_for_fixture() {
+        unsafe {
+            std::env::set_var("ZIRV_PLAN_TEST_TOKEN", "hunter2");
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let options = RunOptions {
+            project_root: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let plan = plan(&fixture(), &["prod".to_string()], &options).unwrap();
+        assert_eq!(
+            plan.params,
+            [PlannedParam {
+                name: "env".to_string(),
+                value: "prod".to_string()
+            }]
+        );
+        let steps = &plan.steps;
+        assert_eq!(steps.len(), 4);
+
+        assert_eq!(steps[0].command, "cargo build --features prod");
+        assert_eq!(steps[0].shell, shell(&options));
+        assert_eq!(steps[0].cwd, dir.path().display().to_string());
+        assert_eq!(steps[0].capture.as_deref(), Some("artifact"));
+        assert_eq!(steps[0].fallbacks, ["cargo clean -p prod"]);
+        assert!(steps[0].proceed_on_failure);
+
+        assert_eq!(steps[2].command, "./upload ${artifact} --token ********");
+        assert_eq!(
+            steps[2].cwd,
+            dir.path().join("target").display().to_string()
+        );
+        assert_eq!(steps[2].skipped, None);
+        assert_eq!(
+            steps[2].path_prepend,
+            [
+                dir.path().join("bin").display().to_string(),
+                dir.path().join("prod-tools").display().to_string()
+            ]
+        );
+        assert!(steps[0].path_prepend.is_empty());
+
+        assert!(
+            steps[3]
+                .skipped
+                .as_deref()
+                .unwrap()
+                .starts_with("only runs on "),
+        );
+
+        let mut text = Vec::new();
+        plan.write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(!text.contains("hunter2"), "{text}");
+        assert!(
+            text.starts_with(
+                "Plan for 'Deploy':\nParams:\n  env = prod\nSecrets (masked):\n  plan_token from $ZIRV_PLAN_TEST_TOKEN\n\nStep 1: build\n  Command: cargo build --features prod\n"
+            ),
+            "{text}"
+        );
+        assert!(text.contains("  Fallback: cargo clean -p prod\n  On failure: continue\n"));
+        assert!(text.contains("\nStep 3:\n  Description: Upload\n"));
+        assert!(text.contains(&format!(
+            "  PATH prefix: {}",
+            steps[2].path_prepend.join(if cfg!(windows) { ";" } else { ":" })
+        )));
+
+        let json = serde_json::to_value(&plan).unwrap();
+        assert_eq!(
+            json["steps"][1]["shell"],
+            "none, zirv changes the working directory"
+        );
+        assert_eq!(json["params"][0]["value"], "prod");
+        assert_eq!(json["secrets"][0]["env_var"], "ZIRV_PLAN_TEST_TOKEN");
+        assert!(json["steps"][2].get("operating_system").is_none());
+    }
+
+    #[test]
+    fn test_plan_shows_when_verdicts() {
+        let script: Script = serde_yaml::from_str(&format!(
+            r#"
+name: Release
+params: [env]
+groups:
+  Deploy:
+    when: ${{env}} == "prod"
+commands:
+  - command: git rev-parse HEAD
+    capture: sha
+    when: os == "{os}"
+  - command: echo ${{sha}}
+    when: ${{sha}} != ""
+  - command: ./deploy.sh
+    group: Deploy
+  - command: echo broken
+    when: os = "linux"
+"#,
+            os = std::env::consts::OS
+        ))
+        .unwrap();
+
+        let steps = plan(&script, &["staging".to_string()], &RunOptions::default())
+            .unwrap()
+            .steps;
+        let verdicts: Vec<(Option<bool>, Option<&str>)> = steps
+            .iter()
+            .flat_map(|step| &step.conditions)
+            .map(|c| (c.verdict, c.reason.as_deref()))
+            .collect();
+        assert_eq!(
+            verdicts,
+            [
+                (Some(true), None),
+                (
+                    None,
+                    Some("decided at run time: undefined variable '${sha}' at column 1")
+                ),
+                (Some(false), None),
+                (None, Some("invalid: unexpected '=' at column 4")),
+            ]
+        );
+        assert_eq!(steps[0].skipped, None);
+        assert_eq!(steps[1].skipped, None);
+        assert_eq!(
+            steps[2].skipped.as_deref(),
+            Some("the when condition of group 'Deploy' is false")
+        );
+
+        let plan = plan(&script, &["staging".to_string()], &RunOptions::default()).unwrap();
+        let mut text = Vec::new();
+        plan.write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(
+            text.contains(&format!(
+                "  When: os == \"{}\" (true)\n",
+                std::env::consts::OS
+            )),
+            "{text}"
+        );
+        assert!(
+            text.contains(
+                "  Group: Deploy\n  When (group Deploy): ${env} == \"prod\" (false)\n  Skipped: the when condition of group 'Deploy' is false\n"
+            ),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn test_plan_checks_params_like_a_run() {
+        let error = plan(&fixture(), &[], &RunOptions::default()).unwrap_err();
+        assert!(error.starts_with("missing params: env"), "{error}");
+    }
+
+    #[test]
+    fn test_overlay_annotations() {
+        unsafe {
+            std::env::set_var("ZIRV_PLAN_TEST_TOKEN", "hunter2");
+        }
+        let mut plan = plan(&fixture(), &["prod".to_string()], &RunOptions::default()).unwrap();
+        plan.annotate(&Overlay {
+            path: PathBuf::from("deploy.prod.yaml"),
+            fields: vec![
+                "description".to_string(),
+                "commands[0].options.proceed_on_failure".to_string(),
+                "commands[2].command".to_string(),
+                "commands[3]".to_string(),
+                "params".to_string(),
+            ],
+        });
+        assert_eq!(plan.steps[0].from_overlay, ["options.proceed_on_failure"]);
+        assert!(plan.steps[1].from_overlay.is_empty());
+        assert_eq!(plan.steps[3].from_overlay, ["the whole step"]);
+
+        let mut text = Vec::new();
+        plan.write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(
+            text.contains("Overlay: deploy.prod.yaml (sets description, params)\n"),
+            "{text}"
+        );
+        assert!(
+            text.contains(
+                "  Description: Upload\n  Command: ./upload ${artifact} --token ********\n"
+            ),
+            "{text}"
+        );
+        assert!(text.contains("  From overlay: command\n"), "{text}");
+
+        let json = serde_json::to_value(&plan).unwrap();
+        assert_eq!(json["overlay"]["fields"][1], "params");
+        assert_eq!(json["steps"][2]["from_overlay"][0], "command");
+    }
+
+    #[test]
+    fn test_plan_lists_vars() {
+        unsafe {
+            std::env::set_var("ZIRV_PLAN_TEST_TOKEN", "hunter2");
+        }
+        let options = RunOptions {
+            vars: crate::utils::Vars::from([
+                ("env".to_string(), "staging".to_string()),
+                ("registry".to_string(), "ghcr.io/acme".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        let plan = plan(&fixture(), &["prod".to_string()], &options).unwrap();
+        assert_eq!(
+            plan.vars,
+            [
+                PlannedVar {
+                    name: "env".to_string(),
+                    value: "staging".to_string(),
+                    overridden_by: Some("param".to_string()),
+                },
+                PlannedVar {
+                    name: "registry".to_string(),
+                    value: "ghcr.io/acme".to_string(),
+                    overridden_by: None,
+                },
+            ]
+        );
+        assert_eq!(plan.steps[0].command, "cargo build --features prod");
+
+        let mut text = Vec::new();
+        plan.write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(
+            text.contains(
+                "Variables:\n  env = staging (overridden by the param)\n  registry = ghcr.io/acme\n"
+            ),
+            "{text}"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command as TokioCommand;
+
+/// How much CPU time a command gets compared to other processes.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+/// `BELOW_NORMAL_PRIORITY_CLASS` and `ABOVE_NORMAL_PRIORITY_CLASS` from the
+/// Windows API.
+const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+const ABOVE_NORMAL_PRIORITY_CLASS: u32 = 0x0000_8000;
+
+impl Priority {
+    /// The niceness the command runs with on unix, if not the default.
+    /// Raising the priority usually needs root; without it `nice` warns and
+    /// runs the command at the normal priority.
+    pub fn niceness(self) -> Option<i32> {
+        match self {
+            Priority::Low => Some(10),
+            Priority::Normal => None,
+            Priority::High => Some(-5),
+        }
+    }
+
+    /// The process creation flag setting the priority class on Windows, if
+    /// not the default.
+    pub fn creation_flags(self) -> Option<u32> {
+        match self {
+            Priority::Low => Some(BELOW_NORMAL_PRIORITY_CLASS),
+            Priority::Normal => None,
+            Priority::High => Some(ABOVE_NORMAL_PRIORITY_CLASS),
+        }
+    }
+
+    /// `process`, made to run at this priority: on unix through `nice`, on
+    /// Windows with a priority class.
+    pub fn apply(self, process: TokioCommand) -> TokioCommand {
+        #[cfg(windows)]
+        {
+            let mut process = process;
+            if let Some(flags) = self.creation_flags() {
+                process.creation_flags(flags);
+            }
+            process
+        }
+        #[cfg(not(windows))]
+        {
+            let Some(niceness) = self.niceness() else {
+                return process;
+            };
+            let std = process.as_std();
+            let mut nice = TokioCommand::new("nice");
+            nice.arg("-n")
+                .arg(niceness.to_string())
+                .arg(std.get_program())
+                .args(std.get_args());
+            nice
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+        };
+        match (cfg!(windows), self.niceness()) {
+            (false, Some(niceness)) => write!(f, "{name} (nice {niceness})"),
+            _ => write!(f, "{name}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let priority: Priority = serde_yaml::from_str("low").unwrap();
+        assert_eq!(priority, Priority::Low);
+        let error = serde_yaml::from_str::<Priority>("lowest").unwrap_err();
+        assert!(
+            error.to_string().contains("unknown variant `lowest`"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let shell = || {
+            let mut process = TokioCommand::new("sh");
+            process.arg("-c").arg("make");
+            process
+        };
+        let process = Priority::Normal.apply(shell());
+        assert_eq!(process.as_std().get_program(), "sh");
+
+        let process = Priority::Low.apply(shell());
+        let args: Vec<_> = process.as_std().get_args().collect();
+        if cfg!(windows) {
+            assert_eq!(process.as_std().get_program(), "sh");
+            assert_eq!(Priority::Low.creation_flags(), Some(0x4000));
+            assert_eq!(Priority::High.creation_flags(), Some(0x8000));
+        } else {
+            assert_eq!(process.as_std().get_program(), "nice");
+            assert_eq!(args, ["-n", "10", "sh", "-c", "make"]);
+            assert_eq!(Priority::Low.to_string(), "low (nice 10)");
+        }
+    }
+}
@@ -0,0 +1,582 @@
+use std::{
+    io,
+    process::ExitStatus,
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+    process::{Child, Command as TokioCommand},
+    sync::Notify,
+};
+
+/// How long a step gets to exit after being asked to stop, unless its
+/// `grace_period_ms` or `config.yaml` says otherwise.
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How long a stopped or finished step's output is still read for, at
+/// most, when processes it left running keep it open.
+pub const DRAIN_LIMIT: Duration = Duration::from_millis(500);
+
+/// How often a step running in a terminal is checked for having been
+/// stopped by job control, e.g. by Ctrl-Z.
+#[cfg(unix)]
+const STOP_POLL: Duration = Duration::from_millis(100);
+
+/// The process group on unix, or the job object handle on Windows, holding
+/// a step's process and everything it starts.
+#[cfg(unix)]
+type Group = i32;
+#[cfg(windows)]
+type Group = isize;
+
+/// The groups of the steps running right now, so that [`kill_all`] can
+/// reach them when zirv is interrupted.
+static RUNNING: Mutex<Vec<Group>> = Mutex::new(Vec::new());
+
+/// How many [`RunGuard`]s are alive.
+static GUARDED_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Notified whenever a step's process tree or a guarded run ends, for
+/// [`interrupt`] to check whether everything has.
+static ENDED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// Stops the steps of every run, see [`interrupt`].
+static INTERRUPT: LazyLock<Stopper> = LazyLock::new(Stopper::default);
+
+/// How a step that was asked to stop ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Termination {
+    /// It exited within its grace period.
+    Graceful,
+    /// It was still running when its grace period ran out and was killed.
+    Forced,
+}
+
+/// Asks the steps of a run to stop, e.g. when the run times out or watch
+/// mode restarts it. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct Stopper(Arc<StopState>);
+
+#[derive(Debug, Default)]
+struct StopState {
+    stopping: AtomicBool,
+    notify: Notify,
+    termination: Mutex<Option<Termination>>,
+}
+
+impl Stopper {
+    /// Makes the running steps stop, first politely, and keeps new ones
+    /// from starting.
+    pub fn stop(&self) {
+        self.0.stopping.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    /// Whether this run, or every run, was asked to stop.
+    pub fn is_stopping(&self) -> bool {
+        self.0.stopping.load(Ordering::SeqCst) || INTERRUPT.0.stopping.load(Ordering::SeqCst)
+    }
+
+    /// How the steps that were asked to stop ended: [`Termination::Forced`]
+    /// if any of them had to be killed.
+    pub fn termination(&self) -> Option<Termination> {
+        *self.0.termination.lock().unwrap()
+    }
+
+    fn record(&self, termination: Termination) {
+        let mut recorded = self.0.termination.lock().unwrap();
+        if *recorded != Some(Termination::Forced) {
+            *recorded = Some(termination);
+        }
+    }
+
+    /// Returns once this run, or every run, is asked to stop.
+    pub(crate) async fn stopped(&self) {
+        tokio::select! {
+            _ = self.0.wait() => {}
+            _ = INTERRUPT.0.wait() => {}
+        }
+    }
+}
+
+impl StopState {
+    async fn wait(&self) {
+        let notified = self.notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if !self.stopping.load(Ordering::SeqCst) {
+            notified.await;
+        }
+    }
+}
+
+/// A step's process together with every process it starts. On unix it runs
+/// in a process group of its own, on Windows in a job object and a console
+/// process group, so stopping it does not leave grandchildren such as
+/// backgrounded servers behind. Dropping it before the process exited kills
+/// the whole tree.
+///
+/// On unix, a process outside the terminal's foreground group is stopped as
+/// soon as it touches the terminal, as `stty` and password prompts do. So
+/// while zirv is in the foreground of a terminal, the group gets the
+/// terminal until the process exits, and Ctrl-C and Ctrl-Z reach the step
+/// from there; see [`ProcessTree::wait`] for how zirv follows along.
+pub struct ProcessTree {
+    child: Child,
+    group: Option<Group>,
+    /// Whether the group was given zirv's terminal.
+    #[cfg(unix)]
+    terminal: bool,
+    exited: bool,
+    stopper: Stopper,
+}
+
+impl ProcessTree {
+    /// Spawns `command` in a new process tree, unless `stopper` was already
+    /// told to stop. With `own_group` false, e.g. for steps that run in a
+    /// session of their own or must share zirv's console on Windows, only
+    /// the process itself is tracked.
+    pub fn spawn(
+        command: &mut TokioCommand,
+        own_group: bool,
+        stopper: &Stopper,
+    ) -> io::Result<Self> {
+        if stopper.is_stopping() {
+            return Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "the run is being stopped",
+            ));
+        }
+        command.kill_on_drop(true);
+        #[cfg(unix)]
+        let tty = terminal::held().filter(|_| own_group);
+        #[cfg(unix)]
+        if own_group {
+            command.process_group(0);
+        }
+        // The child takes the terminal itself, before it can touch it.
+        #[cfg(unix)]
+        if let Some(tty) = tty {
+            unsafe {
+                command.pre_exec(move || {
+                    terminal::give(tty, libc::getpgrp());
+                    Ok(())
+                });
+            }
+        }
+        let child = command.spawn()?;
+        let group = if own_group { new_group(&child) } else { None };
+        if let Some(group) = group {
+            RUNNING.lock().unwrap().push(group);
+        }
+        Ok(Self {
+            child,
+            group,
+            #[cfg(unix)]
+            terminal: tty.is_some(),
+            exited: false,
+            stopper: stopper.clone(),
+        })
+    }
+
+    /// The process itself, e.g. to take its output from.
+    pub fn child(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Waits for the process to exit. When the run is asked to stop first,
+    /// the process is sent SIGTERM, or CTRL_BREAK on Windows, and killed
+    /// along with its tree if it is still running after `grace`. Processes
+    /// it left running are otherwise kept until [`ProcessTree::kill`] is
+    /// called or the tree is dropped.
+    ///
+    /// A step that holds the terminal and is stopped from it, e.g. by
+    /// Ctrl-Z, stops zirv too, and gets the terminal back and continues once
+    /// zirv does; one killed by Ctrl-C interrupts zirv as well.
+    pub async fn wait(&mut self, grace: Duration) -> io::Result<ExitStatus> {
+        let stopper = self.stopper.clone();
+        let status = tokio::select! {
+            status = self.exit() => status?,
+            _ = stopper.stopped() => {
+                let exited = if self.terminate() {
+                    tokio::time::timeout(grace, self.child.wait()).await.ok()
+                } else {
+                    None
+                };
+                match exited {
+                    Some(status) => {
+                        self.stopper.record(Termination::Graceful);
+                        status?
+                    }
+                    None => {
+                        self.kill();
+                        self.stopper.record(Termination::Forced);
+                        self.child.wait().await?
+                    }
+                }
+            }
+        };
+        self.exited = true;
+        #[cfg(unix)]
+        if self.release_terminal() {
+            use std::os::unix::process::ExitStatusExt;
+
+            if status.signal() == Some(libc::SIGINT) {
+                INTERRUPT.stop();
+                unsafe {
+                    libc::raise(libc::SIGINT);
+                }
+            }
+        }
+        Ok(status)
+    }
+
+    /// Waits for the process to exit, following it when job control stops
+    /// it while zirv runs in a terminal.
+    async fn exit(&mut self) -> io::Result<ExitStatus> {
+        #[cfg(unix)]
+        if let Some(group) = self.group
+            && terminal::tty().is_some()
+        {
+            loop {
+                if let Ok(status) = tokio::time::timeout(STOP_POLL, self.child.wait()).await {
+                    return status;
+                }
+                if self.child.id().is_some_and(is_stopped) {
+                    self.resume(group);
+                }
+            }
+        }
+        self.child.wait().await
+    }
+
+    /// Lets a stopped step carry on. If it was stopped from the terminal it
+    /// holds, zirv stops as well and hands the terminal back to the shell;
+    /// whenever zirv is in the terminal's foreground again, the step gets
+    /// the terminal and is continued.
+    #[cfg(unix)]
+    fn resume(&mut self, group: Group) {
+        if self.release_terminal() {
+            unsafe {
+                libc::raise(libc::SIGTSTP);
+            }
+        }
+        if let Some(tty) = terminal::held() {
+            terminal::give(tty, group);
+            self.terminal = true;
+            unsafe {
+                libc::killpg(group, libc::SIGCONT);
+            }
+        }
+    }
+
+    /// Gives the terminal back to zirv's process group if the step had it.
+    /// Returns whether it had.
+    #[cfg(unix)]
+    fn release_terminal(&mut self) -> bool {
+        let had = std::mem::take(&mut self.terminal);
+        if had && let Some(tty) = terminal::tty() {
+            terminal::give(tty, unsafe { libc::getpgrp() });
+        }
+        had
+    }
+
+    /// Kills the process and everything it started that still runs.
+    pub fn kill(&mut self) {
+        if let Some(group) = self.group {
+            kill_group(group);
+        }
+        let _ = self.child.start_kill();
+    }
+
+    /// Asks the process, and on unix its whole group, to exit, continuing
+    /// processes job control stopped so that they can. Returns whether it
+    /// could be asked at all.
+    #[cfg(unix)]
+    fn terminate(&self) -> bool {
+        match (self.group, self.child.id()) {
+            (Some(group), _) => unsafe {
+                let asked = libc::killpg(group, libc::SIGTERM) == 0;
+                libc::killpg(group, libc::SIGCONT);
+                asked
+            },
+            (None, Some(pid)) => unsafe { libc::kill(pid as i32, libc::SIGTERM) == 0 },
+            (None, None) => false,
+        }
+    }
+
+    /// Sends CTRL_BREAK to the process's console process group, which only
+    /// exists when it got its own.
+    #[cfg(windows)]
+    fn terminate(&self) -> bool {
+        use windows_sys::Win32::System::Console::{CTRL_BREAK_EVENT, GenerateConsoleCtrlEvent};
+
+        match (self.group, self.child.id()) {
+            (Some(_), Some(pid)) => unsafe { GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) != 0 },
+            _ => false,
+        }
+    }
+}
+
+impl Drop for ProcessTree {
+    fn drop(&mut self) {
+        if !self.exited {
+            self.kill();
+        }
+        #[cfg(unix)]
+        self.release_terminal();
+        if let Some(group) = self.group {
+            RUNNING.lock().unwrap().retain(|&running| running != group);
+            #[cfg(windows)]
+            unsafe {
+                windows_sys::Win32::Foundation::CloseHandle(group as _);
+            }
+            ENDED.notify_waiters();
+        }
+    }
+}
+
+/// Asks the steps of every run to stop, as [`Stopper::stop`] does for one
+/// run, and returns once none of them is running anymore and every
+/// [`RunGuard`] is gone.
+pub async fn interrupt() {
+    INTERRUPT.stop();
+    loop {
+        let ended = ENDED.notified();
+        tokio::pin!(ended);
+        ended.as_mut().enable();
+        if RUNNING.lock().unwrap().is_empty() && GUARDED_RUNS.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        ended.await;
+    }
+}
+
+/// Keeps [`interrupt`] waiting while it lives, so that a run stopped by
+/// Ctrl-C still reports and records how it ended before zirv exits.
+#[must_use]
+pub struct RunGuard(());
+
+impl RunGuard {
+    pub fn hold() -> RunGuard {
+        GUARDED_RUNS.fetch_add(1, Ordering::SeqCst);
+        RunGuard(())
+    }
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        GUARDED_RUNS.fetch_sub(1, Ordering::SeqCst);
+        ENDED.notify_waiters();
+    }
+}
+
+/// Whether [`interrupt`] was called.
+pub fn interrupted() -> bool {
+    INTERRUPT.0.stopping.load(Ordering::SeqCst)
+}
+
+/// Kills every step's process tree that is still running, e.g. when zirv
+/// is interrupted a second time.
+pub fn kill_all() {
+    for &group in RUNNING.lock().unwrap().iter() {
+        kill_group(group);
+    }
+}
+
+/// Handing zirv's controlling terminal to a step's process group and back.
+#[cfg(unix)]
+mod terminal {
+    use std::{fs::File, os::fd::AsRawFd, sync::LazyLock};
+
+    static TTY: LazyLock<Option<File>> = LazyLock::new(|| File::open("/dev/tty").ok());
+
+    /// zirv's controlling terminal, if it has one.
+    pub fn tty() -> Option<i32> {
+        TTY.as_ref().map(|tty| tty.as_raw_fd())
+    }
+
+    /// The terminal, if zirv's process group is in its foreground.
+    pub fn held() -> Option<i32> {
+        tty().filter(|&tty| unsafe { libc::tcgetpgrp(tty) == libc::getpgrp() })
+    }
+
+    /// Makes `group` the terminal's foreground process group. SIGTTOU is
+    /// blocked meanwhile, as the caller may be in the background. Only
+    /// async-signal-safe calls are made, so a child can call this before
+    /// it execs.
+    pub fn give(tty: i32, group: i32) {
+        unsafe {
+            let mut ttou: libc::sigset_t = std::mem::zeroed();
+            let mut previous: libc::sigset_t = std::mem::zeroed();
+            libc::sigemptyset(&mut ttou);
+            libc::sigaddset(&mut ttou, libc::SIGTTOU);
+            libc::pthread_sigmask(libc::SIG_BLOCK, &ttou, &mut previous);
+            libc::tcsetpgrp(tty, group);
+            libc::pthread_sigmask(libc::SIG_SETMASK, &previous, std::ptr::null_mut());
+        }
+    }
+}
+
+/// Whether the child `pid` is stopped, without reaping it if it exited.
+#[cfg(unix)]
+fn is_stopped(pid: u32) -> bool {
+    unsafe {
+        let mut info: libc::siginfo_t = std::mem::zeroed();
+        libc::waitid(
+            libc::P_PID,
+            pid,
+            &mut info,
+            libc::WSTOPPED | libc::WNOHANG | libc::WNOWAIT,
+        ) == 0
+            && info.si_pid() != 0
+    }
+}
+
+#[cfg(unix)]
+fn new_group(child: &Child) -> Option<Group> {
+    child.id().map(|pid| pid as Group)
+}
+
+#[cfg(unix)]
+fn kill_group(group: Group) {
+    unsafe {
+        libc::killpg(group, libc::SIGKILL);
+    }
+}
+
+/// A job object holding `child`, or `None` if one could not be created; the
+/// process is then killed on its own.
+#[cfg(windows)]
+fn new_group(child: &Child) -> Option<Group> {
+    use windows_sys::Win32::{
+        Foundation::CloseHandle,
+        System::JobObjects::{AssignProcessToJobObject, CreateJobObjectW},
+    };
+
+    let process = child.raw_handle()?;
+    unsafe {
+        let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+        if job.is_null() {
+            return None;
+        }
+        if AssignProcessToJobObject(job, process as _) == 0 {
+            CloseHandle(job);
+            return None;
+        }
+        Some(job as Group)
+    }
+}
+
+#[cfg(windows)]
+fn kill_group(group: Group) {
+    unsafe {
+        windows_sys::Win32::System::JobObjects::TerminateJobObject(group as _, 1);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::process::Stdio;
+
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    use super::*;
+
+    /// Whether `pid` is gone; a zombie nobody reaped counts as gone.
+    fn is_gone(pid: &str) -> bool {
+        let output = std::process::Command::new("ps")
+            .args(["-o", "stat=", "-p", pid])
+            .output()
+            .unwrap();
+        let stat = String::from_utf8_lossy(&output.stdout);
+        stat.trim().is_empty() || stat.trim().starts_with('Z')
+    }
+
+    async fn wait_until_gone(pid: &str) {
+        for _ in 0..50 {
+            if is_gone(pid) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("process {pid} is still running");
+    }
+
+    /// Starts `script` in a shell and returns its tree once it printed its
+    /// first line, along with that line.
+    async fn start(script: &str, stopper: &Stopper) -> (ProcessTree, String) {
+        let mut shell = TokioCommand::new("sh");
+        shell.arg("-c").arg(script).stdout(Stdio::piped());
+        let mut tree = ProcessTree::spawn(&mut shell, true, stopper).unwrap();
+        let stdout = tree.child().stdout.take().unwrap();
+        let mut line = String::new();
+        BufReader::new(stdout).read_line(&mut line).await.unwrap();
+        (tree, line.trim().to_string())
+    }
+
+    /// A shell that backgrounds a sleeper and prints its pid.
+    const SLEEPER: &str = "sleep 30 & echo $!; wait";
+
+    #[tokio::test]
+    async fn test_kill_reaches_grandchildren() {
+        let (mut tree, sleeper) = start(SLEEPER, &Stopper::default()).await;
+        assert!(!is_gone(&sleeper));
+        tree.kill();
+        let status = tree.wait(DEFAULT_GRACE_PERIOD).await.unwrap();
+        assert!(!status.success());
+        wait_until_gone(&sleeper).await;
+        assert!(RUNNING.lock().unwrap().contains(&tree.group.unwrap()));
+        drop(tree);
+    }
+
+    #[tokio::test]
+    async fn test_drop_kills_the_tree() {
+        let (tree, sleeper) = start(SLEEPER, &Stopper::default()).await;
+        let group = tree.group.unwrap();
+        drop(tree);
+        wait_until_gone(&sleeper).await;
+        assert!(!RUNNING.lock().unwrap().contains(&group));
+    }
+
+    #[tokio::test]
+    async fn test_stop_is_graceful_when_the_step_exits() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("marker");
+        let script = format!(
+            "trap 'echo cleaned up > {}; exit 0' TERM; echo ready; while true; do sleep 0.05; done",
+            marker.display()
+        );
+        let stopper = Stopper::default();
+        let (mut tree, _) = start(&script, &stopper).await;
+        stopper.stop();
+        tree.wait(DEFAULT_GRACE_PERIOD).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&marker).unwrap().trim(),
+            "cleaned up"
+        );
+        assert_eq!(stopper.termination(), Some(Termination::Graceful));
+
+        let mut shell = TokioCommand::new("true");
+        let error = ProcessTree::spawn(&mut shell, true, &stopper)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+    }
+
+    #[tokio::test]
+    async fn test_stop_is_forced_after_the_grace_period() {
+        let stopper = Stopper::default();
+        let (mut tree, _) = start("trap '' TERM; echo ready; sleep 30", &stopper).await;
+        stopper.stop();
+        let status = tree.wait(Duration::from_millis(200)).await.unwrap();
+        assert!(!status.success());
+        assert_eq!(stopper.termination(), Some(Termination::Forced));
+    }
+}
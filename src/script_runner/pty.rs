@@ -0,0 +1,207 @@
+use std::io;
+
+use tokio::process::Command as TokioCommand;
+
+use super::output::Stream;
+
+/// A pseudo-terminal for an interactive step whose output is captured. The
+/// step's stdin, stderr and controlling terminal are the pty, so that it can
+/// prompt for input, while its stdout stays a pipe for the capture.
+pub(crate) struct Pty {
+    #[cfg(unix)]
+    master: std::os::fd::OwnedFd,
+    #[cfg(unix)]
+    slave: std::os::fd::OwnedFd,
+}
+
+/// Relays between zirv's terminal and a [`Pty`] until dropped: keystrokes
+/// go to the step, and what it writes to the pty, such as prompts and the
+/// echo of what is typed, to zirv's stdout or stderr.
+pub(crate) struct Relay {
+    #[cfg(unix)]
+    stop: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    #[cfg(unix)]
+    saved: Option<libc::termios>,
+    #[cfg(unix)]
+    input: Option<std::thread::JoinHandle<()>>,
+    #[cfg(unix)]
+    output: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(unix)]
+impl Pty {
+    /// Opens a pty the size of zirv's terminal, if it has one.
+    pub fn open() -> io::Result<Pty> {
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+        let mut master = -1;
+        let mut slave = -1;
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        let size = match unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } {
+            0 => &mut size as *mut libc::winsize,
+            _ => std::ptr::null_mut(),
+        };
+        let opened = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                size,
+            )
+        };
+        if opened != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let pty = unsafe {
+            Pty {
+                master: OwnedFd::from_raw_fd(master),
+                slave: OwnedFd::from_raw_fd(slave),
+            }
+        };
+        // Neither end may leak into the processes of other steps.
+        for fd in [pty.master.as_raw_fd(), pty.slave.as_raw_fd()] {
+            if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(pty)
+    }
+
+    /// Makes `command` run with the pty as its stdin, stderr and
+    /// controlling terminal, in a session of its own.
+    pub fn attach(&self, command: &mut TokioCommand) -> io::Result<()> {
+        command
+            .stdin(self.slave.try_clone()?)
+            .stderr(self.slave.try_clone()?);
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    /// Starts relaying once `command` was spawned. zirv's copies of the
+    /// step's side of the pty, including those `command` holds, are closed,
+    /// so that the relay ends when the step and everything it started
+    /// exited. zirv's terminal is put in raw mode meanwhile, so that every
+    /// keystroke, Ctrl-C included, reaches the step as typed. What the step
+    /// shows goes to `shown`.
+    pub fn relay(self, command: &mut TokioCommand, shown: Stream) -> io::Result<Relay> {
+        use std::io::{Read, Write};
+        use std::sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        };
+
+        command
+            .stdin(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null());
+        drop(self.slave);
+
+        let mut from_step = std::fs::File::from(self.master.try_clone()?);
+        let mut to_step = std::fs::File::from(self.master);
+        let stop = Arc::new(AtomicBool::new(false));
+        let saved = raw_mode();
+
+        let output = std::thread::spawn(move || {
+            let mut chunk = [0u8; 4096];
+            // Reading fails with EIO once the step's side is closed.
+            while let Ok(n @ 1..) = from_step.read(&mut chunk) {
+                shown.write_through(&chunk[..n]);
+            }
+        });
+        let input = std::thread::spawn({
+            let stop = stop.clone();
+            move || {
+                let mut chunk = [0u8; 1024];
+                while !stop.load(Ordering::SeqCst) {
+                    let mut poll = libc::pollfd {
+                        fd: libc::STDIN_FILENO,
+                        events: libc::POLLIN,
+                        revents: 0,
+                    };
+                    if unsafe { libc::poll(&mut poll, 1, 100) } <= 0 {
+                        continue;
+                    }
+                    let n = unsafe {
+                        libc::read(libc::STDIN_FILENO, chunk.as_mut_ptr().cast(), chunk.len())
+                    };
+                    if n <= 0 || to_step.write_all(&chunk[..n as usize]).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Relay {
+            stop,
+            saved,
+            input: Some(input),
+            output: Some(output),
+        })
+    }
+}
+
+/// Puts zirv's terminal in raw mode, but for output processing, returning
+/// its settings to restore, or `None` if stdin is no terminal.
+#[cfg(unix)]
+fn raw_mode() -> Option<libc::termios> {
+    let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut saved) } != 0 {
+        return None;
+    }
+    let mut raw = saved;
+    raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+    raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+    raw.c_cc[libc::VMIN] = 1;
+    raw.c_cc[libc::VTIME] = 0;
+    (unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } == 0).then_some(saved)
+}
+
+#[cfg(unix)]
+impl Drop for Relay {
+    fn drop(&mut self) {
+        self.stop.store(true, std::sync::atomic::Ordering::SeqCst);
+        if let Some(saved) = &self.saved {
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, saved);
+            }
+        }
+        if let Some(input) = self.input.take() {
+            let _ = input.join();
+        }
+        // A process the step left running may keep the pty open; its
+        // output is then left behind rather than waited for.
+        if let Some(output) = self.output.take() {
+            let deadline = std::time::Instant::now() + std::time::Duration::from_millis(200);
+            while !output.is_finished() && std::time::Instant::now() < deadline {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            if output.is_finished() {
+                let _ = output.join();
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl Pty {
+    pub fn open() -> io::Result<Pty> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "pseudo-terminals are only supported on unix",
+        ))
+    }
+
+    pub fn attach(&self, _command: &mut TokioCommand) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn relay(self, _command: &mut TokioCommand, _shown: Stream) -> io::Result<Relay> {
+        Ok(Relay {})
+    }
+}
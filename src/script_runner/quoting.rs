@@ -0,0 +1,182 @@
+/// How the value of a `${name:q}` placeholder is quoted, by the shell the
+/// command runs in, so that it reaches the command as a single argument
+/// whatever it holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quoting {
+    /// sh and compatible shells such as bash and zsh: `'it'\''s'`.
+    Posix,
+    /// PowerShell: `'it''s'`.
+    PowerShell,
+    /// cmd: `^"it's^"`, with cmd's special characters escaped by `^`.
+    Cmd,
+}
+
+impl Quoting {
+    /// The quoting for `program`, a shell such as `bash` or
+    /// `C:\Program Files\PowerShell\7\pwsh.exe`: PowerShell's for
+    /// `powershell` and `pwsh`, cmd's for `cmd`, and sh's for anything else.
+    pub fn for_shell(program: &str) -> Quoting {
+        let name = program
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or(program)
+            .to_ascii_lowercase();
+        match name.strip_suffix(".exe").unwrap_or(&name) {
+            "powershell" | "pwsh" => Quoting::PowerShell,
+            "cmd" => Quoting::Cmd,
+            _ => Quoting::Posix,
+        }
+    }
+
+    /// Fails for a `value` this shell cannot receive as an argument: cmd
+    /// ends a command at a line break.
+    pub fn check(self, value: &str) -> Result<(), String> {
+        match self {
+            Quoting::Cmd if value.contains(['\n', '\r']) => {
+                Err("cmd cannot pass on a value with a line break".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// `value` as a single, literal argument; see [`Quoting::check`] for
+    /// what cmd cannot take.
+    pub fn quote(self, value: &str) -> String {
+        match self {
+            Quoting::Posix => format!("'{}'", value.replace('\'', r"'\''")),
+            // PowerShell also ends single-quoted strings at typographic
+            // single quotes.
+            Quoting::PowerShell => {
+                let mut quoted = String::from("'");
+                for c in value.chars() {
+                    if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+                        quoted.push(c);
+                    }
+                    quoted.push(c);
+                }
+                quoted.push('\'');
+                quoted
+            }
+            Quoting::Cmd => cmd_quote(value),
+        }
+    }
+}
+
+/// Quotes `value` for a program run by cmd: first for the program's own
+/// parsing of its command line, with `\"` for a quote, then with every
+/// character special to cmd, the quotes included, escaped by `^`.
+fn cmd_quote(value: &str) -> String {
+    let mut argument = String::from("\"");
+    let mut backslashes = 0;
+    for c in value.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                argument.push_str(&"\\".repeat(backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                argument.push_str(&"\\".repeat(backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            argument.push(c);
+        }
+    }
+    argument.push_str(&"\\".repeat(backslashes * 2));
+    argument.push('"');
+
+    let mut escaped = String::with_capacity(argument.len() * 2);
+    for c in argument.chars() {
+        if "()[]%!^\"`<>&|;, *?".contains(c) {
+            escaped.push('^');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUES: &[&str] = &[
+        "plain",
+        "fix: handle \"quotes\" & spaces",
+        "it's $HOME and $(whoami) and `id`",
+        "first line\nsecond line",
+        "",
+    ];
+
+    #[test]
+    fn test_quoting_for_shell() {
+        assert_eq!(Quoting::for_shell("sh"), Quoting::Posix);
+        assert_eq!(Quoting::for_shell("/usr/bin/bash"), Quoting::Posix);
+        assert_eq!(Quoting::for_shell("powershell"), Quoting::PowerShell);
+        assert_eq!(
+            Quoting::for_shell(r"C:\Program Files\PowerShell\7\pwsh.exe"),
+            Quoting::PowerShell
+        );
+        assert_eq!(Quoting::for_shell("CMD.EXE"), Quoting::Cmd);
+    }
+
+    #[test]
+    fn test_quote_for_sh() {
+        assert_eq!(
+            Quoting::Posix.quote("it's & \"done\""),
+            r#"'it'\''s & "done"'"#
+        );
+        for value in VALUES {
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("printf '%s' {}", Quoting::Posix.quote(value)))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), *value);
+        }
+    }
+
+    #[test]
+    fn test_quote_for_powershell() {
+        assert_eq!(
+            Quoting::PowerShell.quote("it's $HOME & \"done\""),
+            r#"'it''s $HOME & "done"'"#
+        );
+        assert_eq!(
+            Quoting::PowerShell.quote("it\u{2019}s"),
+            "'it\u{2019}\u{2019}s'"
+        );
+        assert_eq!(Quoting::PowerShell.quote("a\nb"), "'a\nb'");
+        // Runs the values through PowerShell where it is installed.
+        for shell in ["pwsh", "powershell"] {
+            for value in VALUES {
+                let Ok(output) = std::process::Command::new(shell)
+                    .args(["-NoProfile", "-Command"])
+                    .arg(format!(
+                        "[Console]::Out.Write({})",
+                        Quoting::PowerShell.quote(value)
+                    ))
+                    .output()
+                else {
+                    break;
+                };
+                assert_eq!(String::from_utf8_lossy(&output.stdout), *value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_quote_for_cmd() {
+        assert_eq!(
+            Quoting::Cmd.quote(r#"fix: handle "quotes" & 100%"#),
+            r#"^"fix:^ handle^ \^"quotes\^"^ ^&^ 100^%^""#
+        );
+        assert_eq!(Quoting::Cmd.quote(r"C:\dir\"), r#"^"C:\dir\\^""#);
+        assert_eq!(Quoting::Cmd.quote(r#"a\"b"#), r#"^"a\\\^"b^""#);
+        assert!(Quoting::Cmd.check("a & b").is_ok());
+        assert!(Quoting::Cmd.check("a\nb").is_err());
+        assert!(Quoting::Posix.check("a\nb").is_ok());
+        assert!(Quoting::PowerShell.check("a\r\nb").is_ok());
+    }
+}
@@ -0,0 +1,457 @@
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::process::Termination;
+use super::style::Style;
+
+/// Maximum number of characters of a command shown in the run summary.
+const SUMMARY_COMMAND_WIDTH: usize = 40;
+
+/// The outcome of a single step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    /// The step ran and succeeded.
+    Success,
+    /// The step was not executed (e.g. filtered out by `operating_system`).
+    Skipped,
+    /// The step failed. When the script continues afterwards this means
+    /// `proceed_on_failure` was set.
+    Failed,
+}
+
+impl StepStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepStatus::Success => "success",
+            StepStatus::Skipped => "skipped",
+            StepStatus::Failed => "failed",
+        }
+    }
+
+    /// How the status is shown in the summary.
+    pub fn style(&self) -> Style {
+        match self {
+            StepStatus::Success => Style::Green,
+            StepStatus::Skipped => Style::Dim,
+            StepStatus::Failed => Style::Red,
+        }
+    }
+}
+
+/// Why a step was skipped, serialized as an object whose `kind` tells
+/// which of these it is, e.g. `{"kind": "condition", "when": "$ci == true"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The step's `operating_system` is `required`, and zirv runs on
+    /// `current`.
+    OperatingSystem { required: String, current: String },
+    /// `--only` or `--skip-tag` left the step out, as `message` tells.
+    Tags { message: String },
+    /// The `when` condition of the step's `group` is false.
+    Group { group: String },
+    /// The step's own `when` condition is false.
+    Condition { when: String },
+    /// The step's `cache` inputs are unchanged since it last succeeded.
+    Cached,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::OperatingSystem { required, current } => {
+                write!(f, "only runs on {required}, this is {current}")
+            }
+            SkipReason::Tags { message } => write!(f, "{message}"),
+            SkipReason::Group { group } => {
+                write!(f, "the when condition of group '{group}' is false")
+            }
+            SkipReason::Condition { when } => write!(f, "`{when}` is false"),
+            SkipReason::Cached => write!(f, "cached, its inputs are unchanged"),
+        }
+    }
+}
+
+/// Timing and outcome of a single step.
+///
+/// The serialized form is part of the `--report` output; fields may be
+/// added but existing ones must keep their name and meaning.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct StepReport {
+    /// 1-based position of the step in the script.
+    pub index: usize,
+    /// The (unsubstituted) command of the step, so secret values never end up in a report.
+    pub command: String,
+    pub status: StepStatus,
+    #[serde(rename = "duration_ms", with = "duration_ms")]
+    pub duration: Duration,
+    /// Exit code of the last process spawned for this step, if any.
+    pub exit_code: Option<i32>,
+    /// Name of the variable the step captured its output into, if any.
+    pub capture: Option<String>,
+    /// The last lines the step wrote to stderr, if any.
+    #[serde(default)]
+    pub stderr_tail: Option<String>,
+    /// The group the step belongs to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Why the step was skipped, if it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<SkipReason>,
+}
+
+impl StepReport {
+    pub fn new(index: usize, command: String) -> Self {
+        Self {
+            index,
+            command,
+            status: StepStatus::Success,
+            duration: Duration::ZERO,
+            exit_code: None,
+            capture: None,
+            stderr_tail: None,
+            group: None,
+            skip_reason: None,
+        }
+    }
+
+    /// Marks the step as skipped for `reason`.
+    pub fn skip(&mut self, reason: SkipReason) {
+        self.status = StepStatus::Skipped;
+        self.skip_reason = Some(reason);
+    }
+}
+
+/// Collects what happened during a script run.
+///
+/// The serialized form is written by `--report`; fields may be added but
+/// existing ones must keep their name and meaning.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunReport {
+    /// The name of the script that was run.
+    pub script: String,
+    /// Whether the run completed without error.
+    pub success: bool,
+    /// The error that ended the run, if any.
+    pub error: Option<String>,
+    /// Wall-clock time of the whole run.
+    #[serde(rename = "duration_ms", with = "duration_ms")]
+    pub duration: Duration,
+    /// One entry per step that was attempted, in execution order.
+    pub steps: Vec<StepReport>,
+    /// Whether the run was stopped by the script's `timeout_ms`.
+    #[serde(default)]
+    pub timed_out: bool,
+    /// How the step running when the run was stopped ended, if one was.
+    #[serde(default)]
+    pub termination: Option<Termination>,
+    /// The `on_error` commands run after the failure, in order, numbered
+    /// like the step that failed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_error: Vec<StepReport>,
+}
+
+impl RunReport {
+    pub fn new(script: &str) -> Self {
+        Self {
+            script: script.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Writes the report as pretty-printed JSON to `path`.
+    pub fn write_json(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// The total duration of the steps of each group, in the order the
+    /// groups first appear.
+    pub fn group_durations(&self) -> Vec<(&str, Duration)> {
+        let mut groups: Vec<(&str, Duration)> = Vec::new();
+        for step in &self.steps {
+            let Some(group) = step.group.as_deref() else {
+                continue;
+            };
+            match groups.iter_mut().find(|(name, _)| *name == group) {
+                Some((_, total)) => *total += step.duration,
+                None => groups.push((group, step.duration)),
+            }
+        }
+        groups
+    }
+
+    /// Writes a human readable summary table of the run, followed by the
+    /// subtotal of each group if steps have groups.
+    pub fn write_summary<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.write_colored_summary(writer, false)
+    }
+
+    /// Writes the summary table of [`RunReport::write_summary`], with the
+    /// status of each step in green, red or dim if `color`. A skipped step
+    /// is followed by why it was skipped.
+    pub fn write_colored_summary<W: Write>(
+        &self,
+        writer: &mut W,
+        color: bool,
+    ) -> std::io::Result<()> {
+        writeln!(writer, "\nSummary for '{}':", self.script)?;
+        writeln!(
+            writer,
+            "  {:<4}{:<width$}  {:<9}{:>10}",
+            "#",
+            "Command",
+            "Status",
+            "Duration",
+            width = SUMMARY_COMMAND_WIDTH
+        )?;
+        for step in &self.steps {
+            let status = format!("{:<9}", step.status.as_str());
+            write!(
+                writer,
+                "  {:<4}{:<width$}  {}{:>10}",
+                step.index,
+                truncate(&step.command, SUMMARY_COMMAND_WIDTH),
+                step.status.style().paint_if(color, &status),
+                format_duration(step.duration),
+                width = SUMMARY_COMMAND_WIDTH
+            )?;
+            match &step.skip_reason {
+                Some(reason) => writeln!(
+                    writer,
+                    "  {}",
+                    Style::Dim.paint_if(color, &format!("({reason})"))
+                )?,
+                None => writeln!(writer)?,
+            }
+        }
+        let groups = self.group_durations();
+        if !groups.is_empty() {
+            writeln!(writer, "Groups:")?;
+        }
+        for (group, duration) in groups {
+            writeln!(
+                writer,
+                "  {:<width$}{:>10}",
+                truncate(group, SUMMARY_COMMAND_WIDTH),
+                format_duration(duration),
+                width = SUMMARY_COMMAND_WIDTH + 15
+            )?;
+        }
+        if self.timed_out {
+            writeln!(
+                writer,
+                "Total: {} {}",
+                format_duration(self.duration),
+                Style::Red.paint_if(color, "(timed out)")
+            )?;
+        } else {
+            writeln!(writer, "Total: {}", format_duration(self.duration))?;
+        }
+        Ok(())
+    }
+
+    /// One line telling how the run ended, e.g. `'Build' succeeded in
+    /// 1.25s`, in green or red if `color`.
+    pub fn status_line(&self, color: bool) -> String {
+        let (outcome, style) = match (self.success, self.timed_out) {
+            (true, _) => ("succeeded in", Style::Green),
+            (false, true) => ("timed out after", Style::Red),
+            (false, false) => ("failed after", Style::Red),
+        };
+        format!(
+            "'{}' {} {}",
+            self.script,
+            style.paint_if(color, outcome),
+            format_duration(self.duration)
+        )
+    }
+}
+
+/// Formats a duration as seconds with two decimals, e.g. `1.25s`.
+pub fn format_duration(duration: Duration) -> String {
+    format!("{:.2}s", duration.as_secs_f64())
+}
+
+mod duration_ms {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+fn truncate(s: &str, width: usize) -> String {
+    let single_line = s.lines().next().unwrap_or_default();
+    if single_line.chars().count() <= width && single_line.len() == s.len() {
+        return single_line.to_string();
+    }
+    let mut out: String = single_line.chars().take(width - 3).collect();
+    out.push_str("...");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("echo hi", 10), "echo hi");
+        assert_eq!(truncate("echo something long", 10), "echo so...");
+        assert_eq!(truncate("echo a\necho b", 20), "echo a...");
+    }
+
+    #[test]
+    fn test_write_summary() {
+        let report = RunReport {
+            script: "Build".to_string(),
+            success: true,
+            error: None,
+            steps: vec![StepReport {
+                duration: Duration::from_millis(1500),
+                ..StepReport::new(1, "cargo build".to_string())
+            }],
+            duration: Duration::from_millis(1600),
+            timed_out: false,
+            termination: None,
+            on_error: Vec::new(),
+        };
+
+        let mut buffer = Vec::new();
+        report.write_summary(&mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+
+        assert!(output.contains("Summary for 'Build':"));
+        assert!(output.contains("cargo build"));
+        assert!(output.contains("1.50s"));
+        assert!(output.contains("Total: 1.60s"));
+        assert!(!output.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_write_colored_summary() {
+        let mut failed = StepReport::new(2, "cargo test".to_string());
+        failed.status = StepStatus::Failed;
+        let report = RunReport {
+            steps: vec![StepReport::new(1, "cargo build".to_string()), failed],
+            timed_out: true,
+            ..RunReport::new("Build")
+        };
+
+        let mut plain = Vec::new();
+        report.write_colored_summary(&mut plain, false).unwrap();
+        let plain = String::from_utf8(plain).unwrap();
+        let mut buffer = Vec::new();
+        report.write_summary(&mut buffer).unwrap();
+        assert_eq!(plain, String::from_utf8(buffer).unwrap());
+
+        let mut colored = Vec::new();
+        report.write_colored_summary(&mut colored, true).unwrap();
+        let colored = String::from_utf8(colored).unwrap();
+        assert!(colored.contains(&Style::Green.paint("success  ")));
+        assert!(colored.contains(&Style::Red.paint("failed   ")));
+        assert!(colored.contains(&Style::Red.paint("(timed out)")));
+        let stripped = ["\x1b[31m", "\x1b[32m", "\x1b[0m"]
+            .iter()
+            .fold(colored, |text, code| text.replace(code, ""));
+        assert_eq!(stripped, plain);
+    }
+
+    #[test]
+    fn test_summary_subtotals_groups() {
+        let step = |index, group: Option<&str>, ms| StepReport {
+            duration: Duration::from_millis(ms),
+            group: group.map(String::from),
+            ..StepReport::new(index, format!("step {index}"))
+        };
+        let report = RunReport {
+            steps: vec![
+                step(1, Some("Build"), 1000),
+                step(2, None, 100),
+                step(3, Some("Deploy"), 250),
+                step(4, Some("Build"), 500),
+            ],
+            ..RunReport::new("Release")
+        };
+        assert_eq!(
+            report.group_durations(),
+            [
+                ("Build", Duration::from_millis(1500)),
+                ("Deploy", Duration::from_millis(250))
+            ]
+        );
+
+        let mut buffer = Vec::new();
+        report.write_summary(&mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        let groups = output.split_once("Groups:\n").unwrap().1;
+        assert_eq!(
+            groups.lines().collect::<Vec<_>>(),
+            [
+                format!("  {:<55}{:>10}", "Build", "1.50s"),
+                format!("  {:<55}{:>10}", "Deploy", "0.25s"),
+                "Total: 0.00s".to_string()
+            ]
+        );
+
+        let mut buffer = Vec::new();
+        RunReport::new("Plain").write_summary(&mut buffer).unwrap();
+        assert!(!String::from_utf8(buffer).unwrap().contains("Groups:"));
+    }
+
+    #[test]
+    fn test_report_json_schema() {
+        let mut step = StepReport::new(2, "git rev-parse HEAD".to_string());
+        step.status = StepStatus::Failed;
+        step.duration = Duration::from_millis(42);
+        step.exit_code = Some(128);
+        step.capture = Some("sha".to_string());
+
+        let report = RunReport {
+            script: "Release".to_string(),
+            success: false,
+            error: Some("boom".to_string()),
+            duration: Duration::from_millis(50),
+            steps: vec![step],
+            timed_out: false,
+            termination: None,
+            on_error: Vec::new(),
+        };
+
+        let value = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "script": "Release",
+                "success": false,
+                "error": "boom",
+                "duration_ms": 50,
+                "steps": [{
+                    "index": 2,
+                    "command": "git rev-parse HEAD",
+                    "status": "failed",
+                    "duration_ms": 42,
+                    "exit_code": 128,
+                    "capture": "sha",
+                    "stderr_tail": null
+                }],
+                "timed_out": false,
+                "termination": null
+            })
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::event::UiEvent;
+use super::process::Stopper;
+use super::style::ColorMode;
+use crate::utils::Vars;
+
+/// Settings for a single script run, usually derived from the command line.
+///
+/// Fields may be added in any release, so start from
+/// [`RunOptions::default`] and set the ones you need:
+///
+/// ```
+/// let mut options = zirv::script_runner::RunOptions::default();
+/// options.no_summary = true;
+/// ```
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RunOptions {
+    /// Suppresses the summary table printed after the run.
+    pub no_summary: bool,
+    /// If set, a JSON run report is written to this path after the run.
+    pub report: Option<PathBuf>,
+    /// If set, a JUnit XML report is written to this path after the run.
+    pub junit: Option<PathBuf>,
+    /// If non-empty, only steps carrying at least one of these tags run.
+    pub only_tags: Vec<String>,
+    /// Steps carrying any of these tags are skipped.
+    pub skip_tags: Vec<String>,
+    /// If set, everything printed during the run is also appended to this
+    /// file with timestamps.
+    pub log_file: Option<PathBuf>,
+    /// With `--repeat`, keep running the remaining repetitions after a failure.
+    pub keep_going: bool,
+    /// How child output is presented.
+    pub ui: UiMode,
+    /// The directory containing the project's `.zirv`. Steps start there and
+    /// it is available as `${zirv.project_root}`.
+    pub project_root: Option<PathBuf>,
+    /// Variables from `vars.yaml`, available to `${...}` placeholders unless
+    /// a param or secret has the same name.
+    pub vars: Vars,
+    /// If set, the final variables of a successful run are written to this
+    /// YAML file, leaving out secrets and anything derived from them.
+    pub save_context: Option<PathBuf>,
+    /// Variables saved by an earlier run with `--save-context`. They win
+    /// over `vars.yaml`, but not over params and secrets.
+    pub loaded_context: Vars,
+    /// The shell steps run in, instead of `sh` (`powershell` on Windows).
+    pub shell: Option<String>,
+    /// When what zirv prints is colored.
+    pub color: ColorMode,
+    /// How much zirv prints about the run.
+    pub verbosity: Verbosity,
+    /// Stamps every line printed or logged with the time since the run
+    /// started and its step.
+    pub timestamps: bool,
+    /// Whether the run is printed for people or as JSON events.
+    pub output: OutputFormat,
+    /// Values for the script's secrets by name, used instead of reading
+    /// their environment variables.
+    pub secrets: Vars,
+    /// If set, receives a [`UiEvent`] for every step and printed line.
+    pub events: Option<UnboundedSender<UiEvent>>,
+    /// If set, the run report is posted here instead of the script's
+    /// `notify_webhook`.
+    pub webhook: Option<String>,
+    /// Default for every command's `heartbeat_ms` option.
+    pub heartbeat_ms: Option<u64>,
+    /// Default for every command's `grace_period_ms` option.
+    pub grace_period_ms: Option<u64>,
+    /// Runs commands with a `cache` even when their inputs are unchanged.
+    pub no_cache: bool,
+    /// Stops the run's steps when told to, e.g. by watch mode on a change.
+    pub stopper: Stopper,
+    /// Warnings about the script found while loading it, such as fields
+    /// zirv does not know, printed before its first step.
+    pub warnings: Vec<String>,
+}
+
+/// How child output is presented: decorated with step prefixes and colors,
+/// or passed through as-is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UiMode {
+    /// Decorated when stdout is a terminal, plain otherwise.
+    #[default]
+    Auto,
+    Plain,
+    Tui,
+}
+
+/// How a run is printed on stdout. The events of `json` are
+/// [`JsonEvent`](super::JsonEvent)s.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Lines meant to be read by people.
+    #[default]
+    Text,
+    /// One JSON event per line, and nothing else.
+    Json,
+}
+
+/// How much zirv prints about a run, from least to most.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    /// Only errors and the final status of the run.
+    Quiet,
+    /// Command echoes, skipped steps, command output and the summary.
+    #[default]
+    Normal,
+    /// Also each step's working directory, shell, environment overrides
+    /// and substituted placeholders.
+    Verbose,
+    /// Also the processes each step starts and how they exit.
+    Debug,
+}
+
+impl UiMode {
+    /// Picks the mode from the command line, falling back to the value of
+    /// the `ZIRV_UI` environment variable and finally to auto-detection.
+    pub fn resolve(cli: UiMode, env: Option<&str>) -> Result<UiMode, String> {
+        if cli != UiMode::Auto {
+            return Ok(cli);
+        }
+        match env.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("") | Some("auto") => Ok(UiMode::Auto),
+            Some("plain") => Ok(UiMode::Plain),
+            Some("tui") => Ok(UiMode::Tui),
+            Some(other) => Err(format!(
+                "Invalid ZIRV_UI value '{other}': expected 'plain', 'tui' or 'auto'"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ui_mode_precedence() {
+        assert_eq!(UiMode::resolve(UiMode::Auto, None), Ok(UiMode::Auto));
+        assert_eq!(
+            UiMode::resolve(UiMode::Auto, Some("plain")),
+            Ok(UiMode::Plain)
+        );
+        assert_eq!(UiMode::resolve(UiMode::Auto, Some("TUI")), Ok(UiMode::Tui));
+        assert_eq!(UiMode::resolve(UiMode::Tui, Some("plain")), Ok(UiMode::Tui));
+        assert_eq!(
+            UiMode::resolve(UiMode::Plain, Some("tui")),
+            Ok(UiMode::Plain)
+        );
+        assert!(UiMode::resolve(UiMode::Auto, Some("fancy")).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use std::sync::LazyLock;
+
+use jsonschema::{ValidationError, Validator, error::ValidationErrorKind};
+use serde_json::Value;
+
+use super::script::Script;
+
+/// The JSON Schema of a script file, derived from the serde definitions.
+///
+/// zirv itself ignores unknown fields when parsing, but in the schema they
+/// are rejected so editors and [`unknown_fields`] flag typos such as
+/// `captur:`.
+pub(crate) fn script_schema() -> Value {
+    let mut schema =
+        serde_json::to_value(schemars::schema_for!(Script)).expect("schemas serialize to JSON");
+    deny_unknown_fields(&mut schema);
+    schema
+}
+
+fn deny_unknown_fields(schema: &mut Value) {
+    match schema {
+        Value::Object(object) => {
+            if object.contains_key("properties") {
+                object
+                    .entry("additionalProperties")
+                    .or_insert(Value::Bool(false));
+            }
+            object.values_mut().for_each(deny_unknown_fields);
+        }
+        Value::Array(items) => items.iter_mut().for_each(deny_unknown_fields),
+        _ => {}
+    }
+}
+
+/// Compiled once, since every script zirv loads is checked against it.
+static VALIDATOR: LazyLock<Validator> = LazyLock::new(|| {
+    jsonschema::validator_for(&script_schema()).expect("the script schema is valid")
+});
+
+/// Parses `content` as a YAML, JSON or TOML document depending on `ext`,
+/// without giving it the shape of a script.
+pub(crate) fn parse_document(content: &str, ext: &str) -> Result<Value, String> {
+    match ext {
+        "yaml" | "yml" => serde_yaml::from_str(content).map_err(|e| e.to_string()),
+        "json" => serde_json::from_str(content).map_err(|e| e.to_string()),
+        "toml" => toml::from_str(content).map_err(|e| e.to_string()),
+        other => Err(format!("Unsupported extension: {other}")),
+    }
+}
+
+/// The fields of `document`, a script, that zirv does not know and would
+/// ignore, such as a misspelled option, e.g. `step 2: unknown field
+/// 'porceed_on_failure' in options`.
+pub(crate) fn unknown_fields(document: &Value) -> Vec<String> {
+    let mut found = Vec::new();
+    for error in VALIDATOR.iter_errors(document) {
+        collect_unknown_fields(&error, &mut found);
+    }
+    found.dedup();
+    found
+}
+
+/// Collects the unknown fields `error` is about. For a value failing every
+/// alternative of an `anyOf`, such as a step, which is a command or a
+/// group, only the alternatives of the value's type are looked into.
+fn collect_unknown_fields(error: &ValidationError, found: &mut Vec<String>) {
+    match error.kind() {
+        ValidationErrorKind::AdditionalProperties { unexpected } => {
+            let location = error.instance_path().to_string();
+            found.extend(unexpected.iter().map(|name| describe(&location, name)));
+        }
+        ValidationErrorKind::AnyOf { context } => {
+            let path = error.instance_path();
+            for errors in context.iter().filter(|errors| {
+                !errors.iter().any(|e| {
+                    e.instance_path() == path
+                        && matches!(e.kind(), ValidationErrorKind::Type { .. })
+                })
+            }) {
+                errors.iter().for_each(|e| collect_unknown_fields(e, found));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Describes the unknown field `name` of the object at `location`, a JSON
+/// pointer, by the step it belongs to.
+fn describe(location: &str, name: &str) -> String {
+    let segments: Vec<&str> = location.split('/').skip(1).collect();
+    let step = |kind: &str, index: &str| {
+        index
+            .parse::<usize>()
+            .map(|i| format!("{kind} {}", i + 1))
+            .ok()
+    };
+    let (owner, rest) = match segments.as_slice() {
+        ["commands", index, rest @ ..] => (step("step", index), rest),
+        ["on_error", index, rest @ ..] => (step("on_error", index), rest),
+        rest => (None, rest),
+    };
+    let mut message = format!("unknown field '{name}'");
+    if !rest.is_empty() {
+        message.push_str(&format!(" in {}", rest.join(".")));
+    }
+    match owner {
+        Some(owner) => format!("{owner}: {message}"),
+        None => message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_rejects_unknown_fields() {
+        let validator = jsonschema::validator_for(&script_schema()).unwrap();
+        let document: Value =
+            serde_yaml::from_str("name: x\ncommands:\n  - command: ls\n    captur: out\n").unwrap();
+        let errors: Vec<String> = validator
+            .iter_errors(&document)
+            .map(|e| e.to_string())
+            .collect();
+        assert!(errors.iter().any(|e| e.contains("captur")), "{errors:?}");
+    }
+
+    #[test]
+    fn test_unknown_fields_are_located() {
+        let document: Value = serde_yaml::from_str(
+            r#"
+name: Deploy
+descripton: Ship it.
+secrets:
+  - name: token
+    env: TOKEN
+commands:
+  - command: ls
+  - command: cargo test
+    options:
+      porceed_on_failure: true
+  - - command: npm run watch
+      captur: out
+on_error:
+  - command: echo failed
+    option: {}
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            unknown_fields(&document),
+            vec![
+                "step 2: unknown field 'porceed_on_failure' in options",
+                "step 3: unknown field 'captur' in 0",
+                "on_error 1: unknown field 'option'",
+                "unknown field 'env' in secrets.0",
+                "unknown field 'descripton'",
+            ]
+        );
+
+        let valid: Value =
+            serde_yaml::from_str("name: x\ncommands:\n  - command: ls\n    capture: out\n")
+                .unwrap();
+        assert_eq!(unknown_fields(&valid), Vec::<String>::new());
+    }
+}
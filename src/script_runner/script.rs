@@ -1,47 +1,291 @@
+use std::collections::BTreeMap;
+use std::time::Instant;
+
 use hashbrown::HashMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use super::{command_types::CommandTypes, secret::Secret};
+use super::{
+    ERROR_VAR, FAILED_COMMAND_VAR, FAILED_STEP_VAR,
+    command::Command,
+    command_types::CommandTypes,
+    condition::Condition,
+    error::{RunError, StepError},
+    event::UiEvent,
+    options::Options,
+    report::{RunReport, SkipReason, StepReport, StepStatus},
+    secret::Secret,
+    session::Session,
+    style::Style,
+};
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default, JsonSchema)]
 pub struct Script {
     /// A descriptive name for the script.
     pub name: String,
     // A description of what the script does.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    /// Other names the script can be run by, e.g. `[b, bld]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
     /// Optional list of expected parameter names (in order).
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub params: Option<Vec<String>>,
     /// Optional list of secret definitions.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub secrets: Option<Vec<Secret>>,
+    /// The versions of zirv the script runs with, e.g. `">=0.4"`; see
+    /// [`VersionRequirement`](super::compat::VersionRequirement).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zirv_version: Option<String>,
+    /// The version of the script format the script is written in; zirv
+    /// rejects scripts newer than [`SCHEMA_VERSION`](super::compat::SCHEMA_VERSION).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<u32>,
     /// A list of commands to execute.
     pub commands: Vec<CommandTypes>,
+    /// Default for every command's `export_context` option.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub export_context: bool,
+    /// Default for every command's `export_secrets` option.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub export_secrets: bool,
+    /// Default for every command's `export_prefix` option.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub export_prefix: Option<String>,
+    /// Default for every command's `isolate_env` option.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub isolate_env: bool,
+    /// Options applied to every command; a command's own options win.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<Options>,
+    /// Require the user to type the script's name before it runs, unless
+    /// `--yes` is passed.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dangerous: bool,
+    /// What to tell the user before asking to confirm a `dangerous` script.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirm_message: Option<String>,
+    /// A URL the run report is posted to as JSON when the script finishes;
+    /// `--webhook` takes precedence.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_webhook: Option<String>,
+    /// The longest the whole run may take, in milliseconds. When it is
+    /// exceeded, the running step is killed and the run fails.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    /// Commands run once when a step fails the run, after its own
+    /// fallbacks, with `${zirv.failed_step}`, `${zirv.failed_command}` and
+    /// `${zirv.error}` describing the failure.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_error: Vec<Command>,
+    /// Settings of the groups named by the commands' `group`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, Group>,
+}
+
+/// Settings shared by the steps of a group.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct Group {
+    /// Runs the group's steps only while this condition holds when the
+    /// group starts; see [`Condition`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
 }
 
 impl Script {
-    pub async fn run(&self, context: &mut HashMap<String, String>) -> Result<(), String> {
+    /// The script with its `defaults` merged into the options of every
+    /// command, and removed; see [`Options::merged_over`].
+    pub fn with_defaults(mut self) -> Script {
+        let Some(defaults) = self.defaults.take() else {
+            return self;
+        };
+        let steps = self
+            .commands
+            .iter_mut()
+            .flat_map(CommandTypes::commands_mut);
+        for command in steps.chain(&mut self.on_error) {
+            command.options = Some(
+                command
+                    .options
+                    .take()
+                    .unwrap_or_default()
+                    .merged_over(&defaults),
+            );
+        }
+        self
+    }
+
+    /// Runs every step in order, recording the timing and outcome of each
+    /// attempted step in `report`.
+    pub async fn run(
+        &self,
+        context: &mut HashMap<String, String>,
+        session: &Session,
+        report: &mut RunReport,
+    ) -> Result<(), RunError> {
+        // The group being run, and whether its steps run.
+        let mut group: Option<(&str, Result<bool, String>)> = None;
+
         // Execution loop
-        for step in &self.commands {
-            match step.execute(context).await {
-                Ok(Some(output)) => {
-                    println!("Command output: {output}");
-                }
-                Ok(None) => {}
-                Err(e) => {
-                    return Err(format!(
-                        "Error executing command in script '{}': {}",
-                        self.name, e
-                    ));
+        for (i, step) in self.commands.iter().enumerate() {
+            // Whoever stopped the run decides how it ends.
+            if session.stopper.is_stopping() {
+                break;
+            }
+            session.enter_step(i + 1);
+            let mut step_report = StepReport::new(i + 1, step.label());
+            step_report.group = step.group().map(String::from);
+            session.output.enter_group(step.group());
+            if step.group() != group.as_ref().map(|(name, _)| *name) {
+                group = step
+                    .group()
+                    .map(|name| (name, self.group_holds(name, context)));
+            }
+            let group_skip = match &group {
+                Some((name, Ok(false))) => Some(SkipReason::Group {
+                    group: name.to_string(),
+                }),
+                _ => None,
+            };
+            let tag_skip = session
+                .tag_filter
+                .skip_reason(&step.tags())
+                .map(|message| SkipReason::Tags { message });
+
+            if let Some(reason) = tag_skip.or(group_skip) {
+                session.output.print_styled(
+                    Some(i + 1),
+                    Style::Dim,
+                    &format!("Skipping step {}: {reason}", i + 1),
+                );
+                step_report.skip(reason);
+                send_finished(session, &step_report);
+                report.steps.push(step_report);
+                continue;
+            }
+
+            session.output.send(UiEvent::StepStarted {
+                index: i + 1,
+                label: step_report.command.clone(),
+                group: step_report.group.clone(),
+            });
+            let start = Instant::now();
+            let result = match &group {
+                Some((_, Err(message))) => Err(StepError::Failed(message.clone())),
+                _ => step.execute(context, session, &mut step_report).await,
+            };
+
+            step_report.status = *result.as_ref().unwrap_or(&StepStatus::Failed);
+            step_report.duration = start.elapsed();
+            send_finished(session, &step_report);
+            let exit_code = step_report.exit_code;
+            report.steps.push(step_report);
+
+            if let Err(error) = result {
+                // A stopped run ends without running anything more; one
+                // that timed out runs its handlers once the step stopped.
+                if !session.stopper.is_stopping() {
+                    self.handle_error(i + 1, step, &error.to_string(), context, session, report)
+                        .await;
                 }
+                return Err(RunError::Step {
+                    script: self.name.clone(),
+                    exit_code,
+                    error,
+                });
             }
         }
 
         Ok(())
     }
+
+    /// Whether the steps of `group` run, by the `when` condition in its
+    /// settings, if it has one.
+    fn group_holds(&self, group: &str, context: &HashMap<String, String>) -> Result<bool, String> {
+        let Some(when) = self.groups.get(group).and_then(|g| g.when.as_deref()) else {
+            return Ok(true);
+        };
+        Condition::parse(when)
+            .and_then(|condition| condition.evaluate(context))
+            .map_err(|e| format!("Invalid when condition of group '{group}': {e}"))
+    }
+
+    /// Runs the `on_error` commands after step `index` failed with `error`,
+    /// with the failure added to a copy of `context`, and records them in
+    /// `report`. A failing handler is reported and ends the handlers, but
+    /// never the failure of the run.
+    pub(crate) async fn handle_error(
+        &self,
+        index: usize,
+        step: &CommandTypes,
+        error: &str,
+        context: &HashMap<String, String>,
+        session: &Session,
+        report: &mut RunReport,
+    ) {
+        if self.on_error.is_empty() {
+            return;
+        }
+        let mut context = context.clone();
+        context.insert(FAILED_STEP_VAR.to_string(), step.label());
+        context.insert(
+            FAILED_COMMAND_VAR.to_string(),
+            step.command_line(&context, session.shell.as_deref()),
+        );
+        context.insert(ERROR_VAR.to_string(), error.to_string());
+
+        session.output.print_styled(
+            Some(index),
+            Style::Dim,
+            &format!("Step {index} failed, running on_error"),
+        );
+        for handler in &self.on_error {
+            let mut handler_report = StepReport::new(index, handler.command.clone());
+            let start = Instant::now();
+            let result = handler
+                .execute(&mut context, session, &mut handler_report)
+                .await;
+            handler_report.status = *result.as_ref().unwrap_or(&StepStatus::Failed);
+            handler_report.duration = start.elapsed();
+            report.on_error.push(handler_report);
+            if let Err(e) = result {
+                session.output.eprint_styled(
+                    Some(index),
+                    Style::Red,
+                    &format!("on_error command failed: {e}"),
+                );
+                break;
+            }
+        }
+    }
+}
+
+/// Sends that `step` finished, after why it was skipped if it was.
+fn send_finished(session: &Session, step: &StepReport) {
+    if let Some(reason) = &step.skip_reason {
+        session.output.send(UiEvent::CommandSkipped {
+            index: step.index,
+            reason: reason.clone(),
+        });
+    }
+    session.output.send(UiEvent::StepFinished {
+        index: step.index,
+        status: step.status,
+        duration: step.duration,
+        exit_code: step.exit_code,
+    });
 }
 
 #[cfg(test)]
 mod tests {
+    #[cfg(unix)]
+    use std::sync::Arc;
+
     use crate::script_runner::command::Command;
+    #[cfg(unix)]
+    use crate::script_runner::{output::Output, run_options::RunOptions};
 
     use super::*;
 
@@ -57,12 +301,16 @@ mod tests {
                 capture: None,
                 description: Some("Prints Hello World".to_string()),
                 options: None,
+                ..Default::default()
             })],
+            ..Default::default()
         };
 
         let mut context = HashMap::new();
 
-        let result = script.run(&mut context).await;
+        let result = script
+            .run(&mut context, &Session::default(), &mut RunReport::default())
+            .await;
         assert!(result.is_ok());
     }
 
@@ -79,19 +327,24 @@ mod tests {
                     capture: None,
                     description: Some("Prints First Command".to_string()),
                     options: None,
+                    ..Default::default()
                 }),
                 CommandTypes::Command(Command {
                     command: "echo 'Second Command'".to_string(),
                     capture: None,
                     description: Some("Prints Second Command".to_string()),
                     options: None,
+                    ..Default::default()
                 }),
             ],
+            ..Default::default()
         };
 
         let mut context = HashMap::new();
 
-        let result = script.run(&mut context).await;
+        let result = script
+            .run(&mut context, &Session::default(), &mut RunReport::default())
+            .await;
         assert!(result.is_ok());
     }
 
@@ -110,7 +363,9 @@ mod tests {
                 capture: None,
                 description: Some("Prints the commit password".to_string()),
                 options: None,
+                ..Default::default()
             })],
+            ..Default::default()
         };
 
         let mut context = HashMap::new();
@@ -119,7 +374,9 @@ mod tests {
             "my_secret_password".to_string(),
         );
 
-        let result = script.run(&mut context).await;
+        let result = script
+            .run(&mut context, &Session::default(), &mut RunReport::default())
+            .await;
         assert!(result.is_ok());
     }
 
@@ -135,14 +392,18 @@ mod tests {
                 capture: None,
                 description: Some("Prints parameters".to_string()),
                 options: None,
+                ..Default::default()
             })],
+            ..Default::default()
         };
 
         let mut context = HashMap::new();
         context.insert("param1".to_string(), "value1".to_string());
         context.insert("param2".to_string(), "value2".to_string());
 
-        let result = script.run(&mut context).await;
+        let result = script
+            .run(&mut context, &Session::default(), &mut RunReport::default())
+            .await;
         assert!(result.is_ok());
     }
 
@@ -154,11 +415,178 @@ mod tests {
             params: None,
             secrets: None,
             commands: vec![],
+            ..Default::default()
         };
 
         let mut context = HashMap::new();
 
-        let result = script.run(&mut context).await;
+        let result = script
+            .run(&mut context, &Session::default(), &mut RunReport::default())
+            .await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_script_run_records_steps() {
+        let script = Script {
+            name: "Summary Script".to_string(),
+            description: None,
+            params: None,
+            secrets: None,
+            commands: vec![
+                CommandTypes::Command(Command {
+                    command: "echo 'step one'".to_string(),
+                    capture: None,
+                    description: None,
+                    options: None,
+                    ..Default::default()
+                }),
+                CommandTypes::Command(Command {
+                    command: "echo 'step two'".to_string(),
+                    capture: None,
+                    description: None,
+                    options: None,
+                    ..Default::default()
+                }),
+            ],
+            ..Default::default()
+        };
+
+        let mut context = HashMap::new();
+        let mut report = RunReport::new(&script.name);
+        script
+            .run(&mut context, &Session::default(), &mut report)
+            .await
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        report.write_summary(&mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+
+        for command in ["echo 'step one'", "echo 'step two'"] {
+            let rows: Vec<&str> = output.lines().filter(|l| l.contains(command)).collect();
+            assert_eq!(
+                rows.len(),
+                1,
+                "'{command}' should appear once in:\n{output}"
+            );
+            assert!(rows[0].contains("success"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_script_run_with_tag_filters() {
+        let dir = tempfile::tempdir().unwrap();
+        let touch = |name: &str, tags: &[&str]| {
+            CommandTypes::Command(Command {
+                command: format!("touch '{}'", dir.path().join(name).display()),
+                tags: (!tags.is_empty()).then(|| tags.iter().map(|t| t.to_string()).collect()),
+                ..Default::default()
+            })
+        };
+        let script = Script {
+            name: "CI".to_string(),
+            commands: vec![
+                touch("lint", &["lint"]),
+                touch("test", &["test"]),
+                touch("publish", &["publish", "release"]),
+                touch("untagged", &[]),
+            ],
+            ..Default::default()
+        };
+        let run = |only: &[&str], skip: &[&str]| {
+            for entry in std::fs::read_dir(dir.path()).unwrap() {
+                std::fs::remove_file(entry.unwrap().path()).unwrap();
+            }
+            let options = RunOptions {
+                only_tags: only.iter().map(|t| t.to_string()).collect(),
+                skip_tags: skip.iter().map(|t| t.to_string()).collect(),
+                ..Default::default()
+            };
+            let script = script.clone();
+            async move {
+                let mut report = RunReport::default();
+                script
+                    .run(
+                        &mut HashMap::new(),
+                        &Session::new(&script, &options),
+                        &mut report,
+                    )
+                    .await
+                    .unwrap();
+                report
+            }
+        };
+        let created = || {
+            let mut names: Vec<String> = std::fs::read_dir(dir.path())
+                .unwrap()
+                .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+
+        run(&[], &[]).await;
+        assert_eq!(created(), vec!["lint", "publish", "test", "untagged"]);
+
+        run(&[], &["release"]).await;
+        assert_eq!(created(), vec!["lint", "test", "untagged"]);
+
+        let report = run(&["lint", "test"], &[]).await;
+        assert_eq!(created(), vec!["lint", "test"]);
+        assert_eq!(report.steps[2].status, StepStatus::Skipped);
+        assert_eq!(report.steps[3].status, StepStatus::Skipped);
+
+        run(&["test", "publish"], &["release"]).await;
+        assert_eq!(created(), vec!["test"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_script_run_prefixes_streamed_output() {
+        let script = Script {
+            name: "Prefixed".to_string(),
+            commands: vec![
+                CommandTypes::Command(Command {
+                    name: Some("build".to_string()),
+                    command: "echo built; echo warned >&2".to_string(),
+                    ..Default::default()
+                }),
+                CommandTypes::Command(Command {
+                    command: "echo tested".to_string(),
+                    ..Default::default()
+                }),
+                CommandTypes::Command(Command {
+                    command: "true".to_string(),
+                    options: Some(Options {
+                        interactive: Some(true),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+            ],
+            ..Default::default()
+        };
+        let session = Session {
+            output: Arc::new(Output::captured(true)),
+            ..Default::default()
+        };
+
+        script
+            .run(&mut HashMap::new(), &session, &mut RunReport::default())
+            .await
+            .unwrap();
+
+        let output = session.output.captured_text();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines.contains(&"[build] built"), "{output}");
+        assert!(lines.contains(&"[build] warned"), "{output}");
+        assert!(lines.contains(&"[2] tested"), "{output}");
+        assert!(
+            lines.contains(&"Executing command: echo tested"),
+            "{output}"
+        );
+        assert!(!lines.iter().any(|l| l.starts_with("[3]")), "{output}");
+    }
 }
@@ -0,0 +1,269 @@
+use hashbrown::HashSet;
+
+use super::{command_types::CommandTypes, script::Script};
+
+/// Which steps of a script to run, as given by `--from`, `--to` and `--step`.
+///
+/// Steps are referenced either by their `name` or by their 1-based index.
+#[derive(Debug, Clone, Default)]
+pub struct StepSelection {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub steps: Vec<String>,
+}
+
+impl StepSelection {
+    pub fn is_empty(&self) -> bool {
+        self.from.is_none() && self.to.is_none() && self.steps.is_empty()
+    }
+
+    /// Returns the 0-based indices of the selected steps, in script order.
+    pub fn resolve(&self, script: &Script) -> Result<Vec<usize>, String> {
+        let count = script.commands.len();
+        if self.is_empty() {
+            return Ok((0..count).collect());
+        }
+
+        let mut selected = HashSet::new();
+
+        if self.from.is_some() || self.to.is_some() {
+            let from = match &self.from {
+                Some(spec) => resolve_step(script, spec)?,
+                None => 0,
+            };
+            let to = match &self.to {
+                Some(spec) => resolve_step(script, spec)?,
+                None => count.saturating_sub(1),
+            };
+            if from > to {
+                return Err(format!(
+                    "Invalid step range: --from step {} comes after --to step {}",
+                    from + 1,
+                    to + 1
+                ));
+            }
+            selected.extend(from..=to);
+        }
+
+        for spec in &self.steps {
+            selected.insert(resolve_step(script, spec)?);
+        }
+
+        let mut indices: Vec<usize> = selected.into_iter().collect();
+        indices.sort_unstable();
+        Ok(indices)
+    }
+
+    /// Returns a copy of `script` containing only the selected steps.
+    ///
+    /// Fails early if a selected step uses a variable that is only captured
+    /// by a step that was not selected.
+    pub fn apply(&self, script: &Script) -> Result<Script, String> {
+        if self.is_empty() {
+            return Ok(script.clone());
+        }
+
+        let indices = self.resolve(script)?;
+
+        let provided: HashSet<&str> = script
+            .params
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .chain(script.secrets.iter().flatten().map(|s| s.name.as_str()))
+            .collect();
+
+        for &i in &indices {
+            for var in script.commands[i].placeholders() {
+                if provided.contains(var.as_str()) {
+                    continue;
+                }
+                let captured_by_selected = indices[..indices.binary_search(&i).unwrap_or(0)]
+                    .iter()
+                    .any(|&j| script.commands[j].variables().contains(&var.as_str()));
+                if captured_by_selected {
+                    continue;
+                }
+                if let Some(source) = (0..script.commands.len())
+                    .find(|&j| script.commands[j].variables().contains(&var.as_str()))
+                {
+                    return Err(format!(
+                        "Unknown variable '${{{var}}}' in step {}: it is captured by step {} which is not selected",
+                        i + 1,
+                        source + 1
+                    ));
+                }
+            }
+        }
+
+        let mut selected = script.clone();
+        selected.commands = indices
+            .into_iter()
+            .map(|i| script.commands[i].clone())
+            .collect();
+        Ok(selected)
+    }
+}
+
+/// Resolves a step reference (a name or a 1-based index) to a 0-based index.
+fn resolve_step(script: &Script, spec: &str) -> Result<usize, String> {
+    let count = script.commands.len();
+
+    if let Some(i) = script.commands.iter().position(|step| match step {
+        CommandTypes::Command(cmd) => cmd.name.as_deref() == Some(spec),
+        CommandTypes::Commands(_) => false,
+    }) {
+        return Ok(i);
+    }
+
+    match spec.parse::<usize>() {
+        Ok(n) if (1..=count).contains(&n) => Ok(n - 1),
+        Ok(n) => Err(format!(
+            "Step {n} is out of range (script '{}' has {count} steps)",
+            script.name
+        )),
+        Err(_) => Err(format!(
+            "No step named '{spec}' in script '{}'",
+            script.name
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script_runner::command::Command;
+
+    fn script() -> Script {
+        let step = |name: &str, command: &str, capture: Option<&str>| {
+            CommandTypes::Command(Command {
+                name: Some(name.to_string()),
+                command: command.to_string(),
+                capture: capture.map(str::to_string),
+                ..Default::default()
+            })
+        };
+        Script {
+            name: "Release".to_string(),
+            params: Some(vec!["target".to_string()]),
+            commands: vec![
+                step("lint", "cargo clippy", None),
+                step("version", "cargo pkgid", Some("version")),
+                step("build", "cargo build --target ${target}", None),
+                step("tag", "git tag v${version}", None),
+                step("publish", "cargo publish", None),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn commands(script: &Script) -> Vec<String> {
+        script.commands.iter().map(|c| c.label()).collect()
+    }
+
+    #[test]
+    fn test_no_selection_keeps_all_steps() {
+        let script = script();
+        let selected = StepSelection::default().apply(&script).unwrap();
+        assert_eq!(selected.commands.len(), 5);
+    }
+
+    #[test]
+    fn test_select_by_index() {
+        let selection = StepSelection {
+            from: Some("2".to_string()),
+            to: Some("4".to_string()),
+            ..Default::default()
+        };
+        let selected = selection.apply(&script()).unwrap();
+        assert_eq!(commands(&selected), vec!["version", "build", "tag"]);
+
+        let selection = StepSelection {
+            steps: vec!["5".to_string(), "1".to_string()],
+            ..Default::default()
+        };
+        let selected = selection.apply(&script()).unwrap();
+        assert_eq!(commands(&selected), vec!["lint", "publish"]);
+    }
+
+    #[test]
+    fn test_select_by_name() {
+        let selection = StepSelection {
+            from: Some("build".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(selection.resolve(&script()).unwrap(), vec![2, 3, 4]);
+
+        let selection = StepSelection {
+            to: Some("version".to_string()),
+            ..Default::default()
+        };
+        let selected = selection.apply(&script()).unwrap();
+        assert_eq!(commands(&selected), vec!["lint", "version"]);
+    }
+
+    #[test]
+    fn test_out_of_range_and_unknown_names() {
+        let error = StepSelection {
+            steps: vec!["9".to_string()],
+            ..Default::default()
+        }
+        .apply(&script())
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "Step 9 is out of range (script 'Release' has 5 steps)"
+        );
+
+        let error = StepSelection {
+            steps: vec!["deploy".to_string()],
+            ..Default::default()
+        }
+        .apply(&script())
+        .unwrap_err();
+        assert_eq!(error, "No step named 'deploy' in script 'Release'");
+
+        let error = StepSelection {
+            from: Some("4".to_string()),
+            to: Some("2".to_string()),
+            ..Default::default()
+        }
+        .apply(&script())
+        .unwrap_err();
+        assert!(error.contains("comes after"));
+    }
+
+    #[test]
+    fn test_variable_from_unselected_step_is_rejected() {
+        let error = StepSelection {
+            steps: vec!["tag".to_string()],
+            ..Default::default()
+        }
+        .apply(&script())
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "Unknown variable '${version}' in step 4: it is captured by step 2 which is not selected"
+        );
+
+        // Params are provided on the command line and may always be used.
+        assert!(
+            StepSelection {
+                steps: vec!["build".to_string()],
+                ..Default::default()
+            }
+            .apply(&script())
+            .is_ok()
+        );
+
+        // Selecting the capturing step as well is fine.
+        assert!(
+            StepSelection {
+                steps: vec!["version".to_string(), "tag".to_string()],
+                ..Default::default()
+            }
+            .apply(&script())
+            .is_ok()
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use hashbrown::HashMap;
+use tokio::process::Command as TokioCommand;
+
+use crate::utils::{SCRIPT_DIR_NAME, zirv_home};
+
+use super::{
+    cache::CACHE_DIR_NAME,
+    container::{CONTAINER_RUNTIMES, container_args, find_container_runtime, uses_containers},
+    options::Options,
+    output::Output,
+    process::{DEFAULT_GRACE_PERIOD, Stopper},
+    run_options::RunOptions,
+    script::Script,
+    tags::TagFilter,
+    wsl::{WSL_PROGRAM, wsl_args, wslenv},
+};
+
+/// State shared by every step of a single script run.
+#[derive(Debug, Default, Clone)]
+pub struct Session {
+    /// Names of context variables holding secret values.
+    pub secret_names: Vec<String>,
+    /// Names of the script's params, whose values are part of every cache key.
+    pub param_names: Vec<String>,
+    /// Script-level default for `Options::export_context`.
+    pub export_context: bool,
+    /// Script-level default for `Options::export_secrets`.
+    pub export_secrets: bool,
+    /// Script-level default for `Options::export_prefix`.
+    pub export_prefix: Option<String>,
+    /// Script-level default for `Options::isolate_env`.
+    pub isolate_env: bool,
+    /// Default for `Options::heartbeat_ms`, from `config.yaml`.
+    pub heartbeat_ms: Option<u64>,
+    /// Default for `Options::grace_period_ms`, from `config.yaml`.
+    pub grace_period_ms: Option<u64>,
+    /// Tells the running step to stop.
+    pub stopper: Stopper,
+    /// The index of the step being run, 0 before the first.
+    pub running_step: Arc<AtomicUsize>,
+    /// Where commands with a `cache` keep their entries, if anywhere.
+    pub cache_dir: Option<PathBuf>,
+    /// Runs commands with a `cache` even when their inputs are unchanged.
+    pub no_cache: bool,
+    /// Decides which steps are skipped because of their tags.
+    pub tag_filter: TagFilter,
+    /// Where everything printed during the run goes.
+    pub output: Arc<Output>,
+    /// The shell steps run in, if not the platform's default.
+    pub shell: Option<String>,
+    /// The program running `container` steps, if the script has any and
+    /// one was found.
+    pub container_runtime: Option<String>,
+}
+
+impl Session {
+    pub fn new(script: &Script, options: &RunOptions) -> Self {
+        Self {
+            secret_names: script
+                .secrets
+                .iter()
+                .flatten()
+                .map(|s| s.name.clone())
+                .collect(),
+            param_names: script.params.clone().unwrap_or_default(),
+            export_context: script.export_context,
+            export_secrets: script.export_secrets,
+            export_prefix: script.export_prefix.clone(),
+            isolate_env: script.isolate_env,
+            heartbeat_ms: options.heartbeat_ms,
+            grace_period_ms: options.grace_period_ms,
+            stopper: options.stopper.clone(),
+            running_step: Arc::default(),
+            cache_dir: match &options.project_root {
+                Some(root) => Some(root.join(SCRIPT_DIR_NAME)),
+                None => zirv_home(),
+            }
+            .map(|dir| dir.join(CACHE_DIR_NAME)),
+            no_cache: options.no_cache,
+            tag_filter: TagFilter {
+                only: options.only_tags.clone(),
+                skip: options.skip_tags.clone(),
+            },
+            output: Arc::new(
+                Output::new(options.log_file.as_deref(), options.ui, options.color)
+                    .with_events(options.events.clone())
+                    .with_verbosity(options.verbosity)
+                    .with_timestamps(options.timestamps)
+                    .with_format(options.output),
+            ),
+            shell: options.shell.clone(),
+            container_runtime: uses_containers(script)
+                .then(find_container_runtime)
+                .flatten(),
+        }
+    }
+
+    /// Notes that the step at `index` is being run.
+    pub fn enter_step(&self, index: usize) {
+        self.running_step.store(index, Ordering::SeqCst);
+    }
+
+    /// The index of the step being run, 0 before the first.
+    pub fn running_step(&self) -> usize {
+        self.running_step.load(Ordering::SeqCst)
+    }
+
+    /// A process running `command` in the session's shell.
+    pub fn shell_command(&self, command: &str) -> TokioCommand {
+        let mut shell = TokioCommand::new(shell_program(self.shell.as_deref()));
+        shell.arg(SHELL_FLAG).arg(command);
+        shell
+    }
+
+    /// The process that runs `command` with `options`: in the shell, in the
+    /// `container` image when one is set, or in WSL on Windows with `wsl`.
+    /// `env` is the environment the command gets, which a container and WSL
+    /// are given by name. The process runs at the `priority` of `options`,
+    /// and with `isolate_env` only gets `env` and the variables kept by
+    /// [`ISOLATED_ENV_KEEP`] and `env_keep`.
+    pub fn step_command(
+        &self,
+        command: &str,
+        options: Option<&Options>,
+        cwd: Option<&Path>,
+        mut env: Vec<(String, String)>,
+    ) -> TokioCommand {
+        let mut process = match options.and_then(|o| o.container.as_deref()) {
+            Some(image) => {
+                let runtime = self
+                    .container_runtime
+                    .as_deref()
+                    .unwrap_or(CONTAINER_RUNTIMES[0]);
+                let host_dir = match cwd {
+                    Some(cwd) => cwd.to_path_buf(),
+                    None => std::env::current_dir().unwrap_or_default(),
+                };
+                let names: Vec<String> = env.iter().map(|(name, _)| name.clone()).collect();
+                let mut process = TokioCommand::new(runtime);
+                process.args(container_args(
+                    image,
+                    options
+                        .and_then(|o| o.container_args.as_deref())
+                        .unwrap_or_default(),
+                    &host_dir,
+                    &names,
+                    options.is_some_and(|o| o.interactive()),
+                    command,
+                ));
+                process
+            }
+            None if cfg!(windows) && options.is_some_and(|o| o.wsl()) => {
+                let names: Vec<String> = env.iter().map(|(name, _)| name.clone()).collect();
+                let existing = std::env::var("WSLENV").ok();
+                env.push(("WSLENV".to_string(), wslenv(existing.as_deref(), &names)));
+                let mut process = TokioCommand::new(WSL_PROGRAM);
+                process.args(wsl_args(
+                    cwd.map(|cwd| cwd.to_string_lossy()).as_deref(),
+                    command,
+                ));
+                process
+            }
+            None => self.shell_command(command),
+        };
+        #[cfg(not(windows))]
+        if let Some(priority) = options.and_then(|o| o.priority) {
+            process = priority.apply(process);
+        }
+        // A console process group of its own lets the step be sent
+        // CTRL_BREAK without zirv getting it too.
+        #[cfg(windows)]
+        {
+            let mut flags = options
+                .and_then(|o| o.priority)
+                .and_then(|priority| priority.creation_flags())
+                .unwrap_or_default();
+            if !options.is_some_and(|o| o.interactive()) {
+                flags |= CREATE_NEW_PROCESS_GROUP;
+            }
+            process.creation_flags(flags);
+        }
+        if let Some(cwd) = cwd {
+            process.current_dir(cwd);
+        }
+        if options
+            .and_then(|o| o.isolate_env)
+            .unwrap_or(self.isolate_env)
+        {
+            process.env_clear();
+            let keep = options.and_then(|o| o.env_keep.as_deref());
+            let names = ISOLATED_ENV_KEEP
+                .iter()
+                .copied()
+                .chain(keep.unwrap_or_default().iter().map(String::as_str));
+            for name in names {
+                if let Some(value) = std::env::var_os(name) {
+                    process.env(name, value);
+                }
+            }
+        }
+        process.envs(env);
+        process
+    }
+
+    /// How long a command run with `options` gets to exit when the run is
+    /// stopped.
+    pub fn grace_period(&self, options: Option<&Options>) -> Duration {
+        options
+            .and_then(|o| o.grace_period_ms)
+            .or(self.grace_period_ms)
+            .map_or(DEFAULT_GRACE_PERIOD, Duration::from_millis)
+    }
+
+    pub fn is_secret(&self, name: &str) -> bool {
+        self.secret_names.iter().any(|s| s == name)
+    }
+}
+
+/// The variables a command run with `isolate_env` keeps from zirv's own
+/// environment, on top of its `env_keep`.
+pub const ISOLATED_ENV_KEEP: &[&str] = if cfg!(windows) {
+    &["PATH", "USERPROFILE", "SystemRoot"]
+} else {
+    &["PATH", "HOME"]
+};
+
+/// `CREATE_NEW_PROCESS_GROUP` from the Windows API.
+#[cfg(windows)]
+const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+
+/// The flag that passes a command to the shell.
+pub const SHELL_FLAG: &str = if cfg!(windows) { "-Command" } else { "-c" };
+
+/// The shell steps run in: `shell` if one is configured, else `sh`, or
+/// `powershell` on Windows.
+pub fn shell_program(shell: Option<&str>) -> &str {
+    match shell {
+        Some(shell) => shell,
+        None if cfg!(windows) => "powershell",
+        None => "sh",
+    }
+}
+
+/// Converts a context key into an environment variable name, e.g.
+/// `commit_message` with prefix `ZIRV_` becomes `ZIRV_COMMIT_MESSAGE`.
+pub fn env_var_name(prefix: &str, key: &str) -> String {
+    let key: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{prefix}{key}")
+}
+
+/// Returns the environment variables to export for `context`, skipping the
+/// internal `cwd` entry and, unless `include_secrets` is set, secrets.
+pub fn exported_env(
+    context: &HashMap<String, String>,
+    session: &Session,
+    prefix: &str,
+    include_secrets: bool,
+) -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = context
+        .iter()
+        .filter(|(key, _)| key.as_str() != "cwd")
+        .filter(|(key, _)| include_secrets || !session.is_secret(key))
+        .map(|(key, value)| (env_var_name(prefix, key), value.clone()))
+        .collect();
+    vars.sort();
+    vars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_var_name() {
+        assert_eq!(env_var_name("", "commit_message"), "COMMIT_MESSAGE");
+        assert_eq!(env_var_name("ZIRV_", "version"), "ZIRV_VERSION");
+        assert_eq!(env_var_name("", "my-var.x"), "MY_VAR_X");
+    }
+
+    #[test]
+    fn test_exported_env_excludes_secrets_and_cwd() {
+        let session = Session {
+            secret_names: vec!["token".to_string()],
+            ..Default::default()
+        };
+        let mut context = HashMap::new();
+        context.insert("branch".to_string(), "main".to_string());
+        context.insert("token".to_string(), "hunter2".to_string());
+        context.insert("cwd".to_string(), "/tmp".to_string());
+
+        assert_eq!(
+            exported_env(&context, &session, "", false),
+            vec![("BRANCH".to_string(), "main".to_string())]
+        );
+        assert_eq!(
+            exported_env(&context, &session, "Z_", true),
+            vec![
+                ("Z_BRANCH".to_string(), "main".to_string()),
+                ("Z_TOKEN".to_string(), "hunter2".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+const RESET: &str = "\x1b[0m";
+
+/// When zirv colors what it prints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorMode {
+    /// Colors when printing to a terminal, unless `NO_COLOR` is set;
+    /// `CLICOLOR_FORCE` colors even when not.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    /// Whether to color text printed to a stream that is a terminal or not.
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let force = std::env::var_os("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0");
+        self.resolve(no_color, force, is_terminal)
+    }
+
+    fn resolve(self, no_color: bool, force: bool, is_terminal: bool) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => !no_color && (force || is_terminal),
+        }
+    }
+}
+
+/// How a piece of text zirv prints is colored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Bold,
+    Dim,
+    Red,
+    Green,
+    Yellow,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::Bold => "\x1b[1m",
+            Style::Dim => "\x1b[2m",
+            Style::Red => "\x1b[31m",
+            Style::Green => "\x1b[32m",
+            Style::Yellow => "\x1b[33m",
+        }
+    }
+
+    /// `text` in this style.
+    pub fn paint(self, text: &str) -> String {
+        format!("{}{text}{RESET}", self.code())
+    }
+
+    /// `text` in this style if `color`, as-is otherwise.
+    pub fn paint_if(self, color: bool, text: &str) -> String {
+        if color {
+            self.paint(text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_mode_resolution() {
+        for (no_color, force, is_terminal) in [
+            (false, false, false),
+            (true, true, true),
+            (false, false, true),
+        ] {
+            assert!(ColorMode::Always.resolve(no_color, force, is_terminal));
+            assert!(!ColorMode::Never.resolve(no_color, force, is_terminal));
+        }
+        assert!(ColorMode::Auto.resolve(false, false, true));
+        assert!(!ColorMode::Auto.resolve(false, false, false));
+        assert!(ColorMode::Auto.resolve(false, true, false));
+        assert!(!ColorMode::Auto.resolve(true, false, true));
+        assert!(!ColorMode::Auto.resolve(true, true, true));
+    }
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(Style::Red.paint("failed"), "\x1b[31mfailed\x1b[0m");
+        assert_eq!(Style::Green.paint_if(false, "success"), "success");
+        assert_eq!(Style::Dim.paint_if(true, "[1]"), "\x1b[2m[1]\x1b[0m");
+    }
+}
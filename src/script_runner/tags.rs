@@ -0,0 +1,66 @@
+/// Selects steps by their tags, as given by `--only` and `--skip-tag`.
+#[derive(Debug, Clone, Default)]
+pub struct TagFilter {
+    /// If non-empty, only steps carrying at least one of these tags run.
+    pub only: Vec<String>,
+    /// Steps carrying any of these tags are skipped.
+    pub skip: Vec<String>,
+}
+
+impl TagFilter {
+    /// Returns why a step with `tags` should be skipped, or `None` if it runs.
+    ///
+    /// Untagged steps always run unless `only` is set. `skip` wins over `only`.
+    pub fn skip_reason(&self, tags: &[String]) -> Option<String> {
+        if let Some(tag) = tags.iter().find(|t| self.skip.contains(t)) {
+            return Some(format!("tagged '{tag}' which is excluded by --skip-tag"));
+        }
+
+        if self.only.is_empty() || tags.iter().any(|t| self.only.contains(t)) {
+            return None;
+        }
+
+        if tags.is_empty() {
+            Some("untagged and --only is set".to_string())
+        } else {
+            Some(format!(
+                "none of its tags ({}) match --only",
+                tags.join(", ")
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_skip_reason() {
+        let filter = TagFilter::default();
+        assert_eq!(filter.skip_reason(&tags(&["lint"])), None);
+        assert_eq!(filter.skip_reason(&[]), None);
+
+        let filter = TagFilter {
+            only: tags(&["test"]),
+            skip: tags(&["slow"]),
+        };
+        assert_eq!(filter.skip_reason(&tags(&["test"])), None);
+        assert_eq!(
+            filter.skip_reason(&tags(&["test", "slow"])).unwrap(),
+            "tagged 'slow' which is excluded by --skip-tag"
+        );
+        assert_eq!(
+            filter.skip_reason(&[]).unwrap(),
+            "untagged and --only is set"
+        );
+        assert_eq!(
+            filter.skip_reason(&tags(&["lint", "fmt"])).unwrap(),
+            "none of its tags (lint, fmt) match --only"
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use tokio::time::timeout;
+
+use super::{
+    execute,
+    process::{Stopper, interrupted},
+    run_options::{OutputFormat, RunOptions, Verbosity},
+    script::Script,
+};
+
+/// Quiet period after a change before the script is (re)started.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Settings for `--watch` mode.
+#[derive(Debug, Clone, Default)]
+pub struct WatchOptions {
+    /// Files or directories whose changes trigger a new run.
+    pub paths: Vec<PathBuf>,
+    /// Patterns for changed paths that are ignored. A pattern matches a path
+    /// if it matches the whole path or any single component; `*` matches any
+    /// sequence of characters.
+    pub ignore: Vec<String>,
+}
+
+/// Runs `script`, then re-runs it whenever a file under one of the watched
+/// paths changes. A change during a run stops it, giving the running step
+/// its grace period to exit.
+pub async fn execute_watching(
+    script: &Script,
+    params: &[String],
+    options: &RunOptions,
+    watch: &WatchOptions,
+) -> Result<(), String> {
+    watch_runs(script, params, options, watch, None).await
+}
+
+async fn watch_runs(
+    script: &Script,
+    params: &[String],
+    options: &RunOptions,
+    watch: &WatchOptions,
+    max_runs: Option<usize>,
+) -> Result<(), String> {
+    let (tx, mut changes) = unbounded_channel();
+    let ignore = watch.ignore.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        if let Ok(event) = event
+            && is_relevant(&event, &ignore)
+        {
+            let _ = tx.send(());
+        }
+    })
+    .map_err(|e| format!("Failed to start file watcher: {e}"))?;
+
+    for path in &watch.paths {
+        watcher
+            .watch(path, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {e}", path.display()))?;
+    }
+
+    let watched = watch
+        .paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut runs = 0;
+    loop {
+        runs += 1;
+
+        let stopper = Stopper::default();
+        let run_options = RunOptions {
+            stopper: stopper.clone(),
+            ..options.clone()
+        };
+        let changed = {
+            let run = execute(script, params, &run_options);
+            tokio::pin!(run);
+            tokio::select! {
+                result = &mut run => {
+                    if let Err(e) = result {
+                        eprintln!("{e}");
+                    }
+                    false
+                }
+                Some(()) = changes.recv() => {
+                    // Give the running step its grace period to exit.
+                    stopper.stop();
+                    let _ = run.await;
+                    true
+                }
+            }
+        };
+
+        if interrupted() {
+            return Err(format!("Script '{}' was stopped", script.name));
+        }
+        if max_runs.is_some_and(|max| runs >= max) {
+            return Ok(());
+        }
+
+        let announce =
+            options.verbosity >= Verbosity::Normal && options.output == OutputFormat::Text;
+        if changed {
+            if announce {
+                println!("\nChange detected, restarting '{}'...", script.name);
+            }
+        } else {
+            if announce {
+                println!("\nWaiting for changes in {watched}... (press Ctrl-C to exit)");
+            }
+            tokio::select! {
+                change = changes.recv() => {
+                    if change.is_none() {
+                        return Ok(());
+                    }
+                }
+                _ = stopper.stopped() => {
+                    return Err(format!("Script '{}' was stopped", script.name));
+                }
+            }
+        }
+        debounce(&mut changes).await;
+    }
+}
+
+/// Waits until no change has arrived for `DEBOUNCE`.
+async fn debounce(changes: &mut UnboundedReceiver<()>) {
+    while let Ok(Some(())) = timeout(DEBOUNCE, changes.recv()).await {}
+}
+
+fn is_relevant(event: &Event, ignore: &[String]) -> bool {
+    matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    ) && event.paths.iter().any(|p| !is_ignored(p, ignore))
+}
+
+fn is_ignored(path: &Path, ignore: &[String]) -> bool {
+    let full = path.to_string_lossy();
+    ignore.iter().any(|pattern| {
+        wildcard_match(pattern, &full)
+            || path
+                .components()
+                .any(|c| wildcard_match(pattern, &c.as_os_str().to_string_lossy()))
+    })
+}
+
+/// Matches `text` against `pattern`, where `*` matches any sequence of characters.
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use crate::script_runner::{command::Command, command_types::CommandTypes};
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("target", "target"));
+        assert!(!wildcard_match("target", "targets"));
+        assert!(wildcard_match("*.log", "build.log"));
+        assert!(!wildcard_match("*.log", "build.txt"));
+        assert!(wildcard_match("out*", "output"));
+        assert!(wildcard_match("a*b*c", "aXXbYYc"));
+        assert!(!wildcard_match("a*b*c", "aXXcYYb"));
+        assert!(wildcard_match("*", "anything"));
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let ignore = vec!["target".to_string(), "*.log".to_string()];
+        assert!(is_ignored(Path::new("/repo/target/debug/zirv"), &ignore));
+        assert!(is_ignored(Path::new("/repo/run.log"), &ignore));
+        assert!(!is_ignored(Path::new("/repo/src/main.rs"), &ignore));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_change_triggers_second_run() {
+        let watched = tempfile::tempdir().unwrap();
+        let state = tempfile::tempdir().unwrap();
+        let counter = state.path().join("counter");
+
+        let script = Script {
+            name: "Watched".to_string(),
+            commands: vec![CommandTypes::Command(Command {
+                command: format!("echo run >> '{}'", counter.display()),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+        let watch = WatchOptions {
+            paths: vec![watched.path().to_path_buf()],
+            ignore: vec!["*.tmp".to_string()],
+        };
+        let options = RunOptions {
+            no_summary: true,
+            ..Default::default()
+        };
+
+        let counter_clone = counter.clone();
+        let watched_path = watched.path().to_path_buf();
+        let toucher = tokio::spawn(async move {
+            while !counter_clone.exists() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            std::fs::write(watched_path.join("ignored.tmp"), "x").unwrap();
+            std::fs::write(watched_path.join("changed.txt"), "x").unwrap();
+        });
+
+        timeout(
+            Duration::from_secs(10),
+            watch_runs(&script, &[], &options, &watch, Some(2)),
+        )
+        .await
+        .expect("a second run should start after the change")
+        .unwrap();
+        toucher.await.unwrap();
+
+        let runs = std::fs::read_to_string(&counter).unwrap();
+        assert_eq!(runs.lines().count(), 2);
+    }
+}
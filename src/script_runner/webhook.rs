@@ -0,0 +1,248 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::report::{RunReport, StepStatus};
+
+/// How long a single attempt to deliver the webhook may take.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many times delivery is attempted before giving up.
+const WEBHOOK_ATTEMPTS: usize = 2;
+
+/// The JSON body posted to `notify_webhook` or `--webhook` after a run: the
+/// run report, plus a few fields a chat bot can show without digging.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WebhookPayload {
+    #[serde(flatten)]
+    pub report: RunReport,
+    /// `success`, `failed` or `timed_out`.
+    pub status: &'static str,
+    /// 1-based index of the step that ended the run, if one did.
+    pub failed_step: Option<usize>,
+    /// The machine the script ran on.
+    pub host: String,
+}
+
+impl WebhookPayload {
+    /// The payload for `report`, with its error and stderr tails passed
+    /// through `redact`, as [`Output::redact`](super::output::Output::redact)
+    /// masks what the run shows.
+    pub fn new(report: &RunReport, redact: impl Fn(&str) -> String, host: String) -> Self {
+        let mut report = report.clone();
+        report.error = report.error.map(|error| redact(&error));
+        for step in &mut report.steps {
+            step.stderr_tail = step.stderr_tail.take().map(|tail| redact(&tail));
+        }
+        let failed_step = if report.success {
+            None
+        } else {
+            report
+                .steps
+                .iter()
+                .rev()
+                .find(|step| step.status == StepStatus::Failed)
+                .map(|step| step.index)
+        };
+        Self {
+            status: match (report.success, report.timed_out) {
+                (true, _) => "success",
+                (false, true) => "timed_out",
+                (false, false) => "failed",
+            },
+            report,
+            failed_step,
+            host,
+        }
+    }
+}
+
+/// The name of this machine, or `unknown`.
+pub fn host_name() -> String {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+        .or_else(|| {
+            let output = std::process::Command::new("hostname").output().ok()?;
+            let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            (output.status.success() && !name.is_empty()).then_some(name)
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Posts `payload` as JSON to `url`, trying once more if the first attempt
+/// fails.
+pub fn notify(url: &str, payload: &WebhookPayload) -> Result<(), String> {
+    let body = serde_json::to_string(payload).map_err(|e| e.to_string())?;
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(WEBHOOK_TIMEOUT))
+        .build()
+        .into();
+    let mut error = String::new();
+    for _ in 0..WEBHOOK_ATTEMPTS {
+        match agent
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header(
+                "User-Agent",
+                concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+            )
+            .send(body.as_str())
+        {
+            Ok(_) => return Ok(()),
+            Err(e) => error = e.to_string(),
+        }
+    }
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script_runner::{RunOptions, output::MASK, report::StepReport, run_with_report};
+    use crate::utils::{Vars, parse_script_content};
+
+    fn failed_report() -> RunReport {
+        let mut failed = StepReport::new(2, "deploy --token ${token}".to_string());
+        failed.status = StepStatus::Failed;
+        failed.stderr_tail = Some("401 for token s3cr3t".to_string());
+        RunReport {
+            script: "Deploy".to_string(),
+            success: false,
+            error: Some("`deploy --token s3cr3t` failed".to_string()),
+            duration: Duration::from_millis(1200),
+            steps: vec![StepReport::new(1, "make".to_string()), failed],
+            timed_out: false,
+            termination: None,
+            on_error: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_payload_redacts_secrets() {
+        let redact = |text: &str| text.replace("s3cr3t", MASK);
+        let payload = WebhookPayload::new(&failed_report(), redact, "ci-1".into());
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value["script"], "Deploy");
+        assert_eq!(value["status"], "failed");
+        assert_eq!(value["duration_ms"], 1200);
+        assert_eq!(value["failed_step"], 2);
+        assert_eq!(value["host"], "ci-1");
+        assert_eq!(value["error"], "`deploy --token ********` failed");
+        assert_eq!(value["steps"][1]["stderr_tail"], "401 for token ********");
+        assert!(!value.to_string().contains("s3cr3t"));
+
+        let report = RunReport {
+            success: true,
+            error: None,
+            ..RunReport::new("Build")
+        };
+        let payload = WebhookPayload::new(&report, str::to_string, "ci-1".into());
+        assert_eq!(payload.status, "success");
+        assert_eq!(payload.failed_step, None);
+    }
+
+    /// Answers one request with each of `statuses` and returns the url to
+    /// post to and the bodies received.
+    fn serve(statuses: &'static [&'static str]) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':')
+                        && name.eq_ignore_ascii_case("content-length")
+                    {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                bodies.push(String::from_utf8(body).unwrap());
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+            }
+            bodies
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn test_notify_retries_once() {
+        let (url, server) = serve(&["500 Internal Server Error", "204 No Content"]);
+        let payload = WebhookPayload::new(&failed_report(), str::to_string, "ci-1".into());
+        notify(&url, &payload).unwrap();
+        let bodies = server.join().unwrap();
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0], bodies[1]);
+        let body: serde_json::Value = serde_json::from_str(&bodies[1]).unwrap();
+        assert_eq!(body, serde_json::to_value(&payload).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_run_posts_to_the_script_webhook() {
+        let (url, server) = serve(&["200 OK"]);
+        let mut script = parse_script_content(
+            r#"
+name: Deploy
+secrets:
+  - name: token
+    env_var: ZIRV_TEST_WEBHOOK_TOKEN
+commands:
+  - command: echo starting
+  - command: echo "bad token ${token}" >&2 && exit 4
+"#,
+            "yaml",
+        )
+        .unwrap();
+        script.notify_webhook = Some(url);
+        let options = RunOptions {
+            no_summary: true,
+            secrets: Vars::from([("token".to_string(), "hunter2".to_string())]),
+            ..Default::default()
+        };
+        let (_, result) = run_with_report(&script, &[], &options).await;
+        assert!(result.is_err());
+
+        let bodies = server.join().unwrap();
+        assert!(!bodies[0].contains("hunter2"), "{}", bodies[0]);
+        let body: serde_json::Value = serde_json::from_str(&bodies[0]).unwrap();
+        assert_eq!(body["script"], "Deploy");
+        assert_eq!(body["status"], "failed");
+        assert_eq!(body["failed_step"], 2);
+        assert_eq!(body["steps"][1]["stderr_tail"], "bad token ********");
+        assert!(body["host"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_webhook_does_not_fail_the_run() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        drop(listener);
+        let script =
+            parse_script_content("name: Build\ncommands:\n  - command: echo ok\n", "yaml").unwrap();
+        let options = RunOptions {
+            no_summary: true,
+            webhook: Some(url),
+            ..Default::default()
+        };
+        let (report, result) = run_with_report(&script, &[], &options).await;
+        assert!(result.is_ok());
+        assert!(report.success);
+    }
+}
@@ -0,0 +1,83 @@
+/// The program that runs `wsl` steps.
+pub const WSL_PROGRAM: &str = "wsl.exe";
+
+/// The path a Windows path such as `C:\Users\me` has inside WSL,
+/// `/mnt/c/Users/me`, or `None` if it is not on a drive.
+pub fn wsl_path(path: &str) -> Option<String> {
+    let path = path.strip_prefix(r"\\?\").unwrap_or(path);
+    let mut chars = path.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    let rest = chars.as_str().strip_prefix(':')?;
+    if !(rest.is_empty() || rest.starts_with(['\\', '/'])) {
+        return None;
+    }
+    let rest = rest.replace('\\', "/");
+    Some(format!(
+        "/mnt/{}{}",
+        drive.to_ascii_lowercase(),
+        rest.trim_end_matches('/')
+    ))
+}
+
+/// The arguments to `wsl.exe` that run `command` in a login bash, in `cwd`
+/// when it has a WSL path.
+pub fn wsl_args(cwd: Option<&str>, command: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(dir) = cwd.and_then(wsl_path) {
+        args.push("--cd".to_string());
+        args.push(dir);
+    }
+    args.extend(["--", "bash", "-lc", command].map(String::from));
+    args
+}
+
+/// The value of `WSLENV` that shares the variables `names` with WSL, on
+/// top of those `existing` shares already.
+pub fn wslenv(existing: Option<&str>, names: &[String]) -> String {
+    existing
+        .filter(|value| !value.is_empty())
+        .into_iter()
+        .map(str::to_string)
+        .chain(names.iter().cloned())
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wsl_path() {
+        assert_eq!(
+            wsl_path(r"C:\Users\me\app").as_deref(),
+            Some("/mnt/c/Users/me/app")
+        );
+        assert_eq!(wsl_path(r"\\?\D:\work\").as_deref(), Some("/mnt/d/work"));
+        assert_eq!(wsl_path("e:/src").as_deref(), Some("/mnt/e/src"));
+        assert_eq!(wsl_path("C:").as_deref(), Some("/mnt/c"));
+        assert_eq!(wsl_path(r"\\server\share"), None);
+        assert_eq!(wsl_path("/home/me"), None);
+        assert_eq!(wsl_path("C:relative"), None);
+    }
+
+    #[test]
+    fn test_wsl_args() {
+        assert_eq!(
+            wsl_args(Some(r"C:\app"), "make && uname"),
+            ["--cd", "/mnt/c/app", "--", "bash", "-lc", "make && uname"]
+        );
+        assert_eq!(wsl_args(None, "uname"), ["--", "bash", "-lc", "uname"]);
+    }
+
+    #[test]
+    fn test_wslenv() {
+        let names = ["ZIRV_ENV".to_string(), "FORCE_COLOR".to_string()];
+        assert_eq!(wslenv(None, &names), "ZIRV_ENV:FORCE_COLOR");
+        assert_eq!(
+            wslenv(Some("USERPROFILE/p"), &names),
+            "USERPROFILE/p:ZIRV_ENV:FORCE_COLOR"
+        );
+        assert_eq!(wslenv(Some(""), &[]), "");
+    }
+}
@@ -1,44 +1,1332 @@
-use std::{env, fs, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
 
 use hashbrown::HashMap;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
 
-use crate::script_runner::script::Script;
+use crate::error::ZirvError;
+use crate::script_runner::schema::{parse_document, unknown_fields};
+use crate::script_runner::{compat::Compatibility, script::Script};
 
 pub const SUPPORTED_EXTENSIONS: &[&str] = &["yaml", "yml", "json", "toml"];
 pub const SCRIPT_DIR_NAME: &str = ".zirv";
+/// Environment variable listing extra script directories, separated like `PATH`.
+pub const ZIRV_PATH_VAR: &str = "ZIRV_PATH";
+/// The file of variables shared by the scripts of a directory.
+pub const VARS_FILE: &str = "vars.yaml";
+/// The file of preferences in a script directory, read by the command line.
+pub const CONFIG_FILE: &str = "config.yaml";
+/// Files at the top of a script directory that are not scripts.
+pub const RESERVED_FILES: &[&str] = &[VARS_FILE, CONFIG_FILE];
+
+/// Variables from `vars.yaml` files, by name.
+pub type Vars = BTreeMap<String, String>;
+
+/// Where a script directory comes from, in order of lookup precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptSource {
+    /// The project's `.zirv` directory.
+    Local,
+    /// A directory listed in `ZIRV_PATH`.
+    Extra,
+    /// `~/.zirv`.
+    Global,
+}
+
+impl ScriptSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScriptSource::Local => "local",
+            ScriptSource::Extra => "extra",
+            ScriptSource::Global => "global",
+        }
+    }
+}
 
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Shortcuts {
-    pub shortcuts: HashMap<String, String>,
+    pub shortcuts: HashMap<String, Shortcut>,
+}
+
+/// What a shortcut runs: a script name, or a script with bundled params.
+///
+/// ```yaml
+/// shortcuts:
+///   d: deploy
+///   dp:
+///     script: deploy
+///     params: [prod, { region: eu }]
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Shortcut {
+    Script(String),
+    WithParams {
+        script: String,
+        #[serde(default)]
+        params: Vec<ShortcutParam>,
+    },
+}
+
+/// A param bundled into a shortcut: a positional value, or a value for a
+/// param by name.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ShortcutParam {
+    Positional(String),
+    Named(BTreeMap<String, String>),
+}
+
+impl Shortcut {
+    /// The script the shortcut points at, as written in the file.
+    pub fn script(&self) -> &str {
+        match self {
+            Shortcut::Script(script) | Shortcut::WithParams { script, .. } => script,
+        }
+    }
+
+    pub fn params(&self) -> &[ShortcutParam] {
+        match self {
+            Shortcut::Script(_) => &[],
+            Shortcut::WithParams { params, .. } => params,
+        }
+    }
+
+    /// The same shortcut pointing at `script` instead.
+    pub fn retarget(&self, script: String) -> Shortcut {
+        match self {
+            Shortcut::Script(_) => Shortcut::Script(script),
+            Shortcut::WithParams { params, .. } => Shortcut::WithParams {
+                script,
+                params: params.clone(),
+            },
+        }
+    }
+}
+
+impl From<&str> for Shortcut {
+    fn from(script: &str) -> Self {
+        Shortcut::Script(script.to_string())
+    }
+}
+
+/// Renders the shortcut like a command line, e.g. `deploy prod region=eu`.
+impl std::fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.script())?;
+        for param in self.params() {
+            match param {
+                ShortcutParam::Positional(value) => write!(f, " {value}")?,
+                ShortcutParam::Named(values) => {
+                    for (name, value) in values {
+                        write!(f, " {name}={value}")?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Combines the params bundled into a shortcut with those given on the
+/// command line, for a script declaring the params `names`.
+///
+/// Bundled positional values come first, so `dp: {script: deploy, params:
+/// [prod]}` makes `zirv dp v2` run `deploy prod v2`. Named values fill the
+/// slot of their param, unless the command line supplies enough values to
+/// fill every slot, in which case those override them by position.
+pub fn merge_params(
+    names: &[String],
+    bundled: &[ShortcutParam],
+    cli: &[String],
+) -> Result<Vec<String>, String> {
+    let mut supplied: Vec<String> = Vec::new();
+    let mut named: HashMap<&str, &str> = HashMap::new();
+    for param in bundled {
+        match param {
+            ShortcutParam::Positional(value) => supplied.push(value.clone()),
+            ShortcutParam::Named(values) => {
+                for (name, value) in values {
+                    if !names.contains(name) {
+                        return Err(format!(
+                            "Shortcut sets parameter '{name}', which the script does not declare"
+                        ));
+                    }
+                    named.insert(name, value);
+                }
+            }
+        }
+    }
+    supplied.extend(cli.iter().cloned());
+
+    let mut supplied = supplied.into_iter();
+    let mut merged = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        let slots_left = names.len() - i;
+        match named.get(name.as_str()) {
+            Some(value) if supplied.len() < slots_left => merged.push(value.to_string()),
+            _ => match supplied.next() {
+                Some(value) => merged.push(value),
+                None => break,
+            },
+        }
+    }
+    merged.extend(supplied);
+    Ok(merged)
 }
 
-pub fn home_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    env::var("HOME")
-        .or_else(|_| env::var("USERPROFILE"))
+/// The user's home directory: `HOME`, falling back to `USERPROFILE` on
+/// Windows. Empty values count as unset.
+pub fn home_dir() -> Result<PathBuf, ZirvError> {
+    home_from(env::var_os("HOME"), env::var_os("USERPROFILE"))
+        .ok_or_else(|| "Could not determine home directory".into())
+}
+
+fn home_from(home: Option<OsString>, userprofile: Option<OsString>) -> Option<PathBuf> {
+    home.into_iter()
+        .chain(userprofile)
+        .find(|dir| !dir.is_empty())
         .map(PathBuf::from)
-        .map_err(|_| "Could not determine home directory".into())
-}
-
-pub fn parse_script_content(
-    content: &str,
-    ext: &str,
-) -> Result<Script, Box<dyn std::error::Error>> {
-    let script: Script = match ext {
-        "yaml" | "yml" => serde_yaml::from_str(content)?,
-        "json" => serde_json::from_str(content)?,
-        "toml" => toml::from_str(content)?,
-        other => return Err(format!("Unsupported extension: {other}").into()),
+}
+
+/// Finds the nearest `.zirv` directory in `start` or one of its parents, the
+/// way git finds `.git`; a `.zirv.yaml` file counts, even without the
+/// directory. `global_dir`, if known, is never returned, so a project below
+/// the home directory does not mistake the global scripts for its own.
+pub fn find_project_dir(start: &Path, global_dir: Option<&Path>) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(SCRIPT_DIR_NAME))
+        .find(|dir| {
+            (dir.is_dir() || scripts_file(dir).is_some()) && Some(dir.as_path()) != global_dir
+        })
+}
+
+/// The global script directory, `~/.zirv`, or `None` when no home directory
+/// can be determined. Every command finds the global scripts through this.
+pub fn zirv_home() -> Option<PathBuf> {
+    home_dir().ok().map(|home| home.join(SCRIPT_DIR_NAME))
+}
+
+/// The extra script directories listed in `ZIRV_PATH`, in order.
+pub fn zirv_path_dirs() -> Vec<PathBuf> {
+    env::var_os(ZIRV_PATH_VAR)
+        .map(|value| {
+            env::split_paths(&value)
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Reads the flat map of variables in the `vars.yaml` at `path`. Values
+/// may be strings, numbers or booleans.
+pub fn load_vars(path: &Path) -> Result<Vars, ZirvError> {
+    let values: BTreeMap<String, serde_yaml::Value> =
+        serde_yaml::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut vars = Vars::new();
+    for (name, value) in values {
+        let value = match value {
+            serde_yaml::Value::String(value) => value,
+            serde_yaml::Value::Number(value) => value.to_string(),
+            serde_yaml::Value::Bool(value) => value.to_string(),
+            _ => {
+                return Err(format!(
+                    "{}: variable '{name}' must be a string, number or boolean",
+                    path.display()
+                )
+                .into());
+            }
+        };
+        vars.insert(name, value);
+    }
+    Ok(vars)
+}
+
+/// Combines the variables of several `vars.yaml` files, given with their
+/// paths in order of precedence, along with a warning for every variable
+/// one file overrides in another.
+pub fn merge_vars(layers: &[(PathBuf, Vars)]) -> (Vars, Vec<String>) {
+    let mut merged = Vars::new();
+    let mut origins: BTreeMap<&str, &Path> = BTreeMap::new();
+    let mut warnings = Vec::new();
+    for (path, vars) in layers {
+        for (name, value) in vars {
+            match origins.get(name.as_str()) {
+                Some(winner) => warnings.push(format!(
+                    "variable '{name}' in {} overrides the one in {}",
+                    winner.display(),
+                    path.display()
+                )),
+                None => {
+                    origins.insert(name, path);
+                    merged.insert(name.clone(), value.clone());
+                }
+            }
+        }
+    }
+    (merged, warnings)
+}
+
+/// Returns the candidates closest to `name`, for "did you mean" hints: those
+/// within a small edit distance, or starting with `name`.
+pub fn suggestions<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let max_distance = name.chars().count().div_ceil(3).max(1);
+    let mut scored: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter(|c| *c != name)
+        .filter_map(|c| {
+            let distance = edit_distance(name, c);
+            (distance <= max_distance || (name.len() >= 2 && c.starts_with(name)))
+                .then_some((distance, c))
+        })
+        .collect();
+    scored.sort();
+    scored.dedup_by(|a, b| a.1 == b.1);
+    scored
+        .into_iter()
+        .take(3)
+        .map(|(_, c)| c.to_string())
+        .collect()
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// A script file that could not be parsed, with enough context to find the
+/// problem: the file, its format, the location and the offending source.
+#[derive(Debug)]
+pub struct ScriptParseError {
+    pub path: Option<PathBuf>,
+    /// `YAML`, `JSON` or `TOML`.
+    pub format: &'static str,
+    /// 1-based line and column, if the parser reported them.
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+    /// Up to two lines ending at the error line, followed by a caret marker.
+    snippet: Option<Box<str>>,
+    /// The parser's own error.
+    source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    /// Whether the script parsed but requires another version of zirv.
+    incompatible: bool,
+}
+
+impl ScriptParseError {
+    fn new(
+        content: &str,
+        format: &'static str,
+        location: Option<(usize, usize)>,
+        message: String,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        // serde_yaml and serde_json append the location to their messages.
+        let message = match (location, message.rfind(" at line ")) {
+            (Some(_), Some(i)) => message[..i].to_string(),
+            _ => message,
+        };
+        Self {
+            path: None,
+            format,
+            location,
+            message,
+            snippet: location.map(|(line, column)| snippet(content, line, column).into()),
+            source: Some(Box::new(source)),
+            incompatible: false,
+        }
+    }
+}
+
+impl ScriptParseError {
+    /// An error about a whole script, such as one with unknown fields.
+    pub(crate) fn without_location(ext: &str, message: String) -> Self {
+        Self {
+            path: None,
+            format: match ext {
+                "json" => "JSON",
+                "toml" => "TOML",
+                _ => "YAML",
+            },
+            location: None,
+            message,
+            snippet: None,
+            source: None,
+            incompatible: false,
+        }
+    }
+
+    /// A script whose requirements this version of zirv does not meet; see
+    /// [`Compatibility`].
+    fn incompatible(ext: &str, message: String) -> Self {
+        Self {
+            incompatible: true,
+            ..Self::without_location(ext, message)
+        }
+    }
+}
+
+impl std::fmt::Display for ScriptParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.incompatible {
+            if let Some(path) = &self.path {
+                write!(f, "Cannot run {}: ", path.display())?;
+            }
+            return write!(f, "{}", self.message);
+        }
+        write!(f, "Failed to parse ")?;
+        if let Some(path) = &self.path {
+            write!(f, "{} as ", path.display())?;
+        }
+        write!(f, "{}", self.format)?;
+        if let Some((line, column)) = self.location {
+            write!(f, " at line {line} column {column}")?;
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(snippet) = &self.snippet {
+            write!(f, "\n{snippet}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ScriptParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|e| e as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// Renders the error line and the one before it with line numbers, and a
+/// caret under `column`.
+fn snippet(content: &str, line: usize, column: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let last = line.min(lines.len()).max(1);
+    let first = last.saturating_sub(1).max(1);
+    let width = last.to_string().len();
+
+    let mut out = String::new();
+    for n in first..=last {
+        let text = lines.get(n - 1).copied().unwrap_or("");
+        out.push_str(&format!("{n:>width$} | {text}\n"));
+    }
+    out.push_str(&format!(
+        "{:>width$} | {}^",
+        "",
+        " ".repeat(column.saturating_sub(1))
+    ));
+    out
+}
+
+/// Converts a byte offset into a 1-based line and column.
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, column)
+}
+
+/// Parses a script, applying its `defaults`; see [`Script::with_defaults`].
+/// A script requiring another version of zirv is rejected first, since the
+/// rest of it may not parse as intended; see [`Compatibility`].
+// The error is only built once per failed parse, so its size, a little
+// larger on Windows where paths are, does not matter.
+#[allow(clippy::result_large_err)]
+pub fn parse_script_content(content: &str, ext: &str) -> Result<Script, ScriptParseError> {
+    // Whatever keeps the requirements from being read is reported by the
+    // full parse.
+    if let Ok(compatibility) = parse_content::<Compatibility>(content, ext) {
+        compatibility
+            .check(env!("CARGO_PKG_VERSION"))
+            .map_err(|message| ScriptParseError::incompatible(ext, message))?;
+    }
+    parse_content(content, ext).map(Script::with_defaults)
+}
+
+/// Parses `content` as YAML, JSON or TOML depending on `ext`.
+#[allow(clippy::result_large_err)]
+fn parse_content<T: DeserializeOwned>(content: &str, ext: &str) -> Result<T, ScriptParseError> {
+    match ext {
+        "yaml" | "yml" => serde_yaml::from_str(content).map_err(|e| {
+            let location = e.location().map(|l| (l.line(), l.column()));
+            ScriptParseError::new(content, "YAML", location, e.to_string(), e)
+        }),
+        "json" => serde_json::from_str(content).map_err(|e| {
+            let location = (e.line() > 0).then(|| (e.line(), e.column()));
+            ScriptParseError::new(content, "JSON", location, e.to_string(), e)
+        }),
+        "toml" => toml::from_str(content).map_err(|e| {
+            let location = e.span().map(|span| line_column(content, span.start));
+            ScriptParseError::new(content, "TOML", location, e.message().to_string(), e)
+        }),
+        other => Err(ScriptParseError {
+            path: None,
+            format: "script",
+            location: None,
+            message: format!("Unsupported extension: {other}"),
+            snippet: None,
+            source: None,
+            incompatible: false,
+        }),
+    }
+}
+
+/// The file holding several scripts next to the script directory `dir`:
+/// `.zirv.yaml` (in any supported format) for a `.zirv` directory.
+pub fn scripts_file(dir: &Path) -> Option<PathBuf> {
+    if dir.file_name()? != SCRIPT_DIR_NAME {
+        return None;
+    }
+    SUPPORTED_EXTENSIONS
+        .iter()
+        .map(|ext| dir.with_file_name(format!("{SCRIPT_DIR_NAME}.{ext}")))
+        .find(|path| path.is_file())
+}
+
+/// A file of named scripts, such as `.zirv.yaml`.
+#[derive(Debug, Deserialize)]
+struct ScriptsFile<T = Script> {
+    scripts: BTreeMap<String, T>,
+}
+
+/// The scripts defined in the file of scripts at `path`, by name.
+pub fn file_to_scripts(path: &Path) -> Result<BTreeMap<String, Script>, ZirvError> {
+    let content = fs::read_to_string(path)?;
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let with_path = |mut e: ScriptParseError| {
+        e.path = Some(path.to_path_buf());
+        e
     };
-    Ok(script)
+    if let Ok(file) = parse_content::<ScriptsFile<Compatibility>>(&content, &ext) {
+        for (name, compatibility) in &file.scripts {
+            compatibility
+                .check(env!("CARGO_PKG_VERSION"))
+                .map_err(|message| {
+                    with_path(ScriptParseError::incompatible(
+                        &ext,
+                        format!("script '{name}': {message}"),
+                    ))
+                })?;
+        }
+    }
+    let file: ScriptsFile = parse_content(&content, &ext).map_err(with_path)?;
+    Ok(file
+        .scripts
+        .into_iter()
+        .map(|(name, script)| (name, script.with_defaults()))
+        .collect())
 }
 
-pub fn file_to_script(path: &PathBuf) -> Result<Script, Box<dyn std::error::Error>> {
+/// Where a script is defined: in a file of its own, or under a name in a
+/// file of scripts such as `.zirv.yaml`. Shown as the file, followed by
+/// `#` and the name for the latter, e.g. `.zirv.yaml#build`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ScriptLocation {
+    /// A script file, e.g. `.zirv/build.yaml`.
+    File(PathBuf),
+    /// The script `name` in the file of scripts `file`.
+    Entry { file: PathBuf, name: String },
+}
+
+impl ScriptLocation {
+    /// The file the script is defined in.
+    pub fn file(&self) -> &Path {
+        match self {
+            ScriptLocation::File(file) | ScriptLocation::Entry { file, .. } => file,
+        }
+    }
+
+    /// The same location with its file made absolute and symlinks
+    /// resolved; see [`canonicalize`].
+    pub fn canonicalize(&self) -> std::io::Result<ScriptLocation> {
+        Ok(match self {
+            ScriptLocation::File(file) => ScriptLocation::File(canonicalize(file)?),
+            ScriptLocation::Entry { file, name } => ScriptLocation::Entry {
+                file: canonicalize(file)?,
+                name: name.clone(),
+            },
+        })
+    }
+
+    /// Loads the script; see [`file_to_script`] and [`file_to_scripts`].
+    pub fn load(&self) -> Result<Script, ZirvError> {
+        match self {
+            ScriptLocation::File(file) => file_to_script(file),
+            ScriptLocation::Entry { file, name } => {
+                file_to_scripts(file)?.remove(name).ok_or_else(|| {
+                    ZirvError::not_found(name, format!("No script '{name}' in {}", file.display()))
+                })
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ScriptLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScriptLocation::File(file) => write!(f, "{}", file.display()),
+            ScriptLocation::Entry { file, name } => write!(f, "{}#{name}", file.display()),
+        }
+    }
+}
+
+/// Like [`fs::canonicalize`], but on Windows without the `\\?\` prefix
+/// wherever the path means the same without it, since many tools, such as
+/// cmd's built-ins and Git Bash, do not understand it.
+pub fn canonicalize(path: &Path) -> std::io::Result<PathBuf> {
+    let path = fs::canonicalize(path)?;
+    if cfg!(windows)
+        && let Some(simple) = path.to_str().and_then(strip_verbatim_prefix)
+    {
+        return Ok(PathBuf::from(simple));
+    }
+    Ok(path)
+}
+
+/// `path`, a Windows path, without its verbatim prefix: `\\?\C:\dir`
+/// becomes `C:\dir` and `\\?\UNC\server\share` becomes `\\server\share`.
+/// `None` if `path` has no such prefix or needs it, because it is too long
+/// or has a part that Windows would otherwise read differently, such as
+/// `..`, a trailing dot or a device name like `NUL`.
+fn strip_verbatim_prefix(path: &str) -> Option<String> {
+    // The longest path most Windows programs accept.
+    const MAX_PATH: usize = 260;
+    let rest = path.strip_prefix(r"\\?\")?;
+    let (simple, parts) = match rest.strip_prefix(r"UNC\") {
+        Some(unc) => (format!(r"\\{unc}"), unc),
+        None => {
+            let drive = rest.get(..2).filter(|drive| {
+                drive.as_bytes()[0].is_ascii_alphabetic() && drive.ends_with(':')
+            })?;
+            let parts = rest[2..].strip_prefix('\\')?;
+            (format!(r"{drive}\{parts}"), parts)
+        }
+    };
+    let plain = |part: &str| {
+        let stem = part.split('.').next().unwrap_or_default().trim_end();
+        let device = ["CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$"]
+            .iter()
+            .any(|name| stem.eq_ignore_ascii_case(name))
+            || (stem.len() == 4
+                && stem.get(..3).is_some_and(|prefix| {
+                    ["COM", "LPT"]
+                        .iter()
+                        .any(|name| prefix.eq_ignore_ascii_case(name))
+                })
+                && stem.as_bytes()[3].is_ascii_digit());
+        part != "."
+            && part != ".."
+            && !part.ends_with(['.', ' '])
+            && !part.contains(['/', '<', '>', ':', '"', '|', '?', '*'])
+            && !device
+    };
+    (simple.len() < MAX_PATH && parts.split('\\').filter(|p| !p.is_empty()).all(plain))
+        .then_some(simple)
+}
+
+/// Loads the script file at `path`. For a script in a file of scripts, see
+/// [`ScriptLocation::load`].
+///
+/// ```no_run
+/// use std::path::PathBuf;
+///
+/// let script = zirv::utils::file_to_script(&PathBuf::from(".zirv/build.yaml"))?;
+/// println!("{} has {} steps", script.name, script.commands.len());
+/// # Ok::<(), zirv::error::ZirvError>(())
+/// ```
+pub fn file_to_script(path: &PathBuf) -> Result<Script, ZirvError> {
+    let content = fs::read_to_string(path)?;
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    parse_script_content(&content, &ext).map_err(|mut e| {
+        e.path = Some(path.clone());
+        e.into()
+    })
+}
+
+/// The fields of the script at `location` that zirv does not know, such as
+/// a misspelled option. A script that cannot be read has none, its error
+/// being reported when it is parsed.
+pub fn script_unknown_fields(location: &ScriptLocation) -> Vec<String> {
+    let (file, entry) = match location {
+        ScriptLocation::File(file) => (file, None),
+        ScriptLocation::Entry { file, name } => (file, Some(name)),
+    };
+    let ext = file
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let Some(document) = fs::read_to_string(file)
+        .ok()
+        .and_then(|content| parse_document(&content, &ext).ok())
+    else {
+        return Vec::new();
+    };
+    match entry {
+        Some(name) => document
+            .get("scripts")
+            .and_then(|scripts| scripts.get(name))
+            .map(unknown_fields)
+            .unwrap_or_default(),
+        None => unknown_fields(&document),
+    }
+}
+
+/// An environment overlay applied to a script: the file, and the fields it
+/// set, such as `description` or `commands[1].options.proceed_on_failure`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Overlay {
+    pub path: PathBuf,
+    pub fields: Vec<String>,
+}
+
+impl Overlay {
+    /// The overlay with its step fields renumbered for a script reduced to
+    /// the steps at `indices`, leaving out the others.
+    pub fn for_steps(&self, indices: &[usize]) -> Overlay {
+        let fields = self
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let Some(rest) = field.strip_prefix("commands[") else {
+                    return Some(field.clone());
+                };
+                let (index, rest) = rest.split_once(']')?;
+                let position = indices.iter().position(|i| i.to_string() == index)?;
+                Some(format!("commands[{position}]{rest}"))
+            })
+            .collect();
+        Overlay {
+            path: self.path.clone(),
+            fields,
+        }
+    }
+}
+
+/// The overlay for `env` next to `script`: `deploy.prod.yaml` for
+/// `deploy.yaml`, in any supported format.
+pub fn overlay_path(script: &Path, env: &str) -> Option<PathBuf> {
+    let stem = script.file_stem()?.to_str()?;
+    SUPPORTED_EXTENSIONS
+        .iter()
+        .map(|ext| script.with_file_name(format!("{stem}.{env}.{ext}")))
+        .find(|path| path.is_file())
+}
+
+/// The script `path` is an overlay for and the overlay's environment, e.g.
+/// `deploy.yaml` and `prod` for `deploy.prod.yaml`, or `None` if `path` is
+/// not an overlay.
+pub fn overlay_base(path: &Path) -> Option<(PathBuf, String)> {
+    let (base, env) = path.file_stem()?.to_str()?.rsplit_once('.')?;
+    SUPPORTED_EXTENSIONS
+        .iter()
+        .map(|ext| path.with_file_name(format!("{base}.{ext}")))
+        .find(|base| base.is_file())
+        .map(|base| (base, env.to_string()))
+}
+
+fn file_to_value(path: &Path) -> Result<Value, ZirvError> {
     let content = fs::read_to_string(path)?;
     let ext = path
         .extension()
         .and_then(|s| s.to_str())
         .unwrap_or("")
         .to_lowercase();
-    parse_script_content(&content, &ext)
+    parse_content(&content, &ext).map_err(|mut e| {
+        e.path = Some(path.to_path_buf());
+        e.into()
+    })
+}
+
+/// Loads the script at `location` with its overlay for `env` merged over
+/// it; see [`merge_overlay`]. Scripts in a file of scripts have no overlays.
+pub fn file_to_script_with_overlay(
+    location: &ScriptLocation,
+    env: &str,
+) -> Result<(Script, Overlay), ZirvError> {
+    let path = match location {
+        ScriptLocation::File(path) => path,
+        ScriptLocation::Entry { file, .. } => {
+            return Err(format!("--env is not supported for scripts in {}", file.display()).into());
+        }
+    };
+    let overlay_path = overlay_path(path, env).ok_or_else(|| {
+        format!(
+            "No '{env}' overlay for {}; expected {}",
+            path.display(),
+            path.with_extension(format!("{env}.yaml")).display()
+        )
+    })?;
+    let mut script = file_to_value(path)?;
+    let fields = merge_overlay(&mut script, file_to_value(&overlay_path)?);
+    let script = serde_json::from_value::<Script>(script)
+        .map_err(|e| {
+            format!(
+                "{} merged with {} is not a valid script: {e}",
+                path.display(),
+                overlay_path.display()
+            )
+        })?
+        .with_defaults();
+    Ok((
+        script,
+        Overlay {
+            path: overlay_path,
+            fields,
+        },
+    ))
+}
+
+/// Merges the script `overlay` into `base`, returning the fields it set.
+///
+/// Maps are merged key by key and everything else is replaced, except that
+/// `params` and `secrets` are unioned (a secret of the same name replaces
+/// the base one) and `commands` are merged step by step: an overlay step
+/// with a `name` merges into the base step of that name, one without into
+/// the base step at the same position, and any other is appended.
+pub fn merge_overlay(base: &mut Value, overlay: Value) -> Vec<String> {
+    let mut fields = Vec::new();
+    let (Value::Object(base), Value::Object(overlay)) = (base, overlay) else {
+        return fields;
+    };
+    for (key, value) in overlay {
+        let existing = base.entry(key.clone()).or_insert(Value::Null);
+        match (key.as_str(), existing, value) {
+            ("params", Value::Array(params), Value::Array(added)) => {
+                for param in added {
+                    if !params.contains(&param) {
+                        params.push(param);
+                        fields.push("params".to_string());
+                    }
+                }
+            }
+            ("secrets", Value::Array(secrets), Value::Array(added)) => {
+                for secret in added {
+                    let same_name = secrets.iter().position(|s| {
+                        s.get("name").is_some() && s.get("name") == secret.get("name")
+                    });
+                    match same_name {
+                        Some(i) if secrets[i] == secret => continue,
+                        Some(i) => secrets[i] = secret,
+                        None => secrets.push(secret),
+                    }
+                    fields.push("secrets".to_string());
+                }
+            }
+            ("commands", Value::Array(steps), Value::Array(overlay_steps)) => {
+                merge_steps(steps, overlay_steps, &mut fields);
+            }
+            (_, existing, value) => merge_value(&key, existing, value, &mut fields),
+        }
+    }
+    fields.dedup();
+    fields
+}
+
+fn merge_steps(steps: &mut Vec<Value>, overlay: Vec<Value>, fields: &mut Vec<String>) {
+    for (position, step) in overlay.into_iter().enumerate() {
+        let index = match step.get("name") {
+            Some(name) => steps.iter().position(|s| s.get("name") == Some(name)),
+            None => (position < steps.len()).then_some(position),
+        };
+        match index {
+            Some(i) => merge_value(&format!("commands[{i}]"), &mut steps[i], step, fields),
+            None => {
+                fields.push(format!("commands[{}]", steps.len()));
+                steps.push(step);
+            }
+        }
+    }
+}
+
+/// Merges `value` into `existing` at `path`: maps key by key, anything else
+/// by replacing it.
+fn merge_value(path: &str, existing: &mut Value, value: Value, fields: &mut Vec<String>) {
+    match (existing, value) {
+        (Value::Object(existing), Value::Object(value)) => {
+            for (key, value) in value {
+                let entry = existing.entry(key.clone()).or_insert(Value::Null);
+                merge_value(&format!("{path}.{key}"), entry, value, fields);
+            }
+        }
+        (existing, value) => {
+            if *existing != value {
+                *existing = value;
+                fields.push(path.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script_runner::command_types::CommandTypes;
+
+    fn parse_file(name: &str, content: &str) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(name);
+        fs::write(&path, content).unwrap();
+        let err = file_to_script(&path).unwrap_err().to_string();
+        assert!(err.contains(&path.display().to_string()), "{err}");
+        err
+    }
+
+    #[test]
+    fn test_yaml_error_has_location_and_snippet() {
+        let err = parse_file("build.yaml", "name: Build\ncommands:\n\t- command: ls\n");
+        assert!(err.contains("as YAML at line 3 column 1: "), "{err}");
+        assert!(
+            err.ends_with("2 | commands:\n3 | \t- command: ls\n  | ^"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_json_error_has_location_and_snippet() {
+        let err = parse_file(
+            "build.json",
+            "{\n  \"name\": \"Build\",\n  \"commands\": [,]\n}",
+        );
+        assert!(err.contains("as JSON at line 3 column 16: "), "{err}");
+        assert!(!err.contains(" at line 3 column 16\n"), "{err}");
+        assert!(
+            err.ends_with("3 |   \"commands\": [,]\n  |                ^"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_toml_error_has_location_and_snippet() {
+        let err = parse_file("build.toml", "name = \"Build\"\ncommands = [\n");
+        assert!(err.contains("as TOML at line "), "{err}");
+        assert!(err.contains(" | commands = ["), "{err}");
+        assert!(err.ends_with('^'), "{err}");
+    }
+
+    #[test]
+    fn test_script_for_newer_zirv_is_rejected_before_parsing() {
+        // The commands are in a form this zirv does not know.
+        let err = parse_file(
+            "deploy.yaml",
+            "name: Deploy\nzirv_version: \">=999\"\ncommands:\n  run: deploy\n",
+        );
+        assert!(err.starts_with("Cannot run "), "{err}");
+        assert!(
+            err.ends_with(&format!(
+                "deploy.yaml: this script requires zirv >= 999, you have {}",
+                env!("CARGO_PKG_VERSION")
+            )),
+            "{err}"
+        );
+
+        let err = parse_file("deploy.json", r#"{"name": "Deploy", "schema": 999}"#);
+        assert!(
+            err.contains("deploy.json: this script uses schema 999"),
+            "{err}"
+        );
+        assert!(!err.contains("Failed to parse"), "{err}");
+
+        let current = "name: Deploy\nzirv_version: \">=1, <999\"\nschema: 1\ncommands: []\n";
+        let script = parse_script_content(current, "yaml").unwrap();
+        assert_eq!(script.zirv_version.as_deref(), Some(">=1, <999"));
+        assert_eq!(script.schema, Some(1));
+    }
+
+    #[test]
+    fn test_scripts_file_names_the_incompatible_script() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".zirv.yaml");
+        fs::write(
+            &path,
+            "scripts:\n  build:\n    name: Build\n    commands: []\n  \
+             deploy:\n    name: Deploy\n    zirv_version: \">=0.4, <0.5\"\n    commands: []\n",
+        )
+        .unwrap();
+        let err = file_to_scripts(&path).unwrap_err().to_string();
+        assert!(err.contains(&path.display().to_string()), "{err}");
+        assert!(
+            err.contains("script 'deploy': this script requires zirv >= 0.4, < 0.5"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_home_precedence() {
+        let home = |home: Option<&str>, userprofile: Option<&str>| {
+            home_from(home.map(OsString::from), userprofile.map(OsString::from))
+        };
+        assert_eq!(
+            home(Some("/home/me"), Some("C:\\Users\\me")),
+            Some(PathBuf::from("/home/me"))
+        );
+        assert_eq!(
+            home(None, Some("C:\\Users\\me")),
+            Some(PathBuf::from("C:\\Users\\me"))
+        );
+        assert_eq!(
+            home(Some(""), Some("C:\\Users\\me")),
+            Some(PathBuf::from("C:\\Users\\me"))
+        );
+        assert_eq!(home(Some(""), None), None);
+        assert_eq!(home(None, None), None);
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let candidates = ["build", "bump", "deploy", "db/migrate", "b"];
+        assert_eq!(suggestions("biuld", candidates), vec!["build"]);
+        assert_eq!(suggestions("db/migrat", candidates), vec!["db/migrate"]);
+        assert_eq!(suggestions("dep", candidates), vec!["deploy"]);
+        assert!(suggestions("release", candidates).is_empty());
+    }
+
+    #[test]
+    fn test_shortcut_forms() {
+        let shortcuts: Shortcuts = serde_yaml::from_str(
+            "shortcuts:\n  d: deploy.yaml\n  dp:\n    script: deploy\n    params: [prod, { region: eu }]\n",
+        )
+        .unwrap();
+
+        assert_eq!(shortcuts.shortcuts["d"], Shortcut::from("deploy.yaml"));
+        assert!(shortcuts.shortcuts["d"].params().is_empty());
+        let dp = &shortcuts.shortcuts["dp"];
+        assert_eq!(dp.script(), "deploy");
+        assert_eq!(
+            dp.params(),
+            [
+                ShortcutParam::Positional("prod".to_string()),
+                ShortcutParam::Named(BTreeMap::from([("region".to_string(), "eu".to_string())]))
+            ]
+        );
+        assert_eq!(dp.to_string(), "deploy prod region=eu");
+    }
+
+    #[test]
+    fn test_merge_params() {
+        let names: Vec<String> = ["env", "version", "region"].map(String::from).to_vec();
+        let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        let bundled = [
+            ShortcutParam::Positional("prod".to_string()),
+            ShortcutParam::Named(BTreeMap::from([("region".to_string(), "eu".to_string())])),
+        ];
+
+        // Bundled positional values come first, named ones fill their slot.
+        assert_eq!(
+            merge_params(&names, &bundled, &strings(&["v2"])),
+            Ok(strings(&["prod", "v2", "eu"]))
+        );
+        // Enough CLI values override the named default by position.
+        assert_eq!(
+            merge_params(&names, &bundled, &strings(&["v2", "us"])),
+            Ok(strings(&["prod", "v2", "us"]))
+        );
+        // Plain shortcuts pass CLI params through unchanged.
+        assert_eq!(
+            merge_params(&names, &[], &strings(&["a", "b", "c", "d"])),
+            Ok(strings(&["a", "b", "c", "d"]))
+        );
+        assert_eq!(
+            merge_params(&[], &bundled[..1], &strings(&["x"])),
+            Ok(strings(&["prod", "x"]))
+        );
+        assert!(merge_params(&names[..1], &bundled, &[]).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_extension() {
+        let err = parse_script_content("", "ini").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse script: Unsupported extension: ini"
+        );
+    }
+
+    #[test]
+    fn test_defaults_apply_to_every_command() {
+        let script = parse_script_content(
+            r#"
+name: Build
+defaults:
+  proceed_on_failure: true
+  delay_ms: 10
+commands:
+  - command: make
+    options:
+      delay_ms: 0
+      fallback:
+        - command: make clean
+  - - command: lint
+    - command: test
+"#,
+            "yaml",
+        )
+        .unwrap();
+        assert!(script.defaults.is_none());
+
+        let CommandTypes::Command(make) = &script.commands[0] else {
+            panic!("expected a single command");
+        };
+        let options = make.options.as_ref().unwrap();
+        assert!(options.proceed_on_failure());
+        assert_eq!(options.delay_ms, Some(0));
+        let fallback = options.fallback.as_ref().unwrap()[0].options.as_ref();
+        assert_eq!(fallback.unwrap().delay_ms, Some(10));
+
+        let CommandTypes::Commands(group) = &script.commands[1] else {
+            panic!("expected a group");
+        };
+        for command in group {
+            let options = command.options.as_ref().unwrap();
+            assert!(options.proceed_on_failure());
+            assert_eq!(options.delay_ms, Some(10));
+        }
+    }
+
+    fn merged(base: &str, overlay: &str) -> (Value, Vec<String>) {
+        let mut base: Value = serde_yaml::from_str(base).unwrap();
+        let fields = merge_overlay(&mut base, serde_yaml::from_str(overlay).unwrap());
+        (base, fields)
+    }
+
+    #[test]
+    fn test_overlay_scalars_override() {
+        let (script, fields) = merged(
+            "name: Deploy\ndescription: To dev\ncommands: []\n",
+            "description: To prod\nname: Deploy\n",
+        );
+        assert_eq!(script["description"], "To prod");
+        assert_eq!(fields, ["description"]);
+    }
+
+    #[test]
+    fn test_overlay_maps_merge_key_by_key() {
+        let (script, fields) = merged(
+            "commands:\n  - command: a\n    options:\n      interactive: true\n      delay_ms: 5\n",
+            "commands:\n  - options:\n      delay_ms: 10\n      proceed_on_failure: true\n",
+        );
+        let options = &script["commands"][0]["options"];
+        assert_eq!(options["interactive"], true);
+        assert_eq!(options["delay_ms"], 10);
+        assert_eq!(options["proceed_on_failure"], true);
+        assert_eq!(script["commands"][0]["command"], "a");
+        assert_eq!(
+            fields,
+            [
+                "commands[0].options.delay_ms",
+                "commands[0].options.proceed_on_failure"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlay_steps_match_by_name_then_index() {
+        let (script, fields) = merged(
+            "commands:\n  - command: build\n  - command: notify\n  - name: push\n    command: push dev\n",
+            "commands:\n  - name: push\n    command: push prod\n  - command: notify prod\n",
+        );
+        let commands: Vec<&str> = script["commands"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["command"].as_str().unwrap())
+            .collect();
+        // The unnamed overlay step is second, so it merges into `notify`.
+        assert_eq!(commands, ["build", "notify prod", "push prod"]);
+        assert_eq!(fields, ["commands[2].command", "commands[1].command"]);
+    }
+
+    #[test]
+    fn test_overlay_appends_unmatched_steps() {
+        let (script, fields) = merged(
+            "commands:\n  - command: build\n",
+            "commands:\n  - command: build\n  - command: smoke test\n  - name: tag\n    command: git tag\n",
+        );
+        assert_eq!(script["commands"].as_array().unwrap().len(), 3);
+        assert_eq!(script["commands"][2]["name"], "tag");
+        assert_eq!(fields, ["commands[1]", "commands[2]"]);
+    }
+
+    #[test]
+    fn test_overlay_unions_params_and_secrets() {
+        let (script, fields) = merged(
+            "params: [env]\nsecrets:\n  - name: token\n    env_var: DEV_TOKEN\n  - name: key\n    env_var: KEY\n",
+            "params: [env, region]\nsecrets:\n  - name: token\n    env_var: PROD_TOKEN\n  - name: key\n    env_var: KEY\n  - name: cert\n    env_var: CERT\n",
+        );
+        assert_eq!(script["params"], serde_json::json!(["env", "region"]));
+        let secrets: Vec<(&str, &str)> = script["secrets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| (s["name"].as_str().unwrap(), s["env_var"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            secrets,
+            [("token", "PROD_TOKEN"), ("key", "KEY"), ("cert", "CERT")]
+        );
+        assert_eq!(fields, ["params", "secrets"]);
+    }
+
+    #[test]
+    fn test_overlay_replaces_other_lists_and_adds_missing_fields() {
+        let (script, fields) = merged(
+            "aliases: [d, dep]\ncommands:\n  - command: a\n    tags: [slow, ci]\n",
+            "aliases: [p]\nparams: [env]\ncommands:\n  - tags: [ci]\n",
+        );
+        assert_eq!(script["aliases"], serde_json::json!(["p"]));
+        assert_eq!(script["params"], serde_json::json!(["env"]));
+        assert_eq!(script["commands"][0]["tags"], serde_json::json!(["ci"]));
+        assert_eq!(fields, ["aliases", "commands[0].tags", "params"]);
+    }
+
+    #[test]
+    fn test_overlay_unchanged_values_are_not_fields() {
+        let (_, fields) = merged(
+            "name: A\nparams: [env]\ncommands:\n  - command: a\n",
+            "name: A\nparams: [env]\ncommands:\n  - command: a\n",
+        );
+        assert!(fields.is_empty(), "{fields:?}");
+    }
+
+    #[test]
+    fn test_file_to_script_with_overlay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deploy.yaml");
+        let base = ScriptLocation::File(path.clone());
+        fs::write(
+            &path,
+            "name: Deploy\ncommands:\n  - name: push\n    command: push dev\n",
+        )
+        .unwrap();
+
+        let error = file_to_script_with_overlay(&base, "prod")
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("No 'prod' overlay for "), "{error}");
+        assert!(error.ends_with("deploy.prod.yaml"), "{error}");
+
+        // The overlay may use another format than the script.
+        let overlay = dir.path().join("deploy.prod.json");
+        fs::write(
+            &overlay,
+            r#"{"commands": [{"name": "push", "command": "push prod"}]}"#,
+        )
+        .unwrap();
+        let (script, applied) = file_to_script_with_overlay(&base, "prod").unwrap();
+        assert_eq!(script.commands[0].label(), "push");
+        assert_eq!(
+            applied,
+            Overlay {
+                path: overlay.clone(),
+                fields: vec!["commands[0].command".to_string()]
+            }
+        );
+
+        let selected = applied.for_steps(&[]);
+        assert!(selected.fields.is_empty());
+        let selected = Overlay {
+            path: overlay.clone(),
+            fields: vec!["description".to_string(), "commands[3].name".to_string()],
+        }
+        .for_steps(&[1, 3]);
+        assert_eq!(selected.fields, ["description", "commands[1].name"]);
+
+        fs::write(&overlay, r#"{"commands": "none"}"#).unwrap();
+        let error = file_to_script_with_overlay(&base, "prod")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("is not a valid script"), "{error}");
+    }
+
+    #[test]
+    fn test_load_and_merge_vars() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("local.yaml");
+        let global = dir.path().join("global.yaml");
+        fs::write(&local, "registry: ghcr.io/acme\nport: 8080\n").unwrap();
+        fs::write(&global, "registry: docker.io\nslug: acme\ndebug: false\n").unwrap();
+
+        let layers = [
+            (local.clone(), load_vars(&local).unwrap()),
+            (global.clone(), load_vars(&global).unwrap()),
+        ];
+        let (vars, warnings) = merge_vars(&layers);
+        assert_eq!(
+            vars,
+            Vars::from([
+                ("debug".to_string(), "false".to_string()),
+                ("port".to_string(), "8080".to_string()),
+                ("registry".to_string(), "ghcr.io/acme".to_string()),
+                ("slug".to_string(), "acme".to_string()),
+            ])
+        );
+        assert_eq!(
+            warnings,
+            [format!(
+                "variable 'registry' in {} overrides the one in {}",
+                local.display(),
+                global.display()
+            )]
+        );
+
+        fs::write(&local, "registry:\n  url: x\n").unwrap();
+        assert_eq!(
+            load_vars(&local).unwrap_err().to_string(),
+            format!(
+                "{}: variable 'registry' must be a string, number or boolean",
+                local.display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_strip_verbatim_prefix() {
+        for (path, expected) in [
+            (r"\\?\C:\Users\dev\project", Some(r"C:\Users\dev\project")),
+            (r"\\?\d:\my project\.zirv", Some(r"d:\my project\.zirv")),
+            (r"\\?\C:\", Some(r"C:\")),
+            (r"\\?\UNC\server\share\dir", Some(r"\\server\share\dir")),
+            (r"C:\Users\dev", None),
+            (r"\\?\Volume{1234}\dir", None),
+            (r"\\?\C:\dir\NUL", None),
+            (r"\\?\C:\dir\com1.txt", None),
+            (r"\\?\C:\dir\trailing.", None),
+            (r"\\?\C:\dir\trailing ", None),
+            (r"\\?\C:\dir\..\up", None),
+            (r"\\?\C:\a/b", None),
+            (r"\\?\C:\dir\console", Some(r"C:\dir\console")),
+        ] {
+            assert_eq!(strip_verbatim_prefix(path).as_deref(), expected, "{path}");
+        }
+        let long = format!(r"\\?\C:\{}", "a".repeat(300));
+        assert_eq!(strip_verbatim_prefix(&long), None);
+    }
 }
@@ -0,0 +1,45 @@
+mod common;
+
+use std::path::Path;
+use std::process::Output;
+
+fn zirv(dir: &Path, args: &[&str]) -> Output {
+    common::zirv(dir).args(args).output().unwrap()
+}
+
+/// A broken config.yaml stops script runs, but not the commands that help
+/// find and fix it.
+#[test]
+fn test_broken_config_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    common::write_script(
+        dir.path(),
+        "hello.yaml",
+        "name: Hello\ncommands:\n  - command: echo hello\n",
+    );
+    common::write_script(dir.path(), "config.yaml", "colour: false\n");
+
+    let run = zirv(dir.path(), &["hello"]);
+    let stderr = String::from_utf8_lossy(&run.stderr);
+    assert!(!run.status.success());
+    assert!(stderr.contains("unknown field `colour`"), "{stderr}");
+
+    let validate = zirv(dir.path(), &["validate"]);
+    let stdout = String::from_utf8_lossy(&validate.stdout);
+    assert!(!validate.status.success());
+    assert!(stdout.contains("config.yaml:\n"), "{stdout}");
+    assert!(stdout.contains("unknown field `colour`"), "{stdout}");
+
+    let config = zirv(dir.path(), &["config"]);
+    let stdout = String::from_utf8_lossy(&config.stdout);
+    let stderr = String::from_utf8_lossy(&config.stderr);
+    assert!(!config.status.success());
+    assert!(stdout.contains("shell: "), "{stdout}");
+    assert!(
+        stderr.contains("Ignored invalid configuration: ") && stderr.contains("`colour`"),
+        "{stderr}"
+    );
+
+    let help = zirv(dir.path(), &["help", "--no-pager"]);
+    assert!(help.status.success(), "{help:?}");
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use std::path::Path;
+use std::process::Output;
+
+const BUILD: &str = r#"
+name: Build
+params:
+  - version
+commands:
+  - command: printf 'app-%s.tar.gz' ${version}
+    capture: artifact
+"#;
+
+fn release(export: &str) -> String {
+    format!(
+        r#"
+name: Release
+commands:
+  - command: printf 1.4.2
+    capture: version
+  - command: zirv build ${{version}}
+    export:
+      {export}
+  - command: echo "${{build_artifact}}" > result.txt
+"#
+    )
+}
+
+fn run_release(dir: &Path, export: &str) -> Output {
+    let zirv = Path::new(env!("CARGO_BIN_EXE_zirv"));
+    let path = std::env::join_paths(
+        std::iter::once(zirv.parent().unwrap().to_path_buf())
+            .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+    common::write_script(dir, "build.yaml", BUILD);
+    common::write_script(dir, "release.yaml", &release(export));
+    common::zirv(dir)
+        .args(["release", "--no-summary"])
+        .env("PATH", path)
+        .env_remove("ZIRV_SAVE_CONTEXT")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_called_script_gets_captures_and_exports_back() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = run_release(dir.path(), "artifact: build_artifact");
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("result.txt"))
+            .unwrap()
+            .trim(),
+        "app-1.4.2.tar.gz"
+    );
+}
+
+#[test]
+fn test_export_name_collisions_fail_the_step() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = run_release(dir.path(), "artifact: version");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Cannot export 'artifact' as 'version': 'version' is already set"),
+        "{stderr}"
+    );
+
+    let output = run_release(dir.path(), "checksum: build_artifact");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Cannot export 'checksum': the called script did not save it"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,31 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Writes `content` to `.zirv/<file>` under `dir`.
+pub fn write_script(dir: &Path, file: &str, content: &str) {
+    let scripts = dir.join(".zirv");
+    std::fs::create_dir_all(&scripts).unwrap();
+    std::fs::write(scripts.join(file), content).unwrap();
+}
+
+/// The home directory runs in `dir` use, so that tests never read or write
+/// the real user's config and history.
+pub fn home(dir: &Path) -> PathBuf {
+    dir.join("home")
+}
+
+/// Points `HOME` and `USERPROFILE` of `command` at [`home`].
+pub fn isolate_home<'a>(command: &'a mut Command, dir: &Path) -> &'a mut Command {
+    command.env("HOME", home(dir)).env("USERPROFILE", home(dir))
+}
+
+/// A `zirv` command that runs in `dir` with an isolated home directory.
+pub fn zirv(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_zirv"));
+    command.current_dir(dir);
+    isolate_home(&mut command, dir);
+    command
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use std::process::{Command, Stdio};
+
+/// Without a terminal to ask on, replacing an existing hook needs `--yes`.
+#[test]
+fn test_replacing_a_hook_without_a_terminal_needs_yes() {
+    let dir = tempfile::tempdir().unwrap();
+    let status = Command::new("git")
+        .args(["init", "--quiet"])
+        .arg(dir.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let hooks = dir.path().join(".git").join("hooks");
+    std::fs::create_dir_all(&hooks).unwrap();
+    std::fs::write(hooks.join("pre-push"), "#!/bin/sh\nmake lint\n").unwrap();
+    common::write_script(
+        dir.path(),
+        "lint.yaml",
+        "name: Lint\ncommands:\n  - command: echo lint\n",
+    );
+
+    let output = common::zirv(dir.path())
+        .args(["install-hook", "pre-push", "lint"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("not a terminal; pass --yes to install the hook non-interactively"),
+        "{stderr}"
+    );
+    assert!(!hooks.join("pre-push.bak").exists());
+
+    let output = common::zirv(dir.path())
+        .args(["install-hook", "pre-push", "lint", "--yes"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(hooks.join("pre-push.bak").exists());
+}
@@ -0,0 +1,59 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+const SCRIPT: &str = r#"
+name: Slow
+commands:
+  - command: touch started; sleep 10
+  - command: touch finished
+"#;
+
+/// Waits up to ten seconds for `path` to exist.
+fn wait_for(path: &Path) {
+    let start = Instant::now();
+    while !path.exists() {
+        assert!(start.elapsed() < Duration::from_secs(10), "{path:?}");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// A run stopped by Ctrl-C still writes its report and history entry,
+/// saying how its steps ended.
+#[test]
+fn test_interrupted_run_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    common::write_script(dir.path(), "slow.yaml", SCRIPT);
+
+    let mut child = common::zirv(dir.path())
+        .args(["slow", "--no-summary", "--report", "report.json"])
+        .spawn()
+        .unwrap();
+    wait_for(&dir.path().join("started"));
+    let killed = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    let status = child.wait().unwrap();
+
+    assert_eq!(status.code(), Some(130));
+    assert!(!dir.path().join("finished").exists());
+    let report: Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("report.json")).unwrap())
+            .unwrap();
+    assert_eq!(report["success"], false);
+    assert_eq!(report["error"], "Script 'Slow' was stopped");
+    assert_eq!(report["termination"], "graceful");
+    assert_eq!(report["steps"][0]["status"], "failed");
+    let history =
+        std::fs::read_to_string(common::home(dir.path()).join(".zirv").join("history.jsonl"))
+            .unwrap();
+    assert!(history.contains("130"), "{history}");
+}
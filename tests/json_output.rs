@@ -0,0 +1,119 @@
+mod common;
+
+use serde_json::Value;
+
+const SCRIPT: &str = r#"
+name: Publish
+secrets:
+  - name: token
+    env_var: ZIRV_JSON_TEST_TOKEN
+commands:
+  - command: echo "token ${token}"
+  - command: echo oops >&2; exit 3
+"#;
+
+#[test]
+fn test_json_output_streams_events() {
+    let dir = tempfile::tempdir().unwrap();
+    common::write_script(dir.path(), "publish.yaml", SCRIPT);
+
+    let output = common::zirv(dir.path())
+        .args(["publish", "--output", "json"])
+        .env("ZIRV_JSON_TEST_TOKEN", "s3cr3t-value")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("s3cr3t-value"), "{stdout}");
+    assert!(!stdout.contains("Summary for"), "{stdout}");
+
+    let events: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|_| panic!("{line}")))
+        .collect();
+    let types: Vec<&str> = events
+        .iter()
+        .map(|event| event["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        types,
+        [
+            "run_start",
+            "command_start",
+            "message",
+            "log",
+            "command_end",
+            "command_start",
+            "message",
+            "log",
+            "command_end",
+            "run_end"
+        ]
+    );
+
+    assert_eq!(events[0]["script"], "Publish");
+    assert_eq!(events[1]["index"], 1);
+    assert_eq!(events[1]["command"], "echo \"token ${token}\"");
+    assert_eq!(events[2]["step"], 1);
+    assert_eq!(
+        events[2]["line"],
+        "Executing command: echo \"token ********\""
+    );
+    assert_eq!(events[3]["stream"], "stdout");
+    assert_eq!(events[3]["line"], "token ********");
+    assert_eq!(events[4]["status"], "success");
+    assert_eq!(events[4]["exit_code"], 0);
+    assert!(events[4]["duration_ms"].is_u64());
+    assert_eq!(events[7]["stream"], "stderr");
+    assert_eq!(events[7]["line"], "oops");
+    assert_eq!(events[8]["index"], 2);
+    assert_eq!(events[8]["status"], "failed");
+    assert_eq!(events[8]["exit_code"], 3);
+    assert_eq!(events[9]["success"], false);
+    assert_eq!(events[9]["timed_out"], false);
+    assert!(events[9]["error"].is_string());
+    assert!(events[9]["duration_ms"].is_u64());
+}
+
+const TERMINAL_SCRIPT: &str = r#"
+name: Terminal
+commands:
+  - command: echo shown directly
+    options:
+      interactive: true
+  - command: echo captured
+    capture: answer
+    options:
+      interactive: true
+  - command: exit 1
+    options:
+      proceed_on_failure: true
+      retry_after_fallback: false
+      fallback:
+        - command: echo from the fallback
+          options:
+            interactive: true
+"#;
+
+/// Steps that use the terminal show their output on stderr, so that stdout
+/// stays one event per line.
+#[test]
+fn test_terminal_steps_keep_stdout_for_events() {
+    let dir = tempfile::tempdir().unwrap();
+    common::write_script(dir.path(), "terminal.yaml", TERMINAL_SCRIPT);
+
+    let output = common::zirv(dir.path())
+        .args(["terminal", "--output", "json"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stdout}{stderr}");
+
+    for line in stdout.lines() {
+        assert!(serde_json::from_str::<Value>(line).is_ok(), "{stdout}");
+    }
+    for shown in ["shown directly", "captured", "from the fallback"] {
+        assert!(stderr.contains(shown), "{stderr}");
+    }
+}
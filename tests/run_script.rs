@@ -0,0 +1,156 @@
+use tokio::sync::mpsc::unbounded_channel;
+use zirv::{
+    script_runner::{RunError, RunOptions, StepError, StepStatus, Stream, UiEvent, execute},
+    utils::{Vars, parse_script_content},
+};
+
+const SCRIPT: &str = r#"
+name: Release
+params: [version]
+secrets:
+  - name: token
+    env_var: ZIRV_TEST_UNSET_TOKEN
+commands:
+  - command: echo releasing ${version}
+  - command: echo token ${token}
+    tags: [publish]
+"#;
+
+fn options() -> RunOptions {
+    let mut options = RunOptions::default();
+    options.no_summary = true;
+    options.secrets = Vars::from([("token".to_string(), "t0k3n".to_string())]);
+    options
+}
+
+#[tokio::test]
+async fn test_run_emits_events() {
+    let script = parse_script_content(SCRIPT, "yaml").unwrap();
+    let (events, mut received) = unbounded_channel();
+    let mut options = options();
+    options.events = Some(events);
+
+    let report = execute(&script, &["1.2.0".to_string()], &options)
+        .await
+        .unwrap();
+    assert!(report.success);
+    assert_eq!(report.steps.len(), 2);
+    drop(options);
+
+    let mut all = Vec::new();
+    while let Some(event) = received.recv().await {
+        all.push(event);
+    }
+    let started: Vec<usize> = all
+        .iter()
+        .filter_map(|event| match event {
+            UiEvent::StepStarted { index, .. } => Some(*index),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(started, [1, 2]);
+    assert!(all.contains(&UiEvent::Output {
+        step: Some(1),
+        stream: Stream::Stdout,
+        text: "releasing 1.2.0".to_string(),
+    }));
+    assert!(all.contains(&UiEvent::Output {
+        step: Some(2),
+        stream: Stream::Stdout,
+        text: "token ********".to_string(),
+    }));
+    assert!(all.iter().any(|event| matches!(
+        event,
+        UiEvent::StepFinished {
+            index: 2,
+            status: StepStatus::Success,
+            ..
+        }
+    )));
+}
+
+#[tokio::test]
+async fn test_skipped_steps_only_finish() {
+    let script = parse_script_content(SCRIPT, "yaml").unwrap();
+    let (events, mut received) = unbounded_channel();
+    let mut options = options();
+    options.skip_tags = vec!["publish".to_string()];
+    options.events = Some(events);
+
+    execute(&script, &["1.2.0".to_string()], &options)
+        .await
+        .unwrap();
+    drop(options);
+
+    let mut step_events = Vec::new();
+    while let Some(event) = received.recv().await {
+        match event {
+            UiEvent::StepStarted { index, .. } => step_events.push((index, None)),
+            UiEvent::StepFinished { index, status, .. } => step_events.push((index, Some(status))),
+            _ => {}
+        }
+    }
+    assert_eq!(
+        step_events,
+        [
+            (1, None),
+            (1, Some(StepStatus::Success)),
+            (2, Some(StepStatus::Skipped)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_errors_are_typed() {
+    let script = parse_script_content(SCRIPT, "yaml").unwrap();
+    let error = execute(&script, &[], &options()).await.unwrap_err();
+    assert!(
+        matches!(
+            &error,
+            RunError::Inputs(errors) if errors.missing_params() == ["version"]
+                && errors.missing_secrets.is_empty()
+        ),
+        "{error}"
+    );
+
+    let error = execute(&script, &["1.2.0".to_string()], &RunOptions::default())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.to_string().lines().next(),
+        Some("missing secrets: token (env ZIRV_TEST_UNSET_TOKEN)")
+    );
+
+    let script =
+        parse_script_content("name: Broken\ncommands:\n  - command: exit 3\n", "yaml").unwrap();
+    let mut no_shell = options();
+    no_shell.shell = Some("/nonexistent/shell".to_string());
+    let error = execute(&script, &[], &no_shell).await.unwrap_err();
+    assert!(
+        matches!(
+            &error,
+            RunError::Step {
+                exit_code: None,
+                error: StepError::Spawn { .. },
+                ..
+            }
+        ),
+        "{error}"
+    );
+    // The step's message is already in the run's, so only the step error
+    // itself leads on to the I/O error behind it.
+    assert!(std::error::Error::source(&error).is_none());
+    if let RunError::Step { error, .. } = &error {
+        assert!(std::error::Error::source(error).is_some());
+    }
+
+    let error = execute(&script, &[], &options()).await.unwrap_err();
+    assert!(
+        matches!(
+            &error,
+            RunError::Step { script, exit_code: Some(3), error: StepError::Failed(_) }
+                if script == "Broken"
+        ),
+        "{error}"
+    );
+}
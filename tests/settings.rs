@@ -0,0 +1,43 @@
+mod common;
+
+use std::path::Path;
+
+/// The value and origin `zirv config` shows for `key`.
+fn shown(dir: &Path, zirv_path: Option<&str>, key: &str) -> String {
+    let mut command = common::zirv(dir);
+    match zirv_path {
+        Some(value) => command.env("ZIRV_PATH", value),
+        None => command.env_remove("ZIRV_PATH"),
+    };
+    let output = command.arg("config").output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    stdout
+        .lines()
+        .find_map(|line| line.strip_prefix(&format!("{key}: ")))
+        .unwrap_or_else(|| panic!("no {key} in {stdout}"))
+        .to_string()
+}
+
+/// `ZIRV_PATH` wins over the project's `path`, which wins over the global one.
+#[test]
+fn test_search_path_precedence() {
+    let dir = tempfile::tempdir().unwrap();
+    let global = common::home(dir.path()).join(".zirv");
+    std::fs::create_dir_all(&global).unwrap();
+    std::fs::write(global.join("config.yaml"), "path: [/global/scripts]\n").unwrap();
+    assert!(
+        shown(dir.path(), None, "path").starts_with("[/global/scripts]  # "),
+        "global"
+    );
+
+    common::write_script(dir.path(), "config.yaml", "path: [/project/scripts]\n");
+    assert!(
+        shown(dir.path(), None, "path").starts_with("[/project/scripts]  # "),
+        "project"
+    );
+    assert_eq!(
+        shown(dir.path(), Some("/env/scripts"), "path"),
+        "[/env/scripts]  # ZIRV_PATH"
+    );
+}
@@ -0,0 +1,157 @@
+mod common;
+
+use std::path::Path;
+use std::process::Output;
+
+use serde_json::Value;
+
+const OTHER_OS: &str = if cfg!(target_os = "linux") {
+    "windows"
+} else {
+    "linux"
+};
+
+fn script() -> String {
+    format!(
+        r#"
+name: Release
+groups:
+  Deploy:
+    when: env.ZIRV_SKIP_TEST_DEPLOY == "true"
+commands:
+  - command: echo installed > installed.txt
+    cache:
+      inputs: [lock.txt]
+  - command: echo on {OTHER_OS}
+    options:
+      operating_system: {OTHER_OS}
+  - command: echo notarized
+    when: env.ZIRV_SKIP_TEST_NOTARIZE == "true"
+  - command: echo linted
+    tags: [lint]
+  - command: echo deployed
+    group: Deploy
+"#
+    )
+}
+
+fn run(dir: &Path, args: &[&str]) -> Output {
+    common::zirv(dir)
+        .arg("release")
+        .args(args)
+        .env_remove("ZIRV_SKIP_TEST_DEPLOY")
+        .env_remove("ZIRV_SKIP_TEST_NOTARIZE")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_skip_reasons_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    common::write_script(dir.path(), "release.yaml", &script());
+    std::fs::write(dir.path().join("lock.txt"), "v1").unwrap();
+
+    // The first run fills the cache of step 1.
+    let first = run(dir.path(), &["--no-summary"]);
+    assert!(first.status.success(), "{first:?}");
+
+    let output = run(
+        dir.path(),
+        &[
+            "--skip-tag",
+            "lint",
+            "--report",
+            "report.json",
+            "--junit",
+            "junit.xml",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+
+    let reasons = [
+        "cached, its inputs are unchanged".to_string(),
+        format!("only runs on {OTHER_OS}, this is {}", std::env::consts::OS),
+        "`env.ZIRV_SKIP_TEST_NOTARIZE == \"true\"` is false".to_string(),
+        "tagged 'lint' which is excluded by --skip-tag".to_string(),
+        "the when condition of group 'Deploy' is false".to_string(),
+    ];
+
+    let report: Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("report.json")).unwrap())
+            .unwrap();
+    let steps = report["steps"].as_array().unwrap();
+    let kinds: Vec<&str> = steps
+        .iter()
+        .map(|step| step["skip_reason"]["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        kinds,
+        ["cached", "operating_system", "condition", "tags", "group"]
+    );
+    assert_eq!(steps[1]["skip_reason"]["required"], OTHER_OS);
+    assert_eq!(
+        steps[2]["skip_reason"]["when"],
+        "env.ZIRV_SKIP_TEST_NOTARIZE == \"true\""
+    );
+    assert_eq!(steps[4]["skip_reason"]["group"], "Deploy");
+
+    let summary = stdout.split_once("Summary for 'Release':").unwrap().1;
+    let junit = std::fs::read_to_string(dir.path().join("junit.xml")).unwrap();
+    for reason in &reasons {
+        assert!(summary.contains(&format!("({reason})")), "{summary}");
+        let escaped = reason.replace('\'', "&apos;").replace('"', "&quot;");
+        assert!(
+            junit.contains(&format!("<skipped message=\"{escaped}\"/>")),
+            "{junit}"
+        );
+    }
+}
+
+#[test]
+fn test_skip_reasons_are_json_events() {
+    let dir = tempfile::tempdir().unwrap();
+    common::write_script(dir.path(), "release.yaml", &script());
+    std::fs::write(dir.path().join("lock.txt"), "v1").unwrap();
+
+    let output = run(dir.path(), &["--output", "json"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{stdout}");
+
+    let events: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .collect();
+    let skipped: Vec<(u64, &str, &str)> = events
+        .iter()
+        .filter(|event| event["type"] == "command_skipped")
+        .map(|event| {
+            (
+                event["index"].as_u64().unwrap(),
+                event["reason"]["kind"].as_str().unwrap(),
+                event["message"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    let os_message = format!("only runs on {OTHER_OS}, this is {}", std::env::consts::OS);
+    assert_eq!(
+        skipped,
+        [
+            (2, "operating_system", os_message.as_str()),
+            (
+                3,
+                "condition",
+                "`env.ZIRV_SKIP_TEST_NOTARIZE == \"true\"` is false"
+            ),
+            (5, "group", "the when condition of group 'Deploy' is false"),
+        ]
+    );
+    // Each is followed by the end of its step.
+    for (i, event) in events.iter().enumerate() {
+        if event["type"] == "command_skipped" {
+            assert_eq!(events[i + 1]["type"], "command_end");
+            assert_eq!(events[i + 1]["index"], event["index"]);
+            assert_eq!(events[i + 1]["status"], "skipped");
+        }
+    }
+}
@@ -0,0 +1,88 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+const SCRIPT: &str = r#"
+name: Term
+commands:
+  - command: stty -echo </dev/tty; stty echo </dev/tty
+  - command: read answer; echo "got $answer"
+"#;
+
+const SLOW: &str = r#"
+name: Slow
+timeout_ms: 500
+commands:
+  - command: sleep 7771 & echo $! > sleeper.pid; sleep 5
+"#;
+
+/// Whether `script` from util-linux is there to give zirv a terminal.
+fn has_script() -> bool {
+    Command::new("script").arg("--version").output().is_ok()
+}
+
+/// Starts `zirv <args>` in `dir` with a pty as its controlling terminal.
+fn spawn_in_terminal(dir: &Path, args: &str) -> Child {
+    let mut command = Command::new("timeout");
+    command
+        .args(["30", "script", "-qec"])
+        .arg(format!("{} {args}", env!("CARGO_BIN_EXE_zirv")))
+        .arg("/dev/null")
+        .current_dir(dir);
+    common::isolate_home(&mut command, dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap()
+}
+
+/// Steps that use the terminal zirv runs in, like a password prompt does,
+/// must not be stopped for doing so.
+#[test]
+fn test_steps_can_use_the_terminal() {
+    if !has_script() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    common::write_script(dir.path(), "term.yaml", SCRIPT);
+
+    let mut child = spawn_in_terminal(dir.path(), "term --no-summary");
+    child.stdin.take().unwrap().write_all(b"yes\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}: {stdout}", output.status);
+    assert!(stdout.contains("got yes"), "{stdout}");
+}
+
+/// In a terminal too, a step that times out is stopped together with what
+/// it started in the background.
+#[test]
+fn test_timeout_stops_the_step_in_a_terminal() {
+    if !has_script() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    common::write_script(dir.path(), "slow.yaml", SLOW);
+
+    let start = Instant::now();
+    let output = spawn_in_terminal(dir.path(), "slow --no-summary")
+        .wait_with_output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(124), "{stdout}");
+    assert!(start.elapsed() < Duration::from_secs(4), "{stdout}");
+    let pid = std::fs::read_to_string(dir.path().join("sleeper.pid")).unwrap();
+    let sleeper = Path::new("/proc").join(pid.trim());
+    let start = Instant::now();
+    while sleeper.exists() {
+        assert!(start.elapsed() < Duration::from_secs(2), "{stdout}");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
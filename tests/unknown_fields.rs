@@ -0,0 +1,75 @@
+mod common;
+
+use std::path::Path;
+use std::process::Output;
+
+const CLEANUP: &str = r#"
+name: Cleanup
+commands:
+  - command: "false"
+    options:
+      porceed_on_failure: true
+  - command: echo cleaned > cleaned.txt
+"#;
+
+fn run_cleanup(dir: &Path, args: &[&str]) -> Output {
+    common::write_script(dir, "cleanup.yaml", CLEANUP);
+    common::zirv(dir)
+        .args(["cleanup", "--no-summary"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_misspelled_option_is_warned_about() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = run_cleanup(dir.path(), &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(
+        stderr.contains("Warning: ")
+            && stderr
+                .contains("cleanup.yaml: step 1: unknown field 'porceed_on_failure' in options\n"),
+        "{stderr}"
+    );
+    // The option is ignored, so the failing step still stops the run.
+    assert!(!output.status.success());
+    assert!(!dir.path().join("cleaned.txt").exists());
+}
+
+#[test]
+fn test_misspelled_option_fails_when_strict() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = run_cleanup(dir.path(), &["--strict"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(65), "{stderr}");
+    assert!(
+        stderr.contains("as YAML: step 1: unknown field 'porceed_on_failure' in options"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("Warning: "), "{stderr}");
+}
+
+#[test]
+fn test_warnings_are_json_events_with_json_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = run_cleanup(dir.path(), &["--output", "json"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!stderr.contains("Warning: "), "{stderr}");
+    let messages: Vec<String> = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .filter(|event| event["type"] == "message")
+        .map(|event| event["line"].as_str().unwrap().to_string())
+        .collect();
+    assert!(
+        messages.iter().any(|line| line.starts_with("Warning: ")
+            && line
+                .ends_with("cleanup.yaml: step 1: unknown field 'porceed_on_failure' in options")),
+        "{stdout}"
+    );
+}